@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::spec::GeneratorSpec;
+
+const GENERATOR_CACHE_TTL: Duration = Duration::from_secs(5);
+const MAX_GENERATED_ITEMS: usize = 200;
+/// A generator still running after this is killed and yields nothing.
+const GENERATOR_TIMEOUT: Duration = Duration::from_secs(3);
+
+type CacheKey = (PathBuf, String);
+
+#[derive(Default)]
+pub struct GeneratorCache {
+    entries: Mutex<HashMap<CacheKey, (Instant, Vec<String>)>>,
+}
+
+impl GeneratorCache {
+    /// What `spec` produced in `cwd` within the last few seconds, if it ran.
+    pub fn cached(&self, spec: &GeneratorSpec, cwd: &Path) -> Option<Vec<String>> {
+        let key = (cwd.to_path_buf(), cache_key(spec));
+        let entries = self.entries.lock().ok()?;
+        let (at, items) = entries.get(&key)?;
+        (at.elapsed() < GENERATOR_CACHE_TTL).then(|| items.clone())
+    }
+
+    /// Runs `spec` in `cwd`, reusing results produced within the last few
+    /// seconds so typing does not spawn a process per keystroke. Blocks
+    /// until the generator exits or is killed for running too long.
+    pub fn run(&self, spec: &GeneratorSpec, cwd: &Path) -> Vec<String> {
+        if let Some(items) = self.cached(spec, cwd) {
+            return items;
+        }
+
+        let key = (cwd.to_path_buf(), cache_key(spec));
+        let items = run_generator(spec, cwd);
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(key, (Instant::now(), items.clone()));
+        }
+        items
+    }
+}
+
+fn cache_key(spec: &GeneratorSpec) -> String {
+    format!(
+        "{}|{}|{}",
+        spec.command.join("\u{1f}"),
+        spec.json_file.as_deref().unwrap_or(""),
+        spec.json_pointer.as_deref().unwrap_or("")
+    )
+}
+
+pub fn run_generator(spec: &GeneratorSpec, cwd: &Path) -> Vec<String> {
+    let raw = if let Some(file) = spec.json_file.as_deref() {
+        json_keys(&cwd.join(file), spec.json_pointer.as_deref().unwrap_or(""))
+    } else if let Some((program, args)) = spec.command.split_first() {
+        command_lines(program, args, cwd)
+    } else {
+        Vec::new()
+    };
+
+    let mut items: Vec<String> = raw
+        .into_iter()
+        .map(|item| {
+            let item = item.trim();
+            spec.strip_prefix
+                .as_deref()
+                .and_then(|prefix| item.strip_prefix(prefix))
+                .unwrap_or(item)
+                .to_string()
+        })
+        .filter(|item| !item.is_empty())
+        .collect();
+    items.dedup();
+    items.truncate(MAX_GENERATED_ITEMS);
+    items
+}
+
+fn command_lines(program: &str, args: &[String], cwd: &Path) -> Vec<String> {
+    let Ok(mut child) = Command::new(program)
+        .args(args)
+        .current_dir(cwd)
+        // Fail instead of asking for credentials nobody will see.
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+    else {
+        return Vec::new();
+    };
+    let Some(mut stdout) = child.stdout.take() else {
+        return Vec::new();
+    };
+    let reader = std::thread::spawn(move || {
+        let mut raw = Vec::new();
+        let _ = stdout.read_to_end(&mut raw);
+        raw
+    });
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if started.elapsed() < GENERATOR_TIMEOUT => {
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Vec::new();
+            }
+        }
+    };
+    match reader.join() {
+        Ok(raw) if status.success() => String::from_utf8_lossy(&raw)
+            .lines()
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn json_keys(path: &Path, pointer: &str) -> Vec<String> {
+    let Ok(raw) = std::fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(&raw) else {
+        return Vec::new();
+    };
+    value
+        .pointer(pointer)
+        .and_then(|value| value.as_object())
+        .map(|object| object.keys().cloned().collect())
+        .unwrap_or_default()
+}
@@ -0,0 +1,301 @@
+pub mod generator;
+pub mod shell;
+pub mod spec;
+
+use std::path::Path;
+
+use crate::acp::storage;
+use crate::terminal::command_line::{Token, TokenKind, last_command_words, tokenize};
+
+use self::generator::GeneratorCache;
+use self::spec::{ArgSpec, ArgTemplate, CommandSpec, GeneratorSpec};
+
+const USER_SPECS_DIR: &str = "completions";
+
+const BUILTIN_SPECS: [&str; 6] = [
+    include_str!("../../assets/completions/git.json"),
+    include_str!("../../assets/completions/cargo.json"),
+    include_str!("../../assets/completions/npm.json"),
+    include_str!("../../assets/completions/pnpm.json"),
+    include_str!("../../assets/completions/docker.json"),
+    include_str!("../../assets/completions/kubectl.json"),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompletionKind {
+    Subcommand,
+    Option,
+    Argument,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Completion {
+    pub value: String,
+    pub description: Option<String>,
+    pub kind: CompletionKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SpecCompletion {
+    /// Candidates that replace the word starting at byte `token_start`.
+    Items {
+        token_start: usize,
+        items: Vec<Completion>,
+    },
+    /// The spec expects a filesystem path; callers fall back to path completion.
+    Paths { folders_only: bool },
+}
+
+#[derive(Default)]
+pub struct CompletionEngine {
+    specs: Vec<CommandSpec>,
+    generators: GeneratorCache,
+}
+
+impl CompletionEngine {
+    pub fn from_specs(specs: Vec<CommandSpec>) -> Self {
+        Self {
+            specs,
+            generators: GeneratorCache::default(),
+        }
+    }
+
+    pub fn builtin() -> Self {
+        Self::from_specs(builtin_specs())
+    }
+
+    /// Built-in specs plus any `*.json` specs in the user's `completions`
+    /// directory. A user spec replaces a built-in one with the same name.
+    pub fn load() -> Self {
+        let mut specs = builtin_specs();
+        if let Ok(root) = storage::app_root() {
+            for spec in load_spec_dir(&root.join(USER_SPECS_DIR)) {
+                specs.retain(|existing| existing.name != spec.name);
+                specs.push(spec);
+            }
+        }
+        Self::from_specs(specs)
+    }
+
+    pub fn spec_for(&self, command: &str) -> Option<&CommandSpec> {
+        let name = Path::new(command)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(command);
+        self.specs.iter().find(|spec| spec.matches(name))
+    }
+
+    /// Completes the last word of `line`, which is the input text to the left
+    /// of the cursor. Returns `None` when no spec covers the current command or
+    /// the cursor is still on the command name itself. Generators run first
+    /// when they have no cached output.
+    pub fn complete(&self, line: &str, cwd: &Path) -> Option<SpecCompletion> {
+        self.complete_with(line, cwd, None)
+    }
+
+    /// Like [`Self::complete`], but without running anything. Generators with
+    /// no cached output are left out and returned, to be run off the UI
+    /// thread with [`Self::run_generators`] before completing again.
+    pub fn complete_cached(
+        &self,
+        line: &str,
+        cwd: &Path,
+    ) -> (Option<SpecCompletion>, Vec<GeneratorSpec>) {
+        let mut pending = Vec::new();
+        let completion = self.complete_with(line, cwd, Some(&mut pending));
+        (completion, pending)
+    }
+
+    /// Runs `generators` in `cwd` and caches what they produce.
+    pub fn run_generators(&self, generators: &[GeneratorSpec], cwd: &Path) {
+        for generator in generators {
+            self.generators.run(generator, cwd);
+        }
+    }
+
+    fn complete_with(
+        &self,
+        line: &str,
+        cwd: &Path,
+        mut pending: Option<&mut Vec<GeneratorSpec>>,
+    ) -> Option<SpecCompletion> {
+        let tokens = tokenize(line);
+        if tokens
+            .last()
+            .is_some_and(|token| token.kind == TokenKind::Operator && token.end == line.len())
+        {
+            return None;
+        }
+        let words = last_command_words(&tokens);
+        let at_word_boundary = line.ends_with(char::is_whitespace)
+            || words.last().is_some_and(|word| word.end < line.len());
+        let (previous, partial, token_start) = match words.split_last() {
+            Some((last, rest)) if !at_word_boundary => (rest, last.value.as_str(), last.start),
+            _ => (words, "", line.len()),
+        };
+        let (command, args) = previous.split_first()?;
+        let spec = self.spec_for(&command.value)?;
+
+        let (node, state) = walk_spec(spec, args);
+        let mut items = Vec::new();
+        match state {
+            WalkState::OptionArg(arg) => {
+                if let Some(paths) = path_template(arg) {
+                    return Some(paths);
+                }
+                self.push_arg_items(arg, cwd, &mut items, pending.as_deref_mut());
+            }
+            WalkState::Positional(index) => {
+                if partial.starts_with('-') {
+                    for option in &node.options {
+                        let description = option.description.clone();
+                        for name in &option.names {
+                            items.push(Completion {
+                                value: name.clone(),
+                                description: description.clone(),
+                                kind: CompletionKind::Option,
+                            });
+                        }
+                    }
+                } else {
+                    if index == 0 {
+                        items.extend(node.subcommands.iter().map(|sub| Completion {
+                            value: sub.name.clone(),
+                            description: sub.description.clone(),
+                            kind: CompletionKind::Subcommand,
+                        }));
+                    }
+                    let arg = node
+                        .args
+                        .get(index)
+                        .or_else(|| node.args.last().filter(|arg| arg.variadic));
+                    if let Some(arg) = arg {
+                        if items.is_empty()
+                            && let Some(paths) = path_template(arg)
+                        {
+                            return Some(paths);
+                        }
+                        self.push_arg_items(arg, cwd, &mut items, pending);
+                    }
+                }
+            }
+        }
+
+        items.retain(|item| item.value.starts_with(partial) && item.value != partial);
+        let mut seen = std::collections::HashSet::new();
+        items.retain(|item| seen.insert(item.value.clone()));
+        if items.is_empty() {
+            return None;
+        }
+        Some(SpecCompletion::Items { token_start, items })
+    }
+
+    fn push_arg_items(
+        &self,
+        arg: &ArgSpec,
+        cwd: &Path,
+        items: &mut Vec<Completion>,
+        pending: Option<&mut Vec<GeneratorSpec>>,
+    ) {
+        items.extend(arg.suggestions.iter().map(|suggestion| Completion {
+            value: suggestion.name.clone(),
+            description: suggestion.description.clone(),
+            kind: CompletionKind::Argument,
+        }));
+        if let Some(generator) = arg.generator.as_ref() {
+            let description = generator
+                .description
+                .clone()
+                .or_else(|| arg.description.clone());
+            let generated = match pending {
+                Some(pending) => self.generators.cached(generator, cwd).unwrap_or_else(|| {
+                    pending.push(generator.clone());
+                    Vec::new()
+                }),
+                None => self.generators.run(generator, cwd),
+            };
+            items.extend(generated.into_iter().map(|value| Completion {
+                value,
+                description: description.clone(),
+                kind: CompletionKind::Argument,
+            }));
+        }
+    }
+}
+
+enum WalkState<'a> {
+    /// The next word is the value of an option such as `--package`.
+    OptionArg(&'a ArgSpec),
+    /// The next word is the positional argument with this index.
+    Positional(usize),
+}
+
+fn walk_spec<'a>(spec: &'a CommandSpec, args: &[Token]) -> (&'a CommandSpec, WalkState<'a>) {
+    let mut node = spec;
+    let mut positional = 0usize;
+    let mut pending: Option<&ArgSpec> = None;
+    let mut options_ended = false;
+
+    for word in args {
+        let value = word.value.as_str();
+        if pending.take().is_some() {
+            continue;
+        }
+        if !options_ended && value == "--" {
+            options_ended = true;
+            continue;
+        }
+        if !options_ended && value.starts_with('-') && value.len() > 1 {
+            if let Some(option) = node.option(value)
+                && !value.contains('=')
+            {
+                pending = option.arg.as_ref();
+            }
+            continue;
+        }
+        if positional == 0
+            && let Some(sub) = node.subcommand(value)
+        {
+            node = sub;
+            continue;
+        }
+        positional += 1;
+    }
+
+    match pending {
+        Some(arg) => (node, WalkState::OptionArg(arg)),
+        None => (node, WalkState::Positional(positional)),
+    }
+}
+
+fn path_template(arg: &ArgSpec) -> Option<SpecCompletion> {
+    if !arg.suggestions.is_empty() || arg.generator.is_some() {
+        return None;
+    }
+    arg.template.map(|template| SpecCompletion::Paths {
+        folders_only: template == ArgTemplate::Folders,
+    })
+}
+
+pub fn builtin_specs() -> Vec<CommandSpec> {
+    BUILTIN_SPECS
+        .iter()
+        .filter_map(|raw| serde_json::from_str(raw).ok())
+        .collect()
+}
+
+fn load_spec_dir(dir: &Path) -> Vec<CommandSpec> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<_> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+    paths
+        .iter()
+        .filter_map(|path| storage::load_json_file::<CommandSpec>(path).ok())
+        .collect()
+}
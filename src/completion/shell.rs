@@ -0,0 +1,197 @@
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+const INTROSPECTION_TIMEOUT: Duration = Duration::from_secs(5);
+
+const BASH_SCRIPT: &str = "echo ::aliases; alias; echo ::functions; compgen -A function; \
+                           echo ::builtins; compgen -b";
+const ZSH_SCRIPT: &str = "echo ::aliases; alias; echo ::functions; print -l ${(k)functions}; \
+                          echo ::builtins; print -l ${(k)builtins}";
+const FISH_SCRIPT: &str = "echo ::aliases; alias; echo ::functions; functions -n; \
+                           echo ::builtins; builtin -n";
+
+static SHELL_COMMANDS: OnceLock<Vec<ShellCommand>> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShellCommandKind {
+    Alias,
+    Function,
+    Builtin,
+}
+
+/// A command the shell resolves itself rather than through `PATH`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShellCommand {
+    pub name: String,
+    pub kind: ShellCommandKind,
+    /// What an alias expands to.
+    pub expansion: Option<String>,
+}
+
+impl ShellCommand {
+    pub fn description(&self) -> String {
+        match (self.kind, self.expansion.as_deref()) {
+            (ShellCommandKind::Alias, Some(expansion)) => format!("alias: {expansion}"),
+            (ShellCommandKind::Alias, None) => "alias".to_string(),
+            (ShellCommandKind::Function, _) => "shell function".to_string(),
+            (ShellCommandKind::Builtin, _) => "shell builtin".to_string(),
+        }
+    }
+}
+
+/// Aliases, functions and builtins of the user's shell, or an empty list until
+/// `load_in_background` has finished.
+pub fn shell_commands() -> &'static [ShellCommand] {
+    SHELL_COMMANDS.get().map(Vec::as_slice).unwrap_or_default()
+}
+
+/// Starts the one-time introspection of `$SHELL`. Later calls do nothing.
+pub fn load_in_background() {
+    static STARTED: OnceLock<()> = OnceLock::new();
+    if STARTED.set(()).is_err() {
+        return;
+    }
+    std::thread::spawn(|| {
+        let commands = std::env::var("SHELL")
+            .ok()
+            .map(|shell| introspect(&shell))
+            .unwrap_or_default();
+        let _ = SHELL_COMMANDS.set(commands);
+    });
+}
+
+/// Runs `shell` interactively, so its rc files define the aliases and
+/// functions, and lists what it knows about.
+pub fn introspect(shell: &str) -> Vec<ShellCommand> {
+    let name = Path::new(shell)
+        .file_stem()
+        .and_then(|name| name.to_str())
+        .unwrap_or(shell);
+    let script = match name {
+        "bash" => BASH_SCRIPT,
+        "zsh" => ZSH_SCRIPT,
+        "fish" => FISH_SCRIPT,
+        _ => return Vec::new(),
+    };
+    let Ok(mut child) = Command::new(shell)
+        .args(["-i", "-c", script])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+    else {
+        return Vec::new();
+    };
+
+    // Read on another thread so a long listing cannot fill the pipe while we
+    // wait, and so an rc file waiting on the terminal cannot hang us forever.
+    let Some(mut stdout) = child.stdout.take() else {
+        return Vec::new();
+    };
+    let reader = std::thread::spawn(move || {
+        let mut raw = Vec::new();
+        let _ = stdout.read_to_end(&mut raw);
+        raw
+    });
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if started.elapsed() < INTROSPECTION_TIMEOUT => {
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Vec::new();
+            }
+        }
+    }
+    match reader.join() {
+        Ok(raw) => parse_introspection(&String::from_utf8_lossy(&raw)),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Parses the `::aliases`/`::functions`/`::builtins` sections written by the
+/// introspection scripts. Output before the first section (rc file noise) is
+/// ignored, as are functions starting with `_`, which are completion helpers.
+pub fn parse_introspection(output: &str) -> Vec<ShellCommand> {
+    let mut commands: Vec<ShellCommand> = Vec::new();
+    let mut section = None;
+    for line in output.lines() {
+        let line = line.trim_end();
+        match line {
+            "::aliases" => section = Some(ShellCommandKind::Alias),
+            "::functions" => section = Some(ShellCommandKind::Function),
+            "::builtins" => section = Some(ShellCommandKind::Builtin),
+            _ => match section {
+                Some(ShellCommandKind::Alias) => {
+                    if let Some((name, expansion)) = parse_alias_line(line) {
+                        commands.push(ShellCommand {
+                            name,
+                            kind: ShellCommandKind::Alias,
+                            expansion: Some(expansion),
+                        });
+                    }
+                }
+                Some(kind) => {
+                    for name in line.split(',').map(str::trim) {
+                        if name.is_empty()
+                            || name.starts_with('_')
+                            || name.contains(char::is_whitespace)
+                            || commands.iter().any(|command| command.name == name)
+                        {
+                            continue;
+                        }
+                        commands.push(ShellCommand {
+                            name: name.to_string(),
+                            kind,
+                            expansion: None,
+                        });
+                    }
+                }
+                None => {}
+            },
+        }
+    }
+    commands
+}
+
+/// Accepts bash (`alias ll='ls -l'`), zsh (`ll='ls -l'`) and fish
+/// (`alias ll 'ls -l'`) alias listings.
+fn parse_alias_line(line: &str) -> Option<(String, String)> {
+    let line = line.trim();
+    let rest = line.strip_prefix("alias ").unwrap_or(line).trim_start();
+    let (name, value) = match rest.find(['=', ' ']) {
+        Some(index) => (&rest[..index], rest[index + 1..].trim_start()),
+        None => return None,
+    };
+    let name = unquote(name);
+    if name.is_empty() {
+        return None;
+    }
+    Some((name, unquote(value)))
+}
+
+fn unquote(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    let mut quote: Option<char> = None;
+    while let Some(ch) = chars.next() {
+        match (quote, ch) {
+            (None, '\'' | '"') => quote = Some(ch),
+            (Some(open), _) if ch == open => quote = None,
+            (Some('"') | None, '\\') => {
+                if let Some(next) = chars.next() {
+                    out.push(next);
+                }
+            }
+            _ => out.push(ch),
+        }
+    }
+    out
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+/// Declarative description of a command line tool, loaded from the JSON files
+/// under `assets/completions` or the user's `completions` directory.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CommandSpec {
+    pub name: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub subcommands: Vec<CommandSpec>,
+    #[serde(default)]
+    pub options: Vec<OptionSpec>,
+    #[serde(default)]
+    pub args: Vec<ArgSpec>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct OptionSpec {
+    pub names: Vec<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub arg: Option<ArgSpec>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ArgSpec {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub suggestions: Vec<SuggestionSpec>,
+    #[serde(default)]
+    pub template: Option<ArgTemplate>,
+    #[serde(default)]
+    pub generator: Option<GeneratorSpec>,
+    /// Repeats for every remaining positional word.
+    #[serde(default)]
+    pub variadic: bool,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SuggestionSpec {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ArgTemplate {
+    Filepaths,
+    Folders,
+}
+
+/// Produces argument candidates at completion time, either by running a
+/// command in the current directory or by reading keys out of a JSON file.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GeneratorSpec {
+    #[serde(default)]
+    pub command: Vec<String>,
+    #[serde(default)]
+    pub json_file: Option<String>,
+    /// JSON pointer to an object whose keys become candidates.
+    #[serde(default)]
+    pub json_pointer: Option<String>,
+    #[serde(default)]
+    pub strip_prefix: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+impl CommandSpec {
+    pub fn matches(&self, word: &str) -> bool {
+        self.name == word || self.aliases.iter().any(|alias| alias == word)
+    }
+
+    pub fn subcommand(&self, word: &str) -> Option<&CommandSpec> {
+        self.subcommands.iter().find(|sub| sub.matches(word))
+    }
+
+    pub fn option(&self, word: &str) -> Option<&OptionSpec> {
+        let name = word.split_once('=').map(|(name, _)| name).unwrap_or(word);
+        self.options
+            .iter()
+            .find(|option| option.names.iter().any(|n| n == name))
+    }
+}
@@ -0,0 +1,93 @@
+const MATCH_SCORE: i64 = 16;
+const CONSECUTIVE_BONUS: i64 = 24;
+const BOUNDARY_BONUS: i64 = 12;
+const LEADING_BONUS: i64 = 8;
+const MAX_GAP_PENALTY: i64 = 8;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    /// Char indices of `candidate` that matched the query, ascending.
+    pub positions: Vec<usize>,
+}
+
+/// Case-insensitive subsequence match. Runs of consecutive characters and
+/// matches at word starts score higher; gaps between matches cost a little.
+/// An empty query matches everything with a zero score.
+pub fn fuzzy_match(query: &str, candidate: &str) -> Option<FuzzyMatch> {
+    let query: Vec<char> = query
+        .chars()
+        .filter(|ch| !ch.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    if query.is_empty() {
+        return Some(FuzzyMatch {
+            score: 0,
+            positions: Vec::new(),
+        });
+    }
+    let chars: Vec<char> = candidate.chars().collect();
+    // Lowercased the same way as the query; a char may lower to several,
+    // so each one keeps the index of the char it came from.
+    let (lower, origin): (Vec<char>, Vec<usize>) = chars
+        .iter()
+        .enumerate()
+        .flat_map(|(index, ch)| ch.to_lowercase().map(move |lower| (lower, index)))
+        .unzip();
+
+    // Greedy from every possible start of the first query char keeps this
+    // linear per start while still preferring tight, well-placed runs.
+    let mut best: Option<FuzzyMatch> = None;
+    for start in (0..lower.len()).filter(|&index| lower[index] == query[0]) {
+        let Some(positions) = greedy_positions(&lower, &query, start) else {
+            break;
+        };
+        let mut positions: Vec<usize> = positions.into_iter().map(|index| origin[index]).collect();
+        positions.dedup();
+        let score = score_positions(&chars, &positions);
+        if best.as_ref().is_none_or(|best| score > best.score) {
+            best = Some(FuzzyMatch { score, positions });
+        }
+    }
+    best
+}
+
+fn greedy_positions(lower: &[char], query: &[char], start: usize) -> Option<Vec<usize>> {
+    let mut positions = Vec::with_capacity(query.len());
+    let mut index = start;
+    for ch in query {
+        let offset = lower[index..]
+            .iter()
+            .position(|candidate| candidate == ch)?;
+        positions.push(index + offset);
+        index += offset + 1;
+    }
+    Some(positions)
+}
+
+fn score_positions(chars: &[char], positions: &[usize]) -> i64 {
+    let mut score = 0;
+    let mut previous: Option<usize> = None;
+    for &position in positions {
+        score += MATCH_SCORE;
+        if is_word_start(chars, position) {
+            score += BOUNDARY_BONUS;
+        }
+        match previous {
+            Some(previous) if position == previous + 1 => score += CONSECUTIVE_BONUS,
+            Some(previous) => score -= ((position - previous - 1) as i64).min(MAX_GAP_PENALTY),
+            None if position == 0 => score += LEADING_BONUS,
+            None => score -= (position as i64).min(MAX_GAP_PENALTY),
+        }
+        previous = Some(position);
+    }
+    score
+}
+
+fn is_word_start(chars: &[char], position: usize) -> bool {
+    position == 0
+        || matches!(
+            chars[position - 1],
+            ' ' | '/' | '\\' | '-' | '_' | '.' | ':' | '=' | '"' | '\'' | '|'
+        )
+}
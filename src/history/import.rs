@@ -0,0 +1,159 @@
+use std::path::{Path, PathBuf};
+
+/// A command read from a shell history file, oldest first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImportedCommand {
+    pub command: String,
+    pub timestamp: Option<i64>,
+}
+
+/// Plain one-command-per-line histories (bash, PSReadLine, the legacy app
+/// file). Bash `#<timestamp>` lines written with `HISTTIMEFORMAT` are used as
+/// the time of the following command.
+pub fn parse_plain_history(contents: &str) -> Vec<ImportedCommand> {
+    let mut out = Vec::new();
+    let mut timestamp = None;
+    for line in contents.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if let Some(ts) = trimmed
+            .strip_prefix('#')
+            .and_then(|rest| rest.parse::<i64>().ok())
+        {
+            timestamp = Some(ts);
+            continue;
+        }
+        push_command(&mut out, trimmed, timestamp.take());
+    }
+    out
+}
+
+/// zsh history, in either the plain or the `: <start>:<elapsed>;<command>`
+/// extended format. Lines ending in a backslash continue the command.
+pub fn parse_zsh_history(contents: &str) -> Vec<ImportedCommand> {
+    let mut out = Vec::new();
+    let mut lines = contents.lines();
+    while let Some(line) = lines.next() {
+        let mut command = line.to_string();
+        while command.ends_with('\\') {
+            command.pop();
+            match lines.next() {
+                Some(next) => {
+                    command.push('\n');
+                    command.push_str(next);
+                }
+                None => break,
+            }
+        }
+
+        let mut timestamp = None;
+        if let Some(rest) = command.strip_prefix(": ")
+            && let Some((meta, cmd)) = rest.split_once(';')
+        {
+            timestamp = meta
+                .split(':')
+                .next()
+                .and_then(|ts| ts.trim().parse::<i64>().ok());
+            command = cmd.to_string();
+        }
+        push_command(&mut out, command.trim(), timestamp);
+    }
+    out
+}
+
+/// fish history, a YAML-like list of `- cmd:` entries with `when:` times.
+pub fn parse_fish_history(contents: &str) -> Vec<ImportedCommand> {
+    let mut out: Vec<ImportedCommand> = Vec::new();
+    for line in contents.lines() {
+        let trimmed = line.trim();
+        if let Some(cmd) = trimmed.strip_prefix("- cmd:") {
+            let command = cmd.trim().replace("\\n", "\n").replace("\\\\", "\\");
+            push_command(&mut out, &command, None);
+        } else if let Some(when) = trimmed.strip_prefix("when:")
+            && let Some(last) = out.last_mut()
+            && last.timestamp.is_none()
+        {
+            last.timestamp = when.trim().parse::<i64>().ok();
+        }
+    }
+    out
+}
+
+fn push_command(out: &mut Vec<ImportedCommand>, command: &str, timestamp: Option<i64>) {
+    if command.is_empty() {
+        return;
+    }
+    if let Some(last) = out.last_mut()
+        && last.command == command
+    {
+        last.timestamp = timestamp.or(last.timestamp);
+        return;
+    }
+    out.push(ImportedCommand {
+        command: command.to_string(),
+        timestamp,
+    });
+}
+
+#[cfg(windows)]
+fn doskey_history() -> Vec<ImportedCommand> {
+    let Ok(out) = std::process::Command::new("cmd")
+        .args(["/c", "doskey", "/history"])
+        .output()
+    else {
+        return Vec::new();
+    };
+    if !out.status.success() {
+        return Vec::new();
+    }
+    parse_plain_history(&String::from_utf8_lossy(&out.stdout))
+}
+
+fn read_with(path: &Path, parse: fn(&str) -> Vec<ImportedCommand>) -> Vec<ImportedCommand> {
+    std::fs::read_to_string(path)
+        .map(|contents| parse(&contents))
+        .unwrap_or_default()
+}
+
+/// Reads every history this machine is known to keep. `legacy_app_history`
+/// is the flat `history.txt` older builds appended to.
+pub fn collect_shell_history(legacy_app_history: Option<&Path>) -> Vec<ImportedCommand> {
+    let mut commands = Vec::new();
+    if let Some(path) = legacy_app_history {
+        commands.extend(read_with(path, parse_plain_history));
+    }
+
+    if cfg!(windows) {
+        if let Ok(appdata) = std::env::var("APPDATA") {
+            let windows_ps = PathBuf::from(&appdata)
+                .join("Microsoft")
+                .join("Windows")
+                .join("PowerShell")
+                .join("PSReadLine")
+                .join("ConsoleHost_history.txt");
+            commands.extend(read_with(&windows_ps, parse_plain_history));
+
+            let pwsh_ps = PathBuf::from(&appdata)
+                .join("Microsoft")
+                .join("PowerShell")
+                .join("PSReadLine")
+                .join("ConsoleHost_history.txt");
+            commands.extend(read_with(&pwsh_ps, parse_plain_history));
+        }
+
+        #[cfg(windows)]
+        commands.extend(doskey_history());
+    } else if let Some(home) = std::env::var_os("HOME").map(PathBuf::from) {
+        commands.extend(read_with(&home.join(".bash_history"), parse_plain_history));
+        commands.extend(read_with(&home.join(".zsh_history"), parse_zsh_history));
+        let fish = home.join(".config").join("fish").join("fish_history");
+        commands.extend(read_with(&fish, parse_fish_history));
+    }
+
+    // Sources without timestamps keep their file order; stable sorting puts
+    // timestamped entries in chronological order around them.
+    commands.sort_by_key(|command| command.timestamp.unwrap_or(0));
+    commands
+}
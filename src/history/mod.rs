@@ -0,0 +1,472 @@
+pub mod fuzzy;
+pub mod import;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, mpsc};
+use std::thread;
+use std::time::Duration;
+
+use crate::acp::storage;
+
+const HISTORY_FILE: &str = "history.jsonl";
+const IMPORT_MARKER_FILE: &str = "history_import.json";
+const MAX_HISTORY_ENTRIES: usize = 50_000;
+
+/// One executed command. The store is an append-only JSON Lines log: an
+/// entry is written when the command starts and rewritten with the same `id`
+/// once its exit status is known, and the last line for an id wins on load.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub id: u64,
+    pub command: String,
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Unix timestamp in seconds; zero when an imported entry had none.
+    #[serde(default)]
+    pub timestamp: i64,
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+    #[serde(default)]
+    pub git_branch: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HistoryFilter {
+    #[default]
+    All,
+    Failed,
+    Succeeded,
+}
+
+impl HistoryFilter {
+    pub fn next(self) -> Self {
+        match self {
+            Self::All => Self::Failed,
+            Self::Failed => Self::Succeeded,
+            Self::Succeeded => Self::All,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::All => "All",
+            Self::Failed => "Failed",
+            Self::Succeeded => "Succeeded",
+        }
+    }
+
+    pub fn matches(self, entry: &HistoryEntry) -> bool {
+        match self {
+            Self::All => true,
+            Self::Failed => entry.exit_code.is_some_and(|code| code != 0),
+            Self::Succeeded => entry.exit_code == Some(0),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct HistoryQuery<'a> {
+    pub prefix: &'a str,
+    pub cwd: Option<&'a str>,
+    pub filter: HistoryFilter,
+    /// Unix timestamp used to age entries.
+    pub now: i64,
+    pub limit: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RankedCommand {
+    pub command: String,
+    pub score: f64,
+    pub run_count: usize,
+    pub last_run: i64,
+    pub last_cwd: Option<String>,
+    pub last_exit_code: Option<i32>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoryMatch {
+    pub ranked: RankedCommand,
+    /// Char indices of `ranked.command` matched by the search pattern.
+    pub positions: Vec<usize>,
+}
+
+#[derive(Debug, Default)]
+pub struct HistoryStore {
+    path: Option<PathBuf>,
+    entries: Vec<HistoryEntry>,
+    next_id: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ImportMarker {
+    imported_at: i64,
+    imported_commands: usize,
+}
+
+pub fn default_store_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("orbitshell").join(HISTORY_FILE))
+}
+
+fn data_dir() -> Option<PathBuf> {
+    if let Ok(appdata) = std::env::var("APPDATA") {
+        return Some(PathBuf::from(appdata));
+    }
+    if let Ok(xdg) = std::env::var("XDG_DATA_HOME") {
+        return Some(PathBuf::from(xdg));
+    }
+    if let Ok(home) = std::env::var("HOME") {
+        return Some(PathBuf::from(home).join(".local").join("share"));
+    }
+    None
+}
+
+pub fn now_timestamp() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+enum WriteJob {
+    Append(PathBuf, Vec<HistoryEntry>),
+    Rewrite(PathBuf, Vec<HistoryEntry>),
+    Flush(mpsc::Sender<()>),
+}
+
+/// History files are written on one background thread, in the order the
+/// writes were made, so recording a command never waits on the disk.
+fn writer() -> &'static mpsc::Sender<WriteJob> {
+    static WRITER: OnceLock<mpsc::Sender<WriteJob>> = OnceLock::new();
+    WRITER.get_or_init(|| {
+        let (sender, jobs) = mpsc::channel();
+        thread::spawn(move || {
+            for job in jobs {
+                match job {
+                    WriteJob::Append(path, entries) => {
+                        let _ = append_entries(&path, &entries);
+                    }
+                    WriteJob::Rewrite(path, entries) => {
+                        let _ = write_entries(&path, &entries);
+                    }
+                    WriteJob::Flush(done) => {
+                        let _ = done.send(());
+                    }
+                }
+            }
+        });
+        sender
+    })
+}
+
+/// Waits until every history write made so far is on disk.
+pub fn flush_writes() {
+    let (done, wait) = mpsc::channel();
+    if writer().send(WriteJob::Flush(done)).is_ok() {
+        let _ = wait.recv();
+    }
+}
+
+impl HistoryStore {
+    pub fn in_memory() -> Self {
+        Self {
+            next_id: 1,
+            ..Self::default()
+        }
+    }
+
+    pub fn open(path: &Path) -> Result<Self> {
+        flush_writes();
+        let mut store = Self {
+            path: Some(path.to_path_buf()),
+            entries: Vec::new(),
+            next_id: 1,
+        };
+        if !path.exists() {
+            return Ok(store);
+        }
+
+        let raw = fs::read_to_string(path)
+            .with_context(|| format!("failed to read history file {}", path.display()))?;
+        let mut index_by_id: HashMap<u64, usize> = HashMap::new();
+        let mut line_count = 0usize;
+        for line in raw.lines().filter(|line| !line.trim().is_empty()) {
+            line_count += 1;
+            let Ok(entry) = serde_json::from_str::<HistoryEntry>(line) else {
+                continue;
+            };
+            store.next_id = store.next_id.max(entry.id + 1);
+            match index_by_id.get(&entry.id) {
+                Some(&index) => store.entries[index] = entry,
+                None => {
+                    index_by_id.insert(entry.id, store.entries.len());
+                    store.entries.push(entry);
+                }
+            }
+        }
+
+        let overflow = store.entries.len().saturating_sub(MAX_HISTORY_ENTRIES);
+        if overflow > 0 {
+            store.entries.drain(..overflow);
+        }
+        if overflow > 0 || line_count > store.entries.len() * 2 + 100 {
+            store.rewrite();
+        }
+        Ok(store)
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Records a command that just started and returns its id for `finish`.
+    pub fn start(
+        &mut self,
+        command: &str,
+        cwd: Option<String>,
+        git_branch: Option<String>,
+        timestamp: i64,
+    ) -> u64 {
+        let entry = HistoryEntry {
+            id: self.next_id,
+            command: command.to_string(),
+            cwd,
+            timestamp,
+            exit_code: None,
+            duration_ms: None,
+            git_branch,
+        };
+        self.next_id += 1;
+        let id = entry.id;
+        self.append(vec![entry.clone()]);
+        self.entries.push(entry);
+        id
+    }
+
+    pub fn finish(&mut self, id: u64, exit_code: Option<i32>, duration: Duration) {
+        let Some(entry) = self.entries.iter_mut().rev().find(|entry| entry.id == id) else {
+            return;
+        };
+        entry.exit_code = exit_code;
+        entry.duration_ms = Some(duration.as_millis() as u64);
+        let entry = entry.clone();
+        self.append(vec![entry]);
+    }
+
+    /// Adds commands from another history source, oldest first.
+    pub fn import(&mut self, commands: Vec<import::ImportedCommand>) {
+        let mut imported = Vec::with_capacity(commands.len());
+        for command in commands {
+            imported.push(HistoryEntry {
+                id: self.next_id,
+                command: command.command,
+                timestamp: command.timestamp.unwrap_or(0),
+                ..HistoryEntry::default()
+            });
+            self.next_id += 1;
+        }
+        self.append(imported.clone());
+        self.entries.extend(imported);
+    }
+
+    /// Imports shell and legacy app histories the first time the store is
+    /// used. A marker file next to the store keeps this from repeating.
+    pub fn import_once(&mut self, sources: impl FnOnce() -> Vec<import::ImportedCommand>) {
+        let Some(marker_path) = self
+            .path
+            .as_ref()
+            .and_then(|path| path.parent())
+            .map(|dir| dir.join(IMPORT_MARKER_FILE))
+        else {
+            return;
+        };
+        if marker_path.exists() {
+            return;
+        }
+        let commands = sources();
+        let marker = ImportMarker {
+            imported_at: now_timestamp(),
+            imported_commands: commands.len(),
+        };
+        self.import(commands);
+        let _ = storage::save_json_file(&marker_path, &marker);
+    }
+
+    /// Ranks distinct commands starting with `query.prefix` by frecency:
+    /// every run adds a weight that decays with age, and runs in the current
+    /// directory count three times as much.
+    pub fn ranked(&self, query: &HistoryQuery<'_>) -> Vec<RankedCommand> {
+        let mut ranked: Vec<RankedCommand> = Vec::new();
+        let mut index_by_command: HashMap<&str, usize> = HashMap::new();
+        let mut order: Vec<u64> = Vec::new();
+
+        for entry in &self.entries {
+            if !entry.command.starts_with(query.prefix)
+                || entry.command == query.prefix
+                || !query.filter.matches(entry)
+            {
+                continue;
+            }
+            let mut weight = recency_weight(query.now - entry.timestamp);
+            if query.cwd.is_some() && entry.cwd.as_deref() == query.cwd {
+                weight *= 3.0;
+            }
+            match index_by_command.get(entry.command.as_str()) {
+                Some(&index) => {
+                    let item = &mut ranked[index];
+                    item.score += weight;
+                    item.run_count += 1;
+                    item.last_run = item.last_run.max(entry.timestamp);
+                    item.last_cwd = entry.cwd.clone().or(item.last_cwd.take());
+                    item.last_exit_code = entry.exit_code.or(item.last_exit_code);
+                    order[index] = entry.id;
+                }
+                None => {
+                    index_by_command.insert(entry.command.as_str(), ranked.len());
+                    order.push(entry.id);
+                    ranked.push(RankedCommand {
+                        command: entry.command.clone(),
+                        score: weight,
+                        run_count: 1,
+                        last_run: entry.timestamp,
+                        last_cwd: entry.cwd.clone(),
+                        last_exit_code: entry.exit_code,
+                    });
+                }
+            }
+        }
+
+        let mut indices: Vec<usize> = (0..ranked.len()).collect();
+        indices.sort_by(|&a, &b| {
+            ranked[b]
+                .score
+                .total_cmp(&ranked[a].score)
+                .then(ranked[b].last_run.cmp(&ranked[a].last_run))
+                .then(order[b].cmp(&order[a]))
+        });
+        let limit = if query.limit == 0 {
+            usize::MAX
+        } else {
+            query.limit
+        };
+        indices
+            .into_iter()
+            .take(limit)
+            .map(|index| ranked[index].clone())
+            .collect()
+    }
+
+    /// Fuzzy search over distinct commands for the reverse-search overlay.
+    /// Match quality decides the order, with frecency breaking close calls.
+    pub fn search(&self, pattern: &str, query: &HistoryQuery<'_>) -> Vec<HistoryMatch> {
+        let candidates = self.ranked(&HistoryQuery {
+            limit: 0,
+            ..query.clone()
+        });
+        let mut matches: Vec<(f64, HistoryMatch)> = candidates
+            .into_iter()
+            .filter_map(|ranked| {
+                let found = fuzzy::fuzzy_match(pattern, &ranked.command)?;
+                let score = found.score as f64 + (1.0 + ranked.score).ln() * 8.0;
+                Some((
+                    score,
+                    HistoryMatch {
+                        ranked,
+                        positions: found.positions,
+                    },
+                ))
+            })
+            .collect();
+        // `ranked` is already in frecency order, so a stable sort keeps it
+        // for equal scores.
+        matches.sort_by(|a, b| b.0.total_cmp(&a.0));
+        let limit = if query.limit == 0 {
+            usize::MAX
+        } else {
+            query.limit
+        };
+        matches
+            .into_iter()
+            .take(limit)
+            .map(|(_, found)| found)
+            .collect()
+    }
+
+    /// Forgets every run of `command` and returns how many were removed.
+    pub fn delete_command(&mut self, command: &str) -> usize {
+        let before = self.entries.len();
+        self.entries.retain(|entry| entry.command != command);
+        let removed = before - self.entries.len();
+        if removed > 0 {
+            self.rewrite();
+        }
+        removed
+    }
+
+    fn append(&self, entries: Vec<HistoryEntry>) {
+        let Some(path) = self.path.clone() else {
+            return;
+        };
+        if entries.is_empty() {
+            return;
+        }
+        let _ = writer().send(WriteJob::Append(path, entries));
+    }
+
+    fn rewrite(&self) {
+        let Some(path) = self.path.clone() else {
+            return;
+        };
+        let _ = writer().send(WriteJob::Rewrite(path, self.entries.clone()));
+    }
+}
+
+fn append_entries(path: &Path, entries: &[HistoryEntry]) -> Result<()> {
+    storage::ensure_parent_dir(path)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("failed to open history file {}", path.display()))?;
+    file.write_all(jsonl(entries)?.as_bytes())?;
+    Ok(())
+}
+
+fn write_entries(path: &Path, entries: &[HistoryEntry]) -> Result<()> {
+    let raw = jsonl(entries)?;
+    storage::ensure_parent_dir(path)?;
+    fs::write(path, raw).with_context(|| format!("failed to write history file {}", path.display()))
+}
+
+fn jsonl(entries: &[HistoryEntry]) -> Result<String> {
+    let mut raw = String::new();
+    for entry in entries {
+        raw.push_str(&serde_json::to_string(entry)?);
+        raw.push('\n');
+    }
+    Ok(raw)
+}
+
+fn recency_weight(age_seconds: i64) -> f64 {
+    const HOUR: i64 = 60 * 60;
+    const DAY: i64 = 24 * HOUR;
+    match age_seconds {
+        age if age < HOUR => 4.0,
+        age if age < DAY => 2.0,
+        age if age < 7 * DAY => 1.0,
+        age if age < 30 * DAY => 0.5,
+        _ => 0.25,
+    }
+}
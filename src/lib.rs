@@ -1,5 +1,8 @@
 pub mod acp;
+pub mod completion;
 pub mod git;
+pub mod history;
 pub mod mcp;
+pub mod secrets;
 pub mod terminal;
 pub mod ui;
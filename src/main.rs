@@ -9,6 +9,8 @@ fn main() {
         cx.text_system()
             .add_fonts(vec![Cow::Borrowed(lucide_icons::LUCIDE_FONT_BYTES)])
             .ok();
+        cx.on_app_quit(|_| async { orbitshell::history::flush_writes() })
+            .detach();
         let mut options = WindowOptions::default();
         options.titlebar = Some(TitlebarOptions {
             title: Some("OrbitShell".into()),
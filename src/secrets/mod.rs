@@ -0,0 +1,317 @@
+use regex::{Regex, RegexSet};
+use std::borrow::Cow;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+/// What redacted text is replaced with when it leaves the app.
+pub const REDACTED: &str = "[REDACTED]";
+/// What secrets are drawn as on screen.
+pub const MASK: &str = "••••••••";
+
+/// Built-in patterns as (name, regex). When the regex has a capture group,
+/// only the first group is the secret, so `Bearer` or `API_KEY=` stay visible.
+const BUILTIN_PATTERNS: &[(&str, &str)] = &[
+    ("AWS access key", r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b"),
+    (
+        "AWS secret key",
+        r#"(?i)aws_secret_access_key\s*[=:]\s*["']?([A-Za-z0-9/+=]{40})"#,
+    ),
+    (
+        "GitHub token",
+        r"\b(?:ghp|gho|ghu|ghs|ghr)_[A-Za-z0-9]{36,}\b",
+    ),
+    ("GitHub token", r"\bgithub_pat_[A-Za-z0-9_]{22,}\b"),
+    ("GitLab token", r"\bglpat-[A-Za-z0-9_-]{20,}"),
+    ("Slack token", r"\bxox[abposr]-[A-Za-z0-9-]{10,}"),
+    ("Stripe key", r"\b[rs]k_(?:live|test)_[A-Za-z0-9]{16,}\b"),
+    ("API key", r"\bsk-[A-Za-z0-9_-]{20,}"),
+    ("Google API key", r"\bAIza[0-9A-Za-z_-]{35}"),
+    ("Bearer token", r"(?i)\bbearer\s+([A-Za-z0-9._~+/=-]{16,})"),
+    (
+        "JSON web token",
+        r"\beyJ[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]{10,}",
+    ),
+    // Through the END line, or to the end of the text when it is cut off.
+    (
+        "Private key",
+        r"(?s)-----BEGIN [A-Z ]*PRIVATE KEY-----.*?(?:-----END [A-Z ]*PRIVATE KEY-----|\z)",
+    ),
+    (
+        "Environment secret",
+        r#"\b[A-Z0-9_]*(?:SECRET|TOKEN|PASSWORD|PASSWD|API_?KEY|PRIVATE_KEY|ACCESS_KEY)[A-Z0-9_]*=["']?([^\s"']{4,})"#,
+    ),
+    (
+        "URL password",
+        r"\b[a-zA-Z][a-zA-Z0-9+.-]*://[^\s:/@]+:([^\s:/@]+)@",
+    ),
+];
+
+#[derive(Clone, Debug)]
+struct SecretPattern {
+    name: String,
+    regex: Regex,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SecretMatch {
+    pub name: String,
+    /// Byte range of the secret in the scanned text.
+    pub range: Range<usize>,
+}
+
+/// Finds tokens and passwords in text so they can be masked on screen and
+/// redacted wherever text is stored or leaves the app.
+#[derive(Clone, Debug)]
+pub struct SecretScanner {
+    patterns: Vec<SecretPattern>,
+    /// Screens text with one pass over all patterns. `None` when the
+    /// patterns are too large to combine, so each is tried in turn.
+    set: Option<RegexSet>,
+}
+
+impl Default for SecretScanner {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl SecretScanner {
+    pub fn builtin() -> Self {
+        Self::with_user_patterns(&[]).0
+    }
+
+    /// The built-in patterns plus `user_patterns`. Patterns that fail to
+    /// compile are skipped and returned as errors.
+    pub fn with_user_patterns(user_patterns: &[String]) -> (Self, Vec<String>) {
+        let mut patterns: Vec<SecretPattern> = BUILTIN_PATTERNS
+            .iter()
+            .filter_map(|(name, pattern)| {
+                Some(SecretPattern {
+                    name: (*name).to_string(),
+                    regex: Regex::new(pattern).ok()?,
+                })
+            })
+            .collect();
+        let mut errors = Vec::new();
+        for pattern in user_patterns {
+            match Regex::new(pattern) {
+                Ok(regex) => patterns.push(SecretPattern {
+                    name: "Custom pattern".to_string(),
+                    regex,
+                }),
+                Err(err) => errors.push(format!("{pattern}: {err}")),
+            }
+        }
+        let set = RegexSet::new(patterns.iter().map(|pattern| pattern.regex.as_str())).ok();
+        (Self { patterns, set }, errors)
+    }
+
+    pub fn builtin_names() -> Vec<&'static str> {
+        let mut names: Vec<&'static str> = BUILTIN_PATTERNS.iter().map(|(name, _)| *name).collect();
+        names.dedup();
+        names
+    }
+
+    pub fn contains_secret(&self, text: &str) -> bool {
+        match &self.set {
+            Some(set) => set.is_match(text),
+            None => self
+                .patterns
+                .iter()
+                .any(|pattern| pattern.regex.is_match(text)),
+        }
+    }
+
+    fn matching_patterns(&self, text: &str) -> Vec<usize> {
+        match &self.set {
+            Some(set) => set.matches(text).into_iter().collect(),
+            None => (0..self.patterns.len())
+                .filter(|index| self.patterns[*index].regex.is_match(text))
+                .collect(),
+        }
+    }
+
+    /// Secrets in `text`, ordered by position, with overlaps merged.
+    pub fn find(&self, text: &str) -> Vec<SecretMatch> {
+        let mut found: Vec<SecretMatch> = Vec::new();
+        for index in self.matching_patterns(text) {
+            let pattern = &self.patterns[index];
+            for captures in pattern.regex.captures_iter(text) {
+                let Some(secret) = captures.get(1).or_else(|| captures.get(0)) else {
+                    continue;
+                };
+                if !secret.is_empty() {
+                    found.push(SecretMatch {
+                        name: pattern.name.clone(),
+                        range: secret.range(),
+                    });
+                }
+            }
+        }
+        found.sort_by_key(|found| (found.range.start, std::cmp::Reverse(found.range.end)));
+        let mut merged: Vec<SecretMatch> = Vec::with_capacity(found.len());
+        for next in found {
+            match merged.last_mut() {
+                Some(last) if next.range.start < last.range.end => {
+                    last.range.end = last.range.end.max(next.range.end);
+                }
+                _ => merged.push(next),
+            }
+        }
+        merged
+    }
+
+    /// `lines` with every secret replaced by [`REDACTED`]. Lines of a
+    /// private key block are replaced whole.
+    pub fn redact_lines(&self, lines: &[String]) -> Vec<String> {
+        self.replace_lines(lines, REDACTED)
+    }
+
+    /// `lines` with every secret replaced by [`MASK`]. Lines of a private
+    /// key block are replaced whole.
+    pub fn mask_lines(&self, lines: &[String]) -> Vec<String> {
+        self.replace_lines(lines, MASK)
+    }
+
+    fn replace_lines(&self, lines: &[String], with: &str) -> Vec<String> {
+        let mut keys = PrivateKeyTracker::default();
+        lines
+            .iter()
+            .map(|line| {
+                if keys.in_key(line) {
+                    with.to_string()
+                } else {
+                    self.replace(line, with).into_owned()
+                }
+            })
+            .collect()
+    }
+
+    /// `text` with every secret replaced by [`REDACTED`].
+    pub fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        self.replace(text, REDACTED)
+    }
+
+    /// `text` with every secret replaced by [`MASK`], for display.
+    pub fn mask<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        self.replace(text, MASK)
+    }
+
+    fn replace<'a>(&self, text: &'a str, with: &str) -> Cow<'a, str> {
+        if !self.contains_secret(text) {
+            return Cow::Borrowed(text);
+        }
+        let found = self.find(text);
+        if found.is_empty() {
+            return Cow::Borrowed(text);
+        }
+        let mut out = String::with_capacity(text.len());
+        let mut pos = 0;
+        for secret in found {
+            out.push_str(&text[pos..secret.range.start]);
+            out.push_str(with);
+            pos = secret.range.end;
+        }
+        out.push_str(&text[pos..]);
+        Cow::Owned(out)
+    }
+}
+
+/// Follows PEM private key blocks through text read a line at a time. Only
+/// the BEGIN line can be told apart; the key body looks like any base64.
+#[derive(Clone, Debug, Default)]
+pub struct PrivateKeyTracker {
+    inside: bool,
+}
+
+impl PrivateKeyTracker {
+    /// Whether `line` is part of a private key block, from its BEGIN line
+    /// through its END line.
+    pub fn in_key(&mut self, line: &str) -> bool {
+        if !self.inside {
+            let Some(begin) = key_begin_regex().find(line) else {
+                return false;
+            };
+            self.inside = !key_end_regex().is_match(&line[begin.end()..]);
+            return true;
+        }
+        if key_end_regex().is_match(line) {
+            self.inside = false;
+        }
+        true
+    }
+}
+
+/// A partial line longer than this is redacted and let through rather
+/// than held for its line break.
+pub const MAX_HELD_LINE: usize = 16 * 1024;
+
+/// Redacts text that arrives in pieces, such as terminal output, so that a
+/// secret split between two pieces is still caught. Text is held back to
+/// its last line break, and lines of a private key are replaced whole.
+#[derive(Clone, Debug, Default)]
+pub struct StreamRedactor {
+    pending: String,
+    keys: PrivateKeyTracker,
+}
+
+impl StreamRedactor {
+    /// The redacted complete lines of what was held plus `text`. The rest
+    /// waits for a line break or [`Self::flush`].
+    pub fn push(&mut self, scanner: &SecretScanner, text: &str) -> String {
+        self.pending.push_str(text);
+        let end = match self.pending.rfind('\n') {
+            Some(index) => index + 1,
+            None if self.pending.len() > MAX_HELD_LINE => self.pending.len(),
+            None => return String::new(),
+        };
+        let ready: String = self.pending.drain(..end).collect();
+        self.redact(scanner, &ready)
+    }
+
+    /// Redacts and hands back whatever is held.
+    pub fn flush(&mut self, scanner: &SecretScanner) -> String {
+        let held = std::mem::take(&mut self.pending);
+        self.redact(scanner, &held)
+    }
+
+    pub fn is_holding(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    fn redact(&mut self, scanner: &SecretScanner, text: &str) -> String {
+        text.split_inclusive('\n')
+            .map(|line| {
+                let content = line.trim_end_matches(['\r', '\n']);
+                if self.keys.in_key(content) {
+                    format!("{REDACTED}{}", &line[content.len()..])
+                } else {
+                    scanner.redact(line).into_owned()
+                }
+            })
+            .collect()
+    }
+}
+
+/// For each of `lines`, whether it is part of a private key block.
+pub fn private_key_lines<S: AsRef<str>>(lines: &[S]) -> Vec<bool> {
+    let mut keys = PrivateKeyTracker::default();
+    lines
+        .iter()
+        .map(|line| keys.in_key(line.as_ref()))
+        .collect()
+}
+
+fn key_begin_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"-----BEGIN [A-Z ]*PRIVATE KEY-----").expect("key begin pattern is valid")
+    })
+}
+
+fn key_end_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"-----END [A-Z ]*PRIVATE KEY-----").expect("key end pattern is valid")
+    })
+}
@@ -0,0 +1,159 @@
+/// Longest unterminated escape sequence carried over to the next chunk. Longer
+/// sequences are dropped rather than buffered forever.
+const MAX_PENDING_ESCAPE: usize = 4096;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TerminalEvent {
+    /// OSC 133;D, written by the shell integration before each prompt.
+    CommandFinished { exit_code: Option<i32> },
+    /// OSC 0 or OSC 2, the title a program such as `vim` or `ssh` asks for.
+    TitleChanged(String),
+    /// A BEL outside of any escape sequence.
+    Bell,
+    /// DECSET (`CSI ? n h`) or DECRST (`CSI ? n l`), one per mode.
+    PrivateMode { mode: u16, enabled: bool },
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ParsedChunk {
+    /// The chunk with every escape sequence and bell removed.
+    pub text: String,
+    /// `text` with the SGR color and style sequences left in.
+    pub styled: String,
+    pub events: Vec<TerminalEvent>,
+}
+
+/// Strips escape sequences from PTY output and reports the ones the view acts
+/// on. A sequence split across reads is buffered until the rest arrives.
+#[derive(Debug, Default)]
+pub struct AnsiParser {
+    pending: String,
+}
+
+impl AnsiParser {
+    pub fn feed(&mut self, chunk: &str) -> ParsedChunk {
+        let mut input = std::mem::take(&mut self.pending);
+        input.push_str(chunk);
+
+        let mut out = ParsedChunk::default();
+        let mut index = 0;
+        while index < input.len() {
+            let rest = &input[index..];
+            if rest.starts_with('\x07') {
+                out.events.push(TerminalEvent::Bell);
+                index += 1;
+                continue;
+            }
+            if !rest.starts_with('\x1b') {
+                let end = rest.find(['\x1b', '\x07']).unwrap_or(rest.len());
+                out.text.push_str(&rest[..end]);
+                out.styled.push_str(&rest[..end]);
+                index += end;
+                continue;
+            }
+            match escape_len(rest, &mut out.events) {
+                Some(len) => {
+                    let sequence = &rest[..len];
+                    if sequence.starts_with("\x1b[") && sequence.ends_with('m') {
+                        out.styled.push_str(sequence);
+                    }
+                    index += len;
+                }
+                None => {
+                    if rest.len() <= MAX_PENDING_ESCAPE {
+                        self.pending = rest.to_string();
+                    }
+                    break;
+                }
+            }
+        }
+        out
+    }
+}
+
+pub fn strip_ansi(input: &str) -> String {
+    AnsiParser::default().feed(input).text
+}
+
+/// Length of the escape sequence at the start of `seq`, or `None` when it is
+/// not complete yet.
+fn escape_len(seq: &str, events: &mut Vec<TerminalEvent>) -> Option<usize> {
+    let bytes = seq.as_bytes();
+    match *bytes.get(1)? {
+        b'[' => {
+            let len = bytes[2..]
+                .iter()
+                .position(|byte| (0x40..=0x7e).contains(byte))?
+                + 3;
+            parse_private_mode(&seq[2..len], events);
+            Some(len)
+        }
+        b']' => {
+            let (payload, len) = string_sequence(seq)?;
+            parse_osc(payload, events);
+            Some(len)
+        }
+        b'P' | b'X' | b'^' | b'_' => string_sequence(seq).map(|(_, len)| len),
+        0x20..=0x2f => bytes[2..]
+            .iter()
+            .position(|byte| !(0x20..=0x2f).contains(byte))
+            .map(|offset| offset + 3),
+        0x1b => Some(1),
+        _ => seq[1..].chars().next().map(|ch| 1 + ch.len_utf8()),
+    }
+}
+
+/// Payload and total length of an OSC/DCS-style string terminated by BEL or
+/// ST (`ESC \`).
+fn string_sequence(seq: &str) -> Option<(&str, usize)> {
+    let bytes = seq.as_bytes();
+    let mut index = 2;
+    while index < bytes.len() {
+        match bytes[index] {
+            0x07 => return Some((&seq[2..index], index + 1)),
+            0x1b => {
+                let next = *bytes.get(index + 1)?;
+                let len = if next == b'\\' { index + 2 } else { index };
+                return Some((&seq[2..index], len));
+            }
+            _ => index += 1,
+        }
+    }
+    None
+}
+
+fn parse_private_mode(body: &str, events: &mut Vec<TerminalEvent>) {
+    let Some(params) = body.strip_prefix('?') else {
+        return;
+    };
+    let enabled = match params.as_bytes().last() {
+        Some(b'h') => true,
+        Some(b'l') => false,
+        _ => return,
+    };
+    for mode in params[..params.len() - 1].split(';') {
+        if let Ok(mode) = mode.parse() {
+            events.push(TerminalEvent::PrivateMode { mode, enabled });
+        }
+    }
+}
+
+fn parse_osc(payload: &str, events: &mut Vec<TerminalEvent>) {
+    let Some((code, rest)) = payload.split_once(';') else {
+        return;
+    };
+    match code {
+        "0" | "2" => {
+            let title: String = rest.chars().filter(|ch| !ch.is_control()).collect();
+            events.push(TerminalEvent::TitleChanged(title.trim().to_string()));
+        }
+        "133" => {
+            let mut parts = rest.split(';');
+            if parts.next() == Some("D") {
+                let exit_code = parts.next().and_then(|code| code.trim().parse().ok());
+                events.push(TerminalEvent::CommandFinished { exit_code });
+            }
+        }
+        _ => {}
+    }
+}
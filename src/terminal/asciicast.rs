@@ -0,0 +1,167 @@
+use anyhow::{Context, Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+pub const CAST_VERSION: u32 = 2;
+
+/// The first line of an asciicast v2 file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CastHeader {
+    pub version: u32,
+    pub width: u16,
+    pub height: u16,
+    /// Unix time the recording started.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<i64>,
+    /// Longest pause kept on playback, in seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_time_limit: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+impl CastHeader {
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            version: CAST_VERSION,
+            width,
+            height,
+            timestamp: None,
+            idle_time_limit: None,
+            title: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CastEventKind {
+    /// Bytes the program wrote, `o`.
+    Output,
+    /// Bytes sent to the program, `i`.
+    Input,
+    /// A labelled point in time, `m`. OrbitShell marks each command.
+    Marker,
+}
+
+impl CastEventKind {
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Output => "o",
+            Self::Input => "i",
+            Self::Marker => "m",
+        }
+    }
+
+    fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "o" => Some(Self::Output),
+            "i" => Some(Self::Input),
+            "m" => Some(Self::Marker),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CastEvent {
+    /// Seconds since the start of the recording.
+    pub time: f64,
+    pub kind: CastEventKind,
+    pub data: String,
+}
+
+/// A parsed recording.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cast {
+    pub header: CastHeader,
+    pub events: Vec<CastEvent>,
+}
+
+impl Cast {
+    /// Parses asciicast v2. Events of other kinds, such as resizes, are
+    /// skipped.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+        let header_line = lines.next().ok_or_else(|| anyhow!("recording is empty"))?;
+        let header: CastHeader =
+            serde_json::from_str(header_line).context("invalid asciicast header")?;
+        if header.version != CAST_VERSION {
+            bail!("unsupported asciicast version {}", header.version);
+        }
+
+        let mut events = Vec::new();
+        for (index, line) in lines.enumerate() {
+            let (time, code, data): (f64, String, String) = serde_json::from_str(line)
+                .with_context(|| format!("invalid asciicast event on line {}", index + 2))?;
+            if let Some(kind) = CastEventKind::from_code(&code) {
+                events.push(CastEvent { time, kind, data });
+            }
+        }
+        Ok(Self { header, events })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        Self::parse(&text)
+    }
+
+    /// Seconds until the last event.
+    pub fn duration(&self) -> f64 {
+        self.events.last().map_or(0.0, |event| event.time)
+    }
+}
+
+/// Writes events as they happen, timed from when the recorder was created.
+pub struct CastRecorder<W: Write> {
+    writer: W,
+    started: Instant,
+}
+
+impl CastRecorder<BufWriter<File>> {
+    pub fn create(path: &Path, header: &CastHeader) -> Result<Self> {
+        let file =
+            File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+        Self::new(BufWriter::new(file), header)
+    }
+}
+
+impl<W: Write> CastRecorder<W> {
+    pub fn new(mut writer: W, header: &CastHeader) -> Result<Self> {
+        serde_json::to_writer(&mut writer, header)?;
+        writer.write_all(b"\n")?;
+        Ok(Self {
+            writer,
+            started: Instant::now(),
+        })
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    pub fn record(&mut self, kind: CastEventKind, data: &str) -> Result<()> {
+        self.record_at(kind, data, Instant::now())
+    }
+
+    /// Records an event that happened at `at`, for data that was held back
+    /// before being written. Events must still be recorded in order.
+    pub fn record_at(&mut self, kind: CastEventKind, data: &str, at: Instant) -> Result<()> {
+        // Microseconds, as asciinema writes them.
+        let elapsed = at.saturating_duration_since(self.started);
+        let time = (elapsed.as_secs_f64() * 1e6).round() / 1e6;
+        serde_json::to_writer(&mut self.writer, &(time, kind.code(), data))?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Flushes and hands back the writer.
+    pub fn finish(mut self) -> Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
@@ -0,0 +1,578 @@
+use anyhow::{Context as _, Result};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::terminal::command_line::{Token, TokenKind, split_commands, tokenize};
+
+/// Per-project rules, read from the nearest directory at or above the
+/// working directory that has this file. See [`ProjectGuardConfig`].
+pub const PROJECT_GUARD_FILE: &str = "orbitshell_guard.json";
+
+/// Programs that run the command after them, so `sudo rm -rf /` is checked
+/// as `rm -rf /`.
+const WRAPPERS: [&str; 7] = ["sudo", "doas", "command", "env", "nohup", "time", "exec"];
+
+/// A destructive command shape. Every condition that is set must hold.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct GuardRule {
+    /// Shown in the confirmation, and used to disable or replace the rule
+    /// in the user's config.
+    pub name: String,
+    /// Why the command deserves a second look.
+    pub reason: String,
+    /// Program the rule applies to. Empty matches any program.
+    pub command: String,
+    /// First argument after the program, such as `push` for git.
+    pub subcommand: Option<String>,
+    /// Flags that must all be present. Alternatives are separated by `|`,
+    /// and a one-letter flag also matches inside combined flags like `-rf`.
+    pub flags: Vec<String>,
+    /// Exact argument values, one of which must be present. A trailing `/`
+    /// on the argument is ignored.
+    pub targets: Vec<String>,
+    /// Branch globs matched against pushed refs, or against the checked-out
+    /// branch when the command names none.
+    pub branches: Vec<String>,
+    /// Kubernetes context globs matched against `--context`, or against the
+    /// current context when the command names none.
+    pub kube_contexts: Vec<String>,
+    /// Case-insensitive regex matched against the whole command.
+    pub pattern: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct GuardConfig {
+    /// Turns the guard off.
+    pub enabled: bool,
+    /// Names of rules to switch off, built-in or inherited.
+    pub disabled: Vec<String>,
+    /// Extra rules. A rule named like an existing one replaces it.
+    pub rules: Vec<GuardRule>,
+}
+
+/// What a [`PROJECT_GUARD_FILE`] may hold. A checked-out repository is not
+/// trusted to weaken the guard, so projects can only add rules; switching
+/// the guard or its rules off stays in the user's [`GuardConfig`].
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ProjectGuardConfig {
+    /// Extra rules. One named like an existing rule is skipped rather than
+    /// replacing it.
+    pub rules: Vec<GuardRule>,
+}
+
+impl Default for GuardConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            disabled: Vec::new(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// What the guard knows about the shell beyond the command line.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GuardContext {
+    pub git_branch: Option<String>,
+    pub kube_context: Option<String>,
+}
+
+impl GuardContext {
+    /// Context for checking `line`. The kubeconfig is only read when the
+    /// line runs kubectl.
+    pub fn for_command(line: &str, git_branch: Option<String>) -> Self {
+        Self {
+            git_branch,
+            kube_context: line
+                .contains("kubectl")
+                .then(current_kube_context)
+                .flatten(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GuardMatch {
+    pub rule: String,
+    pub reason: String,
+    /// The simple command that matched, as typed.
+    pub command: String,
+}
+
+#[derive(Clone, Debug)]
+struct CompiledRule {
+    rule: GuardRule,
+    pattern: Option<Regex>,
+}
+
+/// Checks command lines against destructive-command rules before they run.
+#[derive(Clone, Debug, Default)]
+pub struct CommandGuard {
+    rules: Vec<CompiledRule>,
+}
+
+impl CommandGuard {
+    pub fn builtin() -> Self {
+        Self::new(&[]).0
+    }
+
+    pub fn builtin_rules() -> Vec<GuardRule> {
+        vec![
+            GuardRule {
+                name: "Recursive delete of root or home".to_string(),
+                reason: "Deletes everything under / or your home directory.".to_string(),
+                command: "rm".to_string(),
+                flags: vec!["-r|-R|--recursive".to_string()],
+                targets: [
+                    "/",
+                    "/*",
+                    "~",
+                    "~/*",
+                    "$HOME",
+                    "$HOME/*",
+                    "${HOME}",
+                    "${HOME}/*",
+                ]
+                .map(str::to_string)
+                .to_vec(),
+                ..GuardRule::default()
+            },
+            GuardRule {
+                name: "Force push to a protected branch".to_string(),
+                reason: "Rewrites shared history on the remote.".to_string(),
+                command: "git".to_string(),
+                subcommand: Some("push".to_string()),
+                flags: vec!["-f|--force|--force-with-lease".to_string()],
+                branches: ["main", "master", "production", "release/*"]
+                    .map(str::to_string)
+                    .to_vec(),
+                ..GuardRule::default()
+            },
+            GuardRule {
+                name: "Drop database".to_string(),
+                reason: "Permanently removes a database or schema.".to_string(),
+                pattern: Some(r"\bdrop\s+(database|schema)\b".to_string()),
+                ..GuardRule::default()
+            },
+            GuardRule {
+                name: "kubectl delete in production".to_string(),
+                reason: "Deletes resources from a production cluster.".to_string(),
+                command: "kubectl".to_string(),
+                subcommand: Some("delete".to_string()),
+                kube_contexts: vec!["*prod*".to_string()],
+                ..GuardRule::default()
+            },
+            GuardRule {
+                name: "Recursive world-writable chmod".to_string(),
+                reason: "Makes every file below the target writable by anyone.".to_string(),
+                command: "chmod".to_string(),
+                flags: vec!["-R|--recursive".to_string()],
+                targets: ["777", "0777", "a+rwx", "ugo+rwx", "a+w", "o+w"]
+                    .map(str::to_string)
+                    .to_vec(),
+                ..GuardRule::default()
+            },
+        ]
+    }
+
+    /// The built-in rules with each config applied in order, so later
+    /// configs override earlier ones. Rules whose pattern fails to compile
+    /// are skipped and returned as errors.
+    pub fn new(configs: &[&GuardConfig]) -> (Self, Vec<String>) {
+        if configs.iter().any(|config| !config.enabled) {
+            return (Self::default(), Vec::new());
+        }
+        let mut rules = Self::builtin_rules();
+        for config in configs {
+            rules.retain(|rule| !config.disabled.contains(&rule.name));
+            for rule in &config.rules {
+                rules.retain(|existing| existing.name != rule.name);
+                rules.push(rule.clone());
+            }
+        }
+
+        let mut errors = Vec::new();
+        let rules = rules
+            .into_iter()
+            .filter_map(|rule| compile_rule(rule).map_err(|err| errors.push(err)).ok())
+            .collect();
+        (Self { rules }, errors)
+    }
+
+    /// The guard for commands run in `cwd`: `global` plus the rules of the
+    /// project's [`PROJECT_GUARD_FILE`], if there is one.
+    pub fn for_project(global: &GuardConfig, cwd: &Path) -> (Self, Vec<String>) {
+        let (mut guard, mut errors) = Self::new(&[global]);
+        if !global.enabled {
+            return (guard, errors);
+        }
+        match load_project_config(cwd) {
+            Ok(Some((_, project))) => {
+                for rule in project.rules {
+                    if guard
+                        .rules
+                        .iter()
+                        .any(|existing| existing.rule.name == rule.name)
+                    {
+                        errors.push(format!(
+                            "{}: a project rule cannot replace an existing rule",
+                            rule.name
+                        ));
+                        continue;
+                    }
+                    match compile_rule(rule) {
+                        Ok(rule) => guard.rules.push(rule),
+                        Err(err) => errors.push(err),
+                    }
+                }
+            }
+            Ok(None) => {}
+            Err(err) => errors.push(format!("{err:#}")),
+        }
+        (guard, errors)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The rules `line` trips, once each, in rule order.
+    pub fn check(&self, line: &str, context: &GuardContext) -> Vec<GuardMatch> {
+        let tokens = tokenize(line);
+        let commands = split_commands(&tokens);
+        self.rules
+            .iter()
+            .filter_map(|compiled| {
+                let words = commands
+                    .iter()
+                    .find(|words| rule_matches(compiled, line, words, context))?;
+                let text = &line[words[0].start..words[words.len() - 1].end];
+                Some(GuardMatch {
+                    rule: compiled.rule.name.clone(),
+                    reason: compiled.rule.reason.clone(),
+                    command: text.to_string(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// The nearest [`PROJECT_GUARD_FILE`] at or above `cwd`.
+pub fn project_guard_path(cwd: &Path) -> Option<PathBuf> {
+    cwd.ancestors()
+        .map(|dir| dir.join(PROJECT_GUARD_FILE))
+        .find(|path| path.is_file())
+}
+
+pub fn load_project_config(cwd: &Path) -> Result<Option<(PathBuf, ProjectGuardConfig)>> {
+    let Some(path) = project_guard_path(cwd) else {
+        return Ok(None);
+    };
+    let contents = std::fs::read_to_string(&path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let config = serde_json::from_str(&contents)
+        .with_context(|| format!("failed to parse {}", path.display()))?;
+    Ok(Some((path, config)))
+}
+
+/// The guard for the last working directory, so the project file is not
+/// looked up and parsed again for every command. It is rebuilt when the
+/// directory or the user's config changes, or the project file is edited.
+#[derive(Debug, Default)]
+pub struct GuardCache {
+    entry: Option<CacheEntry>,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    cwd: PathBuf,
+    global: GuardConfig,
+    project: Option<(PathBuf, Option<SystemTime>)>,
+    guard: CommandGuard,
+}
+
+impl GuardCache {
+    pub fn guard(&mut self, global: &GuardConfig, cwd: &Path) -> &CommandGuard {
+        let fresh = self.entry.as_ref().is_some_and(|entry| {
+            entry.cwd == cwd
+                && entry.global == *global
+                && entry
+                    .project
+                    .as_ref()
+                    .is_none_or(|(path, modified)| modified_time(path) == *modified)
+        });
+        if !fresh {
+            let project = project_guard_path(cwd).map(|path| {
+                let modified = modified_time(&path);
+                (path, modified)
+            });
+            let (guard, _) = CommandGuard::for_project(global, cwd);
+            self.entry = Some(CacheEntry {
+                cwd: cwd.to_path_buf(),
+                global: global.clone(),
+                project,
+                guard,
+            });
+        }
+        &self
+            .entry
+            .as_ref()
+            .expect("guard cache entry was just filled")
+            .guard
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+/// `current-context` from the first kubeconfig file, as kubectl reads it.
+pub fn current_kube_context() -> Option<String> {
+    let path = match std::env::var_os("KUBECONFIG") {
+        Some(paths) => std::env::split_paths(&paths).find(|path| path.is_file())?,
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".kube/config"),
+    };
+    let contents = std::fs::read_to_string(path).ok()?;
+    contents.lines().find_map(|line| {
+        let value = line.strip_prefix("current-context:")?.trim();
+        let value = value.trim_matches(|ch| ch == '"' || ch == '\'');
+        (!value.is_empty()).then(|| value.to_string())
+    })
+}
+
+fn compile_rule(rule: GuardRule) -> std::result::Result<CompiledRule, String> {
+    let pattern = match rule
+        .pattern
+        .as_deref()
+        .map(|pattern| RegexBuilder::new(pattern).case_insensitive(true).build())
+    {
+        Some(Ok(pattern)) => Some(pattern),
+        Some(Err(err)) => return Err(format!("{}: {err}", rule.name)),
+        None => None,
+    };
+    Ok(CompiledRule { rule, pattern })
+}
+
+fn rule_matches(
+    compiled: &CompiledRule,
+    line: &str,
+    words: &[Token],
+    context: &GuardContext,
+) -> bool {
+    let rule = &compiled.rule;
+    let Some((program, args)) = program_and_args(words) else {
+        return false;
+    };
+    if !rule.command.is_empty() && rule.command != program {
+        return false;
+    }
+    let positionals = match &rule.subcommand {
+        Some(subcommand) => match subcommand_index(args, subcommand) {
+            Some(index) => positionals(&args[index + 1..]),
+            None => return false,
+        },
+        None => positionals(args),
+    };
+    if !rule
+        .flags
+        .iter()
+        .all(|alternatives| alternatives.split('|').any(|flag| has_flag(args, flag)))
+    {
+        return false;
+    }
+    if !rule.targets.is_empty()
+        && !positionals.iter().any(|arg| {
+            let trimmed = arg.trim_end_matches('/');
+            let arg = if trimmed.is_empty() { "/" } else { trimmed };
+            rule.targets
+                .iter()
+                .any(|target| target.trim_end_matches('/') == arg || target == arg)
+        })
+    {
+        return false;
+    }
+    if !rule.branches.is_empty() {
+        let branches = pushed_branches(&positionals, context.git_branch.as_deref());
+        if !branches
+            .iter()
+            .any(|branch| rule.branches.iter().any(|glob| glob_match(glob, branch)))
+        {
+            return false;
+        }
+    }
+    if !rule.kube_contexts.is_empty() {
+        let Some(kube_context) =
+            option_value(args, "--context").or(context.kube_context.as_deref())
+        else {
+            return false;
+        };
+        if !rule
+            .kube_contexts
+            .iter()
+            .any(|glob| glob_match(glob, kube_context))
+        {
+            return false;
+        }
+    }
+    if let Some(pattern) = &compiled.pattern {
+        let text = &line[words[0].start..words[words.len() - 1].end];
+        if !pattern.is_match(text) {
+            return false;
+        }
+    }
+    true
+}
+
+/// The program's name, skipping variable assignments and wrappers such as
+/// `sudo`, and the words after it.
+fn program_and_args(words: &[Token]) -> Option<(&str, &[Token])> {
+    let mut index = 0;
+    while index < words.len() {
+        let word = &words[index];
+        if word.kind != TokenKind::Word {
+            index += 1;
+            continue;
+        }
+        let value = word.value.as_str();
+        if is_assignment(value) {
+            index += 1;
+            continue;
+        }
+        let program = value.rsplit('/').next().unwrap_or(value);
+        if WRAPPERS.contains(&program) {
+            index += 1;
+            while words
+                .get(index)
+                .is_some_and(|word| word.value.starts_with('-'))
+            {
+                index += 1;
+            }
+            continue;
+        }
+        return Some((program, &words[index + 1..]));
+    }
+    None
+}
+
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=').is_some_and(|(name, _)| {
+        !name.is_empty()
+            && !name.starts_with(|ch: char| ch.is_ascii_digit())
+            && name
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+    })
+}
+
+/// Where `subcommand` sits in `args`. Leading words before it must be
+/// flags or the values of flags, as in `git -C repo push`.
+fn subcommand_index(args: &[Token], subcommand: &str) -> Option<usize> {
+    let mut previous_takes_value = false;
+    for (index, arg) in args.iter().enumerate() {
+        let value = arg.value.as_str();
+        if value == subcommand {
+            return Some(index);
+        }
+        if value.starts_with('-') {
+            previous_takes_value = !value.contains('=');
+        } else if previous_takes_value {
+            previous_takes_value = false;
+        } else {
+            return None;
+        }
+    }
+    None
+}
+
+fn positionals(args: &[Token]) -> Vec<&str> {
+    args.iter()
+        .map(|arg| arg.value.as_str())
+        .filter(|value| !value.starts_with('-'))
+        .collect()
+}
+
+fn has_flag(args: &[Token], flag: &str) -> bool {
+    let short = flag
+        .strip_prefix('-')
+        .filter(|letter| !letter.starts_with('-') && letter.chars().count() == 1);
+    args.iter().any(|arg| {
+        let value = arg.value.as_str();
+        if value == flag
+            || value
+                .strip_prefix(flag)
+                .is_some_and(|rest| rest.starts_with('='))
+        {
+            return true;
+        }
+        match (short, value.strip_prefix('-')) {
+            (Some(letter), Some(combined)) if !combined.starts_with('-') => {
+                combined.contains(letter)
+            }
+            _ => false,
+        }
+    })
+}
+
+fn option_value<'a>(args: &'a [Token], option: &str) -> Option<&'a str> {
+    args.iter().enumerate().find_map(|(index, arg)| {
+        let value = arg.value.as_str();
+        if value == option {
+            args.get(index + 1).map(|next| next.value.as_str())
+        } else {
+            value.strip_prefix(option)?.strip_prefix('=')
+        }
+    })
+}
+
+/// Branches a `git push` updates: the destination of each refspec after the
+/// remote, or the checked-out branch when there are none.
+fn pushed_branches<'a>(positionals: &[&'a str], current: Option<&'a str>) -> Vec<&'a str> {
+    let refspecs = positionals.get(1..).unwrap_or_default();
+    if refspecs.is_empty() {
+        return current.into_iter().collect();
+    }
+    refspecs
+        .iter()
+        .filter_map(|refspec| {
+            let refspec = refspec.trim_start_matches('+');
+            let destination = refspec.rsplit(':').next().unwrap_or(refspec);
+            let destination = destination
+                .strip_prefix("refs/heads/")
+                .unwrap_or(destination);
+            if destination == "HEAD" {
+                current
+            } else {
+                Some(destination)
+            }
+        })
+        .collect()
+}
+
+/// Matches `text` against `pattern`, where `*` stands for any run of chars.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let Some(first) = parts.next() else {
+        return text.is_empty();
+    };
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
@@ -0,0 +1,132 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    Word,
+    Operator,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// Source text of the token, quotes included.
+    pub raw: String,
+    /// Word value with quotes and escapes removed.
+    pub value: String,
+    /// Byte offset of the token in the command line.
+    pub start: usize,
+    /// Byte offset one past the end of the token.
+    pub end: usize,
+    /// Set when a quote opened inside the token is never closed.
+    pub unterminated_quote: bool,
+}
+
+const OPERATORS: [&str; 7] = ["&&", "||", ";;", "|", ";", "&", "\n"];
+
+/// Splits a command line into shell words and control operators.
+///
+/// This is a lexer for editor features, not a shell: it understands quotes,
+/// backslash escapes and the operators that separate commands, and keeps byte
+/// offsets so callers can map tokens back onto the input.
+pub fn tokenize(line: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = line.char_indices().peekable();
+
+    while let Some(&(start, ch)) = chars.peek() {
+        if ch.is_whitespace() && ch != '\n' {
+            chars.next();
+            continue;
+        }
+
+        if let Some(op) = OPERATORS.iter().find(|op| line[start..].starts_with(**op)) {
+            for _ in 0..op.chars().count() {
+                chars.next();
+            }
+            tokens.push(Token {
+                kind: TokenKind::Operator,
+                raw: (*op).to_string(),
+                value: (*op).to_string(),
+                start,
+                end: start + op.len(),
+                unterminated_quote: false,
+            });
+            continue;
+        }
+
+        let mut value = String::new();
+        let mut quote: Option<char> = None;
+        let mut end = start;
+        while let Some(&(index, ch)) = chars.peek() {
+            match quote {
+                Some(q) if ch == q => {
+                    quote = None;
+                }
+                Some('"') if ch == '\\' => {
+                    chars.next();
+                    end = index + ch.len_utf8();
+                    if let Some(&(next_index, next)) = chars.peek() {
+                        if !matches!(next, '"' | '\\' | '$' | '`') {
+                            value.push('\\');
+                        }
+                        value.push(next);
+                        end = next_index + next.len_utf8();
+                        chars.next();
+                    }
+                    continue;
+                }
+                Some(_) => value.push(ch),
+                None => {
+                    if ch.is_whitespace()
+                        || OPERATORS.iter().any(|op| line[index..].starts_with(*op))
+                    {
+                        break;
+                    }
+                    match ch {
+                        '\'' | '"' => quote = Some(ch),
+                        '\\' => {
+                            chars.next();
+                            end = index + ch.len_utf8();
+                            if let Some(&(next_index, next)) = chars.peek() {
+                                value.push(next);
+                                end = next_index + next.len_utf8();
+                                chars.next();
+                            }
+                            continue;
+                        }
+                        _ => value.push(ch),
+                    }
+                }
+            }
+            end = index + ch.len_utf8();
+            chars.next();
+        }
+
+        tokens.push(Token {
+            kind: TokenKind::Word,
+            raw: line[start..end].to_string(),
+            value,
+            start,
+            end,
+            unterminated_quote: quote.is_some(),
+        });
+    }
+
+    tokens
+}
+
+/// Returns the words of the last simple command, i.e. every token after the
+/// final `|`, `&&`, `;` or similar operator.
+pub fn last_command_words(tokens: &[Token]) -> &[Token] {
+    let start = tokens
+        .iter()
+        .rposition(|token| token.kind == TokenKind::Operator)
+        .map(|index| index + 1)
+        .unwrap_or(0);
+    &tokens[start..]
+}
+
+/// Splits tokens into the simple commands separated by control operators.
+pub fn split_commands(tokens: &[Token]) -> Vec<&[Token]> {
+    tokens
+        .split(|token| token.kind == TokenKind::Operator)
+        .filter(|words| !words.is_empty())
+        .collect()
+}
@@ -0,0 +1,144 @@
+use regex::Regex;
+use std::sync::OnceLock;
+
+#[cfg(target_os = "linux")]
+use crate::terminal::processes::{process_parents, tree_order};
+
+/// A local URL a command printed, such as `http://localhost:3000/`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServerUrl {
+    /// The URL to open. Wildcard hosts such as `0.0.0.0` become `localhost`.
+    pub url: String,
+    pub port: u16,
+}
+
+/// A TCP port something in the process tree listens on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ListeningPort {
+    pub port: u16,
+    pub pid: u32,
+}
+
+/// Local server URLs in a line of output. A line with no URL but a phrase
+/// such as `listening on port 8080` gives `http://localhost:8080`.
+pub fn find_server_urls(line: &str) -> Vec<ServerUrl> {
+    let mut urls: Vec<ServerUrl> = Vec::new();
+    for capture in url_regex().captures_iter(line) {
+        let Ok(port) = capture["port"].parse::<u16>() else {
+            continue;
+        };
+        let host = match &capture["host"] {
+            "0.0.0.0" | "[::]" => "localhost",
+            host => host,
+        };
+        let path = capture
+            .name("path")
+            .map_or("", |path| path.as_str())
+            .trim_end_matches(['.', ',', ';', ':']);
+        let url = format!("{}://{host}:{port}{path}", &capture["scheme"]);
+        if urls.iter().all(|seen| seen.port != port) {
+            urls.push(ServerUrl { url, port });
+        }
+    }
+    if urls.is_empty()
+        && let Some(capture) = port_phrase_regex().captures(line)
+        && let Ok(port) = capture[1].parse::<u16>()
+    {
+        urls.push(ServerUrl {
+            url: format!("http://localhost:{port}"),
+            port,
+        });
+    }
+    urls
+}
+
+fn url_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(concat!(
+            r"(?i)\b(?P<scheme>https?)://",
+            r"(?P<host>localhost|127\.0\.0\.1|0\.0\.0\.0|\[::1?\])",
+            r":(?P<port>\d{2,5})",
+            r#"(?P<path>/[^\s'"<>)\]]*)?"#,
+        ))
+        .expect("server url pattern is valid")
+    })
+}
+
+fn port_phrase_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(
+            r"(?i)\b(?:listening|running|serving|started|server)\b.*?\bport\s*:?\s*(\d{2,5})\b",
+        )
+        .expect("port phrase pattern is valid")
+    })
+}
+
+/// Ports in the `LISTEN` state in the text of `/proc/net/tcp` or
+/// `/proc/net/tcp6`, with the inode of their socket.
+pub fn parse_listening_sockets(table: &str) -> Vec<(u16, u64)> {
+    table
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            // sl local_address rem_address st tx:rx tr:when retrnsmt uid timeout inode
+            if fields.get(3) != Some(&"0A") {
+                return None;
+            }
+            let port = fields.get(1)?.rsplit(':').next()?;
+            let port = u16::from_str_radix(port, 16).ok()?;
+            let inode = fields.get(9)?.parse().ok()?;
+            Some((port, inode))
+        })
+        .collect()
+}
+
+/// TCP ports listened on by `root` or a process it started, or `None` where
+/// the system does not say.
+#[cfg(target_os = "linux")]
+pub fn listening_ports(root: u32) -> Option<Vec<ListeningPort>> {
+    use std::collections::HashMap;
+    use std::fs;
+
+    let mut sockets: HashMap<u64, u32> = HashMap::new();
+    for (pid, _) in tree_order(&process_parents(), root) {
+        let Ok(fds) = fs::read_dir(format!("/proc/{pid}/fd")) else {
+            continue;
+        };
+        for fd in fds.flatten() {
+            let Ok(target) = fs::read_link(fd.path()) else {
+                continue;
+            };
+            if let Some(inode) = target
+                .to_str()
+                .and_then(|target| target.strip_prefix("socket:["))
+                .and_then(|rest| rest.strip_suffix(']'))
+                .and_then(|inode| inode.parse().ok())
+            {
+                sockets.insert(inode, pid);
+            }
+        }
+    }
+
+    let mut ports: Vec<ListeningPort> = Vec::new();
+    for table in ["/proc/net/tcp", "/proc/net/tcp6"] {
+        let Ok(text) = fs::read_to_string(table) else {
+            continue;
+        };
+        for (port, inode) in parse_listening_sockets(&text) {
+            if let Some(&pid) = sockets.get(&inode)
+                && ports.iter().all(|seen| seen.port != port)
+            {
+                ports.push(ListeningPort { port, pid });
+            }
+        }
+    }
+    Some(ports)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn listening_ports(_root: u32) -> Option<Vec<ListeningPort>> {
+    None
+}
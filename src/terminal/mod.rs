@@ -1,12 +1,24 @@
+pub mod ansi;
+pub mod asciicast;
+pub mod command_guard;
+pub mod command_line;
+pub mod dev_server;
+pub mod mouse;
+pub mod problems;
+pub mod processes;
+pub mod secure_input;
+pub mod shell_integration;
+pub mod test_results;
+
 use anyhow::Result;
 use portable_pty::{Child, CommandBuilder, MasterPty, PtySize, native_pty_system};
 use std::io::{Read, Write};
 use std::path::Path;
 
 pub struct TerminalPty {
-    _master: Box<dyn MasterPty + Send>,
+    master: Box<dyn MasterPty + Send>,
     writer: Box<dyn Write + Send>,
-    _child: Box<dyn Child + Send + Sync>,
+    child: Box<dyn Child + Send + Sync>,
 }
 
 impl TerminalPty {
@@ -31,7 +43,9 @@ impl TerminalPty {
             c
         } else {
             let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string());
-            CommandBuilder::new(shell)
+            let mut c = CommandBuilder::new(&shell);
+            shell_integration::configure(&mut c, &shell);
+            c
         };
         if let Some(dir) = cwd {
             cmd.cwd(dir);
@@ -45,16 +59,60 @@ impl TerminalPty {
 
         Ok((
             Self {
-                _master: master,
+                master,
                 writer,
-                _child: child,
+                child,
             },
             reader,
         ))
     }
 
+    /// Pid of the shell.
+    pub fn process_id(&self) -> Option<u32> {
+        self.child.process_id()
+    }
+
+    /// Whether a job other than the shell holds the terminal, or `None`
+    /// where that cannot be told.
+    #[cfg(unix)]
+    pub fn has_foreground_job(&self) -> Option<bool> {
+        let shell = self.child.process_id()?;
+        let leader = self.master.process_group_leader()?;
+        Some(u32::try_from(leader).ok()? != shell)
+    }
+
+    #[cfg(not(unix))]
+    pub fn has_foreground_job(&self) -> Option<bool> {
+        None
+    }
+
     pub fn write(&mut self, data: &[u8]) -> Result<()> {
         self.writer.write_all(data)?;
         Ok(())
     }
+
+    /// True while the program on the terminal reads a line with echo off, as
+    /// password prompts do. Shells and full-screen programs turn echo off too
+    /// but also leave canonical mode, so they do not count.
+    #[cfg(unix)]
+    pub fn reads_secret(&self) -> bool {
+        let Some(fd) = self.master.as_raw_fd() else {
+            return false;
+        };
+        let mut termios = std::mem::MaybeUninit::<libc::termios>::uninit();
+        // SAFETY: `fd` is the open pty master and `termios` is only read
+        // after tcgetattr reports that it filled it in.
+        let termios = unsafe {
+            if libc::tcgetattr(fd, termios.as_mut_ptr()) != 0 {
+                return false;
+            }
+            termios.assume_init()
+        };
+        termios.c_lflag & libc::ECHO == 0 && termios.c_lflag & libc::ICANON != 0
+    }
+
+    #[cfg(not(unix))]
+    pub fn reads_secret(&self) -> bool {
+        false
+    }
 }
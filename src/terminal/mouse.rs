@@ -0,0 +1,137 @@
+/// Which mouse events the running program asked for with DECSET 1000, 1002
+/// or 1003.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MouseTracking {
+    #[default]
+    Off,
+    /// 1000: presses, releases and the wheel.
+    Clicks,
+    /// 1002: also motion while a button is held.
+    Drags,
+    /// 1003: all motion.
+    Motion,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PointerButton {
+    Left,
+    Middle,
+    Right,
+}
+
+impl PointerButton {
+    fn code(self) -> u8 {
+        match self {
+            Self::Left => 0,
+            Self::Middle => 1,
+            Self::Right => 2,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MouseInput {
+    Press(PointerButton),
+    Release(PointerButton),
+    /// Motion, with the button held down if any.
+    Move(Option<PointerButton>),
+    WheelUp,
+    WheelDown,
+}
+
+/// A mouse event over the terminal grid. Cells are 1-based, as on the wire.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MouseReport {
+    pub input: MouseInput,
+    pub column: u16,
+    pub row: u16,
+    pub shift: bool,
+    pub alt: bool,
+    pub control: bool,
+}
+
+/// The mouse reporting state set by the program through private modes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MouseProtocol {
+    pub tracking: MouseTracking,
+    /// 1006: SGR encoding, without the 223 cell limit of the legacy one.
+    pub sgr: bool,
+}
+
+impl MouseProtocol {
+    /// Applies DECSET (`enabled`) or DECRST for one private mode. Returns
+    /// whether the mode is a mouse mode.
+    pub fn set_mode(&mut self, mode: u16, enabled: bool) -> bool {
+        let tracking = match mode {
+            1000 => MouseTracking::Clicks,
+            1002 => MouseTracking::Drags,
+            1003 => MouseTracking::Motion,
+            1006 => {
+                self.sgr = enabled;
+                return true;
+            }
+            _ => return false,
+        };
+        if enabled {
+            self.tracking = tracking;
+        } else if self.tracking == tracking {
+            self.tracking = MouseTracking::Off;
+        }
+        true
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.tracking != MouseTracking::Off
+    }
+
+    /// The bytes to send for `report`, or `None` when the current mode does
+    /// not ask for it.
+    pub fn encode(&self, report: MouseReport) -> Option<Vec<u8>> {
+        let (code, release) = match report.input {
+            MouseInput::Press(button) => (button.code(), false),
+            MouseInput::Release(button) => (button.code(), true),
+            MouseInput::Move(Some(button)) if self.tracking >= MouseTracking::Drags => {
+                (button.code() + 32, false)
+            }
+            MouseInput::Move(None) if self.tracking == MouseTracking::Motion => (3 + 32, false),
+            MouseInput::Move(_) => return None,
+            MouseInput::WheelUp => (64, false),
+            MouseInput::WheelDown => (65, false),
+        };
+        if !self.is_active() {
+            return None;
+        }
+        let mut code = code;
+        if report.shift {
+            code += 4;
+        }
+        if report.alt {
+            code += 8;
+        }
+        if report.control {
+            code += 16;
+        }
+        let column = report.column.max(1);
+        let row = report.row.max(1);
+
+        if self.sgr {
+            let suffix = if release { 'm' } else { 'M' };
+            return Some(format!("\x1b[<{code};{column};{row}{suffix}").into_bytes());
+        }
+        // The legacy encoding has no button for releases and stops at 223.
+        if release {
+            code = (code & !0b11) | 3;
+        }
+        if column > 223 || row > 223 {
+            return None;
+        }
+        Some(vec![
+            0x1b,
+            b'[',
+            b'M',
+            32 + code,
+            32 + column as u8,
+            32 + row as u8,
+        ])
+    }
+}
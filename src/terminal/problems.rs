@@ -0,0 +1,317 @@
+use regex::Regex;
+use serde_json::Value;
+use std::sync::OnceLock;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Severity {
+    fn from_label(label: &str) -> Option<Self> {
+        match label.to_ascii_lowercase().as_str() {
+            "error" | "fatal error" | "error: internal compiler error" => Some(Self::Error),
+            "warning" => Some(Self::Warning),
+            "note" | "help" | "info" => Some(Self::Note),
+            _ => None,
+        }
+    }
+}
+
+/// A diagnostic found in command output. `file` is as printed, usually
+/// relative to the directory the command ran in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Problem {
+    pub severity: Severity,
+    pub message: String,
+    pub file: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+/// Diagnostics from rustc and cargo (human and JSON output), tsc, eslint,
+/// gcc and clang, go and pytest, in the order printed. Repeats are dropped.
+pub fn parse_problems(lines: &[String]) -> Vec<Problem> {
+    let mut parser = Parser::default();
+    for line in lines {
+        parser.line(line);
+    }
+    parser.problems
+}
+
+#[derive(Default)]
+struct Parser {
+    problems: Vec<Problem>,
+    /// A rustc header waiting for its `-->` location.
+    rustc_header: Option<(Severity, String)>,
+    /// The file eslint lists problems under.
+    eslint_file: Option<String>,
+    /// The first `E   ` line of a pytest failure.
+    pytest_error: Option<String>,
+}
+
+impl Parser {
+    fn push(&mut self, problem: Problem) {
+        if !self.problems.contains(&problem) {
+            self.problems.push(problem);
+        }
+    }
+
+    fn line(&mut self, line: &str) {
+        let trimmed = line.trim_end();
+        if trimmed.trim().is_empty() {
+            self.eslint_file = None;
+            return;
+        }
+        if trimmed.starts_with('{') {
+            if let Some(problem) = parse_json_diagnostic(trimmed) {
+                self.push(problem);
+            }
+            return;
+        }
+
+        if let Some(captures) = rustc_header_regex().captures(trimmed) {
+            self.rustc_header = Severity::from_label(&captures["severity"])
+                .map(|severity| (severity, captures["message"].to_string()));
+            return;
+        }
+        if let Some(captures) = rustc_location_regex().captures(trimmed) {
+            if let Some((severity, message)) = self.rustc_header.take() {
+                self.push(Problem {
+                    severity,
+                    message,
+                    file: captures["file"].to_string(),
+                    line: captures["line"].parse().ok(),
+                    column: captures["column"].parse().ok(),
+                });
+            }
+            return;
+        }
+
+        if let Some(problem) = parse_located_line(trimmed) {
+            self.eslint_file = None;
+            self.push(problem);
+            return;
+        }
+
+        if let Some(captures) = eslint_item_regex().captures(trimmed)
+            && let Some(file) = self.eslint_file.clone()
+        {
+            let message = match captures.name("rule") {
+                Some(rule) => format!("{} ({})", &captures["message"], rule.as_str()),
+                None => captures["message"].to_string(),
+            };
+            self.push(Problem {
+                severity: Severity::from_label(&captures["severity"]).unwrap_or(Severity::Error),
+                message,
+                file,
+                line: captures["line"].parse().ok(),
+                column: captures["column"].parse().ok(),
+            });
+            return;
+        }
+
+        if let Some(message) = trimmed.trim_start().strip_prefix("E ") {
+            if self.pytest_error.is_none() {
+                self.pytest_error = Some(message.trim().to_string());
+            }
+            return;
+        }
+        if let Some(captures) = pytest_location_regex().captures(trimmed) {
+            let exception = &captures["exception"];
+            let message = match self.pytest_error.take() {
+                Some(detail) if !detail.starts_with(exception) => {
+                    format!("{exception}: {detail}")
+                }
+                Some(detail) => detail,
+                None => exception.to_string(),
+            };
+            self.push(Problem {
+                severity: Severity::Error,
+                message,
+                file: captures["file"].to_string(),
+                line: captures["line"].parse().ok(),
+                column: None,
+            });
+            return;
+        }
+        if let Some(captures) = pytest_summary_regex().captures(trimmed) {
+            let file = captures["file"].to_string();
+            // The traceback already gave this failure a line.
+            if self.problems.iter().all(|problem| problem.file != file) {
+                self.push(Problem {
+                    severity: Severity::Error,
+                    message: captures
+                        .name("message")
+                        .map_or("failed", |message| message.as_str())
+                        .to_string(),
+                    file,
+                    line: None,
+                    column: None,
+                });
+            }
+            return;
+        }
+
+        if looks_like_eslint_file(trimmed) {
+            self.eslint_file = Some(trimmed.to_string());
+        }
+    }
+}
+
+/// A diagnostic that names its file on the same line: gcc and clang, tsc,
+/// go, and rustc's short format.
+fn parse_located_line(line: &str) -> Option<Problem> {
+    if let Some(captures) = compiler_regex().captures(line) {
+        return Some(Problem {
+            severity: Severity::from_label(&captures["severity"])?,
+            message: captures["message"].to_string(),
+            file: captures["file"].to_string(),
+            line: captures["line"].parse().ok(),
+            column: captures
+                .name("column")
+                .and_then(|column| column.as_str().parse().ok()),
+        });
+    }
+    if let Some(captures) = tsc_regex().captures(line) {
+        // `file(line,col): ...` from plain tsc, `file:line:col - ...` with `--pretty`.
+        let number = |name: &str, fallback: &str| {
+            captures
+                .name(name)
+                .or_else(|| captures.name(fallback))
+                .and_then(|value| value.as_str().parse().ok())
+        };
+        return Some(Problem {
+            severity: Severity::from_label(&captures["severity"])?,
+            message: captures["message"].to_string(),
+            file: captures["file"].to_string(),
+            line: number("line", "line2"),
+            column: number("column", "column2"),
+        });
+    }
+    let captures = go_regex().captures(line)?;
+    Some(Problem {
+        severity: Severity::Error,
+        message: captures["message"].to_string(),
+        file: captures["file"].to_string(),
+        line: captures["line"].parse().ok(),
+        column: captures
+            .name("column")
+            .and_then(|column| column.as_str().parse().ok()),
+    })
+}
+
+/// A line of `cargo --message-format=json` or `rustc --error-format=json`.
+fn parse_json_diagnostic(line: &str) -> Option<Problem> {
+    let value: Value = serde_json::from_str(line).ok()?;
+    let diagnostic = match value.get("reason").and_then(Value::as_str) {
+        Some("compiler-message") => value.get("message")?,
+        Some(_) => return None,
+        None => &value,
+    };
+    let severity = Severity::from_label(diagnostic.get("level")?.as_str()?)?;
+    let message = diagnostic.get("message")?.as_str()?.to_string();
+    let span = diagnostic
+        .get("spans")?
+        .as_array()?
+        .iter()
+        .find(|span| span.get("is_primary").and_then(Value::as_bool) == Some(true))?;
+    let number = |key: &str| {
+        span.get(key)
+            .and_then(Value::as_u64)
+            .and_then(|value| usize::try_from(value).ok())
+    };
+    Some(Problem {
+        severity,
+        message,
+        file: span.get("file_name")?.as_str()?.to_string(),
+        line: number("line_start"),
+        column: number("column_start"),
+    })
+}
+
+fn looks_like_eslint_file(line: &str) -> bool {
+    !line.starts_with(char::is_whitespace)
+        && (line.starts_with('/') || line.starts_with("./") || line.chars().nth(1) == Some(':'))
+        && line
+            .rsplit(['/', '\\'])
+            .next()
+            .is_some_and(|name| name.contains('.') && !name.contains(' '))
+}
+
+fn rustc_header_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"^(?P<severity>error|warning)(?:\[\w+\])?: (?P<message>.+)$")
+            .expect("rustc header pattern is valid")
+    })
+}
+
+fn rustc_location_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"^\s*--> (?P<file>.+?):(?P<line>\d+):(?P<column>\d+)$")
+            .expect("rustc location pattern is valid")
+    })
+}
+
+fn compiler_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(concat!(
+            r"^(?P<file>[^\s:][^:]*?):(?P<line>\d+):(?:(?P<column>\d+):)?\s*",
+            r"(?P<severity>fatal error|error|warning|note)(?:\[\w+\])?:\s*(?P<message>.+)$",
+        ))
+        .expect("compiler pattern is valid")
+    })
+}
+
+fn tsc_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(concat!(
+            r"^(?P<file>[^\s(:]+)(?:\((?P<line>\d+),(?P<column>\d+)\):|:(?P<line2>\d+):(?P<column2>\d+) -)",
+            r"\s*(?P<severity>error|warning) (?P<message>TS\d+: .+)$",
+        ))
+        .expect("tsc pattern is valid")
+    })
+}
+
+fn go_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(
+            r"^\s*(?P<file>[^\s:]+\.go):(?P<line>\d+):(?:(?P<column>\d+):)?\s+(?P<message>.+)$",
+        )
+        .expect("go pattern is valid")
+    })
+}
+
+fn eslint_item_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(concat!(
+            r"^\s+(?P<line>\d+):(?P<column>\d+)\s+(?P<severity>error|warning)\s+",
+            r"(?P<message>.+?)(?:\s{2,}(?P<rule>[@\w/-]+))?$",
+        ))
+        .expect("eslint pattern is valid")
+    })
+}
+
+fn pytest_location_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"^(?P<file>[^\s:]+\.py):(?P<line>\d+): (?P<exception>\w+(?:Error|Exception|Failed|Interrupt|Exit)\w*)$")
+            .expect("pytest location pattern is valid")
+    })
+}
+
+fn pytest_summary_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"^(?:FAILED|ERROR) (?P<file>[^\s:]+\.py)(?:::\S+)?(?: - (?P<message>.+))?$")
+            .expect("pytest summary pattern is valid")
+    })
+}
@@ -0,0 +1,216 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// The fields of `/proc/<pid>/stat` the process panel uses.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProcStat {
+    pub name: String,
+    pub ppid: u32,
+    /// User and system time, in clock ticks.
+    pub cpu_ticks: u64,
+    /// When the process started, in clock ticks after boot.
+    pub start_ticks: u64,
+    pub rss_pages: u64,
+}
+
+/// Parses the text of `/proc/<pid>/stat`.
+pub fn parse_stat(text: &str) -> Option<ProcStat> {
+    // The name in parentheses can hold spaces and parentheses of its own.
+    let open = text.find('(')?;
+    let close = text.rfind(')')?;
+    let name = text.get(open + 1..close)?.to_string();
+    let fields: Vec<&str> = text.get(close + 1..)?.split_whitespace().collect();
+    // Numbered from the state, field 3 in proc(5).
+    let field = |index: usize| fields.get(index)?.parse::<u64>().ok();
+    Some(ProcStat {
+        name,
+        ppid: u32::try_from(field(1)?).ok()?,
+        cpu_ticks: field(11)? + field(12)?,
+        start_ticks: field(19)?,
+        rss_pages: field(21)?,
+    })
+}
+
+/// `root` and its descendants depth first, each with its depth under
+/// `root`. Children come in pid order.
+pub fn tree_order(parents: &[(u32, u32)], root: u32) -> Vec<(u32, usize)> {
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    for &(pid, ppid) in parents {
+        if pid != ppid {
+            children.entry(ppid).or_default().push(pid);
+        }
+    }
+    let mut order = Vec::new();
+    let mut stack = vec![(root, 0)];
+    while let Some((pid, depth)) = stack.pop() {
+        order.push((pid, depth));
+        if let Some(kids) = children.get_mut(&pid) {
+            kids.sort_unstable();
+            stack.extend(kids.iter().rev().map(|&kid| (kid, depth + 1)));
+        }
+    }
+    order
+}
+
+/// Pid and parent pid of every process, read from `/proc`.
+#[cfg(target_os = "linux")]
+pub fn process_parents() -> Vec<(u32, u32)> {
+    read_stats()
+        .into_iter()
+        .map(|(pid, stat)| (pid, stat.ppid))
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn process_parents() -> Vec<(u32, u32)> {
+    Vec::new()
+}
+
+#[cfg(target_os = "linux")]
+fn read_stats() -> Vec<(u32, ProcStat)> {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let pid = entry.file_name().to_str()?.parse().ok()?;
+            let stat = std::fs::read_to_string(entry.path().join("stat")).ok()?;
+            Some((pid, parse_stat(&stat)?))
+        })
+        .collect()
+}
+
+/// A process in the panel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    /// Depth under the shell, which is at 0.
+    pub depth: usize,
+    pub command: String,
+    /// Share of one core used since the previous sample.
+    pub cpu_percent: f32,
+    pub memory_bytes: u64,
+    pub runtime: Duration,
+}
+
+/// Reads the process tree of a shell. CPU use is measured between two
+/// calls to [`ProcessSampler::sample`].
+#[derive(Clone, Debug, Default)]
+pub struct ProcessSampler {
+    cpu_ticks: HashMap<u32, u64>,
+    sampled_at: Option<Instant>,
+}
+
+impl ProcessSampler {
+    /// `root` and its descendants, or `None` where `/proc` is not
+    /// available.
+    #[cfg(target_os = "linux")]
+    pub fn sample(&mut self, root: u32) -> Option<Vec<ProcessInfo>> {
+        let stats: HashMap<u32, ProcStat> = read_stats().into_iter().collect();
+        if !stats.contains_key(&root) {
+            return None;
+        }
+        let parents: Vec<(u32, u32)> = stats.iter().map(|(pid, stat)| (*pid, stat.ppid)).collect();
+        // SAFETY: sysconf only reads configuration values.
+        let (ticks_per_second, page_size) = unsafe {
+            (
+                libc::sysconf(libc::_SC_CLK_TCK).max(1) as f64,
+                libc::sysconf(libc::_SC_PAGESIZE).max(1) as u64,
+            )
+        };
+        let uptime = std::fs::read_to_string("/proc/uptime")
+            .ok()
+            .and_then(|text| text.split_whitespace().next()?.parse::<f64>().ok())
+            .unwrap_or_default();
+        let now = Instant::now();
+        let elapsed = self
+            .sampled_at
+            .map(|at| now.duration_since(at).as_secs_f64())
+            .filter(|elapsed| *elapsed > 0.0);
+
+        let mut cpu_ticks = HashMap::new();
+        let processes = tree_order(&parents, root)
+            .into_iter()
+            .filter_map(|(pid, depth)| {
+                let stat = stats.get(&pid)?;
+                cpu_ticks.insert(pid, stat.cpu_ticks);
+                let cpu_percent = match (elapsed, self.cpu_ticks.get(&pid)) {
+                    (Some(elapsed), Some(before)) => {
+                        let used = stat.cpu_ticks.saturating_sub(*before) as f64;
+                        (used / ticks_per_second / elapsed * 100.0) as f32
+                    }
+                    _ => 0.0,
+                };
+                let started = stat.start_ticks as f64 / ticks_per_second;
+                Some(ProcessInfo {
+                    pid,
+                    depth,
+                    command: command_line(pid).unwrap_or_else(|| stat.name.clone()),
+                    cpu_percent,
+                    memory_bytes: stat.rss_pages * page_size,
+                    runtime: Duration::from_secs_f64((uptime - started).max(0.0)),
+                })
+            })
+            .collect();
+        self.cpu_ticks = cpu_ticks;
+        self.sampled_at = Some(now);
+        Some(processes)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn sample(&mut self, _root: u32) -> Option<Vec<ProcessInfo>> {
+        None
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn command_line(pid: u32) -> Option<String> {
+    let raw = std::fs::read(format!("/proc/{pid}/cmdline")).ok()?;
+    let args: Vec<String> = raw
+        .split(|byte| *byte == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect();
+    (!args.is_empty()).then(|| args.join(" "))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProcessSignal {
+    Interrupt,
+    Terminate,
+    Kill,
+}
+
+impl ProcessSignal {
+    pub const ALL: [Self; 3] = [Self::Interrupt, Self::Terminate, Self::Kill];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Interrupt => "INT",
+            Self::Terminate => "TERM",
+            Self::Kill => "KILL",
+        }
+    }
+}
+
+#[cfg(unix)]
+pub fn send_signal(pid: u32, signal: ProcessSignal) -> Result<()> {
+    let pid = libc::pid_t::try_from(pid)?;
+    let signal = match signal {
+        ProcessSignal::Interrupt => libc::SIGINT,
+        ProcessSignal::Terminate => libc::SIGTERM,
+        ProcessSignal::Kill => libc::SIGKILL,
+    };
+    // SAFETY: kill only sends a signal; a stale pid fails with ESRCH.
+    if unsafe { libc::kill(pid, signal) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn send_signal(_pid: u32, _signal: ProcessSignal) -> Result<()> {
+    anyhow::bail!("signals are not supported on this system")
+}
@@ -0,0 +1,27 @@
+const SECRET_WORDS: [&str; 5] = [
+    "password",
+    "passphrase",
+    "passcode",
+    "pin",
+    "verification code",
+];
+
+/// Whether `line`, the unfinished last line of output, asks for a secret:
+/// `[sudo] password for me:`, `Enter passphrase for key '...':`, `PIN:`.
+pub fn is_password_prompt(line: &str) -> bool {
+    let trimmed = line.trim_end();
+    let Some(body) = trimmed
+        .strip_suffix(':')
+        .or_else(|| trimmed.strip_suffix('>'))
+    else {
+        return false;
+    };
+    let lower = body.to_lowercase();
+    SECRET_WORDS.iter().any(|word| {
+        lower.match_indices(word).any(|(index, _)| {
+            let before = lower[..index].chars().next_back();
+            let after = lower[index + word.len()..].chars().next();
+            !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+        })
+    })
+}
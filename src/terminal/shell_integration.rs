@@ -0,0 +1,89 @@
+use anyhow::Result;
+use portable_pty::CommandBuilder;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::acp::storage;
+
+const SHELL_DIR: &str = "shell";
+
+const BASH_RC: &str = r#"# Generated by OrbitShell. Loads the user's bashrc, then reports each
+# command's exit status with OSC 133;D.
+[ -f /etc/bash.bashrc ] && . /etc/bash.bashrc
+[ -f "$HOME/.bashrc" ] && . "$HOME/.bashrc"
+
+__orbitshell_prompt() {
+  local status=$?
+  printf '\033]133;D;%s\007' "$status"
+  return $status
+}
+PROMPT_COMMAND="__orbitshell_prompt${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+"#;
+
+const ZSH_ENV: &str = r#"# Generated by OrbitShell.
+__orbitshell_zdotdir="$ZDOTDIR"
+ZDOTDIR="${ORBITSHELL_USER_ZDOTDIR:-$HOME}"
+[ -f "$ZDOTDIR/.zshenv" ] && source "$ZDOTDIR/.zshenv"
+ZDOTDIR="$__orbitshell_zdotdir"
+unset __orbitshell_zdotdir
+"#;
+
+const ZSH_RC: &str = r#"# Generated by OrbitShell. Loads the user's zshrc, then reports each
+# command's exit status with OSC 133;D.
+ZDOTDIR="${ORBITSHELL_USER_ZDOTDIR:-$HOME}"
+[ -f "$ZDOTDIR/.zshrc" ] && source "$ZDOTDIR/.zshrc"
+
+__orbitshell_precmd() {
+  printf '\033]133;D;%s\007' "$?"
+}
+precmd_functions=(__orbitshell_precmd $precmd_functions)
+"#;
+
+const FISH_INIT: &str = r#"function __orbitshell_postexec --on-event fish_postexec; printf '\e]133;D;%s\a' $status; end"#;
+
+/// Adds the hooks that make the shell report exit statuses. Shells without an
+/// integration, or a failure to write the rc files, leave `cmd` unchanged.
+pub fn configure(cmd: &mut CommandBuilder, shell: &str) {
+    let name = Path::new(shell)
+        .file_stem()
+        .and_then(|name| name.to_str())
+        .unwrap_or(shell);
+    let _ = match name {
+        "bash" => configure_bash(cmd),
+        "zsh" => configure_zsh(cmd),
+        "fish" => {
+            cmd.arg("--init-command");
+            cmd.arg(FISH_INIT);
+            Ok(())
+        }
+        _ => Ok(()),
+    };
+}
+
+fn configure_bash(cmd: &mut CommandBuilder) -> Result<()> {
+    let rc = write_integration_file("bashrc", BASH_RC)?;
+    cmd.arg("--rcfile");
+    cmd.arg(rc);
+    Ok(())
+}
+
+fn configure_zsh(cmd: &mut CommandBuilder) -> Result<()> {
+    write_integration_file("zsh/.zshenv", ZSH_ENV)?;
+    let zshrc = write_integration_file("zsh/.zshrc", ZSH_RC)?;
+    if let Ok(user_zdotdir) = std::env::var("ZDOTDIR") {
+        cmd.env("ORBITSHELL_USER_ZDOTDIR", user_zdotdir);
+    }
+    if let Some(dir) = zshrc.parent() {
+        cmd.env("ZDOTDIR", dir);
+    }
+    Ok(())
+}
+
+fn write_integration_file(name: &str, contents: &str) -> Result<PathBuf> {
+    let path = storage::app_root()?.join(SHELL_DIR).join(name);
+    if fs::read_to_string(&path).ok().as_deref() != Some(contents) {
+        storage::ensure_parent_dir(&path)?;
+        fs::write(&path, contents)?;
+    }
+    Ok(path)
+}
@@ -0,0 +1,576 @@
+use regex::Regex;
+use serde_json::Value;
+use std::sync::OnceLock;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TestRunner {
+    Cargo,
+    Pytest,
+    Jest,
+}
+
+impl TestRunner {
+    /// The runner `command` starts, looking past environment assignments
+    /// and wrappers such as `npx` or `python -m`.
+    pub fn detect(command: &str) -> Option<Self> {
+        let tokens: Vec<&str> = command.split_whitespace().collect();
+        invocation(&tokens).map(|(runner, _)| runner)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Cargo => "cargo test",
+            Self::Pytest => "pytest",
+            Self::Jest => "jest",
+        }
+    }
+}
+
+/// The runner and the index of its first argument.
+fn invocation(tokens: &[&str]) -> Option<(TestRunner, usize)> {
+    let mut index = 0;
+    while let Some(token) = tokens.get(index) {
+        let is_assignment = token
+            .split_once('=')
+            .is_some_and(|(name, _)| !name.is_empty() && !name.starts_with('-'));
+        if is_assignment || matches!(*token, "env" | "time" | "command") {
+            index += 1;
+        } else {
+            break;
+        }
+    }
+    let rest = tokens.get(index..)?;
+    let at = |offset: usize| rest.get(offset).copied();
+    let found = match (at(0)?, at(1), at(2)) {
+        ("cargo", Some("test" | "t"), _) => (TestRunner::Cargo, 2),
+        ("cargo", Some(toolchain), Some(sub))
+            if toolchain.starts_with('+') && matches!(sub, "test" | "t") =>
+        {
+            (TestRunner::Cargo, 3)
+        }
+        ("pytest" | "py.test", _, _) => (TestRunner::Pytest, 1),
+        ("python" | "python3" | "py", Some("-m"), Some("pytest")) => (TestRunner::Pytest, 3),
+        ("uv" | "poetry" | "pipenv", Some("run"), Some("pytest")) => (TestRunner::Pytest, 3),
+        ("jest", _, _) => (TestRunner::Jest, 1),
+        ("npx" | "bunx" | "yarn" | "pnpm", Some("jest"), _) => (TestRunner::Jest, 2),
+        ("npm" | "yarn" | "pnpm" | "bun", Some("test" | "t"), _) => (TestRunner::Jest, 2),
+        ("npm" | "yarn" | "pnpm" | "bun", Some("run"), Some("test")) => (TestRunner::Jest, 3),
+        _ => return None,
+    };
+    Some((found.0, index + found.1))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TestOutcome {
+    Passed,
+    Failed,
+    Ignored,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestCase {
+    /// The name the runner selects the test by: a path for cargo, a node id
+    /// for pytest and the full title for jest.
+    pub name: String,
+    pub outcome: TestOutcome,
+    /// What the runner printed for a failure.
+    pub output: Vec<String>,
+}
+
+/// The results of one test run. Counts come from the runner's summary when
+/// it reports more than the tests listed, as pytest and jest do unless
+/// verbose.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestReport {
+    pub runner: TestRunner,
+    pub tests: Vec<TestCase>,
+    pub passed: usize,
+    pub failed: usize,
+    pub ignored: usize,
+}
+
+impl TestReport {
+    pub fn failures(&self) -> impl Iterator<Item = &TestCase> {
+        self.tests
+            .iter()
+            .filter(|test| test.outcome == TestOutcome::Failed)
+    }
+}
+
+/// Results in the output of a `runner` run, or `None` when it shows no
+/// tests ran.
+pub fn parse_test_report(runner: TestRunner, lines: &[String]) -> Option<TestReport> {
+    let mut report = Collector::default();
+    match runner {
+        TestRunner::Cargo => report.cargo(lines),
+        TestRunner::Pytest => report.pytest(lines),
+        TestRunner::Jest => report.jest(lines),
+    }
+    let listed = |outcome: TestOutcome| {
+        report
+            .tests
+            .iter()
+            .filter(|test| test.outcome == outcome)
+            .count()
+    };
+    let passed = listed(TestOutcome::Passed).max(report.summary[0]);
+    let failed = listed(TestOutcome::Failed).max(report.summary[1]);
+    let ignored = listed(TestOutcome::Ignored).max(report.summary[2]);
+    if passed + failed + ignored == 0 {
+        return None;
+    }
+    Some(TestReport {
+        runner,
+        tests: report.tests,
+        passed,
+        failed,
+        ignored,
+    })
+}
+
+#[derive(Default)]
+struct Collector {
+    tests: Vec<TestCase>,
+    /// Passed, failed and ignored, as summed from the summary lines.
+    summary: [usize; 3],
+}
+
+impl Collector {
+    /// Records `name` or updates its outcome.
+    fn set(&mut self, name: &str, outcome: TestOutcome) -> &mut TestCase {
+        let position = match self.tests.iter().position(|test| test.name == name) {
+            Some(position) => position,
+            None => {
+                self.tests.push(TestCase {
+                    name: name.to_string(),
+                    outcome,
+                    output: Vec::new(),
+                });
+                self.tests.len() - 1
+            }
+        };
+        let test = &mut self.tests[position];
+        test.outcome = outcome;
+        test
+    }
+
+    fn add_summary(&mut self, text: &str) {
+        for capture in count_regex().captures_iter(text) {
+            let Ok(count) = capture[1].parse::<usize>() else {
+                continue;
+            };
+            let slot = match &capture[2] {
+                "passed" => 0,
+                "failed" | "error" | "errors" => 1,
+                _ => 2,
+            };
+            self.summary[slot] += count;
+        }
+    }
+
+    fn cargo(&mut self, lines: &[String]) {
+        let mut capturing: Option<(String, Vec<String>)> = None;
+        let mut captured: Vec<(String, Vec<String>)> = Vec::new();
+        for line in lines {
+            let line = line.trim_end();
+            if line.starts_with('{') {
+                self.libtest_json(line);
+                continue;
+            }
+            let ends_capture = line == "failures:"
+                || line.starts_with("test result:")
+                || cargo_output_regex().is_match(line);
+            if ends_capture && let Some(done) = capturing.take() {
+                captured.push(done);
+            }
+            if let Some(capture) = cargo_output_regex().captures(line) {
+                capturing = Some((capture[1].to_string(), Vec::new()));
+            } else if let Some((_, output)) = capturing.as_mut() {
+                output.push(line.to_string());
+            } else if let Some(capture) = cargo_result_regex().captures(line) {
+                let outcome = match &capture[2] {
+                    "ok" => TestOutcome::Passed,
+                    "FAILED" => TestOutcome::Failed,
+                    _ => TestOutcome::Ignored,
+                };
+                self.set(&capture[1], outcome);
+            } else if let Some(summary) = line.strip_prefix("test result:") {
+                self.add_summary(summary);
+            }
+        }
+        captured.extend(capturing);
+        for (name, output) in captured {
+            if let Some(test) = self.tests.iter_mut().find(|test| test.name == name) {
+                test.output = trim_blank_lines(output);
+            }
+        }
+    }
+
+    /// A line of `cargo test -- -Z unstable-options --format json`.
+    fn libtest_json(&mut self, line: &str) {
+        let Ok(value) = serde_json::from_str::<Value>(line) else {
+            return;
+        };
+        let event = value.get("event").and_then(Value::as_str);
+        match value.get("type").and_then(Value::as_str) {
+            Some("test") => {
+                let Some(name) = value.get("name").and_then(Value::as_str) else {
+                    return;
+                };
+                let outcome = match event {
+                    Some("ok") => TestOutcome::Passed,
+                    Some("failed" | "timeout") => TestOutcome::Failed,
+                    Some("ignored") => TestOutcome::Ignored,
+                    _ => return,
+                };
+                let output = value
+                    .get("stdout")
+                    .and_then(Value::as_str)
+                    .map(|stdout| stdout.lines().map(str::to_string).collect())
+                    .unwrap_or_default();
+                self.set(name, outcome).output = trim_blank_lines(output);
+            }
+            Some("suite") if matches!(event, Some("ok" | "failed")) => {
+                let count = |key: &str| {
+                    value
+                        .get(key)
+                        .and_then(Value::as_u64)
+                        .and_then(|count| usize::try_from(count).ok())
+                        .unwrap_or_default()
+                };
+                self.summary[0] += count("passed");
+                self.summary[1] += count("failed");
+                self.summary[2] += count("ignored");
+            }
+            _ => {}
+        }
+    }
+
+    fn pytest(&mut self, lines: &[String]) {
+        let mut section: Option<(String, Vec<String>)> = None;
+        let mut sections: Vec<(String, Vec<String>)> = Vec::new();
+        for line in lines {
+            let line = line.trim_end();
+            if let Some(capture) = pytest_section_regex().captures(line) {
+                sections.extend(section.take());
+                section = Some((capture[1].to_string(), Vec::new()));
+                continue;
+            }
+            if line.starts_with("==") {
+                sections.extend(section.take());
+                if let Some(capture) = pytest_summary_regex().captures(line) {
+                    self.add_summary(&capture[1]);
+                }
+                continue;
+            }
+            if let Some((_, output)) = section.as_mut() {
+                output.push(line.to_string());
+            } else if let Some(capture) = pytest_result_regex().captures(line) {
+                let outcome = match &capture[2] {
+                    "PASSED" | "XPASS" => TestOutcome::Passed,
+                    "FAILED" | "ERROR" => TestOutcome::Failed,
+                    _ => TestOutcome::Ignored,
+                };
+                self.set(&capture[1], outcome);
+            } else if let Some(capture) = pytest_failed_regex().captures(line) {
+                self.set(&capture[1], TestOutcome::Failed);
+            }
+        }
+        sections.extend(section);
+        for (title, output) in sections {
+            // `TestCart.test_total` heads the output of `...::TestCart::test_total`.
+            let suffix = format!("::{}", title.replace('.', "::"));
+            if let Some(test) = self
+                .tests
+                .iter_mut()
+                .find(|test| test.outcome == TestOutcome::Failed && test.name.ends_with(&suffix))
+            {
+                test.output = trim_blank_lines(output);
+            }
+        }
+    }
+
+    fn jest(&mut self, lines: &[String]) {
+        let mut failure: Option<(Vec<String>, Vec<String>)> = None;
+        let mut failures: Vec<(Vec<String>, Vec<String>)> = Vec::new();
+        for line in lines {
+            let line = line.trim_end();
+            let trimmed = line.trim_start();
+            if let Some(title) = trimmed.strip_prefix("● ") {
+                failures.extend(failure.take());
+                // Logged output and suites that did not load are not tests.
+                if title != "Console" && !title.starts_with("Test suite failed") {
+                    let path = title.split(" › ").map(str::to_string).collect();
+                    failure = Some((path, Vec::new()));
+                }
+                continue;
+            }
+            if jest_end_regex().is_match(line) {
+                failures.extend(failure.take());
+            }
+            if let Some(summary) = line.strip_prefix("Tests:") {
+                self.add_summary(summary);
+            } else if let Some((_, output)) = failure.as_mut() {
+                output.push(line.to_string());
+            } else if let Some(capture) = jest_result_regex().captures(line) {
+                let outcome = match &capture[1] {
+                    "✓" | "√" => TestOutcome::Passed,
+                    "✕" | "×" => TestOutcome::Failed,
+                    _ => TestOutcome::Ignored,
+                };
+                self.set(&capture[2], outcome);
+            }
+        }
+        failures.extend(failure);
+        for (path, output) in failures {
+            let title = path.last().cloned().unwrap_or_default();
+            let full_name = path.join(" ");
+            // Listed under its own title; `-t` needs the full name.
+            match self.tests.iter_mut().find(|test| {
+                test.outcome == TestOutcome::Failed
+                    && (test.name == title || test.name == full_name)
+            }) {
+                Some(test) => {
+                    test.name = full_name;
+                    test.output = trim_blank_lines(output);
+                }
+                None => self.set(&full_name, TestOutcome::Failed).output = trim_blank_lines(output),
+            }
+        }
+    }
+}
+
+fn trim_blank_lines(mut lines: Vec<String>) -> Vec<String> {
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+    let leading = lines
+        .iter()
+        .take_while(|line| line.trim().is_empty())
+        .count();
+    lines.drain(..leading);
+    lines
+}
+
+/// Options whose value is the next argument, for each runner.
+const CARGO_VALUE_OPTIONS: &[&str] = &[
+    "-p",
+    "--package",
+    "--exclude",
+    "--test",
+    "--bin",
+    "--example",
+    "--bench",
+    "-F",
+    "--features",
+    "--manifest-path",
+    "--target",
+    "--target-dir",
+    "-j",
+    "--jobs",
+    "--profile",
+    "--color",
+    "--message-format",
+    "-Z",
+    "--config",
+];
+const LIBTEST_VALUE_OPTIONS: &[&str] = &[
+    "--skip",
+    "--test-threads",
+    "--format",
+    "--logfile",
+    "--color",
+    "-Z",
+    "--shuffle-seed",
+];
+const PYTEST_VALUE_OPTIONS: &[&str] = &[
+    "-m",
+    "-p",
+    "-c",
+    "-o",
+    "-n",
+    "-W",
+    "-r",
+    "--tb",
+    "--maxfail",
+    "--rootdir",
+    "--basetemp",
+    "--junitxml",
+    "--cov",
+    "--durations",
+    "--deselect",
+    "--ignore",
+    "--log-level",
+];
+
+/// `command`, changed to run only `tests` from its report.
+pub fn rerun_command(command: &str, tests: &[String]) -> Option<String> {
+    let tokens: Vec<&str> = command.split_whitespace().collect();
+    let (runner, start) = invocation(&tokens)?;
+    if tests.is_empty() {
+        return None;
+    }
+    let program = tokens[..start].join(" ");
+    let args = &tokens[start..];
+    let mut parts = vec![program];
+    match runner {
+        TestRunner::Cargo => {
+            let split = args.iter().position(|arg| *arg == "--");
+            let (cargo_args, libtest_args) = match split {
+                Some(split) => (&args[..split], &args[split + 1..]),
+                None => (args, &[][..]),
+            };
+            parts.extend(options_only(cargo_args, CARGO_VALUE_OPTIONS, &[]));
+            parts.push("--".to_string());
+            parts.extend(options_only(
+                libtest_args,
+                LIBTEST_VALUE_OPTIONS,
+                &["--exact"],
+            ));
+            parts.push("--exact".to_string());
+            parts.extend(tests.iter().map(|test| shell_quote(test)));
+        }
+        TestRunner::Pytest => {
+            let mut kept = Vec::new();
+            let mut skip_value = false;
+            for arg in args {
+                if skip_value {
+                    skip_value = false;
+                } else if *arg == "-k" {
+                    skip_value = true;
+                } else if !arg.starts_with("-k") {
+                    kept.push(*arg);
+                }
+            }
+            parts.extend(options_only(
+                &kept,
+                PYTEST_VALUE_OPTIONS,
+                &["--lf", "--last-failed", "--ff", "--failed-first"],
+            ));
+            parts.extend(tests.iter().map(|test| shell_quote(test)));
+        }
+        TestRunner::Jest => {
+            let mut skip_value = false;
+            for arg in args {
+                if skip_value {
+                    skip_value = false;
+                } else if matches!(*arg, "-t" | "--testNamePattern") {
+                    skip_value = true;
+                } else if !arg.starts_with("-t=") && !arg.starts_with("--testNamePattern=") {
+                    parts.push(arg.to_string());
+                }
+            }
+            // npm passes arguments on to the script only after `--`.
+            if tokens[0] == "npm" && !args.contains(&"--") {
+                parts.push("--".to_string());
+            }
+            let pattern = tests
+                .iter()
+                .map(|test| regex::escape(test))
+                .collect::<Vec<_>>()
+                .join("|");
+            parts.push("-t".to_string());
+            parts.push(shell_quote(&format!("^(?:{pattern})$")));
+        }
+    }
+    Some(parts.join(" "))
+}
+
+/// `args` without positional arguments, which select tests, and without
+/// `dropped` flags.
+fn options_only(args: &[&str], value_options: &[&str], dropped: &[&str]) -> Vec<String> {
+    let mut kept = Vec::new();
+    let mut takes_value = false;
+    for arg in args {
+        if takes_value {
+            kept.push(arg.to_string());
+            takes_value = false;
+        } else if arg.starts_with('-') {
+            if dropped.contains(arg) {
+                continue;
+            }
+            takes_value = value_options.contains(arg);
+            kept.push(arg.to_string());
+        }
+    }
+    kept
+}
+
+fn shell_quote(text: &str) -> String {
+    let plain = text
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "_-./:=+@%,".contains(c));
+    if plain && !text.is_empty() {
+        text.to_string()
+    } else {
+        format!("'{}'", text.replace('\'', r"'\''"))
+    }
+}
+
+fn count_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"(\d+) (passed|failed|errors?|ignored|skipped|todo|xfailed|xpassed)\b")
+            .expect("count pattern is valid")
+    })
+}
+
+fn cargo_result_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"^test (\S+)(?: - should panic)? \.\.\. (ok|FAILED|ignored)\b")
+            .expect("cargo result pattern is valid")
+    })
+}
+
+fn cargo_output_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"^---- (\S+) std(?:out|err) ----$").expect("cargo output pattern is valid")
+    })
+}
+
+fn pytest_result_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"^(\S+::\S+) (PASSED|FAILED|SKIPPED|XFAIL|XPASS|ERROR)\b")
+            .expect("pytest result pattern is valid")
+    })
+}
+
+fn pytest_failed_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"^(?:FAILED|ERROR) (\S+::\S+)").expect("pytest failed pattern is valid")
+    })
+}
+
+fn pytest_section_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"^_{3,} (\S.*?) _{3,}$").expect("pytest section pattern is valid")
+    })
+}
+
+fn pytest_summary_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"^=+ (.*\d+ \w+.*) in [\d.]+s\b.*=+$").expect("pytest summary pattern is valid")
+    })
+}
+
+fn jest_result_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"^\s+(✓|√|✕|×|○)(?: skipped| todo)? (.+?)(?: \(\d+(?:\.\d+)? ?m?s\))?$")
+            .expect("jest result pattern is valid")
+    })
+}
+
+fn jest_end_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"^(?:PASS|FAIL) |^(?:Test Suites|Tests|Snapshots|Time):")
+            .expect("jest end pattern is valid")
+    })
+}
@@ -0,0 +1,265 @@
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+/// Above this many cells in the comparison table, the changed middle of the
+/// two outputs is shown as removed and re-added instead of lined up.
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// Stands in for dates, clock times and elapsed times when they are ignored.
+const TIMESTAMP_PLACEHOLDER: &str = "<time>";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DiffOptions {
+    /// Compares lines with runs of whitespace collapsed and the ends trimmed.
+    pub ignore_whitespace: bool,
+    /// Compares lines with dates, clock times and durations such as `0.42s`
+    /// masked out.
+    pub ignore_timestamps: bool,
+}
+
+/// One line of a unified diff, by index into the left or right lines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffLine {
+    Same { left: usize, right: usize },
+    Removed(usize),
+    Added(usize),
+}
+
+/// One row of a side-by-side diff. Removed and added lines next to each other
+/// share rows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffRow {
+    Same {
+        left: usize,
+        right: usize,
+    },
+    Changed {
+        left: Option<usize>,
+        right: Option<usize>,
+    },
+}
+
+/// Line diff of `left` against `right`, as a longest common subsequence.
+pub fn diff_lines(left: &[String], right: &[String], options: DiffOptions) -> Vec<DiffLine> {
+    let left_keys: Vec<String> = left.iter().map(|line| comparable(line, options)).collect();
+    let right_keys: Vec<String> = right.iter().map(|line| comparable(line, options)).collect();
+
+    let prefix = left_keys
+        .iter()
+        .zip(&right_keys)
+        .take_while(|(left, right)| left == right)
+        .count();
+    let suffix = left_keys[prefix..]
+        .iter()
+        .rev()
+        .zip(right_keys[prefix..].iter().rev())
+        .take_while(|(left, right)| left == right)
+        .count();
+    let left_middle = &left_keys[prefix..left_keys.len() - suffix];
+    let right_middle = &right_keys[prefix..right_keys.len() - suffix];
+
+    let mut lines: Vec<DiffLine> = (0..prefix)
+        .map(|index| DiffLine::Same {
+            left: index,
+            right: index,
+        })
+        .collect();
+    lines.extend(
+        diff_middle(left_middle, right_middle)
+            .into_iter()
+            .map(|line| match line {
+                DiffLine::Same { left, right } => DiffLine::Same {
+                    left: left + prefix,
+                    right: right + prefix,
+                },
+                DiffLine::Removed(left) => DiffLine::Removed(left + prefix),
+                DiffLine::Added(right) => DiffLine::Added(right + prefix),
+            }),
+    );
+    lines.extend((0..suffix).map(|offset| DiffLine::Same {
+        left: left_keys.len() - suffix + offset,
+        right: right_keys.len() - suffix + offset,
+    }));
+    lines
+}
+
+/// Pairs each run of removed lines with the added lines that follow it.
+pub fn side_by_side(lines: &[DiffLine]) -> Vec<DiffRow> {
+    let mut rows = Vec::new();
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    let flush = |rows: &mut Vec<DiffRow>, removed: &mut Vec<usize>, added: &mut Vec<usize>| {
+        for row in 0..removed.len().max(added.len()) {
+            rows.push(DiffRow::Changed {
+                left: removed.get(row).copied(),
+                right: added.get(row).copied(),
+            });
+        }
+        removed.clear();
+        added.clear();
+    };
+    for line in lines {
+        match *line {
+            DiffLine::Same { left, right } => {
+                flush(&mut rows, &mut removed, &mut added);
+                rows.push(DiffRow::Same { left, right });
+            }
+            DiffLine::Removed(left) => {
+                if !added.is_empty() {
+                    flush(&mut rows, &mut removed, &mut added);
+                }
+                removed.push(left);
+            }
+            DiffLine::Added(right) => added.push(right),
+        }
+    }
+    flush(&mut rows, &mut removed, &mut added);
+    rows
+}
+
+/// Counts of removed and added lines.
+pub fn change_counts(lines: &[DiffLine]) -> (usize, usize) {
+    lines
+        .iter()
+        .fold((0, 0), |(removed, added), line| match line {
+            DiffLine::Same { .. } => (removed, added),
+            DiffLine::Removed(_) => (removed + 1, added),
+            DiffLine::Added(_) => (removed, added + 1),
+        })
+}
+
+fn diff_middle(left: &[String], right: &[String]) -> Vec<DiffLine> {
+    let (rows, columns) = (left.len(), right.len());
+    if rows.saturating_mul(columns) > MAX_DIFF_CELLS {
+        return (0..rows)
+            .map(DiffLine::Removed)
+            .chain((0..columns).map(DiffLine::Added))
+            .collect();
+    }
+
+    // Longest common subsequence of the suffixes starting at each pair.
+    let width = columns + 1;
+    let mut table = vec![0u32; (rows + 1) * width];
+    for row in (0..rows).rev() {
+        for column in (0..columns).rev() {
+            table[row * width + column] = if left[row] == right[column] {
+                table[(row + 1) * width + column + 1] + 1
+            } else {
+                table[(row + 1) * width + column].max(table[row * width + column + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::with_capacity(rows.max(columns));
+    let (mut row, mut column) = (0, 0);
+    while row < rows && column < columns {
+        if left[row] == right[column] {
+            lines.push(DiffLine::Same {
+                left: row,
+                right: column,
+            });
+            row += 1;
+            column += 1;
+        } else if table[(row + 1) * width + column] >= table[row * width + column + 1] {
+            lines.push(DiffLine::Removed(row));
+            row += 1;
+        } else {
+            lines.push(DiffLine::Added(column));
+            column += 1;
+        }
+    }
+    lines.extend((row..rows).map(DiffLine::Removed));
+    lines.extend((column..columns).map(DiffLine::Added));
+    lines
+}
+
+fn comparable(line: &str, options: DiffOptions) -> String {
+    let mut line = if options.ignore_timestamps {
+        timestamp_regex()
+            .replace_all(line, TIMESTAMP_PLACEHOLDER)
+            .into_owned()
+    } else {
+        line.to_string()
+    };
+    if options.ignore_whitespace {
+        line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+    }
+    line
+}
+
+fn timestamp_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(concat!(
+            // 2026-10-18, 2026/10/18 and an optional time after it.
+            r"\d{4}[-/]\d{2}[-/]\d{2}(?:[T ]\d{2}:\d{2}(?::\d{2})?(?:[.,]\d+)?(?:Z|[+-]\d{2}:?\d{2})?)?",
+            // 14:03:27.512 on its own.
+            r"|\b\d{1,2}:\d{2}:\d{2}(?:[.,]\d+)?\b",
+            // Elapsed times: 0.42s, 120ms, 1m30s, and ages such as 5d.
+            r"|\b\d+(?:\.\d+)?(?:ns|µs|us|ms|s|m|h|d)(?:\d+(?:\.\d+)?(?:ms|s|m|h))*\b",
+        ))
+        .expect("timestamp pattern is valid")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn diff_keeps_common_lines_and_pairs_changes() {
+        let before = lines("running 3 tests\ntest a ... ok\ntest b ... FAILED\ntest c ... ok");
+        let after = lines("running 3 tests\ntest a ... ok\ntest b ... ok\ntest c ... ok\ndone");
+        let diff = diff_lines(&before, &after, DiffOptions::default());
+        assert_eq!(
+            diff,
+            vec![
+                DiffLine::Same { left: 0, right: 0 },
+                DiffLine::Same { left: 1, right: 1 },
+                DiffLine::Removed(2),
+                DiffLine::Added(2),
+                DiffLine::Same { left: 3, right: 3 },
+                DiffLine::Added(4),
+            ]
+        );
+        assert_eq!(change_counts(&diff), (1, 2));
+        assert_eq!(
+            side_by_side(&diff)[2..],
+            [
+                DiffRow::Changed {
+                    left: Some(2),
+                    right: Some(2)
+                },
+                DiffRow::Same { left: 3, right: 3 },
+                DiffRow::Changed {
+                    left: None,
+                    right: Some(4)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn options_ignore_whitespace_and_timestamps() {
+        let before =
+            lines("NAME   READY  AGE\nweb-1  1/1    5m\n[2026-10-18 14:03:27] built in 0.42s");
+        let after = lines("NAME READY AGE\nweb-1 1/1 12m\n[2026-10-18 14:05:01] built in 1.3s");
+        let strict = diff_lines(&before, &after, DiffOptions::default());
+        assert_eq!(change_counts(&strict), (3, 3));
+
+        let loose = diff_lines(
+            &before,
+            &after,
+            DiffOptions {
+                ignore_whitespace: true,
+                ignore_timestamps: true,
+            },
+        );
+        assert_eq!(change_counts(&loose), (0, 0));
+    }
+}
@@ -0,0 +1,1102 @@
+use serde::{Deserialize, Serialize};
+
+use crate::ui::text_edit::TextEditState;
+
+const MAX_KILL_RING: usize = 30;
+const MAX_UNDO: usize = 100;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EditingMode {
+    #[default]
+    Standard,
+    Emacs,
+    Vi,
+}
+
+impl EditingMode {
+    pub const ALL: [EditingMode; 3] = [Self::Standard, Self::Emacs, Self::Vi];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Standard => "Standard",
+            Self::Emacs => "Emacs",
+            Self::Vi => "Vi",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Standard => "Arrow keys, word jumps and selection only.",
+            Self::Emacs => "Readline bindings: Ctrl+A/E/K/U/W/Y, Alt+B/F/D, kill ring, transpose.",
+            Self::Vi => "Normal, insert and visual states with motions, operators and counts.",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ViState {
+    #[default]
+    Insert,
+    Normal,
+    Visual,
+}
+
+/// A key press reduced to what the editor needs. `text` is the character the
+/// key produces, if any.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EditKey<'a> {
+    pub key: &'a str,
+    pub text: Option<&'a str>,
+    pub ctrl: bool,
+    pub alt: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditAction {
+    /// Not an editor binding; the caller handles the key as before.
+    Ignored,
+    /// Consumed without changing the text.
+    Moved,
+    Changed,
+    HistoryPrevious,
+    HistoryNext,
+}
+
+/// The text, cursor and selection of one input, in the shape the
+/// `TextEditState` helpers take them.
+pub struct EditBuffer<'a> {
+    pub text: &'a mut String,
+    pub cursor: &'a mut usize,
+    pub selection: &'a mut Option<(usize, usize)>,
+    pub anchor: &'a mut Option<usize>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct KillRing {
+    entries: Vec<String>,
+}
+
+impl KillRing {
+    /// Adds a kill. Consecutive kills are merged into one entry, in text order.
+    fn push(&mut self, text: String, merge: bool, before: bool) {
+        if text.is_empty() {
+            return;
+        }
+        match self.entries.last_mut() {
+            Some(last) if merge && before => last.insert_str(0, &text),
+            Some(last) if merge => last.push_str(&text),
+            _ => {
+                self.entries.push(text);
+                if self.entries.len() > MAX_KILL_RING {
+                    self.entries.remove(0);
+                }
+            }
+        }
+    }
+
+    pub fn yank(&self) -> Option<&str> {
+        self.entries.last().map(String::as_str)
+    }
+
+    fn rotate(&mut self) -> Option<&str> {
+        let last = self.entries.pop()?;
+        self.entries.insert(0, last);
+        self.yank()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Find {
+    Forward,
+    ForwardTill,
+    Backward,
+    BackwardTill,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Awaiting {
+    Find(Find),
+    Replace,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Motion {
+    target: usize,
+    inclusive: bool,
+}
+
+/// Emacs or Vi key handling on top of the `TextEditState` helpers. Each input
+/// owns one, so its Vi state and kill ring are per input.
+#[derive(Clone, Debug, Default)]
+pub struct ModalEditor {
+    mode: EditingMode,
+    vi_state: ViState,
+    kill_ring: KillRing,
+    last_was_kill: bool,
+    last_yank: Option<(usize, usize)>,
+    count: Option<usize>,
+    operator: Option<(Operator, usize)>,
+    awaiting: Option<Awaiting>,
+    last_find: Option<(Find, char)>,
+    visual_anchor: usize,
+    undo: Vec<(String, usize)>,
+}
+
+impl ModalEditor {
+    pub fn new(mode: EditingMode) -> Self {
+        Self {
+            mode,
+            ..Self::default()
+        }
+    }
+
+    pub fn mode(&self) -> EditingMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: EditingMode) {
+        if self.mode != mode {
+            *self = Self {
+                kill_ring: std::mem::take(&mut self.kill_ring),
+                ..Self::new(mode)
+            };
+        }
+    }
+
+    pub fn vi_state(&self) -> Option<ViState> {
+        (self.mode == EditingMode::Vi).then_some(self.vi_state)
+    }
+
+    pub fn kill_ring(&self) -> &KillRing {
+        &self.kill_ring
+    }
+
+    /// Label for the mode indicator, if the mode has one.
+    pub fn indicator(&self) -> Option<&'static str> {
+        match (self.mode, self.vi_state) {
+            (EditingMode::Standard, _) => None,
+            (EditingMode::Emacs, _) => Some("EMACS"),
+            (EditingMode::Vi, ViState::Insert) => Some("INSERT"),
+            (EditingMode::Vi, ViState::Normal) => Some("NORMAL"),
+            (EditingMode::Vi, ViState::Visual) => Some("VISUAL"),
+        }
+    }
+
+    /// Starts a fresh line: Vi returns to insert state and pending input and
+    /// undo history are dropped. The kill ring is kept.
+    pub fn reset_line(&mut self) {
+        *self = Self {
+            kill_ring: std::mem::take(&mut self.kill_ring),
+            last_find: self.last_find,
+            ..Self::new(self.mode)
+        };
+    }
+
+    pub fn handle_key(&mut self, key: EditKey<'_>, mut buffer: EditBuffer<'_>) -> EditAction {
+        let was_kill = std::mem::take(&mut self.last_was_kill);
+        let last_yank = self.last_yank.take();
+        let buffer = &mut buffer;
+        match self.mode {
+            EditingMode::Standard => EditAction::Ignored,
+            EditingMode::Emacs => self.handle_emacs(key, buffer, was_kill, last_yank),
+            EditingMode::Vi => match self.vi_state {
+                ViState::Insert => self.handle_vi_insert(key, buffer, was_kill),
+                ViState::Normal | ViState::Visual => self.handle_vi_command(key, buffer),
+            },
+        }
+    }
+
+    fn handle_emacs(
+        &mut self,
+        key: EditKey<'_>,
+        buffer: &mut EditBuffer<'_>,
+        was_kill: bool,
+        last_yank: Option<(usize, usize)>,
+    ) -> EditAction {
+        let chars: Vec<char> = buffer.text.chars().collect();
+        let len = chars.len();
+        let cursor = (*buffer.cursor).min(len);
+        let name = key.key.to_ascii_lowercase();
+        if key.ctrl && !key.alt {
+            match name.as_str() {
+                "a" => self.move_to(buffer, 0),
+                "e" => self.move_to(buffer, len),
+                "b" => self.move_to(buffer, cursor.saturating_sub(1)),
+                "f" => self.move_to(buffer, (cursor + 1).min(len)),
+                "k" => self.kill(buffer, cursor, len, was_kill, false),
+                "u" => self.kill(buffer, 0, cursor, was_kill, true),
+                "w" => {
+                    let start = unix_word_start(&chars, cursor);
+                    self.kill(buffer, start, cursor, was_kill, true)
+                }
+                "y" => self.yank(buffer, cursor),
+                "t" => transpose_chars(buffer, &chars, cursor),
+                "h" if cursor > 0 => replace_range(buffer, cursor - 1, cursor, ""),
+                "d" if cursor < len => replace_range(buffer, cursor, cursor + 1, ""),
+                _ => EditAction::Ignored,
+            }
+        } else if key.alt && !key.ctrl {
+            match name.as_str() {
+                "b" => self.move_to(buffer, emacs_word_start(&chars, cursor)),
+                "f" => self.move_to(buffer, emacs_word_end(&chars, cursor)),
+                "d" => {
+                    let end = emacs_word_end(&chars, cursor);
+                    self.kill(buffer, cursor, end, was_kill, false)
+                }
+                "backspace" => {
+                    let start = emacs_word_start(&chars, cursor);
+                    self.kill(buffer, start, cursor, was_kill, true)
+                }
+                "y" => match last_yank {
+                    Some((start, end)) => {
+                        let Some(text) = self.kill_ring.rotate().map(str::to_string) else {
+                            return EditAction::Moved;
+                        };
+                        replace_range(buffer, start, end, &text);
+                        self.last_yank = Some((start, start + text.chars().count()));
+                        EditAction::Changed
+                    }
+                    None => EditAction::Moved,
+                },
+                "t" => transpose_words(buffer, &chars, cursor),
+                "u" | "l" | "c" => {
+                    let end = emacs_word_end(&chars, cursor);
+                    let word: String = chars[cursor..end].iter().collect();
+                    let cased = match name.as_str() {
+                        "u" => word.to_uppercase(),
+                        "l" => word.to_lowercase(),
+                        _ => capitalize(&word),
+                    };
+                    replace_range(buffer, cursor, end, &cased);
+                    *buffer.cursor = end;
+                    EditAction::Changed
+                }
+                _ => EditAction::Ignored,
+            }
+        } else {
+            EditAction::Ignored
+        }
+    }
+
+    fn handle_vi_insert(
+        &mut self,
+        key: EditKey<'_>,
+        buffer: &mut EditBuffer<'_>,
+        was_kill: bool,
+    ) -> EditAction {
+        let chars: Vec<char> = buffer.text.chars().collect();
+        let cursor = (*buffer.cursor).min(chars.len());
+        if key.key == "escape" && !key.ctrl && !key.alt {
+            self.vi_state = ViState::Normal;
+            let cursor = cursor.saturating_sub(1);
+            return self.move_to(buffer, clamp_normal(cursor, chars.len()));
+        }
+        if key.ctrl && !key.alt {
+            return match key.key.to_ascii_lowercase().as_str() {
+                "w" => {
+                    let start = unix_word_start(&chars, cursor);
+                    self.kill(buffer, start, cursor, was_kill, true)
+                }
+                "u" => self.kill(buffer, 0, cursor, was_kill, true),
+                "h" if cursor > 0 => replace_range(buffer, cursor - 1, cursor, ""),
+                _ => EditAction::Ignored,
+            };
+        }
+        EditAction::Ignored
+    }
+
+    fn handle_vi_command(&mut self, key: EditKey<'_>, buffer: &mut EditBuffer<'_>) -> EditAction {
+        if key.ctrl || key.alt {
+            return EditAction::Ignored;
+        }
+        let ch = match key.key {
+            "escape" => {
+                let pending =
+                    self.count.is_some() || self.operator.is_some() || self.awaiting.is_some();
+                self.clear_pending();
+                if self.vi_state == ViState::Visual {
+                    self.vi_state = ViState::Normal;
+                    TextEditState::clear_selection(buffer.selection, buffer.anchor);
+                    return EditAction::Moved;
+                }
+                return if pending {
+                    EditAction::Moved
+                } else {
+                    EditAction::Ignored
+                };
+            }
+            "enter" | "return" | "numpadenter" | "up" | "arrowup" | "down" | "arrowdown"
+            | "tab" => return EditAction::Ignored,
+            "left" | "arrowleft" | "backspace" => 'h',
+            "right" | "arrowright" | "space" => 'l',
+            "home" => '0',
+            "end" => '$',
+            _ => match key.text.and_then(single_char) {
+                Some(ch) => ch,
+                None => return EditAction::Ignored,
+            },
+        };
+
+        let chars: Vec<char> = buffer.text.chars().collect();
+        let len = chars.len();
+        let cursor = (*buffer.cursor).min(len);
+
+        if let Some(awaiting) = self.awaiting.take() {
+            let count = self.take_count();
+            return match awaiting {
+                Awaiting::Find(find) => {
+                    self.last_find = Some((find, ch));
+                    match find_char(&chars, cursor, find, ch, count) {
+                        Some(motion) => self.apply_motion(buffer, &chars, cursor, motion),
+                        None => {
+                            self.clear_pending();
+                            EditAction::Moved
+                        }
+                    }
+                }
+                Awaiting::Replace => {
+                    if cursor + count > len {
+                        return EditAction::Moved;
+                    }
+                    self.snapshot(buffer.text, cursor);
+                    let replacement: String = std::iter::repeat_n(ch, count).collect();
+                    replace_range(buffer, cursor, cursor + count, &replacement);
+                    *buffer.cursor = cursor + count - 1;
+                    EditAction::Changed
+                }
+            };
+        }
+
+        if ch.is_ascii_digit() && (ch != '0' || self.count.is_some()) {
+            let digit = ch as usize - '0' as usize;
+            self.count = Some(self.count.unwrap_or(0).saturating_mul(10) + digit);
+            return EditAction::Moved;
+        }
+
+        if self.vi_state == ViState::Visual {
+            return self.handle_visual(ch, buffer, &chars, cursor);
+        }
+
+        if let Some((operator, _)) = self.operator
+            && operator_char(operator) == ch
+        {
+            // `dd`, `cc`, `yy` act on the whole line.
+            return self.apply_operator(buffer, operator, 0, len, 0);
+        }
+
+        if let Some(motion) = self.motion(ch, &chars, cursor) {
+            return self.apply_motion(buffer, &chars, cursor, motion);
+        }
+        if let Some(find) = find_kind(ch) {
+            self.awaiting = Some(Awaiting::Find(find));
+            return EditAction::Moved;
+        }
+
+        let operator_pending = self.operator.is_some();
+        let count = self.take_count();
+        self.operator = None;
+        if operator_pending {
+            return EditAction::Moved;
+        }
+        match ch {
+            'd' | 'c' | 'y' => {
+                let operator = match ch {
+                    'd' => Operator::Delete,
+                    'c' => Operator::Change,
+                    _ => Operator::Yank,
+                };
+                self.operator = Some((operator, count));
+                EditAction::Moved
+            }
+            'x' if len > 0 => {
+                let end = (cursor + count).min(len);
+                self.apply_operator(buffer, Operator::Delete, cursor, end, cursor)
+            }
+            'X' if cursor > 0 => {
+                let start = cursor.saturating_sub(count);
+                self.apply_operator(buffer, Operator::Delete, start, cursor, start)
+            }
+            's' => {
+                let end = (cursor + count).min(len);
+                self.apply_operator(buffer, Operator::Change, cursor, end, cursor)
+            }
+            'S' => self.apply_operator(buffer, Operator::Change, 0, len, 0),
+            'D' => self.apply_operator(buffer, Operator::Delete, cursor, len, cursor),
+            'C' => self.apply_operator(buffer, Operator::Change, cursor, len, cursor),
+            'Y' => self.apply_operator(buffer, Operator::Yank, 0, len, cursor),
+            'r' => {
+                self.count = Some(count);
+                self.awaiting = Some(Awaiting::Replace);
+                EditAction::Moved
+            }
+            '~' if len > 0 => {
+                self.snapshot(buffer.text, cursor);
+                let end = (cursor + count).min(len);
+                let toggled: String = chars[cursor..end]
+                    .iter()
+                    .map(|ch| toggle_case(*ch))
+                    .collect();
+                replace_range(buffer, cursor, end, &toggled);
+                *buffer.cursor = clamp_normal(end, len);
+                EditAction::Changed
+            }
+            'i' => self.enter_insert(buffer, cursor),
+            'a' => self.enter_insert(buffer, (cursor + 1).min(len)),
+            'I' => self.enter_insert(buffer, first_non_blank(&chars)),
+            'A' => self.enter_insert(buffer, len),
+            'p' | 'P' => {
+                let Some(text) = self.kill_ring.yank().map(str::to_string) else {
+                    return EditAction::Moved;
+                };
+                self.snapshot(buffer.text, cursor);
+                let at = if ch == 'p' && len > 0 {
+                    cursor + 1
+                } else {
+                    cursor
+                };
+                let pasted = text.repeat(count);
+                replace_range(buffer, at, at, &pasted);
+                *buffer.cursor = at + pasted.chars().count().saturating_sub(1);
+                EditAction::Changed
+            }
+            'u' => match self.undo.pop() {
+                Some((text, cursor)) => {
+                    *buffer.text = text;
+                    *buffer.cursor = clamp_normal(cursor, buffer.text.chars().count());
+                    TextEditState::clear_selection(buffer.selection, buffer.anchor);
+                    EditAction::Changed
+                }
+                None => EditAction::Moved,
+            },
+            'v' => {
+                self.vi_state = ViState::Visual;
+                self.visual_anchor = cursor;
+                self.sync_visual_selection(buffer, cursor, len);
+                EditAction::Moved
+            }
+            'k' => EditAction::HistoryPrevious,
+            'j' => EditAction::HistoryNext,
+            _ => EditAction::Moved,
+        }
+    }
+
+    fn handle_visual(
+        &mut self,
+        ch: char,
+        buffer: &mut EditBuffer<'_>,
+        chars: &[char],
+        cursor: usize,
+    ) -> EditAction {
+        let len = chars.len();
+        if let Some(motion) = self.motion(ch, chars, cursor) {
+            let target = clamp_normal(motion.target, len);
+            *buffer.cursor = target;
+            self.sync_visual_selection(buffer, target, len);
+            return EditAction::Moved;
+        }
+        if let Some(find) = find_kind(ch) {
+            self.awaiting = Some(Awaiting::Find(find));
+            return EditAction::Moved;
+        }
+        self.count = None;
+        let start = self.visual_anchor.min(cursor);
+        let end = (self.visual_anchor.max(cursor) + 1).min(len);
+        let operator = match ch {
+            'd' | 'x' => Operator::Delete,
+            'c' | 's' => Operator::Change,
+            'y' => Operator::Yank,
+            '~' => {
+                self.vi_state = ViState::Normal;
+                self.snapshot(buffer.text, cursor);
+                let toggled: String = chars[start..end]
+                    .iter()
+                    .map(|ch| toggle_case(*ch))
+                    .collect();
+                replace_range(buffer, start, end, &toggled);
+                *buffer.cursor = start;
+                return EditAction::Changed;
+            }
+            'v' => {
+                self.vi_state = ViState::Normal;
+                TextEditState::clear_selection(buffer.selection, buffer.anchor);
+                return EditAction::Moved;
+            }
+            _ => return EditAction::Moved,
+        };
+        self.vi_state = ViState::Normal;
+        self.apply_operator(buffer, operator, start, end, start)
+    }
+
+    fn motion(&mut self, ch: char, chars: &[char], cursor: usize) -> Option<Motion> {
+        let len = chars.len();
+        let count =
+            self.count.unwrap_or(1) * self.operator.map(|(_, op_count)| op_count).unwrap_or(1);
+        let repeat = |mut at: usize, step: &dyn Fn(usize) -> usize| {
+            for _ in 0..count {
+                at = step(at);
+            }
+            at
+        };
+        let changing_word = matches!(self.operator, Some((Operator::Change, _)))
+            && cursor < len
+            && !chars[cursor].is_whitespace();
+        let motion = match ch {
+            'h' => Motion {
+                target: cursor.saturating_sub(count),
+                inclusive: false,
+            },
+            'l' => Motion {
+                target: (cursor + count).min(len),
+                inclusive: false,
+            },
+            // `cw` changes to the end of the word, like `ce`.
+            'w' | 'W' if changing_word => Motion {
+                target: repeat(cursor.saturating_sub(1), &|at| {
+                    TextEditState::word_end_after(chars, at, ch == 'W')
+                }),
+                inclusive: true,
+            },
+            'w' | 'W' => Motion {
+                target: repeat(cursor, &|at| {
+                    TextEditState::next_word_start(chars, at, ch == 'W')
+                }),
+                inclusive: false,
+            },
+            'b' | 'B' => Motion {
+                target: repeat(cursor, &|at| {
+                    TextEditState::word_start_before(chars, at, ch == 'B')
+                }),
+                inclusive: false,
+            },
+            'e' | 'E' => Motion {
+                target: repeat(cursor, &|at| {
+                    TextEditState::word_end_after(chars, at, ch == 'E')
+                }),
+                inclusive: true,
+            },
+            '0' => Motion {
+                target: 0,
+                inclusive: false,
+            },
+            '^' => Motion {
+                target: first_non_blank(chars),
+                inclusive: false,
+            },
+            '$' => Motion {
+                target: len.saturating_sub(1),
+                inclusive: true,
+            },
+            ';' | ',' => {
+                let (find, target) = self.last_find?;
+                let find = if ch == ',' { reverse_find(find) } else { find };
+                find_char(chars, cursor, find, target, count)?
+            }
+            _ => return None,
+        };
+        Some(motion)
+    }
+
+    fn apply_motion(
+        &mut self,
+        buffer: &mut EditBuffer<'_>,
+        chars: &[char],
+        cursor: usize,
+        motion: Motion,
+    ) -> EditAction {
+        let len = chars.len();
+        self.count = None;
+        if self.vi_state == ViState::Visual {
+            let target = clamp_normal(motion.target, len);
+            *buffer.cursor = target;
+            self.sync_visual_selection(buffer, target, len);
+            return EditAction::Moved;
+        }
+        let Some((operator, _)) = self.operator.take() else {
+            return self.move_to(buffer, clamp_normal(motion.target, len));
+        };
+        let (start, end) = if motion.target >= cursor {
+            let end = motion.target + usize::from(motion.inclusive);
+            (cursor, end.min(len))
+        } else {
+            (motion.target, cursor)
+        };
+        self.apply_operator(buffer, operator, start, end, start)
+    }
+
+    fn apply_operator(
+        &mut self,
+        buffer: &mut EditBuffer<'_>,
+        operator: Operator,
+        start: usize,
+        end: usize,
+        cursor_after: usize,
+    ) -> EditAction {
+        self.clear_pending();
+        TextEditState::clear_selection(buffer.selection, buffer.anchor);
+        let text: String = buffer
+            .text
+            .chars()
+            .skip(start)
+            .take(end.saturating_sub(start))
+            .collect();
+        self.kill_ring.push(text, false, false);
+        match operator {
+            Operator::Yank => {
+                *buffer.cursor = clamp_normal(cursor_after, buffer.text.chars().count());
+                EditAction::Moved
+            }
+            Operator::Delete | Operator::Change => {
+                self.snapshot(buffer.text, *buffer.cursor);
+                TextEditState::remove_range(buffer.text, start, end);
+                let len = buffer.text.chars().count();
+                if operator == Operator::Change {
+                    self.vi_state = ViState::Insert;
+                    *buffer.cursor = start.min(len);
+                } else {
+                    *buffer.cursor = clamp_normal(start, len);
+                }
+                EditAction::Changed
+            }
+        }
+    }
+
+    fn enter_insert(&mut self, buffer: &mut EditBuffer<'_>, cursor: usize) -> EditAction {
+        self.snapshot(buffer.text, *buffer.cursor);
+        self.vi_state = ViState::Insert;
+        *buffer.cursor = cursor;
+        EditAction::Moved
+    }
+
+    fn sync_visual_selection(&self, buffer: &mut EditBuffer<'_>, cursor: usize, len: usize) {
+        let start = self.visual_anchor.min(cursor);
+        let end = (self.visual_anchor.max(cursor) + 1).min(len);
+        *buffer.selection = Some((start, end));
+        *buffer.anchor = Some(self.visual_anchor);
+    }
+
+    fn move_to(&mut self, buffer: &mut EditBuffer<'_>, cursor: usize) -> EditAction {
+        *buffer.cursor = cursor;
+        TextEditState::clear_selection(buffer.selection, buffer.anchor);
+        EditAction::Moved
+    }
+
+    fn kill(
+        &mut self,
+        buffer: &mut EditBuffer<'_>,
+        start: usize,
+        end: usize,
+        merge: bool,
+        before: bool,
+    ) -> EditAction {
+        if start >= end {
+            self.last_was_kill = merge;
+            return EditAction::Moved;
+        }
+        let killed = TextEditState::remove_range(buffer.text, start, end);
+        self.kill_ring.push(killed, merge, before);
+        self.last_was_kill = true;
+        *buffer.cursor = start;
+        TextEditState::clear_selection(buffer.selection, buffer.anchor);
+        EditAction::Changed
+    }
+
+    fn yank(&mut self, buffer: &mut EditBuffer<'_>, cursor: usize) -> EditAction {
+        let Some(text) = self.kill_ring.yank().map(str::to_string) else {
+            return EditAction::Moved;
+        };
+        replace_range(buffer, cursor, cursor, &text);
+        self.last_yank = Some((cursor, cursor + text.chars().count()));
+        EditAction::Changed
+    }
+
+    fn snapshot(&mut self, text: &str, cursor: usize) {
+        if self.undo.last().is_some_and(|(last, _)| last == text) {
+            return;
+        }
+        self.undo.push((text.to_string(), cursor));
+        if self.undo.len() > MAX_UNDO {
+            self.undo.remove(0);
+        }
+    }
+
+    fn take_count(&mut self) -> usize {
+        self.count.take().unwrap_or(1).max(1)
+    }
+
+    fn clear_pending(&mut self) {
+        self.count = None;
+        self.operator = None;
+        self.awaiting = None;
+    }
+}
+
+/// Replaces `start..end` with `insert` and leaves the cursor after it.
+fn replace_range(
+    buffer: &mut EditBuffer<'_>,
+    start: usize,
+    end: usize,
+    insert: &str,
+) -> EditAction {
+    TextEditState::remove_range(buffer.text, start, end);
+    *buffer.cursor = start;
+    TextEditState::insert_text(
+        buffer.text,
+        buffer.cursor,
+        buffer.selection,
+        buffer.anchor,
+        insert,
+    );
+    EditAction::Changed
+}
+
+fn transpose_chars(buffer: &mut EditBuffer<'_>, chars: &[char], cursor: usize) -> EditAction {
+    let len = chars.len();
+    if len < 2 || cursor == 0 {
+        return EditAction::Moved;
+    }
+    let at = if cursor >= len { len - 1 } else { cursor };
+    let swapped: String = [chars[at], chars[at - 1]].iter().collect();
+    replace_range(buffer, at - 1, at + 1, &swapped)
+}
+
+fn transpose_words(buffer: &mut EditBuffer<'_>, chars: &[char], cursor: usize) -> EditAction {
+    let second_end = emacs_word_end(chars, cursor);
+    let second_start = emacs_word_start(chars, second_end);
+    let first_start = emacs_word_start(chars, second_start);
+    let first_end = emacs_word_end(chars, first_start);
+    if first_start == second_start || first_end > second_start {
+        return EditAction::Moved;
+    }
+    let first: String = chars[first_start..first_end].iter().collect();
+    let between: String = chars[first_end..second_start].iter().collect();
+    let second: String = chars[second_start..second_end].iter().collect();
+    replace_range(
+        buffer,
+        first_start,
+        second_end,
+        &format!("{second}{between}{first}"),
+    )
+}
+
+fn is_emacs_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+fn emacs_word_start(chars: &[char], from: usize) -> usize {
+    let mut i = from.min(chars.len());
+    while i > 0 && !is_emacs_word_char(chars[i - 1]) {
+        i -= 1;
+    }
+    while i > 0 && is_emacs_word_char(chars[i - 1]) {
+        i -= 1;
+    }
+    i
+}
+
+fn emacs_word_end(chars: &[char], from: usize) -> usize {
+    let len = chars.len();
+    let mut i = from.min(len);
+    while i < len && !is_emacs_word_char(chars[i]) {
+        i += 1;
+    }
+    while i < len && is_emacs_word_char(chars[i]) {
+        i += 1;
+    }
+    i
+}
+
+/// Start of the whitespace-delimited word before `from`, as Ctrl+W kills.
+fn unix_word_start(chars: &[char], from: usize) -> usize {
+    let mut i = from.min(chars.len());
+    while i > 0 && chars[i - 1].is_whitespace() {
+        i -= 1;
+    }
+    while i > 0 && !chars[i - 1].is_whitespace() {
+        i -= 1;
+    }
+    i
+}
+
+fn first_non_blank(chars: &[char]) -> usize {
+    chars
+        .iter()
+        .position(|ch| !ch.is_whitespace())
+        .unwrap_or(chars.len())
+}
+
+/// In normal state the cursor sits on a character, never past the end.
+fn clamp_normal(cursor: usize, len: usize) -> usize {
+    cursor.min(len.saturating_sub(1))
+}
+
+fn find_kind(ch: char) -> Option<Find> {
+    match ch {
+        'f' => Some(Find::Forward),
+        't' => Some(Find::ForwardTill),
+        'F' => Some(Find::Backward),
+        'T' => Some(Find::BackwardTill),
+        _ => None,
+    }
+}
+
+fn reverse_find(find: Find) -> Find {
+    match find {
+        Find::Forward => Find::Backward,
+        Find::ForwardTill => Find::BackwardTill,
+        Find::Backward => Find::Forward,
+        Find::BackwardTill => Find::ForwardTill,
+    }
+}
+
+fn find_char(
+    chars: &[char],
+    cursor: usize,
+    find: Find,
+    target: char,
+    count: usize,
+) -> Option<Motion> {
+    let mut at = cursor;
+    for _ in 0..count {
+        at = match find {
+            Find::Forward | Find::ForwardTill => {
+                let skip = if find == Find::ForwardTill { 2 } else { 1 };
+                let from = (at + skip).min(chars.len());
+                from + chars[from..].iter().position(|ch| *ch == target)?
+                    - usize::from(find == Find::ForwardTill)
+            }
+            Find::Backward | Find::BackwardTill => {
+                let before = if find == Find::BackwardTill {
+                    at.saturating_sub(1)
+                } else {
+                    at
+                };
+                chars[..before].iter().rposition(|ch| *ch == target)?
+                    + usize::from(find == Find::BackwardTill)
+            }
+        };
+    }
+    Some(Motion {
+        target: at,
+        inclusive: matches!(find, Find::Forward | Find::ForwardTill),
+    })
+}
+
+fn operator_char(operator: Operator) -> char {
+    match operator {
+        Operator::Delete => 'd',
+        Operator::Change => 'c',
+        Operator::Yank => 'y',
+    }
+}
+
+fn single_char(text: &str) -> Option<char> {
+    let mut chars = text.chars();
+    let ch = chars.next()?;
+    chars.next().is_none().then_some(ch)
+}
+
+fn toggle_case(ch: char) -> char {
+    if ch.is_uppercase() {
+        ch.to_lowercase().next().unwrap_or(ch)
+    } else {
+        ch.to_uppercase().next().unwrap_or(ch)
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut out = String::with_capacity(word.len());
+    let mut seen_letter = false;
+    for ch in word.chars() {
+        if !seen_letter && ch.is_alphanumeric() {
+            out.extend(ch.to_uppercase());
+            seen_letter = true;
+        } else {
+            out.extend(ch.to_lowercase());
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EditAction, EditBuffer, EditKey, EditingMode, ModalEditor, ViState};
+    use crate::ui::text_edit::TextEditState;
+
+    struct Input {
+        text: String,
+        cursor: usize,
+        selection: Option<(usize, usize)>,
+        anchor: Option<usize>,
+    }
+
+    impl Input {
+        fn new(text: &str, cursor: usize) -> Self {
+            Self {
+                text: text.to_string(),
+                cursor,
+                selection: None,
+                anchor: None,
+            }
+        }
+
+        fn press(&mut self, editor: &mut ModalEditor, key: EditKey<'_>) -> EditAction {
+            editor.handle_key(
+                key,
+                EditBuffer {
+                    text: &mut self.text,
+                    cursor: &mut self.cursor,
+                    selection: &mut self.selection,
+                    anchor: &mut self.anchor,
+                },
+            )
+        }
+
+        fn typed(&mut self, editor: &mut ModalEditor, keys: &str) {
+            for ch in keys.chars() {
+                let text = ch.to_string();
+                let action = self.press(
+                    editor,
+                    EditKey {
+                        key: &text,
+                        text: Some(&text),
+                        ..Default::default()
+                    },
+                );
+                if action == EditAction::Ignored {
+                    // Insert state leaves plain typing to the input itself.
+                    assert_eq!(
+                        editor.vi_state(),
+                        Some(ViState::Insert),
+                        "{ch} was not handled"
+                    );
+                    TextEditState::insert_text(
+                        &mut self.text,
+                        &mut self.cursor,
+                        &mut self.selection,
+                        &mut self.anchor,
+                        &text,
+                    );
+                }
+            }
+        }
+    }
+
+    fn ctrl(key: &str) -> EditKey<'_> {
+        EditKey {
+            key,
+            ctrl: true,
+            ..Default::default()
+        }
+    }
+
+    fn alt(key: &str) -> EditKey<'_> {
+        EditKey {
+            key,
+            alt: true,
+            ..Default::default()
+        }
+    }
+
+    fn escape() -> EditKey<'static> {
+        EditKey {
+            key: "escape",
+            ..Default::default()
+        }
+    }
+
+    fn vi_normal(text: &str, cursor: usize) -> (ModalEditor, Input) {
+        let mut editor = ModalEditor::new(EditingMode::Vi);
+        let mut input = Input::new(text, cursor + 1);
+        input.press(&mut editor, escape());
+        assert_eq!(editor.vi_state(), Some(ViState::Normal));
+        assert_eq!(input.cursor, cursor);
+        (editor, input)
+    }
+
+    #[test]
+    fn emacs_kills_merge_and_yank_back() {
+        let mut editor = ModalEditor::new(EditingMode::Emacs);
+        let mut input = Input::new("git commit --amend", 18);
+
+        input.press(&mut editor, ctrl("w"));
+        input.press(&mut editor, ctrl("w"));
+        assert_eq!(input.text, "git ");
+        assert_eq!(editor.kill_ring().yank(), Some("commit --amend"));
+
+        input.press(&mut editor, ctrl("a"));
+        input.press(&mut editor, ctrl("k"));
+        assert_eq!(input.text, "");
+        input.press(&mut editor, ctrl("y"));
+        assert_eq!(input.text, "git ");
+        input.press(&mut editor, alt("y"));
+        assert_eq!(input.text, "commit --amend");
+    }
+
+    #[test]
+    fn emacs_transposes_and_moves_by_word() {
+        let mut editor = ModalEditor::new(EditingMode::Emacs);
+        let mut input = Input::new("sl -la", 1);
+        input.press(&mut editor, ctrl("t"));
+        assert_eq!(input.text, "ls -la");
+
+        let mut input = Input::new("status git", 10);
+        input.press(&mut editor, alt("t"));
+        assert_eq!(input.text, "git status");
+
+        input.press(&mut editor, alt("b"));
+        assert_eq!(input.cursor, 4);
+        assert_eq!(input.press(&mut editor, ctrl("c")), EditAction::Ignored);
+    }
+
+    #[test]
+    fn vi_operators_take_motions_and_counts() {
+        let (mut editor, mut input) = vi_normal("echo one two three", 17);
+        input.typed(&mut editor, "0w");
+        assert_eq!(input.cursor, 5);
+        input.typed(&mut editor, "2dw");
+        assert_eq!(input.text, "echo three");
+        input.typed(&mut editor, "u");
+        assert_eq!(input.text, "echo one two three");
+
+        input.typed(&mut editor, "0fo;");
+        assert_eq!(input.cursor, 5);
+        input.typed(&mut editor, "dt ");
+        assert_eq!(input.text, "echo  two three");
+        input.typed(&mut editor, "$p");
+        assert_eq!(input.text, "echo  two threeone");
+
+        input.typed(&mut editor, "0cwprintf");
+        assert_eq!(editor.vi_state(), Some(ViState::Insert));
+        assert_eq!(input.text, "printf  two threeone");
+    }
+
+    #[test]
+    fn vi_visual_selection_and_history_keys() {
+        let (mut editor, mut input) = vi_normal("cargo build --release", 20);
+        input.typed(&mut editor, "0wv");
+        assert_eq!(editor.indicator(), Some("VISUAL"));
+        input.typed(&mut editor, "e");
+        assert_eq!(input.selection, Some((6, 11)));
+        input.typed(&mut editor, "d");
+        assert_eq!(input.text, "cargo  --release");
+        assert_eq!(editor.vi_state(), Some(ViState::Normal));
+
+        let action = input.press(
+            &mut editor,
+            EditKey {
+                key: "k",
+                text: Some("k"),
+                ..Default::default()
+            },
+        );
+        assert_eq!(action, EditAction::HistoryPrevious);
+    }
+}
@@ -0,0 +1,67 @@
+use serde::Deserialize;
+use std::path::PathBuf;
+
+const RULES_FILE: &str = "orbitshell_rules.json";
+
+/// What the sidebar and watched commands leave out, read from
+/// `orbitshell_rules.json` in the working directory.
+#[derive(Clone, Debug, Deserialize)]
+pub struct OrbitshellRules {
+    pub skip_dirs: Vec<String>,
+    pub skip_files: Vec<String>,
+    pub max_file_kb: u64,
+    pub search_limit: usize,
+}
+
+impl Default for OrbitshellRules {
+    fn default() -> Self {
+        Self {
+            skip_dirs: vec![
+                ".git".to_string(),
+                "node_modules".to_string(),
+                "target".to_string(),
+                "dist".to_string(),
+                ".next".to_string(),
+            ],
+            skip_files: vec![],
+            max_file_kb: 512,
+            search_limit: 200,
+        }
+    }
+}
+
+impl OrbitshellRules {
+    pub fn load() -> Self {
+        let path = PathBuf::from(RULES_FILE);
+        if let Ok(contents) = std::fs::read_to_string(&path)
+            && let Ok(parsed) = serde_json::from_str::<OrbitshellRules>(&contents)
+        {
+            return parsed.normalized();
+        }
+        Self::default().normalized()
+    }
+
+    fn normalized(mut self) -> Self {
+        self.skip_dirs = self
+            .skip_dirs
+            .into_iter()
+            .map(|entry| entry.to_lowercase())
+            .collect();
+        self.skip_files = self
+            .skip_files
+            .into_iter()
+            .map(|entry| entry.to_lowercase())
+            .collect();
+        self
+    }
+
+    pub fn skips_dir(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.skip_dirs.iter().any(|entry| entry == &name)
+    }
+
+    pub fn skips_file(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.skip_files.iter().any(|entry| entry == &name)
+    }
+}
@@ -0,0 +1,371 @@
+use crate::terminal::command_line::{TokenKind, tokenize};
+
+/// Keywords that leave the next word in command position, as in `if git ...`.
+const COMMAND_KEYWORDS: [&str; 10] = [
+    "if", "then", "else", "elif", "do", "while", "until", "!", "{", "time",
+];
+/// Keywords followed by something other than a command.
+const OTHER_KEYWORDS: [&str; 10] = [
+    "fi", "done", "esac", "}", "for", "case", "in", "select", "function", "[[",
+];
+
+/// Redirection operators, longest first so `>>` wins over `>`.
+const REDIRECTIONS: [&str; 10] = ["<<<", ">>", ">&", ">|", "<<", "<>", "<&", "&>", ">", "<"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HighlightKind {
+    Plain,
+    Command,
+    UnknownCommand,
+    Keyword,
+    Flag,
+    String,
+    Variable,
+    Operator,
+    Redirection,
+    UnclosedQuote,
+}
+
+impl HighlightKind {
+    pub fn color(self) -> u32 {
+        match self {
+            Self::Plain => 0xdddddd,
+            Self::Command => 0x7ee787,
+            Self::UnknownCommand | Self::UnclosedQuote => 0xff7b72,
+            Self::Keyword | Self::Operator => 0xd2a8ff,
+            Self::Flag => 0x79c0ff,
+            Self::String => 0xa5d6ff,
+            Self::Variable => 0xffa657,
+            Self::Redirection => 0xf2cc60,
+        }
+    }
+}
+
+/// A run of the input drawn in one style. Offsets are in chars, like the
+/// input cursor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HighlightSpan {
+    pub kind: HighlightKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Styles a command line as it is typed. `is_known` says whether a word in
+/// command position names something the shell can run. The spans are sorted
+/// and cover the whole line.
+pub fn highlight(line: &str, is_known: impl Fn(&str) -> bool) -> Vec<HighlightSpan> {
+    let tokens = tokenize(line);
+    let mut spans: Vec<(HighlightKind, usize, usize)> = Vec::new();
+    let mut command_position = true;
+    let mut redirect_target = false;
+
+    for (index, token) in tokens.iter().enumerate() {
+        let previous = index.checked_sub(1).map(|index| &tokens[index]);
+        let glued = previous.is_some_and(|previous| previous.end == token.start);
+        if token.kind == TokenKind::Operator {
+            // The `&` of `2>&1` is part of the redirection, not a separator.
+            if token.raw == "&"
+                && glued
+                && previous.is_some_and(|previous| previous.raw.ends_with(['>', '<']))
+            {
+                spans.push((HighlightKind::Redirection, token.start, token.end));
+                redirect_target = true;
+                continue;
+            }
+            spans.push((HighlightKind::Operator, token.start, token.end));
+            command_position = true;
+            redirect_target = false;
+            continue;
+        }
+
+        let raw = token.raw.as_str();
+        if redirect_target {
+            let kind = if glued {
+                HighlightKind::Redirection
+            } else {
+                HighlightKind::Plain
+            };
+            word_spans(raw, token.start, kind, &mut spans);
+            redirect_target = false;
+            continue;
+        }
+        if let Some(prefix) = redirection_prefix(raw) {
+            spans.push((
+                HighlightKind::Redirection,
+                token.start,
+                token.start + prefix,
+            ));
+            if prefix == raw.len() {
+                redirect_target = true;
+            } else {
+                word_spans(
+                    &raw[prefix..],
+                    token.start + prefix,
+                    HighlightKind::Plain,
+                    &mut spans,
+                );
+            }
+            continue;
+        }
+        if !command_position {
+            let kind = if raw.starts_with('-') {
+                HighlightKind::Flag
+            } else {
+                HighlightKind::Plain
+            };
+            word_spans(raw, token.start, kind, &mut spans);
+            continue;
+        }
+
+        if let Some(name_len) = assignment_name_len(raw) {
+            spans.push((HighlightKind::Variable, token.start, token.start + name_len));
+            word_spans(
+                &raw[name_len..],
+                token.start + name_len,
+                HighlightKind::Plain,
+                &mut spans,
+            );
+            continue;
+        }
+        if COMMAND_KEYWORDS.contains(&raw) || OTHER_KEYWORDS.contains(&raw) {
+            spans.push((HighlightKind::Keyword, token.start, token.end));
+            command_position = COMMAND_KEYWORDS.contains(&raw);
+            continue;
+        }
+        let kind = if token.unterminated_quote || raw.contains('$') || is_known(&token.value) {
+            HighlightKind::Command
+        } else {
+            HighlightKind::UnknownCommand
+        };
+        word_spans(raw, token.start, kind, &mut spans);
+        command_position = false;
+    }
+
+    to_char_spans(line, spans)
+}
+
+/// Splits one word into quoted strings, `$` expansions and the rest, which
+/// gets `base`. An unclosed quote marks everything from it to the end.
+fn word_spans(
+    raw: &str,
+    offset: usize,
+    base: HighlightKind,
+    spans: &mut Vec<(HighlightKind, usize, usize)>,
+) {
+    let mut push = |kind: HighlightKind, start: usize, end: usize| {
+        if start < end {
+            spans.push((kind, offset + start, offset + end));
+        }
+    };
+    let bytes = raw.as_bytes();
+    let mut quote: Option<(u8, usize)> = None;
+    let mut run_start = 0;
+    let mut at = 0;
+    while at < bytes.len() {
+        let byte = bytes[at];
+        match quote {
+            Some((b'\'', _)) => {
+                if byte == b'\'' {
+                    push(HighlightKind::String, run_start, at + 1);
+                    quote = None;
+                    run_start = at + 1;
+                }
+                at += 1;
+            }
+            Some((_, _)) => match byte {
+                b'\\' => at = (at + 2).min(bytes.len()),
+                b'$' => {
+                    push(HighlightKind::String, run_start, at);
+                    let end = variable_end(raw, at);
+                    push(HighlightKind::Variable, at, end);
+                    run_start = end;
+                    at = end;
+                }
+                b'"' => {
+                    push(HighlightKind::String, run_start, at + 1);
+                    quote = None;
+                    run_start = at + 1;
+                    at += 1;
+                }
+                _ => at += 1,
+            },
+            None => match byte {
+                b'\'' | b'"' => {
+                    push(base, run_start, at);
+                    quote = Some((byte, at));
+                    run_start = at;
+                    at += 1;
+                }
+                b'\\' => at = (at + 2).min(bytes.len()),
+                b'$' => {
+                    push(base, run_start, at);
+                    let end = variable_end(raw, at);
+                    push(HighlightKind::Variable, at, end);
+                    run_start = end;
+                    at = end;
+                }
+                _ => at += 1,
+            },
+        }
+    }
+    match quote {
+        Some((_, opened)) => {
+            // Expansions inside the open quote stay marked as unclosed too.
+            spans.retain(|(_, start, _)| *start < offset + opened);
+            let mut push = |kind: HighlightKind, start: usize, end: usize| {
+                if start < end {
+                    spans.push((kind, offset + start, offset + end));
+                }
+            };
+            push(HighlightKind::UnclosedQuote, opened, bytes.len());
+        }
+        None => push(base, run_start, bytes.len()),
+    }
+}
+
+/// End of the `$` expansion starting at byte `at`: `$NAME`, `${...}`,
+/// `$(...)` or a special parameter such as `$?`.
+fn variable_end(raw: &str, at: usize) -> usize {
+    let rest = &raw[at + 1..];
+    let Some(first) = rest.chars().next() else {
+        return at + 1;
+    };
+    let close = match first {
+        '{' => Some('}'),
+        '(' => Some(')'),
+        _ => None,
+    };
+    if let Some(close) = close {
+        let mut depth = 0;
+        for (index, ch) in rest.char_indices() {
+            if ch == first {
+                depth += 1;
+            } else if ch == close {
+                depth -= 1;
+                if depth == 0 {
+                    return at + 1 + index + 1;
+                }
+            }
+        }
+        return raw.len();
+    }
+    if first.is_ascii_alphabetic() || first == '_' {
+        let len = rest
+            .find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '_'))
+            .unwrap_or(rest.len());
+        return at + 1 + len;
+    }
+    if first.is_ascii_digit() || "?!#$@*-".contains(first) {
+        return at + 2;
+    }
+    at + 1
+}
+
+/// Length of a leading redirection such as `>`, `2>>` or `&>`.
+fn redirection_prefix(raw: &str) -> Option<usize> {
+    let digits = raw.len() - raw.trim_start_matches(|ch: char| ch.is_ascii_digit()).len();
+    let rest = &raw[digits..];
+    REDIRECTIONS
+        .iter()
+        .find(|op| rest.starts_with(**op) && !(digits > 0 && op.starts_with('&')))
+        .map(|op| digits + op.len())
+}
+
+/// Length of `NAME` in a `NAME=value` word.
+fn assignment_name_len(raw: &str) -> Option<usize> {
+    let (name, _) = raw.split_once('=')?;
+    let valid = !name.is_empty()
+        && !name.starts_with(|ch: char| ch.is_ascii_digit())
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '_');
+    valid.then_some(name.len())
+}
+
+/// Converts byte spans to char spans and fills the gaps with plain text.
+fn to_char_spans(line: &str, spans: Vec<(HighlightKind, usize, usize)>) -> Vec<HighlightSpan> {
+    let char_at = |byte: usize| line[..byte].chars().count();
+    let total = line.chars().count();
+    let mut out: Vec<HighlightSpan> = Vec::new();
+    let mut push = |kind: HighlightKind, start: usize, end: usize| {
+        if start >= end {
+            return;
+        }
+        match out.last_mut() {
+            Some(last) if last.kind == kind && last.end == start => last.end = end,
+            _ => out.push(HighlightSpan { kind, start, end }),
+        }
+    };
+    let mut pos = 0;
+    for (kind, start, end) in spans {
+        let (start, end) = (char_at(start), char_at(end));
+        push(HighlightKind::Plain, pos, start);
+        push(kind, start.max(pos), end);
+        pos = pos.max(end);
+    }
+    push(HighlightKind::Plain, pos, total);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(line: &str) -> Vec<(HighlightKind, String)> {
+        let chars: Vec<char> = line.chars().collect();
+        highlight(line, |command| ["git", "echo", "grep"].contains(&command))
+            .into_iter()
+            .filter(|span| span.kind != HighlightKind::Plain)
+            .map(|span| (span.kind, chars[span.start..span.end].iter().collect()))
+            .collect()
+    }
+
+    #[test]
+    fn colors_commands_flags_strings_and_operators() {
+        use HighlightKind::*;
+
+        assert_eq!(
+            kinds("git log --oneline | grep \"fix $NAME\" 2>&1 > out.txt"),
+            vec![
+                (Command, "git".to_string()),
+                (Flag, "--oneline".to_string()),
+                (Operator, "|".to_string()),
+                (Command, "grep".to_string()),
+                (String, "\"fix ".to_string()),
+                (Variable, "$NAME".to_string()),
+                (String, "\"".to_string()),
+                (Redirection, "2>&1".to_string()),
+                (Redirection, ">".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn marks_unknown_commands_and_unclosed_quotes() {
+        use HighlightKind::*;
+
+        assert_eq!(
+            kinds("FOO=1 gti status && echo 'oops"),
+            vec![
+                (Variable, "FOO".to_string()),
+                (UnknownCommand, "gti".to_string()),
+                (Operator, "&&".to_string()),
+                (Command, "echo".to_string()),
+                (UnclosedQuote, "'oops".to_string()),
+            ]
+        );
+        assert_eq!(
+            kinds("if grep -q x f; then ech ok; fi"),
+            vec![
+                (Keyword, "if".to_string()),
+                (Command, "grep".to_string()),
+                (Flag, "-q".to_string()),
+                (Operator, ";".to_string()),
+                (Keyword, "then".to_string()),
+                (UnknownCommand, "ech".to_string()),
+                (Operator, ";".to_string()),
+                (Keyword, "fi".to_string()),
+            ]
+        );
+    }
+}
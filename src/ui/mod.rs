@@ -18,6 +18,7 @@ pub struct Workspace {
     user_menu_open: bool,
     sidebar: Entity<views::sidebar_view::SidebarView>,
     tab_bar: Entity<views::tab_bar::TabBar>,
+    window_title: String,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -38,10 +39,20 @@ pub mod views {
 }
 
 pub mod appearance;
+pub mod block_diff;
+pub mod edit_mode;
+pub mod file_rules;
 pub mod icons;
+pub mod input_highlight;
 pub mod launch;
+pub mod notifications;
+pub mod output_selection;
 pub mod recent;
+pub mod runbook;
+pub mod terminal_settings;
 pub mod text_edit;
+pub mod text_width;
+pub mod watch;
 
 pub(crate) fn move_index(index: usize, from: usize, to: usize) -> usize {
     if index == from {
@@ -82,6 +93,7 @@ impl Workspace {
             user_menu_open: false,
             sidebar: cx.new(|cx| views::sidebar_view::SidebarView::new(cx)),
             tab_bar,
+            window_title: String::new(),
         };
 
         cx.subscribe(
@@ -251,6 +263,40 @@ impl Workspace {
         cx.notify();
     }
 
+    fn add_replay_tab(&mut self, recording: PathBuf, cx: &mut Context<Self>) {
+        let name = recording
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Recording".to_string());
+        let tab = cx.new(|cx| views::tab_view::TabView::new_replay(cx, recording.clone()));
+        let tab_id = tab.entity_id();
+        cx.subscribe(
+            &tab,
+            move |workspace, _tab, event: &views::tab_view::TabViewEvent, cx| {
+                workspace.on_tab_view_event(tab_id, event, cx);
+            },
+        )
+        .detach();
+
+        let path = recording
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."));
+
+        self.tabs.push(tab);
+        self.tab_ids.push(tab_id);
+        self.tab_paths.push(path);
+        self.tab_kinds.push(TabKind::Utility);
+        self.active_tab = self.tabs.len().saturating_sub(1);
+
+        self.tab_bar.update(cx, |tab_bar, cx| {
+            tab_bar.add_tab(name, "Replay".to_string(), cx);
+            tab_bar.set_sidebar_visible(self.sidebar_visible, cx);
+            tab_bar.set_active(self.active_tab, cx);
+        });
+        cx.notify();
+    }
+
     fn open_file_in_active_tab(
         &mut self,
         file_path: PathBuf,
@@ -263,7 +309,9 @@ impl Workspace {
         };
 
         let _ = tab.update(cx, |view, cx| {
-            if let (Some(line), Some(query)) = (line, query.clone()) {
+            if let Some(line) = line {
+                // Without a query the line is shown but nothing is highlighted.
+                let query = query.clone().unwrap_or_default();
                 view.open_file_preview_at_search_result(file_path.clone(), line, query, cx);
             } else {
                 view.open_file_preview(file_path.clone(), cx);
@@ -277,6 +325,18 @@ impl Workspace {
                 sidebar.set_root(path);
             });
         }
+        self.sync_sidebar_problems(cx);
+    }
+
+    fn sync_sidebar_problems(&mut self, cx: &mut Context<Self>) {
+        let groups = self
+            .tabs
+            .get(self.active_tab)
+            .map(|tab| tab.read(cx).problem_groups().to_vec())
+            .unwrap_or_default();
+        self.sidebar.update(cx, |sidebar, cx| {
+            sidebar.set_problems(groups, cx);
+        });
     }
 
     fn on_tab_event(&mut self, event: &views::tab_bar::TabBarEvent, cx: &mut Context<Self>) {
@@ -594,9 +654,109 @@ impl Workspace {
                     self.clone_repository_in_tab(index, parent.clone(), url.clone(), cx);
                 }
             }
+            views::tab_view::TabViewEvent::CommandFinished {
+                command,
+                exit_code,
+                duration,
+            } => {
+                if let Some(index) = self.tab_ids.iter().position(|id| *id == tab_id) {
+                    self.on_command_finished(index, command, *exit_code, *duration, cx);
+                }
+            }
+            views::tab_view::TabViewEvent::TitleChanged(title) => {
+                if let Some(index) = self.tab_ids.iter().position(|id| *id == tab_id) {
+                    self.on_program_title(index, title, cx);
+                }
+            }
+            views::tab_view::TabViewEvent::Bell => {
+                if let Some(index) = self.tab_ids.iter().position(|id| *id == tab_id)
+                    && !self.is_tab_in_view(index, cx)
+                {
+                    self.tab_bar.update(cx, |tab_bar, cx| {
+                        if tab_bar.badge(index).is_none() {
+                            tab_bar.set_badge(index, Some(views::tab_bar::TabBadge::Bell), cx);
+                        }
+                    });
+                }
+            }
+            views::tab_view::TabViewEvent::ReplayRecording(path) => {
+                self.add_replay_tab(path.clone(), cx);
+            }
+            views::tab_view::TabViewEvent::DevServerChanged(url) => {
+                if let Some(index) = self.tab_ids.iter().position(|id| *id == tab_id) {
+                    self.tab_bar.update(cx, |tab_bar, cx| {
+                        tab_bar.set_dev_server(index, url.clone(), cx);
+                    });
+                }
+            }
+            views::tab_view::TabViewEvent::ProblemsChanged => {
+                if self.tab_ids.get(self.active_tab) == Some(&tab_id) {
+                    self.sync_sidebar_problems(cx);
+                }
+            }
         }
     }
 
+    /// Whether the tab is the active one in a focused window.
+    fn is_tab_in_view(&self, index: usize, cx: &mut Context<Self>) -> bool {
+        let this = cx.entity_id();
+        index == self.active_tab
+            && cx
+                .active_window()
+                .and_then(|window| window.downcast::<Workspace>())
+                .and_then(|window| window.entity(cx).ok())
+                .is_some_and(|workspace| workspace.entity_id() == this)
+    }
+
+    /// Shows a title set by the program in the tab, unless the user named it.
+    /// An empty title gives the tab back its own name.
+    fn on_program_title(&mut self, index: usize, title: &str, cx: &mut Context<Self>) {
+        self.tab_bar.update(cx, |tab_bar, cx| {
+            if title.is_empty() {
+                tab_bar.clear_program_title(index, cx);
+            } else {
+                tab_bar.set_program_title(index, title.to_string(), cx);
+            }
+        });
+        cx.notify();
+    }
+
+    /// Drops the finished program's title and reports a long command that
+    /// ended while its tab was out of view.
+    fn on_command_finished(
+        &mut self,
+        index: usize,
+        command: &str,
+        exit_code: Option<i32>,
+        duration: std::time::Duration,
+        cx: &mut Context<Self>,
+    ) {
+        self.tab_bar.update(cx, |tab_bar, cx| {
+            tab_bar.clear_program_title(index, cx);
+        });
+
+        let settings = terminal_settings::TerminalSettings::current();
+        if !settings.completion_notifications.should_notify(duration) {
+            return;
+        }
+        if self.is_tab_in_view(index, cx) {
+            return;
+        }
+
+        let badge = match exit_code {
+            Some(code) if code != 0 => views::tab_bar::TabBadge::Failed,
+            _ => views::tab_bar::TabBadge::Succeeded,
+        };
+        self.tab_bar.update(cx, |tab_bar, cx| {
+            tab_bar.set_badge(index, Some(badge), cx);
+        });
+        // Notifications go to other processes, so secrets never reach them.
+        let command = terminal_settings::TerminalSettings::current_secret_scanner().redact(command);
+        let (summary, body) =
+            notifications::command_finished_message(&command, exit_code, duration);
+        notifications::send(summary, body);
+    }
+
     fn open_base_terminal_in_tab(
         &mut self,
         index: usize,
@@ -798,7 +958,16 @@ impl Workspace {
 }
 
 impl Render for Workspace {
-    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let title = match self.tab_bar.read(cx).active_name() {
+            Some(name) if !name.is_empty() => format!("{name} - OrbitShell"),
+            _ => "OrbitShell".to_string(),
+        };
+        if title != self.window_title {
+            window.set_window_title(&title);
+            self.window_title = title;
+        }
+
         let tab_kind = self
             .tab_kinds
             .get(self.active_tab)
@@ -823,14 +992,14 @@ impl Render for Workspace {
                     .flex()
                     .flex_1()
                     .min_h(px(0.0))
-                    .on_mouse_move(_cx.listener(Self::on_sidebar_resize_mouse_move))
+                    .on_mouse_move(cx.listener(Self::on_sidebar_resize_mouse_move))
                     .on_mouse_up(
                         MouseButton::Left,
-                        _cx.listener(Self::on_sidebar_resize_mouse_up),
+                        cx.listener(Self::on_sidebar_resize_mouse_up),
                     )
                     .on_mouse_up_out(
                         MouseButton::Left,
-                        _cx.listener(Self::on_sidebar_resize_mouse_up),
+                        cx.listener(Self::on_sidebar_resize_mouse_up),
                     )
                     .child(
                         // Sidebar
@@ -864,7 +1033,7 @@ impl Render for Workspace {
                             .hover(|style| style.bg(rgb(0x1a1f28)).border_color(rgb(0x2f3b4f)))
                             .on_mouse_down(
                                 MouseButton::Left,
-                                _cx.listener(Self::on_sidebar_resize_mouse_down),
+                                cx.listener(Self::on_sidebar_resize_mouse_down),
                             )
                     } else {
                         div()
@@ -886,7 +1055,7 @@ impl Render for Workspace {
             );
 
         if self.user_menu_open {
-            root = root.child(self.render_user_menu(_cx));
+            root = root.child(self.render_user_menu(cx));
         }
 
         root
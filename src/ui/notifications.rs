@@ -0,0 +1,163 @@
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::ui::views::tab_view::format_command_duration;
+
+const APP_NAME: &str = "OrbitShell";
+const APP_ICON: &str = "orbitshell";
+
+/// Bells closer together than this play one sound.
+const BEEP_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Summary and body of the notification for a finished command.
+pub fn command_finished_message(
+    command: &str,
+    exit_code: Option<i32>,
+    duration: Duration,
+) -> (String, String) {
+    let took = format_command_duration(duration);
+    match exit_code {
+        Some(0) => (
+            "Command finished".to_string(),
+            format!("{command}\nSucceeded in {took}"),
+        ),
+        Some(code) => (
+            "Command failed".to_string(),
+            format!("{command}\nExit code {code} after {took}"),
+        ),
+        None => (
+            "Command finished".to_string(),
+            format!("{command}\nFinished in {took}"),
+        ),
+    }
+}
+
+/// Shows a desktop notification through the freedesktop notification
+/// service. Runs on its own thread; failures, such as no notification daemon,
+/// are only logged.
+pub fn send(summary: String, body: String) {
+    std::thread::spawn(move || {
+        if let Err(err) = send_blocking(&summary, &body) {
+            eprintln!("failed to send desktop notification: {err}");
+        }
+    });
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn send_blocking(summary: &str, body: &str) -> anyhow::Result<()> {
+    use std::collections::HashMap;
+    use zbus::zvariant::Value;
+
+    let connection = zbus::blocking::Connection::session()?;
+    let actions: Vec<&str> = Vec::new();
+    let hints: HashMap<&str, Value> = HashMap::new();
+    connection.call_method(
+        Some("org.freedesktop.Notifications"),
+        "/org/freedesktop/Notifications",
+        Some("org.freedesktop.Notifications"),
+        "Notify",
+        &(
+            APP_NAME, 0u32, APP_ICON, summary, body, actions, hints, -1i32,
+        ),
+    )?;
+    Ok(())
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn send_blocking(_summary: &str, _body: &str) -> anyhow::Result<()> {
+    let _ = (APP_NAME, APP_ICON);
+    Ok(())
+}
+
+/// Plays the desktop's bell sound. A program ringing in a loop gets one
+/// sound per [`BEEP_INTERVAL`]; failures, such as no sound player, are only
+/// logged.
+pub fn beep() {
+    static LAST: Mutex<Option<Instant>> = Mutex::new(None);
+    {
+        let mut last = LAST.lock().unwrap_or_else(PoisonError::into_inner);
+        if last.is_some_and(|at| at.elapsed() < BEEP_INTERVAL) {
+            return;
+        }
+        *last = Some(Instant::now());
+    }
+    std::thread::spawn(|| {
+        if let Err(err) = beep_blocking() {
+            eprintln!("failed to play the bell sound: {err}");
+        }
+    });
+}
+
+fn play(program: &str, args: &[&str]) -> std::io::Result<bool> {
+    Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+}
+
+#[cfg(target_os = "macos")]
+fn beep_blocking() -> anyhow::Result<()> {
+    if !play("afplay", &["/System/Library/Sounds/Tink.aiff"])? {
+        anyhow::bail!("afplay failed");
+    }
+    Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn beep_blocking() -> anyhow::Result<()> {
+    // The sound theme's bell through libcanberra, or the stock freedesktop
+    // sound where that is not installed.
+    if play("canberra-gtk-play", &["--id", "bell"]).unwrap_or(false) {
+        return Ok(());
+    }
+    if !play("paplay", &["/usr/share/sounds/freedesktop/stereo/bell.oga"])? {
+        anyhow::bail!("paplay failed");
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn beep_blocking() -> anyhow::Result<()> {
+    let _ = play;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::terminal_settings::CompletionNotifications;
+
+    #[test]
+    fn message_includes_exit_status_and_duration() {
+        assert_eq!(
+            command_finished_message("cargo build", Some(0), Duration::from_secs(75)),
+            (
+                "Command finished".to_string(),
+                "cargo build\nSucceeded in 1m 15s".to_string()
+            )
+        );
+        assert_eq!(
+            command_finished_message("make", Some(2), Duration::from_millis(12_500)),
+            (
+                "Command failed".to_string(),
+                "make\nExit code 2 after 12.5s".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn short_commands_are_not_reported() {
+        let settings = CompletionNotifications::default();
+        assert!(!settings.should_notify(Duration::from_secs(3)));
+        assert!(settings.should_notify(Duration::from_secs(10)));
+        let disabled = CompletionNotifications {
+            enabled: false,
+            ..settings
+        };
+        assert!(!disabled.should_notify(Duration::from_secs(600)));
+    }
+}
@@ -0,0 +1,84 @@
+use std::ops::Range;
+
+use crate::ui::text_width::offset_at_column;
+
+/// Punctuation dropped from the end of a word, as in a path ending a
+/// sentence or `file.rs:12:` in compiler output.
+const TRAILING_PUNCTUATION: [char; 5] = ['.', ',', ':', ';', '?'];
+
+/// Chars of the word around char `at` for a double-click. Paths, URLs,
+/// `host:port` and `file:line` stay in one piece.
+pub fn word_at(line: &str, at: usize) -> Range<usize> {
+    let chars: Vec<char> = line.chars().collect();
+    if chars.is_empty() {
+        return 0..0;
+    }
+    let at = at.min(chars.len() - 1);
+    if !is_word_char(chars[at]) {
+        return at..at + 1;
+    }
+    let mut start = at;
+    while start > 0 && is_word_char(chars[start - 1]) {
+        start -= 1;
+    }
+    let mut end = at + 1;
+    while end < chars.len() && is_word_char(chars[end]) {
+        end += 1;
+    }
+    while end > at + 1 && TRAILING_PUNCTUATION.contains(&chars[end - 1]) {
+        end -= 1;
+    }
+    start..end
+}
+
+/// The part of `line` between two display columns, for rectangular
+/// selection. Trailing spaces are dropped.
+pub fn column_slice(line: &str, columns: Range<usize>) -> String {
+    let start = offset_at_column(line, columns.start);
+    let end = offset_at_column(line, columns.end).max(start);
+    line.chars()
+        .skip(start)
+        .take(end - start)
+        .collect::<String>()
+        .trim_end()
+        .to_string()
+}
+
+fn is_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || "-_./\\~:@%+=?&#$*!,;".contains(ch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(line: &str, at: usize) -> String {
+        line.chars().collect::<Vec<_>>()[word_at(line, at)]
+            .iter()
+            .collect()
+    }
+
+    #[test]
+    fn double_click_keeps_paths_and_urls_whole() {
+        let line = "error: see src/ui/mod.rs:42:7, or https://example.com/a?b=1.";
+        assert_eq!(word(line, 14), "src/ui/mod.rs:42:7");
+        assert_eq!(word(line, 40), "https://example.com/a?b=1");
+        assert_eq!(word(line, 2), "error");
+        assert_eq!(word("(foo) bar", 2), "foo");
+        assert_eq!(word("a  b", 1), " ");
+    }
+
+    #[test]
+    fn rectangle_slices_by_display_column() {
+        let table = [
+            "NAME      READY   STATUS",
+            "web-1     1/1     Running",
+            "日本      0/1     Pending",
+        ];
+        let column: Vec<String> = table
+            .iter()
+            .map(|line| column_slice(line, 10..17))
+            .collect();
+        assert_eq!(column, vec!["READY", "1/1", "0/1"]);
+    }
+}
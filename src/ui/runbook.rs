@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use anyhow::Result;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::acp::storage;
+use crate::terminal::command_line::{split_commands, tokenize};
+
+/// Fence languages that run in the tab's shell.
+const SHELL_LANGUAGES: [&str; 8] = [
+    "sh",
+    "bash",
+    "zsh",
+    "shell",
+    "console",
+    "fish",
+    "powershell",
+    "pwsh",
+];
+
+/// Lines of output kept for each fence's last run.
+pub const MAX_SAVED_OUTPUT_LINES: usize = 200;
+
+/// Whether a fence of `language` gets a run button.
+pub fn is_runnable(language: Option<&str>) -> bool {
+    language
+        .and_then(|language| language.split_whitespace().next())
+        .is_some_and(|language| {
+            SHELL_LANGUAGES
+                .iter()
+                .any(|shell| shell.eq_ignore_ascii_case(language))
+        })
+}
+
+/// The command sent for a fence. Comments and blank lines are dropped,
+/// lines ending in `\`, `|`, `&&` or `||` continue onto the next, and
+/// top-level commands are chained with `&&` so the fence stops at the first
+/// failure. Loops, conditionals, `{ }` groups and heredocs keep their lines,
+/// separated by newlines. A `console` fence, or one with `$ ` or `> `
+/// prompts, is a transcript: only the prompt lines and their continuations
+/// run, and the output printed between them is left out.
+pub fn fence_command(language: Option<&str>, lines: &[String]) -> String {
+    let transcript = language
+        .and_then(|language| language.split_whitespace().next())
+        .is_some_and(|language| language.eq_ignore_ascii_case("console"))
+        || lines.iter().any(|line| prompt_command(line).is_some());
+    let mut commands: Vec<String> = Vec::new();
+    let mut continued = false;
+    // Compound commands still open, and the heredoc being read.
+    let mut depth = 0usize;
+    let mut heredoc: Option<(String, bool)> = None;
+    for line in lines {
+        if let Some((delimiter, strip_tabs)) = &heredoc {
+            let body = match line.strip_prefix("> ") {
+                Some(body) if transcript => body,
+                _ => line.as_str(),
+            };
+            let ends = if *strip_tabs {
+                body.trim_start_matches('\t')
+            } else {
+                body
+            } == delimiter;
+            if let Some(last) = commands.last_mut() {
+                last.push('\n');
+                last.push_str(body);
+            }
+            if ends {
+                heredoc = None;
+            }
+            continue;
+        }
+        let line = match prompt_command(line) {
+            Some(command) => command,
+            None if transcript && !continued && depth == 0 => continue,
+            None => line.trim(),
+        };
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (text, continues) = match line.strip_suffix('\\') {
+            Some(text) => (text.trim_end(), true),
+            None => (
+                line,
+                line.ends_with('|') || line.ends_with("&&") || line.ends_with("||"),
+            ),
+        };
+        match commands.last_mut() {
+            Some(last) if continued => {
+                last.push(' ');
+                last.push_str(text);
+            }
+            Some(last) if depth > 0 => {
+                last.push('\n');
+                last.push_str(text);
+            }
+            _ => commands.push(text.to_string()),
+        }
+        depth = compound_depth(depth, text);
+        heredoc = heredoc_delimiter(text);
+        continued = continues;
+    }
+    commands.join(" && ")
+}
+
+/// `depth` after the compound commands `line` opens and closes, such as
+/// `for ...; do` and `done`.
+fn compound_depth(mut depth: usize, line: &str) -> usize {
+    let tokens = tokenize(line);
+    for words in split_commands(&tokens) {
+        match words[0].raw.as_str() {
+            "if" | "for" | "while" | "until" | "case" | "select" | "{" => depth += 1,
+            "fi" | "done" | "esac" | "}" => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        // `name() {` opens a function body.
+        if words.len() > 1 && words[words.len() - 1].raw == "{" {
+            depth += 1;
+        }
+    }
+    depth
+}
+
+/// The delimiter of a heredoc `line` starts, and whether its lines may be
+/// indented with tabs (`<<-`).
+fn heredoc_delimiter(line: &str) -> Option<(String, bool)> {
+    let captures = heredoc_regex().captures(line)?;
+    Some((captures[2].to_string(), !captures[1].is_empty()))
+}
+
+fn heredoc_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r#"(?:^|[^<])<<(-?)\s*['"]?([A-Za-z_][A-Za-z0-9_]*)['"]?"#)
+            .expect("heredoc pattern is valid")
+    })
+}
+
+/// The command after a `$ ` or `> ` prompt.
+fn prompt_command(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    line.strip_prefix("$ ")
+        .or_else(|| line.strip_prefix("> "))
+        .map(str::trim)
+}
+
+/// Names of the `{{name}}` variables in `command`, in order of first use.
+pub fn variables(command: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for capture in variable_regex().captures_iter(command) {
+        let name = capture[1].to_string();
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// `command` with each variable replaced by its value. Variables without a
+/// value are left as they are.
+pub fn fill_variables(command: &str, values: &HashMap<String, String>) -> String {
+    variable_regex()
+        .replace_all(command, |capture: &regex::Captures| {
+            values
+                .get(&capture[1])
+                .cloned()
+                .unwrap_or_else(|| capture[0].to_string())
+        })
+        .into_owned()
+}
+
+fn variable_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}\}").expect("variable pattern is valid")
+    })
+}
+
+/// How a fence went the last time it ran.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FenceRun {
+    /// The command sent, with variables filled in.
+    pub command: String,
+    pub output_lines: Vec<String>,
+    pub exit_code: Option<i32>,
+    /// Unix time the run finished.
+    pub finished_at: i64,
+}
+
+/// What is kept between sessions for one runbook file.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunbookResults {
+    /// Last runs, keyed by the fence's source so edits elsewhere in the
+    /// file keep them.
+    #[serde(default)]
+    pub runs: HashMap<String, FenceRun>,
+    /// Values last given for the variables, offered again at the prompt.
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+}
+
+impl RunbookResults {
+    /// The saved results of `runbook`, or none when there are none yet or
+    /// they cannot be read.
+    pub fn load(runbook: &Path) -> Self {
+        results_file(runbook)
+            .and_then(|path| storage::load_optional_json_file(&path))
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, runbook: &Path) -> Result<()> {
+        storage::save_json_file(&results_file(runbook)?, self)
+    }
+}
+
+/// Fence source used as the key of its saved run.
+pub fn fence_key(lines: &[String]) -> String {
+    lines.join("\n")
+}
+
+fn results_file(runbook: &Path) -> Result<PathBuf> {
+    let runbook = runbook
+        .canonicalize()
+        .unwrap_or_else(|_| runbook.to_path_buf());
+    // FNV-1a, so the file name stays the same across builds.
+    let hash = runbook
+        .to_string_lossy()
+        .bytes()
+        .fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+        });
+    Ok(storage::app_root()?
+        .join("runbooks")
+        .join(format!("{hash:016x}.json")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn fence_becomes_one_chained_command() {
+        let fence = lines(
+            "# restart the api\nkubectl rollout restart deploy/api \\\n    -n {{namespace}}\n\nkubectl get pods |\n  grep api\necho done",
+        );
+        assert_eq!(
+            fence_command(Some("bash"), &fence),
+            "kubectl rollout restart deploy/api -n {{namespace}} && kubectl get pods | grep api && echo done"
+        );
+        assert!(is_runnable(Some("bash")));
+        assert!(is_runnable(Some("Shell title=restart")));
+        assert!(!is_runnable(Some("rust")));
+        assert!(!is_runnable(None));
+    }
+
+    #[test]
+    fn transcript_fences_run_only_their_prompt_lines() {
+        let fence = lines(
+            "$ kubectl get pods \\\n    -n {{namespace}}\nNAME      READY   STATUS\napi-7d9f   1/1     Running\n$ echo done\ndone",
+        );
+        assert_eq!(
+            fence_command(Some("bash"), &fence),
+            "kubectl get pods -n {{namespace}} && echo done"
+        );
+
+        let fence = lines("> git status\nOn branch main\n> git log -1");
+        assert_eq!(fence_command(None, &fence), "git status && git log -1");
+
+        // Without prompts, a console fence is all output.
+        let fence = lines("total 0\ndrwxr-xr-x  2 me  staff  64 .");
+        assert_eq!(fence_command(Some("console"), &fence), "");
+    }
+
+    #[test]
+    fn compound_commands_and_heredocs_keep_their_lines() {
+        let fence = lines(
+            "cd logs\nfor f in *.log; do\n  gzip \"$f\"\ndone\nif [ -d old ]; then\n  rm -r old\nfi\nls",
+        );
+        assert_eq!(
+            fence_command(Some("sh"), &fence),
+            "cd logs && for f in *.log; do\ngzip \"$f\"\ndone && if [ -d old ]; then\nrm -r old\nfi && ls"
+        );
+
+        let fence = lines(
+            "cat <<'EOF' > notes.txt\n# not a comment\n  indented\nEOF\ncase \"$1\" in\n  start) run ;;\nesac",
+        );
+        assert_eq!(
+            fence_command(Some("bash"), &fence),
+            "cat <<'EOF' > notes.txt\n# not a comment\n  indented\nEOF && case \"$1\" in\nstart) run ;;\nesac"
+        );
+
+        let fence = lines("$ while true; do\n> date\n> done\nMon Jan 1");
+        assert_eq!(
+            fence_command(Some("console"), &fence),
+            "while true; do\ndate\ndone"
+        );
+    }
+
+    #[test]
+    fn variables_are_listed_once_and_filled() {
+        let command = "ssh {{ host }} 'tail -n {{lines}} /var/log/{{host}}.log'";
+        assert_eq!(variables(command), vec!["host", "lines"]);
+
+        let values = HashMap::from([("host".to_string(), "db-1".to_string())]);
+        assert_eq!(
+            fill_variables(command, &values),
+            "ssh db-1 'tail -n {{lines}} /var/log/db-1.log'"
+        );
+    }
+}
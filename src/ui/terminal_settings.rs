@@ -0,0 +1,144 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use crate::acp::storage;
+use crate::secrets::SecretScanner;
+use crate::terminal::command_guard::GuardConfig;
+use crate::ui::edit_mode::EditingMode;
+
+const TERMINAL_SETTINGS_FILE: &str = "terminal.json";
+
+static CURRENT: OnceLock<Mutex<TerminalSettings>> = OnceLock::new();
+static SCANNER: OnceLock<Mutex<Arc<SecretScanner>>> = OnceLock::new();
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct TerminalSettings {
+    pub editing_mode: EditingMode,
+    /// Extra regexes for the secret scanner. A capture group, when present,
+    /// marks the part to hide.
+    pub secret_patterns: Vec<String>,
+    /// Confirmation rules for destructive commands. Projects can add rules
+    /// with an `orbitshell_guard.json`, but not switch any off.
+    pub command_guard: GuardConfig,
+    pub completion_notifications: CompletionNotifications,
+    pub bell: BellStyle,
+    /// Copies output to the clipboard as soon as it is selected.
+    pub copy_on_select: bool,
+}
+
+/// What a terminal bell does in the tab that rang it. Background tabs get a
+/// badge either way.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BellStyle {
+    #[default]
+    Visual,
+    /// The desktop's bell sound.
+    Audible,
+    Off,
+}
+
+impl BellStyle {
+    pub const ALL: [BellStyle; 3] = [Self::Visual, Self::Audible, Self::Off];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Visual => "Flash",
+            Self::Audible => "Sound",
+            Self::Off => "Off",
+        }
+    }
+}
+
+/// Badges and desktop notifications for long commands that finish while
+/// their tab is not in view.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct CompletionNotifications {
+    pub enabled: bool,
+    /// Commands that finish sooner than this are not reported.
+    pub min_duration_secs: u64,
+}
+
+impl Default for CompletionNotifications {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_duration_secs: 10,
+        }
+    }
+}
+
+impl CompletionNotifications {
+    pub fn should_notify(&self, duration: Duration) -> bool {
+        self.enabled && duration >= Duration::from_secs(self.min_duration_secs)
+    }
+}
+
+impl TerminalSettings {
+    pub fn load() -> Self {
+        Self::load_result().unwrap_or_default()
+    }
+
+    pub fn load_result() -> Result<Self> {
+        let Some(path) = Self::settings_path() else {
+            return Ok(Self::default());
+        };
+        Ok(storage::load_optional_json_file(&path)?.unwrap_or_default())
+    }
+
+    /// The settings as last loaded or saved by this process. Input handlers
+    /// read this on every key, so it avoids touching the disk.
+    pub fn current() -> Self {
+        Self::cache()
+            .lock()
+            .map(|settings| settings.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Ok(mut current) = Self::cache().lock() {
+            *current = self.clone();
+        }
+        if let Ok(mut scanner) = Self::scanner_cache().lock() {
+            *scanner = Arc::new(self.secret_scanner().0);
+        }
+        let Some(path) = Self::settings_path() else {
+            return Ok(());
+        };
+        storage::save_json_file(&path, self)
+    }
+
+    /// The secret scanner for the current patterns, and any patterns that
+    /// failed to compile.
+    pub fn secret_scanner(&self) -> (SecretScanner, Vec<String>) {
+        SecretScanner::with_user_patterns(&self.secret_patterns)
+    }
+
+    /// The scanner for the current settings, shared by every tab and
+    /// rebuilt when the settings are saved.
+    pub fn current_secret_scanner() -> Arc<SecretScanner> {
+        Self::scanner_cache()
+            .lock()
+            .map(|scanner| scanner.clone())
+            .unwrap_or_default()
+    }
+
+    fn scanner_cache() -> &'static Mutex<Arc<SecretScanner>> {
+        SCANNER.get_or_init(|| Mutex::new(Arc::new(Self::current().secret_scanner().0)))
+    }
+
+    fn cache() -> &'static Mutex<TerminalSettings> {
+        CURRENT.get_or_init(|| Mutex::new(Self::load()))
+    }
+
+    fn settings_path() -> Option<PathBuf> {
+        storage::app_root()
+            .ok()
+            .map(|root| root.join(TERMINAL_SETTINGS_FILE))
+    }
+}
@@ -1,5 +1,40 @@
 pub struct TextEditState;
 
+/// One caret of a multi-cursor edit. `anchor != head` when it has a selection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CursorRange {
+    pub anchor: usize,
+    pub head: usize,
+}
+
+impl CursorRange {
+    pub fn caret(at: usize) -> Self {
+        Self {
+            anchor: at,
+            head: at,
+        }
+    }
+
+    pub fn start(&self) -> usize {
+        self.anchor.min(self.head)
+    }
+
+    pub fn end(&self) -> usize {
+        self.anchor.max(self.head)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.anchor == self.head
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum MultiEdit<'a> {
+    Insert(&'a str),
+    DeleteBackward,
+    DeleteForward,
+}
+
 impl TextEditState {
     pub fn select_all(
         text: &str,
@@ -111,4 +146,139 @@ impl TextEditState {
         *cursor = cursor.saturating_sub(1);
         Self::clear_selection(selection, anchor);
     }
+
+    /// Applies `edit` at every range at once. Selections are replaced or
+    /// deleted; empty ranges insert or delete next to the caret. Returns the
+    /// new caret of each range, in the order given. Callers merge duplicate
+    /// ranges first, or the edit is applied once per duplicate.
+    pub fn edit_at_ranges(
+        text: &mut String,
+        ranges: &[CursorRange],
+        edit: MultiEdit,
+    ) -> Vec<usize> {
+        let chars: Vec<char> = text.chars().collect();
+        let len = chars.len();
+        let mut order: Vec<usize> = (0..ranges.len()).collect();
+        order.sort_by_key(|&index| (ranges[index].start(), ranges[index].end()));
+
+        let mut out = String::with_capacity(text.len());
+        let mut out_len = 0;
+        let mut pos = 0;
+        let mut carets = vec![0; ranges.len()];
+        for index in order {
+            let range = ranges[index];
+            let (mut start, mut end) = (range.start().min(len), range.end().min(len));
+            if range.is_empty() {
+                match edit {
+                    MultiEdit::DeleteBackward => start = start.saturating_sub(1),
+                    MultiEdit::DeleteForward => end = (end + 1).min(len),
+                    MultiEdit::Insert(_) => {}
+                }
+            }
+            // Overlaps with an earlier range were already consumed by it.
+            let start = start.max(pos);
+            let end = end.max(start);
+            for ch in &chars[pos..start] {
+                out.push(*ch);
+                out_len += 1;
+            }
+            if let MultiEdit::Insert(insert) = edit {
+                out.push_str(insert);
+                out_len += insert.chars().count();
+            }
+            carets[index] = out_len;
+            pos = end;
+        }
+        out.extend(&chars[pos..]);
+        *text = out;
+        carets
+    }
+
+    /// Removes the chars in `start..end` and returns them.
+    pub fn remove_range(text: &mut String, start: usize, end: usize) -> String {
+        let mut kept = String::with_capacity(text.len());
+        let mut removed = String::new();
+        for (i, ch) in text.chars().enumerate() {
+            if i >= start && i < end {
+                removed.push(ch);
+            } else {
+                kept.push(ch);
+            }
+        }
+        *text = kept;
+        removed
+    }
+
+    /// Start of the word before `from`, treating any run of non-whitespace
+    /// as a word when `big` is set and splitting at punctuation otherwise.
+    pub fn word_start_before(chars: &[char], from: usize, big: bool) -> usize {
+        let mut i = from.min(chars.len());
+        while i > 0 && chars[i - 1].is_whitespace() {
+            i -= 1;
+        }
+        if i == 0 {
+            return 0;
+        }
+        let class = char_class(chars[i - 1], big);
+        while i > 0 && char_class(chars[i - 1], big) == class {
+            i -= 1;
+        }
+        i
+    }
+
+    /// Start of the next word after the one at `from`.
+    pub fn next_word_start(chars: &[char], from: usize, big: bool) -> usize {
+        let len = chars.len();
+        let mut i = from;
+        if i >= len {
+            return len;
+        }
+        let class = char_class(chars[i], big);
+        if class != CharClass::Space {
+            while i < len && char_class(chars[i], big) == class {
+                i += 1;
+            }
+        }
+        while i < len && chars[i].is_whitespace() {
+            i += 1;
+        }
+        i
+    }
+
+    /// Last char of the word ending after `from`.
+    pub fn word_end_after(chars: &[char], from: usize, big: bool) -> usize {
+        let len = chars.len();
+        if len == 0 {
+            return 0;
+        }
+        let mut i = from + 1;
+        while i < len && chars[i].is_whitespace() {
+            i += 1;
+        }
+        if i >= len {
+            return len - 1;
+        }
+        let class = char_class(chars[i], big);
+        while i + 1 < len && char_class(chars[i + 1], big) == class {
+            i += 1;
+        }
+        i
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Space,
+    Word,
+    Punctuation,
+}
+
+fn char_class(ch: char, big: bool) -> CharClass {
+    if ch.is_whitespace() {
+        CharClass::Space
+    } else if big || ch.is_alphanumeric() || ch == '_' {
+        CharClass::Word
+    } else {
+        CharClass::Punctuation
+    }
 }
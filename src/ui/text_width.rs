@@ -0,0 +1,94 @@
+use std::ops::Range;
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Terminal columns `text` takes: wide CJK characters and emoji count two,
+/// combining marks and joined emoji sequences count as one grapheme.
+pub fn str_width(text: &str) -> usize {
+    text.graphemes(true).map(UnicodeWidthStr::width).sum()
+}
+
+/// Char ranges of the rows `text` wraps into at `max_columns`. Rows break
+/// between graphemes only, so a wide character that does not fit moves to the
+/// next row. Always returns at least one row.
+pub fn wrap_ranges(text: &str, max_columns: usize) -> Vec<Range<usize>> {
+    let mut rows = Vec::new();
+    let mut row_start = 0;
+    let mut row_width = 0;
+    let mut at = 0;
+    for grapheme in text.graphemes(true) {
+        let width = grapheme.width();
+        if max_columns > 0 && row_width > 0 && row_width + width > max_columns {
+            rows.push(row_start..at);
+            row_start = at;
+            row_width = 0;
+        }
+        row_width += width;
+        at += grapheme.chars().count();
+    }
+    if at > row_start || rows.is_empty() {
+        rows.push(row_start..at);
+    }
+    rows
+}
+
+/// `line` wrapped at `max_columns`; zero means no limit.
+pub fn wrap_line(line: &str, max_columns: usize) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    wrap_ranges(line, max_columns)
+        .into_iter()
+        .map(|range| chars[range].iter().collect())
+        .collect()
+}
+
+/// Char offset in `text` of the grapheme boundary closest to `column`
+/// without passing it.
+pub fn offset_at_column(text: &str, column: usize) -> usize {
+    let mut width = 0;
+    let mut offset = 0;
+    for grapheme in text.graphemes(true) {
+        width += grapheme.width();
+        if width > column {
+            break;
+        }
+        offset += grapheme.chars().count();
+    }
+    offset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_wide_joined_and_combining_text() {
+        assert_eq!(str_width("abc"), 3);
+        assert_eq!(str_width("日本語"), 6);
+        assert_eq!(str_width("e\u{301}"), 1);
+        assert_eq!(str_width("👨\u{200d}👩\u{200d}👧"), 2);
+    }
+
+    #[test]
+    fn wrap_ranges_preserve_multiple_rows() {
+        assert_eq!(wrap_ranges("abcdefgh", 3), vec![0..3, 3..6, 6..8]);
+        assert_eq!(wrap_ranges("abcdef", 3), vec![0..3, 3..6]);
+    }
+
+    #[test]
+    fn wraps_by_columns_without_splitting_graphemes() {
+        assert_eq!(wrap_line("日本語です", 5), vec!["日本", "語で", "す"]);
+        assert_eq!(
+            wrap_line("ab👨\u{200d}👩\u{200d}👧cd", 3),
+            vec![
+                "ab".to_string(),
+                "👨\u{200d}👩\u{200d}👧c".to_string(),
+                "d".to_string()
+            ]
+        );
+        assert_eq!(wrap_ranges("", 4), vec![0..0]);
+        assert_eq!(wrap_ranges("cafe\u{301}s", 4), vec![0..5, 5..6]);
+        assert_eq!(offset_at_column("a日b", 2), 1);
+        assert_eq!(offset_at_column("a日b", 3), 2);
+    }
+}
@@ -2,6 +2,7 @@ use crate::acp::client::{AcpClient, AcpResponseText, PermissionDecision, Permiss
 use crate::acp::install::runner::resolve_launch_command;
 use crate::acp::manager::{AgentCommandSpec, AgentSpec};
 use crate::acp::resolve::{AgentKey, ConflictPolicy, EffectiveAgentRow, load_effective_agent_rows};
+use crate::ui::text_width;
 use futures::StreamExt;
 use futures::channel::mpsc;
 use gpui::*;
@@ -861,33 +862,7 @@ fn wrap_agent_text_lines(text: &str, max_chars: usize) -> Vec<String> {
 }
 
 fn wrap_agent_line(line: &str, max_chars: usize) -> Vec<String> {
-    if max_chars == 0 || line.is_empty() {
-        return vec![line.to_string()];
-    }
-
-    let mut wrapped = Vec::new();
-    let mut chunk = String::new();
-    let mut chunk_len = 0usize;
-
-    for ch in line.chars() {
-        chunk.push(ch);
-        chunk_len += 1;
-
-        if chunk_len >= max_chars {
-            wrapped.push(std::mem::take(&mut chunk));
-            chunk_len = 0;
-        }
-    }
-
-    if !chunk.is_empty() {
-        wrapped.push(chunk);
-    }
-
-    if wrapped.is_empty() {
-        wrapped.push(String::new());
-    }
-
-    wrapped
+    text_width::wrap_line(line, max_chars)
 }
 
 fn strip_ansi(input: &str) -> String {
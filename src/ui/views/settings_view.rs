@@ -36,16 +36,22 @@ use crate::acp::resolve::{
 use crate::acp::storage;
 use crate::mcp::config::{GlobalMcpConfig, McpServerConfig};
 use crate::mcp::probe::{McpProbeResult, probe_server_config};
+use crate::secrets::SecretScanner;
+use crate::terminal::command_guard::{CommandGuard, GuardRule, PROJECT_GUARD_FILE};
 use crate::ui::appearance::{
     AppearanceSettings, IconThemeOption, icon_theme_options, resolve_themed_icon,
 };
+use crate::ui::edit_mode::EditingMode;
 use crate::ui::icons::{lucide_icon, registry_avatar};
+use crate::ui::terminal_settings::{BellStyle, TerminalSettings};
 use crate::ui::text_edit::TextEditState;
 
 const ACCENT: u32 = 0x6b9eff;
 const ACCENT_BORDER: u32 = 0x6b9eff66;
 const ACP_REGISTRY_URL: &str =
     "https://cdn.agentclientprotocol.com/registry/v1/latest/registry.json";
+/// Minimum run times offered for completion notifications, in seconds.
+const COMPLETION_THRESHOLDS: [u64; 4] = [5, 10, 30, 60];
 
 #[derive(Clone, Debug)]
 pub struct CatalogAgentRowUI {
@@ -85,6 +91,7 @@ enum ActiveSettingsInput {
     Menu,
     Acp,
     Appearance,
+    SecretPattern,
 }
 
 pub struct SettingsView {
@@ -122,18 +129,26 @@ pub struct SettingsView {
     appearance_search_selection: Option<(usize, usize)>,
     appearance_search_anchor: Option<usize>,
     appearance_search_focus: FocusHandle,
+    terminal_settings: TerminalSettings,
+    terminal_status: Option<String>,
     acp_search_query: String,
     acp_search_cursor: usize,
     acp_search_selection: Option<(usize, usize)>,
     acp_search_anchor: Option<usize>,
     acp_search_focus: FocusHandle,
     acp_installed_count: usize,
+    secret_pattern_input: String,
+    secret_pattern_cursor: usize,
+    secret_pattern_selection: Option<(usize, usize)>,
+    secret_pattern_anchor: Option<usize>,
+    secret_pattern_focus: FocusHandle,
 }
 
 impl SettingsView {
     fn default_sections() -> Vec<&'static str> {
         vec![
             "Appearance",
+            "Terminal",
             "Keyboard shortcuts",
             "ACP Registry",
             "MCP servers",
@@ -210,12 +225,19 @@ impl SettingsView {
             appearance_search_selection: None,
             appearance_search_anchor: None,
             appearance_search_focus: cx.focus_handle(),
+            terminal_settings: TerminalSettings::load(),
+            terminal_status: None,
             acp_search_query: String::new(),
             acp_search_cursor: 0,
             acp_search_selection: None,
             acp_search_anchor: None,
             acp_search_focus: cx.focus_handle(),
             acp_installed_count: 0,
+            secret_pattern_input: String::new(),
+            secret_pattern_cursor: 0,
+            secret_pattern_selection: None,
+            secret_pattern_anchor: None,
+            secret_pattern_focus: cx.focus_handle(),
         };
         view.update_catalog_rows();
         view.refresh_registry_in_background(cx);
@@ -234,6 +256,8 @@ impl SettingsView {
             Some(ActiveSettingsInput::Acp)
         } else if self.appearance_search_focus.is_focused(window) {
             Some(ActiveSettingsInput::Appearance)
+        } else if self.secret_pattern_focus.is_focused(window) {
+            Some(ActiveSettingsInput::SecretPattern)
         } else if self.focus_handle.is_focused(window) {
             Some(ActiveSettingsInput::Menu)
         } else {
@@ -241,6 +265,44 @@ impl SettingsView {
         }
     }
 
+    /// Text, cursor, selection and selection anchor of an input.
+    fn input_state(
+        &mut self,
+        target: ActiveSettingsInput,
+    ) -> (
+        &mut String,
+        &mut usize,
+        &mut Option<(usize, usize)>,
+        &mut Option<usize>,
+    ) {
+        match target {
+            ActiveSettingsInput::Acp => (
+                &mut self.acp_search_query,
+                &mut self.acp_search_cursor,
+                &mut self.acp_search_selection,
+                &mut self.acp_search_anchor,
+            ),
+            ActiveSettingsInput::Appearance => (
+                &mut self.appearance_search_query,
+                &mut self.appearance_search_cursor,
+                &mut self.appearance_search_selection,
+                &mut self.appearance_search_anchor,
+            ),
+            ActiveSettingsInput::SecretPattern => (
+                &mut self.secret_pattern_input,
+                &mut self.secret_pattern_cursor,
+                &mut self.secret_pattern_selection,
+                &mut self.secret_pattern_anchor,
+            ),
+            ActiveSettingsInput::Menu => (
+                &mut self.search_query,
+                &mut self.search_cursor,
+                &mut self.search_selection,
+                &mut self.search_anchor,
+            ),
+        }
+    }
+
     fn on_key_down(&mut self, event: &KeyDownEvent, window: &mut Window, cx: &mut Context<Self>) {
         let ctrl = event.keystroke.modifiers.control;
         let shift = event.keystroke.modifiers.shift;
@@ -249,26 +311,7 @@ impl SettingsView {
         };
 
         if ctrl && event.keystroke.key.eq_ignore_ascii_case("a") {
-            let (query, cursor, selection, anchor) = match target {
-                ActiveSettingsInput::Acp => (
-                    &mut self.acp_search_query,
-                    &mut self.acp_search_cursor,
-                    &mut self.acp_search_selection,
-                    &mut self.acp_search_anchor,
-                ),
-                ActiveSettingsInput::Appearance => (
-                    &mut self.appearance_search_query,
-                    &mut self.appearance_search_cursor,
-                    &mut self.appearance_search_selection,
-                    &mut self.appearance_search_anchor,
-                ),
-                ActiveSettingsInput::Menu => (
-                    &mut self.search_query,
-                    &mut self.search_cursor,
-                    &mut self.search_selection,
-                    &mut self.search_anchor,
-                ),
-            };
+            let (query, cursor, selection, anchor) = self.input_state(target);
             TextEditState::select_all(query, cursor, selection, anchor);
             cx.notify();
             if matches!(target, ActiveSettingsInput::Acp) {
@@ -279,27 +322,12 @@ impl SettingsView {
         }
 
         match event.keystroke.key.as_str() {
+            "enter" if target == ActiveSettingsInput::SecretPattern => {
+                self.add_secret_pattern(cx);
+                cx.stop_propagation();
+            }
             "backspace" => {
-                let (query, cursor, selection, anchor) = match target {
-                    ActiveSettingsInput::Acp => (
-                        &mut self.acp_search_query,
-                        &mut self.acp_search_cursor,
-                        &mut self.acp_search_selection,
-                        &mut self.acp_search_anchor,
-                    ),
-                    ActiveSettingsInput::Appearance => (
-                        &mut self.appearance_search_query,
-                        &mut self.appearance_search_cursor,
-                        &mut self.appearance_search_selection,
-                        &mut self.appearance_search_anchor,
-                    ),
-                    ActiveSettingsInput::Menu => (
-                        &mut self.search_query,
-                        &mut self.search_cursor,
-                        &mut self.search_selection,
-                        &mut self.search_anchor,
-                    ),
-                };
+                let (query, cursor, selection, anchor) = self.input_state(target);
 
                 if TextEditState::delete_selection_if_any(query, cursor, selection, anchor) {
                     cx.notify();
@@ -319,23 +347,7 @@ impl SettingsView {
                 cx.stop_propagation();
             }
             "left" | "arrowleft" => {
-                let (cursor, selection, anchor) = match target {
-                    ActiveSettingsInput::Acp => (
-                        &mut self.acp_search_cursor,
-                        &mut self.acp_search_selection,
-                        &mut self.acp_search_anchor,
-                    ),
-                    ActiveSettingsInput::Appearance => (
-                        &mut self.appearance_search_cursor,
-                        &mut self.appearance_search_selection,
-                        &mut self.appearance_search_anchor,
-                    ),
-                    ActiveSettingsInput::Menu => (
-                        &mut self.search_cursor,
-                        &mut self.search_selection,
-                        &mut self.search_anchor,
-                    ),
-                };
+                let (_, cursor, selection, anchor) = self.input_state(target);
 
                 if shift {
                     let anchor_val = anchor.unwrap_or(*cursor);
@@ -355,26 +367,7 @@ impl SettingsView {
                 cx.stop_propagation();
             }
             "right" | "arrowright" => {
-                let (query, cursor, selection, anchor) = match target {
-                    ActiveSettingsInput::Acp => (
-                        &self.acp_search_query,
-                        &mut self.acp_search_cursor,
-                        &mut self.acp_search_selection,
-                        &mut self.acp_search_anchor,
-                    ),
-                    ActiveSettingsInput::Appearance => (
-                        &self.appearance_search_query,
-                        &mut self.appearance_search_cursor,
-                        &mut self.appearance_search_selection,
-                        &mut self.appearance_search_anchor,
-                    ),
-                    ActiveSettingsInput::Menu => (
-                        &self.search_query,
-                        &mut self.search_cursor,
-                        &mut self.search_selection,
-                        &mut self.search_anchor,
-                    ),
-                };
+                let (query, cursor, selection, anchor) = self.input_state(target);
 
                 let max = query.chars().count();
                 if shift {
@@ -393,75 +386,21 @@ impl SettingsView {
                 cx.stop_propagation();
             }
             "home" => {
-                let (cursor, selection, anchor) = match target {
-                    ActiveSettingsInput::Acp => (
-                        &mut self.acp_search_cursor,
-                        &mut self.acp_search_selection,
-                        &mut self.acp_search_anchor,
-                    ),
-                    ActiveSettingsInput::Appearance => (
-                        &mut self.appearance_search_cursor,
-                        &mut self.appearance_search_selection,
-                        &mut self.appearance_search_anchor,
-                    ),
-                    ActiveSettingsInput::Menu => (
-                        &mut self.search_cursor,
-                        &mut self.search_selection,
-                        &mut self.search_anchor,
-                    ),
-                };
+                let (_, cursor, selection, anchor) = self.input_state(target);
                 *cursor = 0;
                 TextEditState::clear_selection(selection, anchor);
                 cx.notify();
                 cx.stop_propagation();
             }
             "end" => {
-                let (query, cursor, selection, anchor) = match target {
-                    ActiveSettingsInput::Acp => (
-                        &self.acp_search_query,
-                        &mut self.acp_search_cursor,
-                        &mut self.acp_search_selection,
-                        &mut self.acp_search_anchor,
-                    ),
-                    ActiveSettingsInput::Appearance => (
-                        &self.appearance_search_query,
-                        &mut self.appearance_search_cursor,
-                        &mut self.appearance_search_selection,
-                        &mut self.appearance_search_anchor,
-                    ),
-                    ActiveSettingsInput::Menu => (
-                        &self.search_query,
-                        &mut self.search_cursor,
-                        &mut self.search_selection,
-                        &mut self.search_anchor,
-                    ),
-                };
+                let (query, cursor, selection, anchor) = self.input_state(target);
                 *cursor = query.chars().count();
                 TextEditState::clear_selection(selection, anchor);
                 cx.notify();
                 cx.stop_propagation();
             }
             _ => {
-                let (query, cursor, selection, anchor) = match target {
-                    ActiveSettingsInput::Acp => (
-                        &mut self.acp_search_query,
-                        &mut self.acp_search_cursor,
-                        &mut self.acp_search_selection,
-                        &mut self.acp_search_anchor,
-                    ),
-                    ActiveSettingsInput::Appearance => (
-                        &mut self.appearance_search_query,
-                        &mut self.appearance_search_cursor,
-                        &mut self.appearance_search_selection,
-                        &mut self.appearance_search_anchor,
-                    ),
-                    ActiveSettingsInput::Menu => (
-                        &mut self.search_query,
-                        &mut self.search_cursor,
-                        &mut self.search_selection,
-                        &mut self.search_anchor,
-                    ),
-                };
+                let (query, cursor, selection, anchor) = self.input_state(target);
 
                 if let Some(text) = event.keystroke.key_char.as_deref() {
                     if !text.is_empty() && !ctrl {
@@ -491,6 +430,18 @@ impl SettingsView {
         cursor: usize,
         selection: Option<(usize, usize)>,
         is_focused: bool,
+    ) -> Div {
+        self.render_text_input(query, cursor, selection, is_focused, Icon::Search, "Search")
+    }
+
+    fn render_text_input(
+        &self,
+        query: &str,
+        cursor: usize,
+        selection: Option<(usize, usize)>,
+        is_focused: bool,
+        icon: Icon,
+        placeholder_text: &'static str,
     ) -> Div {
         let placeholder = query.is_empty();
         let caret = div()
@@ -531,13 +482,13 @@ impl SettingsView {
                 .flex()
                 .items_center()
                 .gap(px(6.0))
-                .child(lucide_icon(Icon::Search, 14.0, 0x7a7a7a))
+                .child(lucide_icon(icon, 14.0, 0x7a7a7a))
                 .child(caret)
                 .child(
                     div()
                         .text_size(px(13.0))
                         .text_color(rgb(0x666666))
-                        .child("Search"),
+                        .child(placeholder_text),
                 );
         }
 
@@ -663,6 +614,639 @@ impl SettingsView {
         cx.notify();
     }
 
+    fn select_editing_mode(&mut self, mode: EditingMode, cx: &mut Context<Self>) {
+        if self.terminal_settings.editing_mode == mode {
+            self.terminal_status = Some("This editing mode is already active.".to_string());
+            return;
+        }
+
+        self.terminal_settings.editing_mode = mode;
+        if let Err(err) = self.terminal_settings.save() {
+            eprintln!("failed to save terminal settings: {err}");
+            self.terminal_status = Some(format!("Failed to save editing mode: {err}"));
+        } else {
+            self.terminal_status = Some(format!("Editing mode applied: {}", mode.label()));
+        }
+        cx.notify();
+    }
+
+    fn render_editing_mode_card(&self, mode: EditingMode, cx: &Context<Self>) -> Div {
+        let is_selected = self.terminal_settings.editing_mode == mode;
+        let handle = cx.entity().downgrade();
+
+        div()
+            .flex()
+            .items_center()
+            .justify_between()
+            .gap(px(16.0))
+            .p(px(14.0))
+            .rounded(px(10.0))
+            .bg(if is_selected {
+                rgb(0x102132)
+            } else {
+                rgb(0x101010)
+            })
+            .border_1()
+            .border_color(if is_selected {
+                rgba(ACCENT_BORDER)
+            } else {
+                rgb(0x1f1f1f)
+            })
+            .cursor(CursorStyle::PointingHand)
+            .on_mouse_down(MouseButton::Left, move |_event, _window, cx| {
+                cx.stop_propagation();
+                let _ = handle.update(cx, |view, cx| {
+                    view.select_editing_mode(mode, cx);
+                });
+            })
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .gap(px(6.0))
+                    .flex_1()
+                    .child(
+                        div()
+                            .text_size(px(13.0))
+                            .text_color(if is_selected {
+                                rgb(0xffffff)
+                            } else {
+                                rgb(0xe2e2e2)
+                            })
+                            .child(mode.label()),
+                    )
+                    .child(
+                        div()
+                            .text_size(px(12.0))
+                            .text_color(rgb(0x9a9a9a))
+                            .child(mode.description()),
+                    ),
+            )
+            .child(
+                div()
+                    .px(px(12.0))
+                    .py(px(6.0))
+                    .rounded(px(6.0))
+                    .bg(if is_selected {
+                        rgb(0x1e7ce5)
+                    } else {
+                        rgb(0x1a1a1a)
+                    })
+                    .border_1()
+                    .border_color(if is_selected {
+                        rgb(0x54a3ff)
+                    } else {
+                        rgb(0x2a2a2a)
+                    })
+                    .text_size(px(12.0))
+                    .text_color(rgb(0xffffff))
+                    .child(if is_selected { "Selected" } else { "Use" }),
+            )
+    }
+
+    fn toggle_command_guard(&mut self, cx: &mut Context<Self>) {
+        let guard = &mut self.terminal_settings.command_guard;
+        guard.enabled = !guard.enabled;
+        let enabled = guard.enabled;
+        self.save_terminal_settings(
+            if enabled {
+                "Command guard turned on."
+            } else {
+                "Command guard turned off."
+            },
+            cx,
+        );
+    }
+
+    fn toggle_guard_rule(&mut self, name: String, cx: &mut Context<Self>) {
+        let disabled = &mut self.terminal_settings.command_guard.disabled;
+        let status = if let Some(index) = disabled.iter().position(|rule| *rule == name) {
+            disabled.remove(index);
+            format!("Guard rule enabled: {name}")
+        } else {
+            disabled.push(name.clone());
+            format!("Guard rule disabled: {name}")
+        };
+        self.save_terminal_settings(&status, cx);
+    }
+
+    fn add_secret_pattern(&mut self, cx: &mut Context<Self>) {
+        let pattern = self.secret_pattern_input.trim().to_string();
+        if pattern.is_empty() {
+            return;
+        }
+        if let Err(err) = regex::Regex::new(&pattern) {
+            self.terminal_status = Some(format!("Invalid pattern: {err}"));
+            cx.notify();
+            return;
+        }
+        if !self.terminal_settings.secret_patterns.contains(&pattern) {
+            self.terminal_settings.secret_patterns.push(pattern);
+        }
+        self.secret_pattern_input.clear();
+        self.secret_pattern_cursor = 0;
+        TextEditState::clear_selection(
+            &mut self.secret_pattern_selection,
+            &mut self.secret_pattern_anchor,
+        );
+        self.save_terminal_settings("Secret pattern added", cx);
+    }
+
+    fn remove_secret_pattern(&mut self, index: usize, cx: &mut Context<Self>) {
+        if index < self.terminal_settings.secret_patterns.len() {
+            self.terminal_settings.secret_patterns.remove(index);
+            self.save_terminal_settings("Secret pattern removed", cx);
+        }
+    }
+
+    fn save_terminal_settings(&mut self, status: &str, cx: &mut Context<Self>) {
+        if let Err(err) = self.terminal_settings.save() {
+            eprintln!("failed to save terminal settings: {err}");
+            self.terminal_status = Some(format!("Failed to save terminal settings: {err}"));
+        } else {
+            self.terminal_status = Some(status.to_string());
+        }
+        cx.notify();
+    }
+
+    fn toggle_completion_notifications(&mut self, cx: &mut Context<Self>) {
+        let notifications = &mut self.terminal_settings.completion_notifications;
+        notifications.enabled = !notifications.enabled;
+        let status = if notifications.enabled {
+            "Completion notifications turned on."
+        } else {
+            "Completion notifications turned off."
+        };
+        self.save_terminal_settings(status, cx);
+    }
+
+    fn set_completion_threshold(&mut self, secs: u64, cx: &mut Context<Self>) {
+        self.terminal_settings
+            .completion_notifications
+            .min_duration_secs = secs;
+        self.save_terminal_settings(
+            &format!("Notifying for commands that run {secs}s or longer."),
+            cx,
+        );
+    }
+
+    fn set_bell_style(&mut self, bell: BellStyle, cx: &mut Context<Self>) {
+        self.terminal_settings.bell = bell;
+        self.save_terminal_settings(&format!("Terminal bell: {}", bell.label()), cx);
+    }
+
+    fn toggle_copy_on_select(&mut self, cx: &mut Context<Self>) {
+        self.terminal_settings.copy_on_select = !self.terminal_settings.copy_on_select;
+        let status = if self.terminal_settings.copy_on_select {
+            "Selected output is copied right away."
+        } else {
+            "Selected output is copied with Ctrl+Shift+C."
+        };
+        self.save_terminal_settings(status, cx);
+    }
+
+    /// Built-in rules followed by the user's own, each listed once.
+    fn guard_rules(&self) -> Vec<GuardRule> {
+        let mut rules = CommandGuard::builtin_rules();
+        for rule in &self.terminal_settings.command_guard.rules {
+            rules.retain(|existing| existing.name != rule.name);
+            rules.push(rule.clone());
+        }
+        rules
+    }
+
+    fn render_command_guard_panel(&self, cx: &Context<Self>) -> Div {
+        let config = &self.terminal_settings.command_guard;
+        let (_, errors) = CommandGuard::new(&[config]);
+        let handle = cx.entity().downgrade();
+        let toggle = |on: bool| {
+            div()
+                .flex_none()
+                .px(px(10.0))
+                .py(px(4.0))
+                .rounded(px(6.0))
+                .bg(if on { rgb(0x1e7ce5) } else { rgb(0x1a1a1a) })
+                .border_1()
+                .border_color(if on { rgb(0x54a3ff) } else { rgb(0x2a2a2a) })
+                .text_size(px(11.0))
+                .text_color(rgb(0xffffff))
+                .cursor(CursorStyle::PointingHand)
+                .child(if on { "On" } else { "Off" })
+        };
+        let rules = self.guard_rules().into_iter().map(|rule| {
+            let enabled = config.enabled && !config.disabled.contains(&rule.name);
+            let handle = handle.clone();
+            let name = rule.name.clone();
+            div()
+                .flex()
+                .items_center()
+                .justify_between()
+                .gap(px(12.0))
+                .child(
+                    div()
+                        .flex()
+                        .flex_col()
+                        .flex_1()
+                        .min_w(px(0.0))
+                        .gap(px(2.0))
+                        .child(
+                            div()
+                                .text_size(px(12.0))
+                                .text_color(if enabled {
+                                    rgb(0xe2e2e2)
+                                } else {
+                                    rgb(0x7a7a7a)
+                                })
+                                .child(rule.name),
+                        )
+                        .child(
+                            div()
+                                .text_size(px(11.0))
+                                .text_color(rgb(0x8a8a8a))
+                                .child(rule.reason),
+                        ),
+                )
+                .child(toggle(enabled).on_mouse_down(
+                    MouseButton::Left,
+                    move |_event, _window, cx| {
+                        cx.stop_propagation();
+                        let name = name.clone();
+                        let _ = handle.update(cx, |view, cx| view.toggle_guard_rule(name, cx));
+                    },
+                ))
+        });
+        let guard_handle = handle.clone();
+
+        div()
+            .flex()
+            .flex_col()
+            .gap(px(8.0))
+            .child(
+                div()
+                    .flex()
+                    .items_center()
+                    .justify_between()
+                    .child(
+                        div()
+                            .text_size(px(12.0))
+                            .text_color(rgb(0x8a8a8a))
+                            .child("Command guard"),
+                    )
+                    .child(toggle(config.enabled).on_mouse_down(
+                        MouseButton::Left,
+                        move |_event, _window, cx| {
+                            cx.stop_propagation();
+                            let _ = guard_handle.update(cx, |view, cx| view.toggle_command_guard(cx));
+                        },
+                    )),
+            )
+            .child(
+                div()
+                    .rounded(px(10.0))
+                    .bg(rgb(0x101010))
+                    .border_1()
+                    .border_color(rgb(0x1f1f1f))
+                    .p(px(12.0))
+                    .flex()
+                    .flex_col()
+                    .gap(px(10.0))
+                    .child(
+                        div()
+                            .text_size(px(12.0))
+                            .text_color(rgb(0xcfcfcf))
+                            .child("Commands matching these rules wait for confirmation in the input bar before they run."),
+                    )
+                    .children(rules)
+                    .child(
+                        div()
+                            .text_size(px(11.0))
+                            .text_color(rgb(0x8a8a8a))
+                            .child(format!(
+                                "Add rules under `command_guard.rules` in terminal.json. A project can add or disable rules with an {PROJECT_GUARD_FILE} of the same shape."
+                            )),
+                    )
+                    .children(errors.into_iter().map(|error| {
+                        div()
+                            .text_size(px(11.0))
+                            .text_color(rgb(0xff7b72))
+                            .child(format!("Invalid rule {error}"))
+                    })),
+            )
+    }
+
+    fn render_output_selection_panel(&self, cx: &Context<Self>) -> Div {
+        let enabled = self.terminal_settings.copy_on_select;
+        div()
+            .flex()
+            .flex_col()
+            .gap(px(8.0))
+            .child(
+                div()
+                    .flex()
+                    .items_center()
+                    .justify_between()
+                    .child(
+                        div()
+                            .text_size(px(12.0))
+                            .text_color(rgb(0x8a8a8a))
+                            .child("Copy on select"),
+                    )
+                    .child(
+                        div()
+                            .flex_none()
+                            .px(px(10.0))
+                            .py(px(4.0))
+                            .rounded(px(6.0))
+                            .bg(if enabled { rgb(0x1e7ce5) } else { rgb(0x1a1a1a) })
+                            .border_1()
+                            .border_color(if enabled { rgb(0x54a3ff) } else { rgb(0x2a2a2a) })
+                            .text_size(px(11.0))
+                            .text_color(rgb(0xffffff))
+                            .cursor(CursorStyle::PointingHand)
+                            .child(if enabled { "On" } else { "Off" })
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(|view, _event: &MouseDownEvent, _window, cx| {
+                                    cx.stop_propagation();
+                                    view.toggle_copy_on_select(cx);
+                                }),
+                            ),
+                    ),
+            )
+            .child(
+                div()
+                    .text_size(px(12.0))
+                    .text_color(rgb(0x7f8b99))
+                    .child("Double-click selects a word, path or URL, triple-click a line, and Alt+drag a rectangle of columns. Ctrl+Shift+Alt+C copies with colors."),
+            )
+    }
+
+    fn render_completion_notifications_panel(&self, cx: &Context<Self>) -> Div {
+        let settings = &self.terminal_settings.completion_notifications;
+        let handle = cx.entity().downgrade();
+        let chip = |label: String, on: bool| {
+            div()
+                .flex_none()
+                .px(px(10.0))
+                .py(px(4.0))
+                .rounded(px(6.0))
+                .bg(if on { rgb(0x1e7ce5) } else { rgb(0x1a1a1a) })
+                .border_1()
+                .border_color(if on { rgb(0x54a3ff) } else { rgb(0x2a2a2a) })
+                .text_size(px(11.0))
+                .text_color(rgb(0xffffff))
+                .cursor(CursorStyle::PointingHand)
+                .child(label)
+        };
+        let thresholds = COMPLETION_THRESHOLDS.into_iter().map(|secs| {
+            let handle = handle.clone();
+            chip(
+                format!("{secs}s"),
+                settings.enabled && settings.min_duration_secs == secs,
+            )
+            .on_mouse_down(MouseButton::Left, move |_event, _window, cx| {
+                cx.stop_propagation();
+                let _ = handle.update(cx, |view, cx| view.set_completion_threshold(secs, cx));
+            })
+        });
+        let bell = self.terminal_settings.bell;
+        let bell_styles = BellStyle::ALL.into_iter().map(|style| {
+            let handle = handle.clone();
+            chip(style.label().to_string(), bell == style).on_mouse_down(
+                MouseButton::Left,
+                move |_event, _window, cx| {
+                    cx.stop_propagation();
+                    let _ = handle.update(cx, |view, cx| view.set_bell_style(style, cx));
+                },
+            )
+        });
+        let toggle_handle = handle.clone();
+
+        div()
+            .flex()
+            .flex_col()
+            .gap(px(8.0))
+            .child(
+                div()
+                    .flex()
+                    .items_center()
+                    .justify_between()
+                    .child(
+                        div()
+                            .text_size(px(12.0))
+                            .text_color(rgb(0x8a8a8a))
+                            .child("Completion notifications"),
+                    )
+                    .child(
+                        chip(
+                            if settings.enabled { "On" } else { "Off" }.to_string(),
+                            settings.enabled,
+                        )
+                        .on_mouse_down(MouseButton::Left, move |_event, _window, cx| {
+                            cx.stop_propagation();
+                            let _ = toggle_handle
+                                .update(cx, |view, cx| view.toggle_completion_notifications(cx));
+                        }),
+                    ),
+            )
+            .child(
+                div()
+                    .rounded(px(10.0))
+                    .bg(rgb(0x101010))
+                    .border_1()
+                    .border_color(rgb(0x1f1f1f))
+                    .p(px(12.0))
+                    .flex()
+                    .flex_col()
+                    .gap(px(10.0))
+                    .child(
+                        div()
+                            .text_size(px(12.0))
+                            .text_color(rgb(0xcfcfcf))
+                            .child("When a long command finishes in a background tab or while the window is unfocused, the tab gets a badge and a desktop notification shows its exit status and duration."),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .items_center()
+                            .gap(px(8.0))
+                            .child(
+                                div()
+                                    .text_size(px(11.0))
+                                    .text_color(rgb(0x8a8a8a))
+                                    .child("Only for commands running at least"),
+                            )
+                            .children(thresholds),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .items_center()
+                            .gap(px(8.0))
+                            .child(
+                                div()
+                                    .text_size(px(11.0))
+                                    .text_color(rgb(0x8a8a8a))
+                                    .child("Terminal bell"),
+                            )
+                            .children(bell_styles),
+                    )
+                    .child(
+                        div()
+                            .text_size(px(11.0))
+                            .text_color(rgb(0x8a8a8a))
+                            .child("A bell in a background tab marks the tab until you switch to it."),
+                    ),
+            )
+    }
+
+    fn render_secret_redaction_panel(&self, window: &Window, cx: &Context<Self>) -> Div {
+        let (_, errors) = self.terminal_settings.secret_scanner();
+        let focus = self.secret_pattern_focus.clone();
+        let is_focused = self.secret_pattern_focus.is_focused(window);
+        let add_handle = cx.entity().downgrade();
+        let patterns = self
+            .terminal_settings
+            .secret_patterns
+            .iter()
+            .enumerate()
+            .map(|(index, pattern)| {
+                let remove_handle = cx.entity().downgrade();
+                let invalid = errors
+                    .iter()
+                    .any(|error| error.starts_with(&format!("{pattern}: ")));
+                div()
+                    .flex()
+                    .items_center()
+                    .justify_between()
+                    .gap(px(8.0))
+                    .px(px(8.0))
+                    .py(px(4.0))
+                    .rounded(px(6.0))
+                    .bg(rgb(0x141414))
+                    .border_1()
+                    .border_color(if invalid {
+                        rgb(0x5a2a2a)
+                    } else {
+                        rgb(0x2a2a2a)
+                    })
+                    .child(
+                        div()
+                            .flex_1()
+                            .min_w(px(0.0))
+                            .truncate()
+                            .text_size(px(12.0))
+                            .font_family("Cascadia Code")
+                            .text_color(rgb(0xdddddd))
+                            .child(pattern.clone()),
+                    )
+                    .child(
+                        div()
+                            .flex_none()
+                            .cursor(CursorStyle::PointingHand)
+                            .child(lucide_icon(Icon::X, 12.0, 0x9a9a9a))
+                            .on_mouse_down(MouseButton::Left, move |_event, _window, cx| {
+                                cx.stop_propagation();
+                                let _ = remove_handle.update(cx, |view, cx| {
+                                    view.remove_secret_pattern(index, cx);
+                                });
+                            }),
+                    )
+            });
+        div()
+            .flex()
+            .flex_col()
+            .gap(px(8.0))
+            .child(
+                div()
+                    .text_size(px(12.0))
+                    .text_color(rgb(0x8a8a8a))
+                    .child("Secret redaction"),
+            )
+            .child(
+                div()
+                    .rounded(px(10.0))
+                    .bg(rgb(0x101010))
+                    .border_1()
+                    .border_color(rgb(0x1f1f1f))
+                    .p(px(12.0))
+                    .flex()
+                    .flex_col()
+                    .gap(px(6.0))
+                    .child(
+                        div()
+                            .text_size(px(12.0))
+                            .text_color(rgb(0xcfcfcf))
+                            .child("Secrets are masked in output, kept out of history and replaced with [REDACTED] in copied text and agent prompts."),
+                    )
+                    .child(
+                        div()
+                            .text_size(px(11.0))
+                            .text_color(rgb(0x8a8a8a))
+                            .child(format!(
+                                "Built in: {}",
+                                SecretScanner::builtin_names().join(", ")
+                            )),
+                    )
+                    .child(
+                        div()
+                            .text_size(px(11.0))
+                            .text_color(rgb(0x8a8a8a))
+                            .child("Custom patterns are regexes; a capture group marks the part to hide."),
+                    )
+                    .children(patterns)
+                    .child(
+                        div()
+                            .flex()
+                            .items_center()
+                            .gap(px(8.0))
+                            .child(
+                                div()
+                                    .id("secret_pattern_input")
+                                    .track_focus(&focus)
+                                    .flex_1()
+                                    .rounded(px(8.0))
+                                    .bg(rgb(0x0f141b))
+                                    .border_1()
+                                    .border_color(if is_focused {
+                                        rgb(ACCENT)
+                                    } else {
+                                        rgb(0x2a2a2a)
+                                    })
+                                    .px(px(10.0))
+                                    .py(px(6.0))
+                                    .on_mouse_down(MouseButton::Left, move |_event, window, cx| {
+                                        cx.stop_propagation();
+                                        window.focus(&focus);
+                                    })
+                                    .child(self.render_text_input(
+                                        &self.secret_pattern_input,
+                                        self.secret_pattern_cursor,
+                                        self.secret_pattern_selection,
+                                        is_focused,
+                                        Icon::Plus,
+                                        "New pattern, such as internal-[0-9a-f]{32}",
+                                    )),
+                            )
+                            .child(self.render_action_button("Add").on_mouse_down(
+                                MouseButton::Left,
+                                move |_event, _window, cx| {
+                                    cx.stop_propagation();
+                                    let _ = add_handle.update(cx, |view, cx| {
+                                        view.add_secret_pattern(cx);
+                                    });
+                                },
+                            )),
+                    )
+                    .children(errors.into_iter().map(|error| {
+                        div()
+                            .text_size(px(11.0))
+                            .text_color(rgb(0xff7b72))
+                            .child(format!("Invalid pattern {error}"))
+                    })),
+            )
+    }
+
     fn render_icon_theme_preview(&self, theme: IconThemeOption) -> Div {
         let sample_border = rgb(0x2a2a2a);
         let (folder_icon, folder_color) =
@@ -2393,6 +2977,40 @@ impl SettingsView {
                     )
                     .child(appearance_body);
             }
+            "Terminal" => {
+                content = content
+                    .child(
+                        div()
+                            .text_size(px(12.0))
+                            .text_color(rgb(0x8a8a8a))
+                            .child("Input editing mode"),
+                    )
+                    .child(if let Some(status) = &self.terminal_status {
+                        div()
+                            .text_size(px(12.0))
+                            .text_color(rgb(0x8bd06f))
+                            .child(status.clone())
+                    } else {
+                        div()
+                    })
+                    .child(
+                        div().flex().flex_col().gap(px(10.0)).children(
+                            EditingMode::ALL
+                                .into_iter()
+                                .map(|mode| self.render_editing_mode_card(mode, cx)),
+                        ),
+                    )
+                    .child(
+                        div()
+                            .text_size(px(12.0))
+                            .text_color(rgb(0x7f8b99))
+                            .child("Applies to the command input and the picker inputs. Vi starts each line in insert mode; press Esc for normal mode."),
+                    )
+                    .child(self.render_secret_redaction_panel(window, cx))
+                    .child(self.render_command_guard_panel(cx))
+                    .child(self.render_completion_notifications_panel(cx))
+                    .child(self.render_output_selection_panel(cx));
+            }
             "Keyboard shortcuts" => {
                 let rows = vec![
                     ("Accept Autosuggestion", vec!["Ctrl", "Shift", "→"], true),
@@ -2400,7 +3018,9 @@ impl SettingsView {
                     ("Activate Previous Tab", vec!["Ctrl", "PageUp"], false),
                     ("Add Cursor Above", vec!["Ctrl", "Shift", "↑"], true),
                     ("Add Cursor Below", vec!["Ctrl", "Shift", "↓"], true),
+                    ("Add Next Occurrence", vec!["Ctrl", "D"], true),
                     ("Alternate Terminal Paste", vec!["Ctrl", "V"], false),
+                    ("Navigate Blocks", vec!["Ctrl", "↑"], true),
                 ];
                 content =
                     content
@@ -3219,6 +3839,7 @@ mod tests {
             SettingsView::default_sections(),
             vec![
                 "Appearance",
+                "Terminal",
                 "Keyboard shortcuts",
                 "ACP Registry",
                 "MCP servers",
@@ -11,7 +11,6 @@ use std::thread;
 
 use futures::StreamExt;
 use futures::channel::mpsc;
-use serde::Deserialize;
 use std::io::Seek;
 
 enum SearchMessage {
@@ -20,9 +19,12 @@ enum SearchMessage {
 }
 
 use crate::git::{GitChange, GitStatus, get_git_changes, get_git_status};
+use crate::terminal::problems::{Problem, Severity};
 use crate::ui::appearance::{AppearanceSettings, resolve_themed_icon};
+use crate::ui::file_rules::OrbitshellRules;
 use crate::ui::icons::lucide_icon;
 use crate::ui::text_edit::TextEditState;
+use crate::ui::views::tab_view::ProblemGroup;
 
 const ACCENT: u32 = 0x6b9eff;
 const ACCENT_BG: u32 = 0x6b9eff22;
@@ -82,19 +84,12 @@ impl Render for TooltipView {
     }
 }
 
-#[derive(Clone, Debug, Deserialize)]
-struct OrbitshellRules {
-    skip_dirs: Vec<String>,
-    skip_files: Vec<String>,
-    max_file_kb: u64,
-    search_limit: usize,
-}
-
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum SidebarMode {
     Explorer,
     Search,
     Git,
+    Problems,
 }
 
 pub struct OpenFileEvent {
@@ -129,13 +124,16 @@ pub struct SidebarView {
     git_scroll: ScrollHandle,
     explorer_scroll: ScrollHandle,
 
+    problem_groups: Vec<ProblemGroup>,
+    problems_scroll: ScrollHandle,
+
     rules: OrbitshellRules,
 }
 
 impl SidebarView {
     pub fn new(cx: &mut Context<Self>) -> Self {
         let current_path = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
-        let rules = Self::load_rules();
+        let rules = OrbitshellRules::load();
         let entries = Self::read_entries(&current_path, &rules);
         let git_status = get_git_status(&current_path);
         let git_changes = get_git_changes(&current_path);
@@ -161,6 +159,8 @@ impl SidebarView {
             git_changes,
             git_scroll: ScrollHandle::new(),
             explorer_scroll: ScrollHandle::new(),
+            problem_groups: Vec::new(),
+            problems_scroll: ScrollHandle::new(),
             rules,
         }
     }
@@ -174,6 +174,12 @@ impl SidebarView {
         self.git_changes = get_git_changes(&self.current_path);
     }
 
+    /// Shows the problems of the active tab.
+    pub fn set_problems(&mut self, groups: Vec<ProblemGroup>, cx: &mut Context<Self>) {
+        self.problem_groups = groups;
+        cx.notify();
+    }
+
     fn set_mode(&mut self, mode: SidebarMode, cx: &mut Context<Self>) {
         self.mode = mode;
         if mode == SidebarMode::Search {
@@ -203,9 +209,9 @@ impl SidebarView {
                     })
                     .filter(|entry| {
                         if entry.is_dir {
-                            !Self::should_skip_dir(&entry.name, rules)
+                            !rules.skips_dir(&entry.name)
                         } else {
-                            !Self::should_skip_file(&entry.name, rules)
+                            !rules.skips_file(&entry.name)
                         }
                     })
                     .collect()
@@ -264,6 +270,14 @@ impl SidebarView {
         });
     }
 
+    fn open_problem(&mut self, path: PathBuf, line: Option<usize>, cx: &mut Context<Self>) {
+        cx.emit(OpenFileEvent {
+            path,
+            line,
+            query: None,
+        });
+    }
+
     fn render_entry(&self, entry: &FileEntry, depth: usize, cx: &Context<Self>) -> Div {
         let theme_id = AppearanceSettings::load().icon_theme;
         let is_expanded = entry.is_dir && self.expanded_folders.contains(&entry.path);
@@ -679,12 +693,12 @@ impl SidebarView {
                 let path = entry.path();
                 let name = entry.file_name().to_string_lossy().to_string();
                 if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
-                    if Self::should_skip_dir(&name, rules) {
+                    if rules.skips_dir(&name) {
                         continue;
                     }
                     stack.push(path);
                 } else {
-                    if Self::should_skip_file(&name, rules) {
+                    if rules.skips_file(&name) {
                         continue;
                     }
                     if name.to_lowercase().contains(&query_lower) {
@@ -742,16 +756,6 @@ impl SidebarView {
         }
     }
 
-    fn should_skip_dir(name: &str, rules: &OrbitshellRules) -> bool {
-        let name = name.to_lowercase();
-        rules.skip_dirs.iter().any(|entry| entry == &name)
-    }
-
-    fn should_skip_file(name: &str, rules: &OrbitshellRules) -> bool {
-        let name = name.to_lowercase();
-        rules.skip_files.iter().any(|entry| entry == &name)
-    }
-
     fn render_search_input(&self, window: &mut Window) -> Div {
         let is_focused = self.focus_handle.is_focused(window);
         let (left, right) = TextEditState::split_at_cursor(&self.search_query, self.search_cursor);
@@ -1091,41 +1095,6 @@ impl SidebarView {
         path.hash(&mut hasher);
         hasher.finish()
     }
-
-    fn load_rules() -> OrbitshellRules {
-        let path = PathBuf::from("orbitshell_rules.json");
-        if let Ok(contents) = std::fs::read_to_string(&path) {
-            if let Ok(parsed) = serde_json::from_str::<OrbitshellRules>(&contents) {
-                return Self::normalize_rules(parsed);
-            }
-        }
-        Self::normalize_rules(OrbitshellRules {
-            skip_dirs: vec![
-                ".git".to_string(),
-                "node_modules".to_string(),
-                "target".to_string(),
-                "dist".to_string(),
-                ".next".to_string(),
-            ],
-            skip_files: vec![],
-            max_file_kb: 512,
-            search_limit: 200,
-        })
-    }
-
-    fn normalize_rules(mut rules: OrbitshellRules) -> OrbitshellRules {
-        rules.skip_dirs = rules
-            .skip_dirs
-            .into_iter()
-            .map(|entry| entry.to_lowercase())
-            .collect();
-        rules.skip_files = rules
-            .skip_files
-            .into_iter()
-            .map(|entry| entry.to_lowercase())
-            .collect();
-        rules
-    }
 }
 
 impl Render for SidebarView {
@@ -1191,6 +1160,24 @@ impl Render for SidebarView {
                                             });
                                         }
                                     }),
+                            )
+                            .child(
+                                self.header_button(
+                                    Icon::TriangleAlert,
+                                    mode == SidebarMode::Problems,
+                                )
+                                .on_mouse_down(
+                                    MouseButton::Left,
+                                    {
+                                        let handle = handle.clone();
+                                        move |_event, _window, cx| {
+                                            cx.stop_propagation();
+                                            let _ = handle.update(cx, |view, cx| {
+                                                view.set_mode(SidebarMode::Problems, cx);
+                                            });
+                                        }
+                                    },
+                                ),
                             ),
                     ),
             )
@@ -1320,6 +1307,33 @@ impl Render for SidebarView {
                         .child(self.render_git_section("Changes", &unstaged, cx))
                         .into_any_element()
                 }
+                SidebarMode::Problems => div()
+                    .id("sidebar_problems")
+                    .flex()
+                    .flex_1()
+                    .min_h(px(0.0))
+                    .flex_col()
+                    .gap(px(10.0))
+                    .p(px(12.0))
+                    .track_scroll(&self.problems_scroll)
+                    .overflow_scroll()
+                    .scrollbar_width(px(12.0))
+                    .children(if self.problem_groups.is_empty() {
+                        vec![
+                            div()
+                                .px(px(6.0))
+                                .text_size(px(12.0))
+                                .text_color(rgb(0x666666))
+                                .child("No problems in recent output"),
+                        ]
+                    } else {
+                        self.problem_groups
+                            .iter()
+                            .enumerate()
+                            .map(|(index, group)| self.render_problem_group(index, group, cx))
+                            .collect()
+                    })
+                    .into_any_element(),
             })
     }
 }
@@ -1482,6 +1496,120 @@ impl SidebarView {
             )
             .child(list)
     }
+
+    fn render_problem_group(
+        &self,
+        group_index: usize,
+        group: &ProblemGroup,
+        cx: &Context<Self>,
+    ) -> Div {
+        let rows = group.problems.iter().enumerate().map(|(index, problem)| {
+            let path = group.cwd.join(&problem.file);
+            let location = Self::problem_location(problem, &self.relative_path(&path));
+            let (icon, color) = match problem.severity {
+                Severity::Error => (Icon::CircleX, 0xff7b72),
+                Severity::Warning => (Icon::TriangleAlert, 0xe3b341),
+                Severity::Note => (Icon::Info, 0x6b9eff),
+            };
+            let open_handle = cx.entity().downgrade();
+            let line = problem.line;
+            let full_path = path.to_string_lossy().to_string();
+            let mut row = div()
+                .flex()
+                .items_start()
+                .gap(px(8.0))
+                .px(px(6.0))
+                .py(px(6.0))
+                .rounded(px(6.0))
+                .bg(rgb(0x101010))
+                .border_1()
+                .border_color(rgb(0x1f1f1f))
+                .cursor(sidebar_clickable_cursor())
+                .hover(|style| {
+                    style
+                        .bg(rgb(sidebar_row_hover_bg()))
+                        .border_color(rgb(sidebar_row_hover_border()))
+                })
+                .on_mouse_down(MouseButton::Left, move |_event, _window, cx| {
+                    let _ = open_handle.update(cx, |view: &mut SidebarView, cx| {
+                        view.open_problem(path.clone(), line, cx);
+                    });
+                })
+                .child(div().pt(px(2.0)).child(lucide_icon(icon, 12.0, color)))
+                .child(
+                    div()
+                        .flex()
+                        .flex_col()
+                        .gap(px(2.0))
+                        .flex_1()
+                        .min_w(px(0.0))
+                        .child(
+                            div()
+                                .text_size(px(12.0))
+                                .text_color(rgb(0xcccccc))
+                                .child(problem.message.clone()),
+                        )
+                        .child(
+                            div()
+                                .text_size(px(11.0))
+                                .text_color(rgb(0x6f6f6f))
+                                .truncate()
+                                .child(location),
+                        ),
+                )
+                .id(("problem_item", group_index * 10_000 + index));
+
+            row.interactivity().tooltip(move |_window, cx| {
+                let text = full_path.clone();
+                cx.new(|_| TooltipView { text }).into()
+            });
+
+            row
+        });
+
+        div()
+            .flex_col()
+            .gap(px(6.0))
+            .child(
+                div()
+                    .flex()
+                    .items_center()
+                    .justify_between()
+                    .gap(px(8.0))
+                    .child(
+                        div()
+                            .flex_1()
+                            .min_w(px(0.0))
+                            .text_size(px(12.0))
+                            .text_color(rgb(0x9a9a9a))
+                            .truncate()
+                            .child(group.command.clone()),
+                    )
+                    .child(
+                        div()
+                            .text_size(px(11.0))
+                            .text_color(rgb(0xcccccc))
+                            .px(px(6.0))
+                            .py(px(2.0))
+                            .rounded(px(10.0))
+                            .bg(rgb(0x202020))
+                            .child(group.problems.len().to_string()),
+                    ),
+            )
+            .child(div().flex_col().gap(px(8.0)).children(rows))
+    }
+
+    /// `file:line:col`, with the parts the problem has.
+    fn problem_location(problem: &Problem, path: &Path) -> String {
+        let mut location = path.to_string_lossy().to_string();
+        if let Some(line) = problem.line {
+            location.push_str(&format!(":{line}"));
+            if let Some(column) = problem.column {
+                location.push_str(&format!(":{column}"));
+            }
+        }
+        location
+    }
 }
 
 #[cfg(test)]
@@ -26,11 +26,36 @@ pub enum TabBarEvent {
     ToggleUserMenu,
 }
 
+/// Marks a tab that needs attention until it is looked at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TabBadge {
+    Succeeded,
+    Failed,
+    Bell,
+}
+
+impl TabBadge {
+    fn color(self) -> u32 {
+        match self {
+            Self::Succeeded => 0x7ee787,
+            Self::Failed => 0xff7b72,
+            Self::Bell => 0xf2cc60,
+        }
+    }
+}
+
 #[derive(Clone)]
 struct Tab {
     id: u64,
     name: String,
     path: String,
+    badge: Option<TabBadge>,
+    /// URL of a local server started in the tab.
+    dev_server: Option<String>,
+    /// Set once the user renames the tab, so program titles stop replacing it.
+    user_named: bool,
+    /// The name to go back to once a program's title no longer applies.
+    name_before_program: Option<String>,
 
     // reorder animation
     anim_offset: f32,
@@ -101,6 +126,10 @@ impl TabBar {
             id,
             name,
             path,
+            badge: None,
+            dev_server: None,
+            user_named: false,
+            name_before_program: None,
             anim_offset: 0.0,
             anim_token: 0,
         });
@@ -113,6 +142,30 @@ impl TabBar {
         if let Some(tab) = self.tabs.get_mut(index) {
             tab.name = name;
             tab.path = path;
+            tab.user_named = false;
+            tab.name_before_program = None;
+            cx.notify();
+        }
+    }
+
+    /// Shows a title set by a running program, unless the user named the tab.
+    pub fn set_program_title(&mut self, index: usize, title: String, cx: &mut Context<Self>) {
+        if let Some(tab) = self.tabs.get_mut(index)
+            && !tab.user_named
+        {
+            tab.name_before_program
+                .get_or_insert_with(|| tab.name.clone());
+            tab.name = title;
+            cx.notify();
+        }
+    }
+
+    /// Puts back the name the tab had before a program titled it.
+    pub fn clear_program_title(&mut self, index: usize, cx: &mut Context<Self>) {
+        if let Some(tab) = self.tabs.get_mut(index)
+            && let Some(name) = tab.name_before_program.take()
+        {
+            tab.name = name;
             cx.notify();
         }
     }
@@ -138,6 +191,33 @@ impl TabBar {
     pub fn set_active(&mut self, index: usize, cx: &mut Context<Self>) {
         if index < self.tabs.len() {
             self.active_tab = index;
+            self.tabs[index].badge = None;
+            cx.notify();
+        }
+    }
+
+    pub fn path(&self, index: usize) -> Option<&str> {
+        self.tabs.get(index).map(|tab| tab.path.as_str())
+    }
+
+    pub fn badge(&self, index: usize) -> Option<TabBadge> {
+        self.tabs.get(index).and_then(|tab| tab.badge)
+    }
+
+    pub fn active_name(&self) -> Option<&str> {
+        self.tabs.get(self.active_tab).map(|tab| tab.name.as_str())
+    }
+
+    pub fn set_badge(&mut self, index: usize, badge: Option<TabBadge>, cx: &mut Context<Self>) {
+        if let Some(tab) = self.tabs.get_mut(index) {
+            tab.badge = badge;
+            cx.notify();
+        }
+    }
+
+    pub fn set_dev_server(&mut self, index: usize, url: Option<String>, cx: &mut Context<Self>) {
+        if let Some(tab) = self.tabs.get_mut(index) {
+            tab.dev_server = url;
             cx.notify();
         }
     }
@@ -179,6 +259,10 @@ impl TabBar {
 
             if !final_name.is_empty() {
                 if let Some(tab) = self.tabs.get_mut(index) {
+                    if tab.name != final_name {
+                        tab.user_named = true;
+                        tab.name_before_program = None;
+                    }
                     tab.name = final_name.to_string();
                 }
             }
@@ -501,10 +585,16 @@ impl TabBar {
 }
 
 impl Render for TabBar {
-    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         if self.active_tab >= self.tabs.len() && !self.tabs.is_empty() {
             self.active_tab = self.tabs.len() - 1;
         }
+        // The active tab has been seen once its window is back in front.
+        if window.is_window_active()
+            && let Some(tab) = self.tabs.get_mut(self.active_tab)
+        {
+            tab.badge = None;
+        }
 
         let active_tab = self.active_tab;
         let sidebar_active = self.sidebar_visible;
@@ -658,17 +748,65 @@ impl Render for TabBar {
                                         .child(edit_right)
                                 } else {
                                     div()
+                                        .flex()
+                                        .items_center()
+                                        .gap(px(6.0))
                                         .flex_1()
                                         .min_w(px(0.0))
-                                        .text_size(px(12.0))
-                                        .text_color(if is_active {
-                                            rgb(0xffffff)
-                                        } else {
-                                            rgb(0x7a7a7a)
+                                        .when_some(tab.badge, |this, badge| {
+                                            this.child(
+                                                div()
+                                                    .flex_none()
+                                                    .size(px(7.0))
+                                                    .rounded_full()
+                                                    .bg(rgb(badge.color())),
+                                            )
+                                        })
+                                        .child(
+                                            div()
+                                                .flex_1()
+                                                .min_w(px(0.0))
+                                                .text_size(px(12.0))
+                                                .text_color(if is_active {
+                                                    rgb(0xffffff)
+                                                } else {
+                                                    rgb(0x7a7a7a)
+                                                })
+                                                .font_family("Cascadia Code")
+                                                .truncate()
+                                                .child(tab.name.clone()),
+                                        )
+                                        .when_some(tab.dev_server.clone(), |this, url| {
+                                            let port = url
+                                                .split("://")
+                                                .last()
+                                                .and_then(|rest| rest.split('/').next())
+                                                .and_then(|host| host.rsplit_once(':'))
+                                                .map(|(_, port)| port.to_string())
+                                                .unwrap_or_default();
+                                            this.child(
+                                                div()
+                                                    .flex()
+                                                    .flex_none()
+                                                    .items_center()
+                                                    .gap(px(3.0))
+                                                    .px(px(4.0))
+                                                    .rounded(px(4.0))
+                                                    .bg(rgb(0x10251a))
+                                                    .text_size(px(10.0))
+                                                    .text_color(rgb(0x9fdcb4))
+                                                    .cursor(CursorStyle::PointingHand)
+                                                    .child(lucide_icon(Icon::Globe, 10.0, 0x7ee787))
+                                                    .child(port)
+                                                    .on_mouse_down(
+                                                        MouseButton::Left,
+                                                        move |_event, _window, cx| {
+                                                            cx.stop_propagation();
+                                                            let _ = webbrowser::open(&url);
+                                                        },
+                                                    ),
+                                            )
                                         })
-                                        .font_family("Cascadia Code")
-                                        .truncate()
-                                        .child(tab.name.clone())
                                 })
                                 .child(
                                     div()
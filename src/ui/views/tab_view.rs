@@ -1,6 +1,25 @@
+use crate::completion::spec::GeneratorSpec;
+use crate::completion::{CompletionEngine, SpecCompletion, shell};
 use crate::git::get_git_branches;
 use crate::git::get_git_status;
+use crate::history::import::collect_shell_history;
+use crate::history::{
+    HistoryFilter, HistoryMatch, HistoryQuery, HistoryStore, RankedCommand, default_store_path,
+    now_timestamp,
+};
+use crate::secrets::{MASK, PrivateKeyTracker, REDACTED, SecretScanner, StreamRedactor};
 use crate::terminal::TerminalPty;
+use crate::terminal::ansi::{AnsiParser, TerminalEvent, strip_ansi};
+use crate::terminal::asciicast::{Cast, CastEventKind, CastHeader, CastRecorder};
+use crate::terminal::command_guard::{GuardCache, GuardContext, GuardMatch};
+use crate::terminal::dev_server::{self, ListeningPort};
+use crate::terminal::mouse::{MouseInput, MouseProtocol, MouseReport, PointerButton};
+use crate::terminal::problems::{Problem, parse_problems};
+use crate::terminal::processes::{ProcessInfo, ProcessSampler, ProcessSignal, send_signal};
+use crate::terminal::secure_input::is_password_prompt;
+use crate::terminal::test_results::{
+    TestCase, TestReport, TestRunner, parse_test_report, rerun_command,
+};
 use crate::{
     acp::client::{
         AcpClient, AcpResponseText, PermissionDecision, PermissionOption, PermissionRequest,
@@ -18,22 +37,37 @@ use futures::StreamExt;
 use futures::channel::mpsc;
 use gpui::*;
 use lucide_icons::Icon;
-use std::collections::{BTreeMap, HashSet, VecDeque};
-use std::io::Read;
+use std::cell::{Cell, Ref, RefCell};
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+use std::fs::File;
+use std::io::{BufWriter, Read};
+use std::ops::Range;
+use std::panic::AssertUnwindSafe;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex, OnceLock};
 use std::thread;
 use std::time::{Duration, Instant};
 
+use crate::ui::block_diff::{self, DiffLine, DiffOptions, DiffRow};
+use crate::ui::edit_mode::{EditAction, EditBuffer, EditKey, ModalEditor};
+use crate::ui::file_rules::OrbitshellRules;
 use crate::ui::icons::{lucide_icon, registry_avatar};
+use crate::ui::input_highlight::{HighlightKind, highlight};
 use crate::ui::launch;
+use crate::ui::notifications;
+use crate::ui::output_selection::{column_slice, word_at};
 use crate::ui::recent::RecentEntry;
-use crate::ui::text_edit::TextEditState;
+use crate::ui::runbook::{self, FenceRun, MAX_SAVED_OUTPUT_LINES, RunbookResults};
+use crate::ui::terminal_settings::{BellStyle, TerminalSettings};
+use crate::ui::text_edit::{CursorRange, MultiEdit, TextEditState};
+use crate::ui::text_width::{offset_at_column, str_width, wrap_ranges};
 use crate::ui::views::agent_view::AgentView;
 use crate::ui::views::settings_view::SettingsView;
 use crate::ui::views::welcome_view::{
     CloneRepositoryEvent, CreateProjectEvent, OpenRepositoryEvent, WelcomeView,
 };
+use crate::ui::watch::{FileSnapshot, MAX_WATCH_RUNS, WATCH_INTERVALS, WatchOutput, WatchTrigger};
 
 const DEFAULT_PREVIEW_TERMINAL_HEIGHT: f32 = 260.0;
 const MIN_PREVIEW_TERMINAL_HEIGHT: f32 = 180.0;
@@ -46,6 +80,15 @@ const INPUT_MONO_CHAR_WIDTH: f32 = 8.2;
 const MAX_RENDERED_INPUT_LINES: usize = 4;
 const INPUT_LINE_HEIGHT: f32 = 20.0;
 const INPUT_LINE_GAP: f32 = 2.0;
+const TERMINAL_COLS: u16 = 80;
+const TERMINAL_ROWS: u16 = 24;
+/// Padding around the output, used to map the pointer to a terminal cell for
+/// mouse reporting, and the cell size of its 12px font until it is measured.
+const OUTPUT_PADDING: f32 = 16.0;
+const OUTPUT_CHAR_WIDTH: f32 = 7.8;
+const OUTPUT_LINE_HEIGHT: f32 = 21.0;
+const OUTPUT_FONT_SIZE: f32 = 12.0;
+const MAX_HISTORY_SEARCH_RESULTS: usize = 50;
 
 pub struct TabView {
     blocks: Vec<Block>,
@@ -53,15 +96,28 @@ pub struct TabView {
     focus_handle: FocusHandle,
     input: String,
     cursor: usize,
-    history: VecDeque<String>,
-    history_file: Option<PathBuf>,
+    history_store: Arc<Mutex<HistoryStore>>,
+    history_filter: HistoryFilter,
+    running_command: Option<RunningCommand>,
+    secure_input: Option<SecureInput>,
+    pending_guard: Option<PendingGuard>,
+    guard_cache: GuardCache,
+    shell_reports_status: bool,
+    ansi_parser: AnsiParser,
+    editor: ModalEditor,
     history_open: bool,
     history_index: usize,
     history_items: Vec<SuggestionItem>,
     suggestions: Vec<SuggestionItem>,
     suggest_index: usize,
+    suggestion_menu_open: bool,
+    completion_engine: Arc<CompletionEngine>,
+    completion_generators_running: bool,
     selection: Option<(usize, usize)>,
     selection_anchor: Option<usize>,
+    /// Cursors besides `cursor`/`selection`, added with Ctrl+Shift+Up/Down or
+    /// Ctrl+D. Edits apply at all of them.
+    extra_cursors: Vec<CursorRange>,
     path_commands: Vec<String>,
     last_path_scan: Instant,
     last_path_var: String,
@@ -92,10 +148,47 @@ pub struct TabView {
     model_options_loading: bool,
     selected_model_override: Option<String>,
     selected_block: Option<usize>,
+    /// Keys move between blocks instead of editing the input. The focused
+    /// block is `selected_block`.
+    block_nav_active: bool,
+    /// Blocks picked for copying in block navigation.
+    block_selection: BTreeSet<usize>,
+    block_nav_status: Option<String>,
+    /// Counts visual bells; each one restarts the flash animation.
+    bell_flashes: u64,
+    /// Mouse reporting asked for by the running program.
+    mouse_protocol: MouseProtocol,
+    last_mouse_cell: Option<(u16, u16)>,
+    /// Holding Shift hands the mouse back to local selection.
+    shift_held: bool,
     output_selection_anchor: Option<(usize, usize)>,
     output_selection_head: Option<(usize, usize)>,
     output_selecting: bool,
+    output_selection_kind: OutputSelectionKind,
+    /// Left edge of the output text, measured as it is laid out, to find the
+    /// column under the pointer.
+    output_text_left: Rc<Cell<f32>>,
+    /// Distance between the tops of two output rows, as last laid out.
+    output_row_height: Rc<Cell<f32>>,
     file_preview: Option<FilePreviewState>,
+    /// Shown in the preview area instead of a file.
+    block_diff: Option<BlockDiffState>,
+    watch: Option<WatchState>,
+    watch_generation: u64,
+    runbook: Option<RunbookState>,
+    dev_servers: Vec<DevServer>,
+    dev_server_polling: bool,
+    /// Problems from the last run of each command, newest first.
+    problem_groups: Vec<ProblemGroup>,
+    process_panel: Option<ProcessPanel>,
+    process_panel_generation: u64,
+    /// URL last sent to the tab bar.
+    tab_dev_server: Option<String>,
+    recording: Option<Recording>,
+    /// Where the last recording was saved, or why it stopped.
+    recording_status: Option<(String, Option<PathBuf>)>,
+    replay: Option<ReplayState>,
+    replay_generation: u64,
     preview_search_match: Option<PreviewSearchMatch>,
     preview_code_scroll_handle: UniformListScrollHandle,
     preview_focus: bool,
@@ -110,6 +203,19 @@ pub struct TabView {
     preview_terminal_resize_start_height: f32,
 }
 
+/// A password prompt being answered. Keys go straight to the PTY and only
+/// their count is kept, to draw the mask.
+struct SecureInput {
+    prompt: String,
+    typed: usize,
+}
+
+/// A command the guard stopped, waiting for the user to confirm it.
+struct PendingGuard {
+    command: String,
+    matches: Vec<GuardMatch>,
+}
+
 #[derive(Clone)]
 struct Block {
     command: String,
@@ -122,6 +228,150 @@ struct Block {
     agent_stream_line_index: Option<usize>,
     agent_response: Option<AcpResponseText>,
     agent_response_line_count: usize,
+    exit_code: Option<i32>,
+    duration: Option<Duration>,
+    /// Shows the secrets the scanner masks in this block.
+    secrets_revealed: bool,
+    /// Shows only the header. The output is kept.
+    collapsed: bool,
+    /// Output lines that carried SGR colors, as received, by line index. Used
+    /// to copy with ANSI.
+    styled_lines: BTreeMap<usize, String>,
+    /// Results when the command was a test run.
+    tests: Option<BlockTests>,
+    /// Filled in as output is drawn; see `Block::private_key_lines`.
+    private_keys: RefCell<PrivateKeyLines>,
+}
+
+impl Block {
+    /// Which output lines belong to a private key. Only lines added since
+    /// the last call are scanned.
+    fn private_key_lines(&self) -> Ref<'_, [bool]> {
+        self.private_keys.borrow_mut().update(&self.output_lines);
+        Ref::map(self.private_keys.borrow(), |keys| keys.flags.as_slice())
+    }
+}
+
+/// Private key lines of a block, kept as lines are appended so drawing
+/// does not rescan the whole output.
+#[derive(Clone, Default)]
+struct PrivateKeyLines {
+    flags: Vec<bool>,
+    /// The tracker after the last line in `flags`, and before it.
+    keys: PrivateKeyTracker,
+    before_last: PrivateKeyTracker,
+}
+
+impl PrivateKeyLines {
+    /// Scans lines past the ones already seen. The last seen line is scanned
+    /// again, since output keeps growing its final line. Lines inserted or
+    /// removed before it need `clear` or `drop_front` first.
+    fn update(&mut self, lines: &[String]) {
+        if lines.len() < self.flags.len() {
+            self.clear();
+        }
+        if self.flags.pop().is_some() {
+            self.keys = self.before_last.clone();
+        }
+        for line in &lines[self.flags.len()..] {
+            self.before_last = self.keys.clone();
+            let in_key = self.keys.in_key(line);
+            self.flags.push(in_key);
+        }
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Follows `count` lines being trimmed from the start of the output.
+    fn drop_front(&mut self, count: usize) {
+        if count >= self.flags.len() {
+            self.clear();
+        } else {
+            self.flags.drain(..count);
+        }
+    }
+}
+
+/// Test results parsed from a block's output.
+#[derive(Clone)]
+struct BlockTests {
+    report: TestReport,
+    /// Shows the failures under the summary.
+    open: bool,
+    /// Failures whose output is shown, by name.
+    expanded: HashSet<String>,
+}
+
+/// How the output between the selection anchor and head is taken.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum OutputSelectionKind {
+    /// Whole lines; a click selects nothing until it is dragged.
+    #[default]
+    Lines,
+    /// Whole lines, even a single one, from a triple-click.
+    WholeLines,
+    /// Chars of one line, from a double-click.
+    Word { start: usize, end: usize },
+    /// The same display columns of every line, from an Alt+drag.
+    Rectangle {
+        anchor_column: usize,
+        head_column: usize,
+    },
+}
+
+/// The part of one output line that is selected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum LineSelection {
+    Whole,
+    Chars(Range<usize>),
+    Columns(Range<usize>),
+}
+
+impl LineSelection {
+    /// Chars of the wrapped row starting at char `start` and display column
+    /// `column` to highlight, relative to the row.
+    fn row_range(&self, row: &str, start: usize, column: usize) -> Option<Range<usize>> {
+        let len = row.chars().count();
+        let range = match self {
+            Self::Whole => return None,
+            Self::Chars(chars) => {
+                chars.start.saturating_sub(start).min(len)..chars.end.saturating_sub(start).min(len)
+            }
+            Self::Columns(columns) => {
+                let from = columns.start.saturating_sub(column);
+                let to = columns.end.saturating_sub(column);
+                offset_at_column(row, from)..offset_at_column(row, to)
+            }
+        };
+        (range.start < range.end).then_some(range)
+    }
+}
+
+/// What block navigation copies from the chosen blocks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BlockCopy {
+    Command,
+    Output,
+    Both,
+}
+
+impl BlockCopy {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Command => "command",
+            Self::Output => "output",
+            Self::Both => "command and output",
+        }
+    }
+}
+
+/// The shell command whose exit status has not been reported yet.
+struct RunningCommand {
+    history_id: u64,
+    block_index: Option<usize>,
+    started_at: Instant,
 }
 
 #[derive(Clone)]
@@ -168,12 +418,23 @@ struct ModelPickerState {
     selected: usize,
 }
 
+/// Ctrl+R reverse search. Holds the store so the static overlay helpers can
+/// re-run the search as the query changes.
+struct HistorySearchState {
+    store: Arc<Mutex<HistoryStore>>,
+    cwd: String,
+    query: PickerQueryState,
+    matches: Vec<HistoryMatch>,
+    selected: usize,
+}
+
 #[derive(Clone, Default)]
 struct PickerQueryState {
     text: String,
     cursor: usize,
     selection: Option<(usize, usize)>,
     anchor: Option<usize>,
+    editor: ModalEditor,
 }
 
 struct TooltipView {
@@ -227,6 +488,7 @@ enum Overlay {
     Agent(AgentPickerState),
     #[allow(dead_code)]
     Model(ModelPickerState),
+    History(HistorySearchState),
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -236,6 +498,7 @@ enum PickerKind {
     Branch,
     Agent,
     Model,
+    History,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -254,7 +517,10 @@ enum InitialFocusTarget {
 
 #[allow(dead_code)]
 fn picker_has_search_input(kind: PickerKind, option_count: usize) -> bool {
-    matches!(kind, PickerKind::Path | PickerKind::Branch) || option_count >= 6
+    matches!(
+        kind,
+        PickerKind::Path | PickerKind::Branch | PickerKind::History
+    ) || option_count >= 6
 }
 
 #[allow(dead_code)]
@@ -270,7 +536,9 @@ fn picker_header_is_static(kind: PickerKind, option_count: usize) -> bool {
 #[allow(dead_code)]
 fn picker_initial_focus_target(kind: PickerKind) -> InitialFocusTarget {
     match kind {
-        PickerKind::Path | PickerKind::Branch => InitialFocusTarget::SearchInput,
+        PickerKind::Path | PickerKind::Branch | PickerKind::History => {
+            InitialFocusTarget::SearchInput
+        }
         PickerKind::Agent | PickerKind::Model => InitialFocusTarget::List,
     }
 }
@@ -325,6 +593,20 @@ fn compute_trigger_state(
 }
 
 impl PickerQueryState {
+    fn handle_edit_key(&mut self, key: EditKey<'_>) -> EditAction {
+        self.editor
+            .set_mode(TerminalSettings::current().editing_mode);
+        self.editor.handle_key(
+            key,
+            EditBuffer {
+                text: &mut self.text,
+                cursor: &mut self.cursor,
+                selection: &mut self.selection,
+                anchor: &mut self.anchor,
+            },
+        )
+    }
+
     fn normalized_selection(&self) -> Option<(usize, usize)> {
         TextEditState::normalized_selection(self.selection)
     }
@@ -477,6 +759,7 @@ impl PickerQueryState {
 struct SuggestionItem {
     display: String,
     insert: String,
+    description: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -486,17 +769,75 @@ struct InputVisualLine {
     has_cursor: bool,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum InputSegment {
+    Text(String),
+    Selected(String),
+    Caret,
+}
+
 pub enum TabViewEvent {
     CwdChanged(PathBuf),
     OpenRepository(PathBuf),
-    StartBaseTerminal { path: PathBuf, command: String },
-    StartBaseAgent { path: PathBuf, prompt: String },
-    CreateProject { prompt: String, parent: PathBuf },
-    CloneRepository { url: String, parent: PathBuf },
+    StartBaseTerminal {
+        path: PathBuf,
+        command: String,
+    },
+    StartBaseAgent {
+        path: PathBuf,
+        prompt: String,
+    },
+    CreateProject {
+        prompt: String,
+        parent: PathBuf,
+    },
+    CloneRepository {
+        url: String,
+        parent: PathBuf,
+    },
+    CommandFinished {
+        command: String,
+        exit_code: Option<i32>,
+        duration: Duration,
+    },
+    TitleChanged(String),
+    Bell,
+    /// Open the recording in a new read-only tab.
+    ReplayRecording(PathBuf),
+    /// The dev server shown on the tab, if any, changed.
+    DevServerChanged(Option<String>),
+    /// A finished command changed the tab's problem list.
+    ProblemsChanged,
 }
 
+/// Problems found in the output of a command's last run.
+#[derive(Clone, Debug)]
+pub struct ProblemGroup {
+    pub command: String,
+    /// Where the command ran. Relative file names start here.
+    pub cwd: PathBuf,
+    pub problems: Vec<Problem>,
+}
+
+/// How often the ports of the shell's processes are checked.
+const DEV_SERVER_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Checks a printed URL gets to start listening before it is dropped.
+const DEV_SERVER_CHECKS: u8 = 15;
+
+/// How often the process panel refreshes.
+const PROCESS_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// Speeds offered for replaying a recording.
+const REPLAY_SPEEDS: [f64; 4] = [0.5, 1.0, 2.0, 4.0];
+
 const MAX_OUTPUT_LINES: usize = 5000;
+const MAX_SUGGESTION_MENU_ITEMS: usize = 8;
 const MAX_RENDERED_OUTPUT_LINES_PER_BLOCK: usize = 400;
+/// How much of a block must still be on screen for its header to stay pinned.
+const STICKY_HEADER_MIN_VISIBLE: f32 = 48.0;
 
 enum TabViewMode {
     Terminal,
@@ -525,6 +866,208 @@ enum FilePreviewKind {
     },
 }
 
+/// A local server a command printed the URL of. It is shown once something
+/// the shell started listens on its port.
+struct DevServer {
+    url: String,
+    port: u16,
+    block_index: Option<usize>,
+    /// The listening process, when known.
+    pid: Option<u32>,
+    confirmed: bool,
+    /// Shown without port information, so nothing says when it stops. It
+    /// goes when the command that printed it finishes.
+    assumed: bool,
+    stopping: bool,
+    /// Checks left for an unconfirmed URL before it is dropped.
+    checks_left: u8,
+}
+
+/// The processes under the tab's shell, refreshed while the panel is open.
+struct ProcessPanel {
+    processes: Vec<ProcessInfo>,
+    sampler: ProcessSampler,
+    /// Ends the refresh loop of a panel that was closed.
+    generation: u64,
+    /// Why the list is empty, or a signal that could not be sent.
+    message: Option<String>,
+}
+
+/// A previewed Markdown file whose shell fences run in the tab.
+struct RunbookState {
+    path: PathBuf,
+    results: RunbookResults,
+    /// Fence key and command sent, until the shell starts its block.
+    pending: Option<(String, String)>,
+    /// Fence key and the block its command is running in.
+    running: Option<(String, usize)>,
+    /// Fences Run all has yet to start, as key and command.
+    queue: VecDeque<(String, String)>,
+    prompt: Option<RunbookPrompt>,
+    error: Option<String>,
+}
+
+impl RunbookState {
+    fn new(path: PathBuf) -> Self {
+        let results = RunbookResults::load(&path);
+        Self {
+            path,
+            results,
+            pending: None,
+            running: None,
+            queue: VecDeque::new(),
+            prompt: None,
+            error: None,
+        }
+    }
+
+    fn is_busy(&self) -> bool {
+        self.pending.is_some() || self.running.is_some() || !self.queue.is_empty()
+    }
+}
+
+/// Asks for the `{{name}}` variables of the fences about to run, one at a
+/// time.
+struct RunbookPrompt {
+    names: Vec<String>,
+    index: usize,
+    value: String,
+    fences: Vec<(String, String)>,
+}
+
+/// A session being written to an asciicast file.
+struct Recording {
+    recorder: CastRecorder<BufWriter<File>>,
+    path: PathBuf,
+    /// Output waiting for the rest of its line before it is redacted.
+    redactor: StreamRedactor,
+    /// When the held output arrived.
+    held_since: Option<Instant>,
+}
+
+impl Recording {
+    /// Writes an event with its secrets redacted. Output is written a line
+    /// at a time, stamped with when it arrived; what is held goes out before
+    /// any other event so the file stays in order.
+    fn record(
+        &mut self,
+        scanner: &SecretScanner,
+        kind: CastEventKind,
+        data: &str,
+    ) -> anyhow::Result<()> {
+        if kind != CastEventKind::Output {
+            self.flush(scanner)?;
+            return self.recorder.record(kind, &scanner.redact(data));
+        }
+        let now = Instant::now();
+        let arrived = self.held_since.unwrap_or(now);
+        let ready = self.redactor.push(scanner, data);
+        if ready.is_empty() {
+            self.held_since = Some(arrived);
+            return Ok(());
+        }
+        self.held_since = self.redactor.is_holding().then_some(now);
+        self.recorder.record_at(kind, &ready, arrived)
+    }
+
+    fn flush(&mut self, scanner: &SecretScanner) -> anyhow::Result<()> {
+        let held = self.redactor.flush(scanner);
+        match self.held_since.take() {
+            Some(arrived) if !held.is_empty() => {
+                self.recorder
+                    .record_at(CastEventKind::Output, &held, arrived)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn finish(mut self, scanner: &SecretScanner) -> anyhow::Result<PathBuf> {
+        self.flush(scanner)?;
+        self.recorder.finish()?;
+        Ok(self.path)
+    }
+}
+
+/// A recording played back into a read-only tab.
+struct ReplayState {
+    cast: Arc<Cast>,
+    path: PathBuf,
+    /// Index of the next event to play.
+    next_event: usize,
+    /// Recording time reached, in seconds.
+    position: f64,
+    speed: f64,
+    playing: bool,
+    /// Ends the playback loop of an earlier play or restart.
+    generation: u64,
+}
+
+/// A block whose command re-runs on file changes or a timer.
+struct WatchState {
+    command: String,
+    /// Where file changes are looked for: the block's directory.
+    cwd: PathBuf,
+    trigger: WatchTrigger,
+    output: WatchOutput,
+    /// The block of the latest run; `None` once it scrolled away.
+    block_index: Option<usize>,
+    /// Earlier runs, oldest first.
+    runs: Vec<WatchRun>,
+    snapshot: Option<FileSnapshot>,
+    /// A change was seen; the command runs once the shell is idle.
+    due: bool,
+    /// Ends the polling of an earlier watch or trigger.
+    generation: u64,
+}
+
+struct WatchRun {
+    number: usize,
+    output_lines: Vec<String>,
+    exit_code: Option<i32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DiffLayout {
+    SideBySide,
+    Unified,
+}
+
+/// Two block outputs compared in the preview area. The lines are copied when
+/// it opens, so it stays put while output keeps coming.
+struct BlockDiffState {
+    left_title: String,
+    right_title: String,
+    left: Arc<Vec<String>>,
+    right: Arc<Vec<String>>,
+    options: DiffOptions,
+    layout: DiffLayout,
+    lines: Arc<Vec<DiffLine>>,
+    rows: Arc<Vec<DiffRow>>,
+}
+
+impl BlockDiffState {
+    fn new(left: (String, Vec<String>), right: (String, Vec<String>)) -> Self {
+        let mut state = Self {
+            left_title: left.0,
+            right_title: right.0,
+            left: Arc::new(left.1),
+            right: Arc::new(right.1),
+            options: DiffOptions::default(),
+            layout: DiffLayout::SideBySide,
+            lines: Arc::default(),
+            rows: Arc::default(),
+        };
+        state.recompute();
+        state
+    }
+
+    fn recompute(&mut self) {
+        let lines = block_diff::diff_lines(&self.left, &self.right, self.options);
+        self.rows = Arc::new(block_diff::side_by_side(&lines));
+        self.lines = Arc::new(lines);
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum PreviewLanguage {
     Rust,
@@ -749,6 +1292,7 @@ fn append_agent_stream_delta(block: &mut Block, delta: &str, total_output_lines:
             .map(|index| index + 1)
             .unwrap_or(block.output_lines.len());
         block.output_lines.insert(insert_at, trimmed.to_string());
+        block.private_keys.get_mut().clear();
         *total_output_lines += 1;
         insert_index = Some(insert_at);
         block.agent_stream_line_index = insert_index;
@@ -774,6 +1318,7 @@ fn replace_agent_stream_snapshot(
         if start_index <= drain_end && start_index < block.output_lines.len() {
             let removed = drain_end - start_index + 1;
             block.output_lines.drain(start_index..=drain_end);
+            block.private_keys.get_mut().clear();
             *total_output_lines = total_output_lines.saturating_sub(removed);
             insert_at = start_index;
         }
@@ -788,6 +1333,7 @@ fn replace_agent_stream_snapshot(
         }
         let target_index = insert_at + offset;
         block.output_lines.insert(target_index, line.clone());
+        block.private_keys.get_mut().clear();
         *total_output_lines += 1;
         last_line_index = Some(target_index);
     }
@@ -909,11 +1455,11 @@ impl TabView {
         _window: &mut Window,
         cx: &mut Context<Self>,
     ) {
-        self.copy_selected_output(cx);
+        self.copy_selected_output(false, cx);
     }
 
-    fn copy_selected_output(&mut self, cx: &mut Context<Self>) {
-        if let Some(text) = self.selected_output_text() {
+    fn copy_selected_output(&mut self, ansi: bool, cx: &mut Context<Self>) {
+        if let Some(text) = self.selected_output_text(ansi) {
             cx.write_to_clipboard(ClipboardItem::new_string(text));
             return;
         }
@@ -962,6 +1508,12 @@ impl TabView {
                 line = format!("{line}  git:({branch})");
             }
         }
+        if let Some(code) = block.exit_code {
+            line = format!("{line}  exit {code}");
+        }
+        if let Some(duration) = block.duration {
+            line = format!("{line}  {}", format_command_duration(duration));
+        }
         Some(line)
     }
 
@@ -974,13 +1526,14 @@ impl TabView {
             parts.push(block.command.clone());
         }
         parts.extend(block.output_lines.clone());
-        parts.join("\n")
+        self.secret_scanner().redact(&parts.join("\n")).into_owned()
     }
 
     fn clear_output_selection(&mut self) {
         self.output_selection_anchor = None;
         self.output_selection_head = None;
         self.output_selecting = false;
+        self.output_selection_kind = OutputSelectionKind::Lines;
     }
 
     fn clear_preview_selection(&mut self) {
@@ -1042,6 +1595,26 @@ impl TabView {
 
     fn shift_output_indices_after_front_block_removal(&mut self) {
         self.selected_block = self.selected_block.and_then(|index| index.checked_sub(1));
+        self.block_selection = self
+            .block_selection
+            .iter()
+            .filter_map(|index| index.checked_sub(1))
+            .collect();
+        if let Some(running) = self.running_command.as_mut() {
+            running.block_index = running.block_index.and_then(|index| index.checked_sub(1));
+        }
+        if let Some(watch) = self.watch.as_mut() {
+            watch.block_index = watch.block_index.and_then(|index| index.checked_sub(1));
+        }
+        for server in &mut self.dev_servers {
+            server.block_index = server.block_index.and_then(|index| index.checked_sub(1));
+        }
+        if let Some(runbook) = self.runbook.as_mut() {
+            runbook.running = runbook
+                .running
+                .take()
+                .and_then(|(key, index)| Some((key, index.checked_sub(1)?)));
+        }
 
         let shift = |point: Option<(usize, usize)>| -> Option<(usize, usize)> {
             match point {
@@ -1061,7 +1634,7 @@ impl TabView {
     fn normalize_output_selection(&self) -> Option<((usize, usize), (usize, usize))> {
         let anchor = self.output_selection_anchor?;
         let head = self.output_selection_head?;
-        if anchor == head {
+        if anchor == head && self.output_selection_kind == OutputSelectionKind::Lines {
             return None;
         }
         if Self::line_position_key(anchor) <= Self::line_position_key(head) {
@@ -1075,16 +1648,31 @@ impl TabView {
         (block_index, line_index)
     }
 
-    fn is_output_line_selected(&self, block_index: usize, line_index: usize) -> bool {
-        let Some((start, end)) = self.normalize_output_selection() else {
-            return false;
-        };
-        let current = (block_index, line_index);
-        Self::line_position_key(current) >= Self::line_position_key(start)
-            && Self::line_position_key(current) <= Self::line_position_key(end)
+    fn output_line_selection(
+        &self,
+        block_index: usize,
+        line_index: usize,
+    ) -> Option<LineSelection> {
+        let (start, end) = self.normalize_output_selection()?;
+        let current = Self::line_position_key((block_index, line_index));
+        if current < Self::line_position_key(start) || current > Self::line_position_key(end) {
+            return None;
+        }
+        Some(match self.output_selection_kind {
+            OutputSelectionKind::Lines | OutputSelectionKind::WholeLines => LineSelection::Whole,
+            OutputSelectionKind::Word { start, end } => LineSelection::Chars(start..end),
+            OutputSelectionKind::Rectangle {
+                anchor_column,
+                head_column,
+            } => LineSelection::Columns(
+                anchor_column.min(head_column)..anchor_column.max(head_column) + 1,
+            ),
+        })
     }
 
-    fn selected_output_text(&self) -> Option<String> {
+    /// The selected output, with secrets redacted. `ansi` keeps the colors of
+    /// whole-line selections.
+    fn selected_output_text(&self, ansi: bool) -> Option<String> {
         let (start, end) = self.normalize_output_selection()?;
         let mut lines = Vec::new();
 
@@ -1108,1457 +1696,3671 @@ impl TabView {
             if from > to {
                 continue;
             }
-            lines.extend(block.output_lines[from..=to].iter().cloned());
+            let private_key = block.private_key_lines();
+            for (line_index, (line, in_key)) in block
+                .output_lines
+                .iter()
+                .zip(private_key.iter().copied())
+                .enumerate()
+                .take(to + 1)
+                .skip(from)
+            {
+                let Some(selection) = self.output_line_selection(block_index, line_index) else {
+                    continue;
+                };
+                if in_key {
+                    lines.push(REDACTED.to_string());
+                    continue;
+                }
+                lines.push(match selection {
+                    LineSelection::Whole if ansi => {
+                        block.styled_lines.get(&line_index).unwrap_or(line).clone()
+                    }
+                    LineSelection::Whole => line.clone(),
+                    LineSelection::Chars(chars) => {
+                        line.chars().skip(chars.start).take(chars.len()).collect()
+                    }
+                    LineSelection::Columns(columns) => column_slice(line, columns),
+                });
+            }
         }
 
         if lines.is_empty() {
             None
         } else {
-            Some(lines.join("\n"))
+            Some(self.secret_scanner().redact(&lines.join("\n")).into_owned())
         }
     }
 
-    fn on_select_block(&mut self, index: usize, cx: &mut Context<Self>) {
-        self.selected_block = Some(index);
-        self.clear_output_selection();
-        cx.notify();
+    fn toggle_block_collapsed(&mut self, index: usize, cx: &mut Context<Self>) {
+        if let Some(block) = self.blocks.get_mut(index) {
+            block.collapsed = !block.collapsed;
+            self.selected_block = Some(index);
+            cx.notify();
+        }
     }
 
-    fn on_output_line_mouse_down_at(
-        &mut self,
-        block_index: usize,
-        line_index: usize,
-        event: &MouseDownEvent,
-        cx: &mut Context<Self>,
-    ) {
-        let position = (block_index, line_index);
-        if event.modifiers.shift {
-            if self.output_selection_anchor.is_none() {
-                self.output_selection_anchor = Some(position);
-            }
-            self.output_selection_head = Some(position);
-        } else {
-            self.output_selection_anchor = Some(position);
-            self.output_selection_head = Some(position);
+    fn collapse_all_blocks_except_last(&mut self, cx: &mut Context<Self>) {
+        let last = self.blocks.len().saturating_sub(1);
+        for (index, block) in self.blocks.iter_mut().enumerate() {
+            block.collapsed = index != last && !block.command.is_empty();
         }
-        self.selected_block = Some(block_index);
-        self.output_selecting = true;
         cx.notify();
-        cx.stop_propagation();
     }
 
-    fn on_output_line_mouse_move_at(
-        &mut self,
-        block_index: usize,
-        line_index: usize,
-        event: &MouseMoveEvent,
-        cx: &mut Context<Self>,
-    ) {
-        if !self.output_selecting || !event.dragging() {
-            return;
+    fn expand_all_blocks(&mut self, cx: &mut Context<Self>) {
+        for block in &mut self.blocks {
+            block.collapsed = false;
         }
-        self.output_selection_head = Some((block_index, line_index));
-        self.selected_block = Some(block_index);
         cx.notify();
     }
 
-    fn on_output_mouse_up(
-        &mut self,
-        _event: &MouseUpEvent,
-        _window: &mut Window,
-        _cx: &mut Context<Self>,
-    ) {
-        self.output_selecting = false;
-    }
-
-    fn on_preview_line_mouse_down_at(
-        &mut self,
-        line_index: usize,
-        event: &MouseDownEvent,
-        cx: &mut Context<Self>,
-    ) {
-        self.focus_preview();
-        if self
-            .preview_search_match
-            .as_ref()
-            .is_some_and(|search_match| search_match.line_index != line_index)
-        {
-            self.preview_search_match = None;
+    /// Block navigation. Ctrl+Up enters it; once in, this handles every key,
+    /// and a key it has no use for leaves it and goes on to the input.
+    fn handle_block_nav_key(&mut self, event: &KeyDownEvent, cx: &mut Context<Self>) -> bool {
+        let modifiers = event.keystroke.modifiers;
+        let key = event.keystroke.key.to_ascii_lowercase();
+        let Some(last) = self.blocks.len().checked_sub(1) else {
+            self.exit_block_nav();
+            return false;
+        };
+        // Keys belong to the program while it owns the terminal.
+        if !self.input_visible {
+            self.exit_block_nav();
+            return false;
         }
-        if event.modifiers.shift {
-            if self.preview_selection_anchor.is_none() {
-                self.preview_selection_anchor =
-                    Some(self.preview_active_line.unwrap_or(line_index));
+        if !self.block_nav_active {
+            let enter = matches!(key.as_str(), "up" | "arrowup")
+                && modifiers.control
+                && !modifiers.shift
+                && !modifiers.alt
+                && self.input_mode == InputMode::Terminal;
+            if !enter {
+                return false;
+            }
+            self.block_nav_active = true;
+            self.history_open = false;
+            self.suggestion_menu_open = false;
+            let focused = self
+                .selected_block
+                .filter(|index| *index <= last)
+                .unwrap_or(last);
+            self.focus_block(focused);
+            cx.notify();
+            return true;
+        }
+
+        let shift = modifiers.shift;
+        let focused = self.selected_block.unwrap_or(last).min(last);
+        self.block_nav_status = None;
+        match key.as_str() {
+            "up" | "arrowup" | "k" => self.move_block_focus(focused.saturating_sub(1), shift),
+            "down" | "arrowdown" | "j" => self.move_block_focus((focused + 1).min(last), shift),
+            "home" => self.move_block_focus(0, shift),
+            "end" => self.move_block_focus(last, shift),
+            "g" => self.move_block_focus(if shift { last } else { 0 }, false),
+            "space" => {
+                if !self.block_selection.remove(&focused) {
+                    self.block_selection.insert(focused);
+                }
+            }
+            "a" if modifiers.control => self.block_selection = (0..=last).collect(),
+            "c" if modifiers.control => self.copy_blocks(BlockCopy::Both, cx),
+            "c" => self.copy_blocks(BlockCopy::Command, cx),
+            "o" => self.copy_blocks(BlockCopy::Output, cx),
+            "y" => self.copy_blocks(BlockCopy::Both, cx),
+            "d" => self.diff_blocks_from_nav(focused, cx),
+            "e" => self.jump_to_error_block(focused, !shift),
+            "t" => self.scroll_handle.scroll_to_top_of_item(focused),
+            "left" | "arrowleft" | "h" => self.blocks[focused].collapsed = true,
+            "right" | "arrowright" | "l" => self.blocks[focused].collapsed = false,
+            "z" => self.blocks[focused].collapsed = !self.blocks[focused].collapsed,
+            "enter" | "return" | "numpadenter" => {
+                let command = self.blocks[focused].command.clone();
+                self.exit_block_nav();
+                if !command.is_empty() {
+                    self.input = command;
+                    self.cursor = self.input.chars().count();
+                    self.clear_selection();
+                    self.extra_cursors.clear();
+                }
+            }
+            "escape" | "i" => self.exit_block_nav(),
+            _ => {
+                self.exit_block_nav();
+                cx.notify();
+                return false;
             }
-            self.preview_selection_head = Some(line_index);
-        } else {
-            self.preview_selection_anchor = Some(line_index);
-            self.preview_selection_head = Some(line_index);
         }
-        self.preview_active_line = Some(line_index);
-        self.preview_selecting = true;
         cx.notify();
-        cx.stop_propagation();
+        true
     }
 
-    fn on_preview_line_mouse_move_at(
-        &mut self,
-        line_index: usize,
-        event: &MouseMoveEvent,
-        cx: &mut Context<Self>,
-    ) {
-        if !self.preview_selecting || !event.dragging() {
-            return;
-        }
-        self.preview_selection_head = Some(line_index);
-        self.preview_active_line = Some(line_index);
-        cx.notify();
+    fn exit_block_nav(&mut self) {
+        self.block_nav_active = false;
+        self.block_selection.clear();
+        self.block_nav_status = None;
     }
 
-    fn on_preview_mouse_up(
-        &mut self,
-        _event: &MouseUpEvent,
-        _window: &mut Window,
-        _cx: &mut Context<Self>,
-    ) {
-        self.preview_selecting = false;
+    fn focus_block(&mut self, index: usize) {
+        self.selected_block = Some(index);
+        self.follow_output = false;
+        self.scroll_handle.scroll_to_item(index);
+        self.clear_output_selection();
     }
 
-    fn on_toggle_file_preview_mode(
-        &mut self,
-        _event: &MouseDownEvent,
-        _window: &mut Window,
-        cx: &mut Context<Self>,
-    ) {
-        self.toggle_file_preview_mode(cx);
-        cx.stop_propagation();
+    /// Moves the focus to `index`. With `extend`, every block passed over
+    /// joins the selection.
+    fn move_block_focus(&mut self, index: usize, extend: bool) {
+        if extend && let Some(from) = self.selected_block {
+            self.block_selection
+                .extend(from.min(index)..=from.max(index));
+        }
+        self.focus_block(index);
     }
 
-    fn on_preview_terminal_resize_mouse_down(
-        &mut self,
-        event: &MouseDownEvent,
-        _window: &mut Window,
-        cx: &mut Context<Self>,
-    ) {
-        let y: f32 = event.position.y.into();
-        self.preview_terminal_resize_dragging = true;
-        self.preview_terminal_resize_start_y = Some(y);
-        self.preview_terminal_resize_start_height = self.preview_terminal_height;
-        cx.notify();
-        cx.stop_propagation();
+    fn jump_to_error_block(&mut self, from: usize, backward: bool) {
+        let found = if backward {
+            (0..from).rev().find(|index| self.blocks[*index].has_error)
+        } else {
+            (from + 1..self.blocks.len()).find(|index| self.blocks[*index].has_error)
+        };
+        match found {
+            Some(index) => self.focus_block(index),
+            None => {
+                self.block_nav_status = Some(if backward {
+                    "No earlier failed block".to_string()
+                } else {
+                    "No later failed block".to_string()
+                })
+            }
+        }
     }
 
-    fn on_preview_terminal_resize_mouse_move(
-        &mut self,
-        event: &MouseMoveEvent,
-        _window: &mut Window,
-        cx: &mut Context<Self>,
-    ) {
-        if !self.preview_terminal_resize_dragging || !event.dragging() {
-            return;
+    /// The closest earlier block that ran the same command.
+    fn previous_run(&self, index: usize) -> Option<usize> {
+        let command = self.blocks.get(index)?.command.trim();
+        if command.is_empty() {
+            return None;
         }
+        (0..index)
+            .rev()
+            .find(|earlier| self.blocks[*earlier].command.trim() == command)
+    }
 
-        let Some(start_y) = self.preview_terminal_resize_start_y else {
+    /// Diffs the two selected blocks, or the focused block against the
+    /// previous run of its command.
+    fn diff_blocks_from_nav(&mut self, focused: usize, cx: &mut Context<Self>) {
+        let pair = match self.block_selection.len() {
+            2 => {
+                let mut selected = self.block_selection.iter().copied();
+                selected.next().zip(selected.next())
+            }
+            0 => self.previous_run(focused).map(|earlier| (earlier, focused)),
+            _ => None,
+        };
+        match pair {
+            Some((left, right)) => {
+                self.exit_block_nav();
+                self.open_block_diff(left, right, cx);
+            }
+            None if self.block_selection.is_empty() => {
+                self.block_nav_status = Some("No earlier run of this command".to_string());
+            }
+            None => self.block_nav_status = Some("Select two blocks to diff".to_string()),
+        }
+    }
+
+    /// Title and masked output of a block, for one side of a diff.
+    fn diff_side(&self, index: usize) -> Option<(String, Vec<String>)> {
+        let block = self.blocks.get(index)?;
+        let command = self.secret_scanner().mask(&block.command).into_owned();
+        let title = if command.is_empty() {
+            format!("Block {}", index + 1)
+        } else {
+            format!("Block {}: {command}", index + 1)
+        };
+        Some((title, self.masked_lines(&block.output_lines)))
+    }
+
+    /// The scanner for the current settings. It changes when they are saved.
+    fn secret_scanner(&self) -> Arc<SecretScanner> {
+        TerminalSettings::current_secret_scanner()
+    }
+
+    fn masked_lines(&self, lines: &[String]) -> Vec<String> {
+        self.secret_scanner().mask_lines(lines)
+    }
+
+    fn open_block_diff(&mut self, left: usize, right: usize, cx: &mut Context<Self>) {
+        let (Some(left), Some(right)) = (self.diff_side(left), self.diff_side(right)) else {
             return;
         };
+        self.show_block_diff(left, right, cx);
+    }
 
-        let current_y: f32 = event.position.y.into();
-        let delta = start_y - current_y;
-        let next_height =
-            Self::clamp_preview_terminal_height(self.preview_terminal_resize_start_height + delta);
-        if (self.preview_terminal_height - next_height).abs() > f32::EPSILON {
-            self.preview_terminal_height = next_height;
-            cx.notify();
+    fn show_block_diff(
+        &mut self,
+        left: (String, Vec<String>),
+        right: (String, Vec<String>),
+        cx: &mut Context<Self>,
+    ) {
+        if self.file_preview.is_some() {
+            self.close_file_preview(cx);
         }
+        self.block_diff = Some(BlockDiffState::new(left, right));
+        self.preview_code_scroll_handle
+            .0
+            .borrow()
+            .base_handle
+            .set_offset(point(px(0.0), px(0.0)));
+        cx.notify();
     }
 
-    fn on_preview_terminal_resize_mouse_up(
+    fn update_block_diff(
         &mut self,
-        _event: &MouseUpEvent,
-        _window: &mut Window,
         cx: &mut Context<Self>,
+        update: impl FnOnce(&mut BlockDiffState),
     ) {
-        if self.preview_terminal_resize_dragging {
-            self.preview_terminal_resize_dragging = false;
-            self.preview_terminal_resize_start_y = None;
+        if let Some(diff) = self.block_diff.as_mut() {
+            update(diff);
+            diff.recompute();
             cx.notify();
         }
     }
 
-    fn copy_selected_preview(&mut self, cx: &mut Context<Self>) -> bool {
-        let Some(text) = self.selected_preview_text() else {
-            return false;
-        };
-        cx.write_to_clipboard(ClipboardItem::new_string(text));
-        true
-    }
-
-    fn select_all_preview(&mut self) -> bool {
-        let count = self.preview_line_count();
-        if count == 0 {
-            return false;
+    fn toggle_recording(&mut self, cx: &mut Context<Self>) {
+        if let Some(recording) = self.recording.take() {
+            self.recording_status = Some(match recording.finish(&self.secret_scanner()) {
+                Ok(path) => (format!("Saved recording to {}", path.display()), Some(path)),
+                Err(err) => (format!("Failed to save recording: {err:#}"), None),
+            });
+            cx.notify();
+            return;
         }
-        self.preview_selection_anchor = Some(0);
-        self.preview_selection_head = Some(count.saturating_sub(1));
-        self.preview_active_line = Some(count.saturating_sub(1));
-        true
-    }
-
-    fn build_preview_search_match(
-        line_number: usize,
-        query: &str,
-        line_count: usize,
-    ) -> Option<(usize, String)> {
-        let trimmed = query.trim();
-        if line_number == 0 || trimmed.is_empty() || line_count == 0 {
-            return None;
+        let started = Self::recording_path().and_then(|path| {
+            storage::ensure_parent_dir(&path)?;
+            let mut header = CastHeader::new(TERMINAL_COLS, TERMINAL_ROWS);
+            header.timestamp = Some(chrono::Utc::now().timestamp());
+            header.title = Some(self.current_path.clone());
+            let recorder = CastRecorder::create(&path, &header)?;
+            Ok(Recording {
+                recorder,
+                path,
+                redactor: StreamRedactor::default(),
+                held_since: None,
+            })
+        });
+        match started {
+            Ok(recording) => {
+                self.recording = Some(recording);
+                self.recording_status = None;
+            }
+            Err(err) => {
+                self.recording_status = Some((format!("Failed to start recording: {err:#}"), None));
+            }
         }
+        cx.notify();
+    }
 
-        Some((
-            line_number.saturating_sub(1).min(line_count - 1),
-            trimmed.to_string(),
-        ))
+    fn recording_path() -> anyhow::Result<PathBuf> {
+        let name = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+        Ok(storage::app_root()?
+            .join("recordings")
+            .join(format!("{name}.cast")))
     }
 
-    fn apply_preview_search_match_segments(
-        segments: &[HighlightSegment],
-        query: &str,
-    ) -> Vec<PreviewSearchMatchSegment> {
-        let trimmed = query.trim();
-        if trimmed.is_empty() {
-            return segments
-                .iter()
-                .map(|segment| PreviewSearchMatchSegment {
-                    text: segment.text.clone(),
-                    color: segment.color,
-                    bold: segment.bold,
-                    matched: false,
-                })
-                .collect();
+    /// Adds an event to the recording, if one is running. Secrets are
+    /// redacted. A failed write ends the recording.
+    fn record(&mut self, kind: CastEventKind, data: &str) {
+        let scanner = self.secret_scanner();
+        let Some(recording) = self.recording.as_mut() else {
+            return;
+        };
+        if let Err(err) = recording.record(&scanner, kind, data) {
+            self.recording = None;
+            self.recording_status = Some((format!("Recording stopped: {err:#}"), None));
         }
+    }
 
-        let mut rendered = Vec::new();
-        let query_lower = trimmed.to_ascii_lowercase();
+    /// Plays from the current position until paused, restarted or done.
+    fn restart_replay(&mut self, cx: &mut Context<Self>) {
+        let Some(replay) = self.replay.as_mut() else {
+            return;
+        };
+        self.replay_generation += 1;
+        replay.generation = self.replay_generation;
+        let generation = replay.generation;
+        cx.notify();
 
-        for segment in segments {
-            if segment.text.is_empty() {
-                continue;
+        cx.spawn(move |view: WeakEntity<Self>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                loop {
+                    let wait = view
+                        .update(&mut cx, |view, cx| view.step_replay(generation, cx))
+                        .ok()
+                        .flatten();
+                    let Some(wait) = wait else {
+                        break;
+                    };
+                    cx.background_executor().timer(wait).await;
+                }
             }
+        })
+        .detach();
+    }
 
-            let text = segment.text.as_str();
-            let text_lower = text.to_ascii_lowercase();
-            let mut search_from = 0usize;
-
-            while let Some(found_at) = text_lower[search_from..].find(&query_lower) {
-                let match_start = search_from + found_at;
-                let match_end = match_start + trimmed.len();
-
-                if match_start > search_from {
-                    rendered.push(PreviewSearchMatchSegment {
-                        text: text[search_from..match_start].to_string(),
-                        color: segment.color,
-                        bold: segment.bold,
-                        matched: false,
-                    });
+    /// Plays the events that are due and returns the wait until the next
+    /// one, or `None` once playback paused or ended.
+    fn step_replay(&mut self, generation: u64, cx: &mut Context<Self>) -> Option<Duration> {
+        let replay = self
+            .replay
+            .as_mut()
+            .filter(|replay| replay.generation == generation && replay.playing)?;
+        let cast = Arc::clone(&replay.cast);
+        let from = replay.next_event;
+        let due = cast.events[from..]
+            .iter()
+            .take_while(|event| event.time <= replay.position)
+            .count();
+        replay.next_event += due;
+        let wait = match cast.events.get(replay.next_event) {
+            Some(next) => {
+                let mut gap = (next.time - replay.position).max(0.0);
+                if let Some(limit) = cast.header.idle_time_limit {
+                    gap = gap.min(limit);
                 }
-
-                rendered.push(PreviewSearchMatchSegment {
-                    text: text[match_start..match_end].to_string(),
-                    color: segment.color,
-                    bold: segment.bold,
-                    matched: true,
-                });
-                search_from = match_end;
+                replay.position = next.time;
+                Some(Duration::from_secs_f64(gap / replay.speed))
+            }
+            None => {
+                replay.position = cast.duration();
+                replay.playing = false;
+                None
             }
+        };
 
-            if search_from < text.len() {
-                rendered.push(PreviewSearchMatchSegment {
-                    text: text[search_from..].to_string(),
-                    color: segment.color,
-                    bold: segment.bold,
-                    matched: false,
-                });
+        for event in &cast.events[from..from + due] {
+            match event.kind {
+                CastEventKind::Output => self.append_output(&event.data, cx),
+                CastEventKind::Marker => self.start_command(event.data.clone(), None, cx),
+                // What was typed shows up in the output as its echo.
+                CastEventKind::Input => {}
             }
         }
+        cx.notify();
+        wait
+    }
 
-        if rendered.is_empty() {
-            segments
-                .iter()
-                .map(|segment| PreviewSearchMatchSegment {
-                    text: segment.text.clone(),
-                    color: segment.color,
-                    bold: segment.bold,
-                    matched: false,
-                })
-                .collect()
-        } else {
-            rendered
+    fn toggle_replay_playing(&mut self, cx: &mut Context<Self>) {
+        let Some(replay) = self.replay.as_mut() else {
+            return;
+        };
+        if replay.playing {
+            replay.playing = false;
+            cx.notify();
+            return;
+        }
+        if replay.next_event >= replay.cast.events.len() {
+            self.rewind_replay();
         }
+        if let Some(replay) = self.replay.as_mut() {
+            replay.playing = true;
+        }
+        self.restart_replay(cx);
     }
 
-    fn move_preview_selection_by(&mut self, delta: isize, extend: bool) -> bool {
-        let count = self.preview_line_count();
-        if count == 0 {
-            return false;
+    fn set_replay_speed(&mut self, speed: f64, cx: &mut Context<Self>) {
+        if let Some(replay) = self.replay.as_mut() {
+            replay.speed = speed;
+            cx.notify();
         }
+    }
 
-        let (active_line, selection_anchor, selection_head) = Self::move_linear_selection(
-            count,
-            self.preview_active_line,
-            self.preview_selection_anchor,
-            self.preview_selection_head,
-            delta,
-            extend,
-        );
-        self.focus_preview();
-        self.preview_active_line = active_line;
-        self.preview_selection_anchor = selection_anchor;
-        self.preview_selection_head = selection_head;
-        true
+    /// Clears the played output and goes back to the start.
+    fn rewind_replay(&mut self) {
+        let Some(replay) = self.replay.as_mut() else {
+            return;
+        };
+        replay.next_event = 0;
+        replay.position = 0.0;
+        self.blocks.clear();
+        self.total_output_lines = 0;
+        self.ansi_parser = AnsiParser::default();
+        self.last_line_incomplete = false;
+        self.running_command = None;
+        self.selected_block = None;
+        self.block_selection.clear();
+        self.clear_output_selection();
+        self.mouse_protocol = MouseProtocol::default();
     }
 
-    fn move_preview_selection_to_edge(&mut self, to_end: bool, extend: bool) -> bool {
-        let count = self.preview_line_count();
-        if count == 0 {
-            return false;
+    fn restart_replay_from_start(&mut self, cx: &mut Context<Self>) {
+        self.rewind_replay();
+        if let Some(replay) = self.replay.as_mut() {
+            replay.playing = true;
         }
+        self.restart_replay(cx);
+    }
 
-        let target = if to_end { count.saturating_sub(1) } else { 0 };
-        let current = self
-            .preview_active_line
-            .unwrap_or(target)
-            .min(count.saturating_sub(1));
-        self.focus_preview();
-        self.preview_active_line = Some(target);
-        if extend {
-            if self.preview_selection_anchor.is_none() {
-                self.preview_selection_anchor = Some(current);
-            }
-            self.preview_selection_head = Some(target);
-        } else {
-            self.preview_selection_anchor = Some(target);
-            self.preview_selection_head = Some(target);
+    /// Starts watching the command of block `index`, or stops when it is the
+    /// one already watched.
+    fn toggle_watch(&mut self, index: usize, cx: &mut Context<Self>) {
+        if self
+            .watch
+            .as_ref()
+            .is_some_and(|watch| watch.block_index == Some(index))
+        {
+            self.watch = None;
+            cx.notify();
+            return;
         }
-        true
-    }
-
-    fn normalize_linear_selection(
-        anchor: Option<usize>,
-        head: Option<usize>,
-    ) -> Option<(usize, usize)> {
-        let anchor = anchor?;
-        let head = head?;
-        if anchor == head {
-            return None;
+        let Some(block) = self.blocks.get(index) else {
+            return;
+        };
+        if block.command.trim().is_empty() {
+            return;
         }
-        Some((anchor.min(head), anchor.max(head)))
+        let cwd = block
+            .context
+            .as_ref()
+            .map(|context| expand_tilde(&context.cwd))
+            .unwrap_or_else(|| expand_tilde(&self.current_path));
+        self.watch = Some(WatchState {
+            command: block.command.clone(),
+            cwd,
+            trigger: WatchTrigger::FileChanges,
+            output: WatchOutput::Replace,
+            block_index: Some(index),
+            runs: Vec::new(),
+            snapshot: None,
+            due: false,
+            generation: 0,
+        });
+        self.restart_watch_polling(cx);
     }
 
-    fn selected_text_from_lines(
-        lines: &[String],
-        anchor: Option<usize>,
-        head: Option<usize>,
-    ) -> Option<String> {
-        let (start, end) = Self::normalize_linear_selection(anchor, head)?;
-        if start >= lines.len() {
-            return None;
-        }
-        let end = end.min(lines.len().saturating_sub(1));
-        if start > end {
-            return None;
+    fn set_watch_trigger(&mut self, trigger: WatchTrigger, cx: &mut Context<Self>) {
+        let Some(watch) = self.watch.as_mut() else {
+            return;
+        };
+        if watch.trigger != trigger {
+            watch.trigger = trigger;
+            watch.snapshot = None;
+            watch.due = false;
+            self.restart_watch_polling(cx);
         }
-        Some(lines[start..=end].join("\n"))
     }
 
-    fn move_linear_selection(
-        count: usize,
-        active_line: Option<usize>,
-        selection_anchor: Option<usize>,
-        selection_head: Option<usize>,
-        delta: isize,
-        extend: bool,
-    ) -> (Option<usize>, Option<usize>, Option<usize>) {
-        if count == 0 {
-            return (active_line, selection_anchor, selection_head);
+    fn set_watch_output(&mut self, output: WatchOutput, cx: &mut Context<Self>) {
+        if let Some(watch) = self.watch.as_mut() {
+            watch.output = output;
+            cx.notify();
         }
+    }
 
-        let current = active_line.unwrap_or(0).min(count.saturating_sub(1));
-        let next = if delta < 0 {
-            current.saturating_sub(delta.unsigned_abs())
-        } else {
-            (current + delta as usize).min(count.saturating_sub(1))
+    /// Polls for the current trigger until the watch stops or changes.
+    fn restart_watch_polling(&mut self, cx: &mut Context<Self>) {
+        let Some(watch) = self.watch.as_mut() else {
+            return;
         };
+        self.watch_generation += 1;
+        watch.generation = self.watch_generation;
+        let generation = watch.generation;
+        let trigger = watch.trigger;
+        let cwd = watch.cwd.clone();
+        cx.notify();
 
-        if extend {
-            let anchor = selection_anchor.or(Some(current));
-            (Some(next), anchor, Some(next))
-        } else {
-            (Some(next), Some(next), Some(next))
-        }
+        cx.spawn(move |view: WeakEntity<Self>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                loop {
+                    cx.background_executor().timer(trigger.tick()).await;
+                    let snapshot = if trigger == WatchTrigger::FileChanges {
+                        let cwd = cwd.clone();
+                        Some(
+                            cx.background_executor()
+                                .spawn(async move {
+                                    FileSnapshot::scan(&cwd, &OrbitshellRules::load())
+                                })
+                                .await,
+                        )
+                    } else {
+                        None
+                    };
+                    let watching = view
+                        .update(&mut cx, |view, cx| {
+                            view.on_watch_tick(generation, snapshot, cx)
+                        })
+                        .unwrap_or(false);
+                    if !watching {
+                        break;
+                    }
+                }
+            }
+        })
+        .detach();
     }
 
-    fn handle_preview_key(&mut self, event: &KeyDownEvent, cx: &mut Context<Self>) -> bool {
-        if !self.preview_focus || self.file_preview.is_none() {
+    /// Returns whether the watch that polled is still current.
+    fn on_watch_tick(
+        &mut self,
+        generation: u64,
+        snapshot: Option<FileSnapshot>,
+        cx: &mut Context<Self>,
+    ) -> bool {
+        let running = self.running_command.is_some();
+        let Some(watch) = self
+            .watch
+            .as_mut()
+            .filter(|watch| watch.generation == generation)
+        else {
             return false;
-        }
-
-        let ctrl = event.keystroke.modifiers.control;
-        let shift = event.keystroke.modifiers.shift;
-        match event.keystroke.key.as_str() {
-            "c" if ctrl => {
-                self.copy_selected_preview(cx);
-                true
-            }
-            "a" if ctrl => {
-                if self.select_all_preview() {
-                    cx.notify();
-                }
-                true
-            }
-            "up" | "arrowup" => {
-                if self.move_preview_selection_by(-1, shift) {
-                    cx.notify();
-                }
-                true
-            }
-            "down" | "arrowdown" => {
-                if self.move_preview_selection_by(1, shift) {
-                    cx.notify();
-                }
-                true
-            }
-            "left" | "arrowleft" => {
-                if self.move_preview_selection_by(-1, shift) {
-                    cx.notify();
-                }
-                true
-            }
-            "right" | "arrowright" => {
-                if self.move_preview_selection_by(1, shift) {
-                    cx.notify();
-                }
-                true
-            }
-            "home" => {
-                if self.move_preview_selection_to_edge(false, shift) {
-                    cx.notify();
-                }
-                true
-            }
-            "end" => {
-                if self.move_preview_selection_to_edge(true, shift) {
-                    cx.notify();
+        };
+        match snapshot {
+            // The first scan is the baseline.
+            Some(snapshot) => {
+                if watch
+                    .snapshot
+                    .as_ref()
+                    .is_some_and(|seen| *seen != snapshot)
+                {
+                    watch.due = true;
                 }
-                true
+                watch.snapshot = Some(snapshot);
             }
-            _ => false,
+            // A tick that lands on a run still going is skipped, as with
+            // `watch -n`.
+            None if !running => watch.due = true,
+            None => {}
         }
+        self.run_watch_if_due(cx);
+        true
     }
 
-    fn format_path(path: &Path) -> String {
-        let home = std::env::var("USERPROFILE")
-            .or_else(|_| std::env::var("HOME"))
-            .ok()
-            .map(PathBuf::from);
+    /// Whether the shell waits at its prompt with nothing typed, so a command
+    /// can be sent without getting in the user's way.
+    fn is_at_empty_prompt(&self) -> bool {
+        self.pty.is_some()
+            && self.running_command.is_none()
+            && self.input_visible
+            && self.input.trim().is_empty()
+            && self.pending_guard.is_none()
+    }
 
-        if let Some(home_path) = home {
-            if let Ok(stripped) = path.strip_prefix(&home_path) {
-                let stripped = stripped.to_string_lossy();
-                if stripped.is_empty() {
-                    return "~".to_string();
-                }
-                let sep = if cfg!(windows) { "\\" } else { "/" };
-                return format!("~{}{}", sep, stripped);
+    /// Re-runs the watched command once the shell is back at an empty prompt.
+    fn run_watch_if_due(&mut self, cx: &mut Context<Self>) {
+        let idle = self.is_at_empty_prompt();
+        let Some(watch) = self.watch.as_mut().filter(|watch| watch.due && idle) else {
+            return;
+        };
+        watch.due = false;
+        let command = watch.command.clone();
+        let previous = watch.block_index.filter(|index| {
+            self.blocks
+                .get(*index)
+                .is_some_and(|block| block.command == command)
+        });
+
+        if let Some(block) = previous.map(|index| &self.blocks[index]) {
+            let number = watch.runs.last().map_or(1, |run| run.number + 1);
+            watch.runs.push(WatchRun {
+                number,
+                output_lines: block.output_lines.clone(),
+                exit_code: block.exit_code,
+            });
+            if watch.runs.len() > MAX_WATCH_RUNS {
+                watch.runs.remove(0);
             }
         }
+        let reuse = previous.filter(|index| {
+            watch.output == WatchOutput::Replace && *index + 1 == self.blocks.len()
+        });
+        self.start_command(command, reuse, cx);
+        if let Some(watch) = self.watch.as_mut() {
+            watch.block_index = self.blocks.len().checked_sub(1);
+        }
+        cx.notify();
+    }
 
-        let mut out = path.to_string_lossy().to_string();
-        if !cfg!(windows) {
-            out = out.replace('\\', "/");
+    /// Diffs an earlier run of the watched command against the latest one.
+    fn open_watch_run_diff(&mut self, run: usize, cx: &mut Context<Self>) {
+        let Some(watch) = self.watch.as_ref() else {
+            return;
+        };
+        let Some(run) = watch.runs.iter().find(|earlier| earlier.number == run) else {
+            return;
+        };
+        let left = (
+            format!("Run {}", run.number),
+            self.masked_lines(&run.output_lines),
+        );
+        let latest = watch.runs.last().map_or(1, |run| run.number + 1);
+        let Some((_, lines)) = watch.block_index.and_then(|index| self.diff_side(index)) else {
+            return;
+        };
+        self.show_block_diff(left, (format!("Run {latest}"), lines), cx);
+    }
+
+    /// Copies from the selected blocks, or the focused one when none are
+    /// selected. Secrets are redacted.
+    fn copy_blocks(&mut self, what: BlockCopy, cx: &mut Context<Self>) {
+        let indices: Vec<usize> = if self.block_selection.is_empty() {
+            self.selected_block.into_iter().collect()
+        } else {
+            self.block_selection.iter().copied().collect()
+        };
+        let parts: Vec<String> = indices
+            .iter()
+            .filter_map(|index| self.blocks.get(*index))
+            .map(|block| match what {
+                BlockCopy::Command => block.command.clone(),
+                BlockCopy::Output => block.output_lines.join("\n"),
+                BlockCopy::Both => self.block_to_text(block),
+            })
+            .filter(|part| !part.is_empty())
+            .collect();
+        if parts.is_empty() {
+            self.block_nav_status = Some(format!("No {} to copy", what.label()));
+            return;
         }
-        out
+        let separator = if what == BlockCopy::Command {
+            "\n"
+        } else {
+            "\n\n"
+        };
+        let text = self
+            .secret_scanner()
+            .redact(&parts.join(separator))
+            .into_owned();
+        cx.write_to_clipboard(ClipboardItem::new_string(text));
+        self.block_nav_status = Some(match parts.len() {
+            1 => format!("Copied {}", what.label()),
+            count => format!("Copied {} of {count} blocks", what.label()),
+        });
     }
 
-    pub fn new_welcome(cx: &mut Context<Self>, recent: Vec<RecentEntry>) -> Self {
-        let mut view = Self::new_base(cx);
-        let welcome = cx.new(|cx| WelcomeView::with_recent(cx, recent));
-        cx.subscribe(&welcome, |_, _welcome, event: &OpenRepositoryEvent, cx| {
-            cx.emit(TabViewEvent::OpenRepository(event.path.clone()));
-        })
-        .detach();
-        cx.subscribe(&welcome, |_, _welcome, event: &CreateProjectEvent, cx| {
-            cx.emit(TabViewEvent::CreateProject {
-                prompt: event.prompt.clone(),
-                parent: event.parent.clone(),
-            });
-        })
-        .detach();
-        cx.subscribe(&welcome, |_, _welcome, event: &CloneRepositoryEvent, cx| {
-            cx.emit(TabViewEvent::CloneRepository {
-                url: event.url.clone(),
-                parent: event.parent.clone(),
-            });
-        })
-        .detach();
-        view.mode = TabViewMode::Welcome(welcome);
-        view
+    /// A plain-language description of a block, read out in the block
+    /// navigation bar.
+    fn block_summary(block: &Block, index: usize, total: usize, running: bool) -> String {
+        let mut parts = vec![if block.command.is_empty() {
+            format!("Block {} of {total}", index + 1)
+        } else {
+            format!("Block {} of {total}: {}", index + 1, block.command)
+        }];
+        let took = block
+            .duration
+            .map(|duration| format!(" after {}", format_command_duration(duration)))
+            .unwrap_or_default();
+        parts.push(match block.exit_code {
+            _ if running => "Running".to_string(),
+            Some(0) => format!("Succeeded{took}"),
+            Some(code) => format!("Failed with exit code {code}{took}"),
+            None if block.has_error => "Failed".to_string(),
+            None => "Finished".to_string(),
+        });
+        parts.push(match block.output_lines.len() {
+            0 => "No output".to_string(),
+            1 => "1 line of output".to_string(),
+            count => format!("{count} lines of output"),
+        });
+        if block.collapsed {
+            parts.push("Collapsed".to_string());
+        }
+        parts.join(". ") + "."
     }
 
-    pub fn new_settings(cx: &mut Context<Self>) -> Self {
-        let mut view = Self::new_base(cx);
-        let settings = cx.new(|cx| SettingsView::new(cx));
-        view.mode = TabViewMode::Settings(settings);
-        view
+    /// The block whose output fills the top of the panel after its header
+    /// scrolled away, as of the last layout.
+    fn sticky_block_index(&self) -> Option<usize> {
+        let index = self.scroll_handle.top_item();
+        let block = self.blocks.get(index)?;
+        if block.collapsed || block.command.is_empty() {
+            return None;
+        }
+        let bounds = self.scroll_handle.bounds_for_item(index)?;
+        let view_top = self.scroll_handle.bounds().top() - self.scroll_handle.offset().y;
+        let scrolled_past: f32 = (view_top - bounds.top()).into();
+        let still_visible: f32 = (bounds.bottom() - view_top).into();
+        (scrolled_past > 0.0 && still_visible > STICKY_HEADER_MIN_VISIBLE).then_some(index)
     }
 
-    pub fn new_agent(cx: &mut Context<Self>) -> Self {
-        let mut view = Self::new_base(cx);
-        let agent = cx.new(|cx| AgentView::new(cx));
-        view.mode = TabViewMode::Agent(agent);
-        view
+    fn on_select_block(&mut self, index: usize, cx: &mut Context<Self>) {
+        self.selected_block = Some(index);
+        self.clear_output_selection();
+        cx.notify();
     }
 
-    fn new_base(cx: &mut Context<Self>) -> Self {
-        let (history, history_file) = Self::load_initial_history();
-        let last_path_var = std::env::var("PATH").unwrap_or_default();
-        let base_terminal_cwd = launch::default_base_terminal_cwd();
-        let agent_rows = load_effective_agent_rows(ConflictPolicy::LocalWins).unwrap_or_default();
-        let agent_selected_key = agent_rows.first().map(|row| row.agent_key.clone());
-        let model_options_loading = agent_selected_key.is_some();
-        let runtime_preferences =
-            Arc::new(Mutex::new(RuntimePreferences::load().unwrap_or_default()));
-        let registry_manifests = storage::app_root()
+    /// Char offset and display column under `x` in a wrapped output row that
+    /// starts at char `start` and column `column`.
+    fn output_position_at(
+        &self,
+        row: &str,
+        start: usize,
+        column: usize,
+        x: Pixels,
+        window: &Window,
+    ) -> (usize, usize) {
+        let offset = (f32::from(x) - self.output_text_left.get()).max(0.0);
+        let columns = (offset / Self::output_cell_width(window)).floor() as usize;
+        (start + offset_at_column(row, columns), column + columns)
+    }
+
+    fn output_cell_width(window: &Window) -> f32 {
+        let text_system = window.text_system();
+        let font_id = text_system.resolve_font(&font("Cascadia Code"));
+        text_system
+            .em_advance(font_id, px(OUTPUT_FONT_SIZE))
+            .map(f32::from)
             .ok()
-            .and_then(|app_root| load_cached_registry(&app_root).ok().flatten())
-            .map(|cached| cached.manifests)
-            .unwrap_or_default();
-        Self {
-            blocks: Vec::new(),
-            pty: None,
-            focus_handle: cx.focus_handle(),
-            input: String::new(),
-            cursor: 0,
-            history,
-            history_file,
-            history_open: false,
-            history_index: 0,
-            history_items: Vec::new(),
-            suggestions: Vec::new(),
-            suggest_index: 0,
-            selection: None,
-            selection_anchor: None,
-            path_commands: Self::load_path_commands(),
-            last_path_scan: Instant::now(),
-            last_path_var,
-            current_path: Self::format_path(&base_terminal_cwd),
-            git_status: get_git_status(&base_terminal_cwd),
-            auto_focus: true,
-            pending_echo: None,
-            scroll_handle: ScrollHandle::new(),
-            preview_scroll_handle: ScrollHandle::new(),
-            preview_code_scroll_handle: UniformListScrollHandle::new(),
-            input_visible: true,
-            overlay: None,
-            needs_git_refresh: false,
-            mode: TabViewMode::Terminal,
-            last_line_incomplete: false,
-            total_output_lines: 0,
-            follow_output: true,
-            last_scroll_to_bottom_at: Instant::now(),
-            input_mode: InputMode::Terminal,
-            agent_rows,
-            agent_selected_key,
-            agent_client: None,
-            agent_client_key: None,
-            agent_busy: false,
-            agent_needs_auth: false,
-            runtime_preferences,
-            registry_manifests,
-            discovered_models: Vec::new(),
-            model_options_loading,
-            selected_model_override: None,
-            selected_block: None,
-            output_selection_anchor: None,
-            output_selection_head: None,
-            output_selecting: false,
-            file_preview: None,
-            preview_search_match: None,
-            preview_focus: false,
-            preview_selection_anchor: None,
-            preview_selection_head: None,
-            preview_selecting: false,
-            preview_active_line: None,
-            file_preview_mode: FilePreviewMode::Code,
-            preview_terminal_height: DEFAULT_PREVIEW_TERMINAL_HEIGHT,
-            preview_terminal_resize_dragging: false,
-            preview_terminal_resize_start_y: None,
-            preview_terminal_resize_start_height: DEFAULT_PREVIEW_TERMINAL_HEIGHT,
-        }
+            .filter(|width| *width > 0.0)
+            .unwrap_or(OUTPUT_CHAR_WIDTH)
     }
 
-    fn build_file_preview_state(path: &Path, root: Option<&Path>) -> FilePreviewState {
-        let relative_label = root
-            .and_then(|root| path.strip_prefix(root).ok())
-            .map(Self::format_path)
-            .unwrap_or_else(|| Self::format_path(path));
-        let language = Self::detect_preview_language(path);
-        let extension = path
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .map(|ext| ext.to_ascii_lowercase());
-
-        let image_extensions = [
-            "png", "jpg", "jpeg", "gif", "webp", "bmp", "ico", "svg", "avif", "tif", "tiff",
-        ];
-
-        let kind = if extension
-            .as_deref()
-            .map(|ext| image_extensions.contains(&ext))
-            .unwrap_or(false)
-        {
-            FilePreviewKind::Image
+    fn on_output_line_mouse_down_at(
+        &mut self,
+        block_index: usize,
+        line_index: usize,
+        (offset, column): (usize, usize),
+        event: &MouseDownEvent,
+        cx: &mut Context<Self>,
+    ) {
+        let position = (block_index, line_index);
+        self.selected_block = Some(block_index);
+        self.output_selecting = false;
+        if event.click_count >= 3 {
+            self.output_selection_kind = OutputSelectionKind::WholeLines;
+            self.output_selection_anchor = Some(position);
+            self.output_selection_head = Some(position);
+        } else if event.click_count == 2 {
+            let Some(line) = self
+                .blocks
+                .get(block_index)
+                .and_then(|block| block.output_lines.get(line_index))
+            else {
+                return;
+            };
+            let word = word_at(line, offset);
+            self.output_selection_kind = OutputSelectionKind::Word {
+                start: word.start,
+                end: word.end,
+            };
+            self.output_selection_anchor = Some(position);
+            self.output_selection_head = Some(position);
+        } else if event.modifiers.alt {
+            self.output_selection_kind = OutputSelectionKind::Rectangle {
+                anchor_column: column,
+                head_column: column,
+            };
+            self.output_selection_anchor = Some(position);
+            self.output_selection_head = Some(position);
+            self.output_selecting = true;
         } else {
-            match std::fs::read(path) {
-                Ok(bytes) if bytes.len() > MAX_TEXT_PREVIEW_BYTES => FilePreviewKind::Unsupported {
-                    message: format!(
-                        "File too large to preview inline ({} KB limit).",
-                        MAX_TEXT_PREVIEW_BYTES / 1024
-                    ),
-                },
-                Ok(bytes) if bytes.contains(&0) => FilePreviewKind::Unsupported {
-                    message: "Binary file preview is not supported yet.".into(),
-                },
-                Ok(bytes) => match String::from_utf8(bytes) {
-                    Ok(contents) => {
-                        let lines =
-                            Arc::new(contents.lines().map(|line| line.to_string()).collect());
-                        FilePreviewKind::Text { contents, lines }
-                    }
-                    Err(_) => FilePreviewKind::Unsupported {
-                        message: "This file is not valid UTF-8 text.".into(),
-                    },
-                },
-                Err(err) => FilePreviewKind::Unsupported {
-                    message: format!("Failed to read file: {err}"),
-                },
+            if event.modifiers.shift && self.output_selection_anchor.is_some() {
+                self.output_selection_head = Some(position);
+            } else {
+                self.output_selection_anchor = Some(position);
+                self.output_selection_head = Some(position);
             }
-        };
-
-        FilePreviewState {
-            path: path.to_path_buf(),
-            relative_label,
-            language,
-            kind,
+            self.output_selection_kind = OutputSelectionKind::Lines;
+            self.output_selecting = true;
         }
+        cx.notify();
+        cx.stop_propagation();
     }
 
-    fn detect_preview_language(path: &Path) -> PreviewLanguage {
-        let file_name = path
-            .file_name()
-            .and_then(|name| name.to_str())
-            .map(|name| name.to_ascii_lowercase())
-            .unwrap_or_default();
-
-        match file_name.as_str() {
-            "cargo.toml" | "agents.json" | "repo.config.json" | "registry-sample.json" => {
-                return if file_name.ends_with(".toml") {
-                    PreviewLanguage::Toml
-                } else {
-                    PreviewLanguage::Json
-                };
-            }
-            "dockerfile" => {
-                return PreviewLanguage::Dockerfile;
-            }
-            ".gitignore" | ".dockerignore" | ".eslintignore" | ".prettierignore" => {
-                return PreviewLanguage::PlainText;
-            }
-            _ => {}
+    fn on_output_line_mouse_move_at(
+        &mut self,
+        block_index: usize,
+        line_index: usize,
+        column: usize,
+        event: &MouseMoveEvent,
+        cx: &mut Context<Self>,
+    ) {
+        if !self.output_selecting || !event.dragging() {
+            return;
         }
-
-        match path
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .map(|ext| ext.to_ascii_lowercase())
-            .as_deref()
+        self.output_selection_head = Some((block_index, line_index));
+        if let OutputSelectionKind::Rectangle { head_column, .. } = &mut self.output_selection_kind
         {
-            Some("rs") => PreviewLanguage::Rust,
-            Some("md") => PreviewLanguage::Markdown,
-            Some("json") => PreviewLanguage::Json,
-            Some("toml") => PreviewLanguage::Toml,
-            Some("yaml") | Some("yml") => PreviewLanguage::Yaml,
-            Some("sh") | Some("bash") | Some("zsh") => PreviewLanguage::Shell,
-            Some("js") => PreviewLanguage::JavaScript,
-            Some("jsx") => PreviewLanguage::JavaScriptReact,
-            Some("ts") => PreviewLanguage::TypeScript,
-            Some("tsx") => PreviewLanguage::TypeScriptReact,
-            Some("html") | Some("htm") => PreviewLanguage::Html,
-            Some("css") | Some("scss") | Some("less") => PreviewLanguage::Css,
-            Some("sql") => PreviewLanguage::Sql,
-            Some("py") => PreviewLanguage::Python,
-            Some("go") => PreviewLanguage::Go,
-            _ => PreviewLanguage::PlainText,
+            *head_column = column;
         }
+        self.selected_block = Some(block_index);
+        cx.notify();
     }
 
-    fn preview_language_label(language: PreviewLanguage) -> &'static str {
-        match language {
-            PreviewLanguage::Rust => "Rust",
-            PreviewLanguage::Markdown => "Markdown",
-            PreviewLanguage::Json => "JSON",
-            PreviewLanguage::Toml => "TOML",
-            PreviewLanguage::Yaml => "YAML",
-            PreviewLanguage::Shell => "Shell",
-            PreviewLanguage::JavaScript => "JavaScript",
-            PreviewLanguage::JavaScriptReact => "JSX",
-            PreviewLanguage::TypeScript => "TypeScript",
-            PreviewLanguage::TypeScriptReact => "TSX",
-            PreviewLanguage::Html => "HTML",
-            PreviewLanguage::Css => "CSS",
-            PreviewLanguage::Sql => "SQL",
-            PreviewLanguage::Python => "Python",
-            PreviewLanguage::Go => "Go",
-            PreviewLanguage::Dockerfile => "Dockerfile",
-            PreviewLanguage::PlainText => "Plain text",
+    fn on_output_mouse_up(
+        &mut self,
+        _event: &MouseUpEvent,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.output_selecting = false;
+        if TerminalSettings::current().copy_on_select
+            && let Some(text) = self.selected_output_text(false)
+        {
+            cx.write_to_clipboard(ClipboardItem::new_string(text));
         }
     }
 
-    fn highlight_line(line: &str, language: PreviewLanguage) -> Vec<HighlightSegment> {
-        match language {
-            PreviewLanguage::Markdown => Self::highlight_markdown_line(line),
-            PreviewLanguage::Html => Self::highlight_html_line(line),
-            PreviewLanguage::PlainText => vec![HighlightSegment {
-                text: line.to_string(),
-                color: 0xd8dee9,
-                bold: false,
-            }],
-            _ => Self::highlight_code_like_line(line, language),
+    /// Copy buttons shown while output is selected.
+    fn render_selection_actions(&self, cx: &Context<Self>) -> Option<Div> {
+        self.normalize_output_selection()?;
+        let button = |label: &'static str, ansi: bool| {
+            div()
+                .px(px(8.0))
+                .py(px(4.0))
+                .rounded(px(6.0))
+                .bg(rgb(0x1a1a1a))
+                .border_1()
+                .border_color(rgb(0x2a2a2a))
+                .text_size(px(11.0))
+                .text_color(rgb(0xdddddd))
+                .cursor(CursorStyle::PointingHand)
+                .hover(|this| this.bg(rgb(0x242424)).border_color(rgb(0x4a4a4a)))
+                .child(label)
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |view, _event: &MouseDownEvent, _window, cx| {
+                        cx.stop_propagation();
+                        view.copy_selected_output(ansi, cx);
+                    }),
+                )
+        };
+        Some(
+            div()
+                .absolute()
+                .bottom(px(16.0))
+                .right(px(64.0))
+                .flex()
+                .items_center()
+                .gap(px(6.0))
+                .child(
+                    div()
+                        .text_size(px(11.0))
+                        .text_color(rgb(0x7a7a7a))
+                        .child("Ctrl+Shift+C, add Alt for ANSI"),
+                )
+                .child(button("Copy", false))
+                .child(button("Copy with ANSI", true)),
+        )
+    }
+
+    fn tracks_mouse(&self) -> bool {
+        self.mouse_protocol.is_active() && !self.shift_held && self.pty.is_some()
+    }
+
+    /// The 1-based terminal cell under `position`. Rows count up from the
+    /// bottom of the output, where the program draws its screen.
+    fn mouse_cell(&self, position: Point<Pixels>, window: &Window) -> (u16, u16) {
+        let bounds = self.scroll_handle.bounds();
+        let x = f32::from(position.x - bounds.left()) - OUTPUT_PADDING;
+        let from_bottom = f32::from(bounds.bottom() - position.y) - OUTPUT_PADDING;
+        let column = (x / Self::output_cell_width(window))
+            .floor()
+            .clamp(0.0, f32::from(TERMINAL_COLS - 1));
+        let row = (from_bottom / self.output_row_height.get())
+            .floor()
+            .clamp(0.0, f32::from(TERMINAL_ROWS - 1));
+        (column as u16 + 1, TERMINAL_ROWS - row as u16)
+    }
+
+    fn report_mouse(
+        &mut self,
+        input: MouseInput,
+        position: Point<Pixels>,
+        modifiers: Modifiers,
+        window: &Window,
+    ) {
+        let (column, row) = self.mouse_cell(position, window);
+        if matches!(input, MouseInput::Move(_)) && self.last_mouse_cell == Some((column, row)) {
+            return;
+        }
+        self.last_mouse_cell = Some((column, row));
+        let report = MouseReport {
+            input,
+            column,
+            row,
+            shift: modifiers.shift,
+            alt: modifiers.alt,
+            control: modifiers.control,
+        };
+        if let (Some(bytes), Some(pty)) = (self.mouse_protocol.encode(report), self.pty.as_mut()) {
+            let _ = pty.write(&bytes);
         }
     }
 
-    fn highlight_markdown_line(line: &str) -> Vec<HighlightSegment> {
-        let trimmed = line.trim_start();
-        if trimmed.starts_with("```") {
-            return vec![HighlightSegment {
-                text: line.to_string(),
-                color: 0xe3b341,
-                bold: true,
-            }];
-        }
-        if trimmed.starts_with('#') {
-            return vec![HighlightSegment {
-                text: line.to_string(),
-                color: 0x79c0ff,
-                bold: true,
-            }];
+    fn on_tracked_mouse_down(
+        &mut self,
+        event: &MouseDownEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        cx.stop_propagation();
+        window.focus(&self.focus_handle);
+        if let Some(button) = pointer_button(event.button) {
+            self.report_mouse(
+                MouseInput::Press(button),
+                event.position,
+                event.modifiers,
+                window,
+            );
         }
-        if trimmed.starts_with("- ") || trimmed.starts_with("* ") {
-            return vec![
-                HighlightSegment {
-                    text: line.chars().take_while(|ch| ch.is_whitespace()).collect(),
-                    color: 0xd8dee9,
-                    bold: false,
-                },
-                HighlightSegment {
-                    text: trimmed.chars().take(1).collect(),
-                    color: 0xe3b341,
-                    bold: true,
-                },
-                HighlightSegment {
-                    text: trimmed.chars().skip(1).collect(),
-                    color: 0xd8dee9,
-                    bold: false,
-                },
-            ];
+    }
+
+    fn on_tracked_mouse_up(
+        &mut self,
+        event: &MouseUpEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if let Some(button) = pointer_button(event.button) {
+            self.report_mouse(
+                MouseInput::Release(button),
+                event.position,
+                event.modifiers,
+                window,
+            );
         }
+        cx.stop_propagation();
+    }
 
-        vec![HighlightSegment {
-            text: line.to_string(),
-            color: 0xd8dee9,
-            bold: false,
-        }]
+    fn on_tracked_mouse_move(
+        &mut self,
+        event: &MouseMoveEvent,
+        window: &mut Window,
+        _cx: &mut Context<Self>,
+    ) {
+        let button = event.pressed_button.and_then(pointer_button);
+        self.report_mouse(
+            MouseInput::Move(button),
+            event.position,
+            event.modifiers,
+            window,
+        );
     }
 
-    fn highlight_html_line(line: &str) -> Vec<HighlightSegment> {
-        if let Some(comment_start) = line.find("<!--")
-            && let Some(comment_end) = line[comment_start..].find("-->")
-        {
-            let comment_end = comment_start + comment_end + 3;
-            let mut head = Self::highlight_html_line(&line[..comment_start]);
-            head.push(HighlightSegment {
-                text: line[comment_start..comment_end].to_string(),
-                color: 0x6a9955,
-                bold: false,
-            });
-            if comment_end < line.len() {
-                head.extend(Self::highlight_html_line(&line[comment_end..]));
-            }
-            return head;
+    fn on_tracked_scroll_wheel(
+        &mut self,
+        event: &ScrollWheelEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        cx.stop_propagation();
+        let row_height = self.output_row_height.get();
+        let delta = f32::from(event.delta.pixel_delta(px(row_height)).y);
+        if delta == 0.0 {
+            return;
+        }
+        let input = if delta > 0.0 {
+            MouseInput::WheelUp
+        } else {
+            MouseInput::WheelDown
+        };
+        let steps = (delta.abs() / row_height).ceil().clamp(1.0, 5.0) as usize;
+        for _ in 0..steps {
+            self.report_mouse(input, event.position, event.modifiers, window);
         }
+    }
 
-        let mut segments = Vec::new();
-        let chars: Vec<char> = line.chars().collect();
-        let mut index = 0;
-        while index < chars.len() {
-            let ch = chars[index];
-            if ch == '<' {
-                let start = index;
-                index += 1;
-                while index < chars.len() && !chars[index].is_whitespace() && chars[index] != '>' {
-                    index += 1;
-                }
-                segments.push(HighlightSegment {
-                    text: chars[start..index].iter().collect(),
-                    color: 0x79c0ff,
-                    bold: true,
-                });
-                continue;
-            }
-            if ch == '"' || ch == '\'' {
-                let quote = ch;
-                let start = index;
-                index += 1;
-                while index < chars.len() {
-                    let current = chars[index];
-                    index += 1;
-                    if current == quote {
-                        break;
-                    }
-                }
-                segments.push(HighlightSegment {
-                    text: chars[start..index].iter().collect(),
-                    color: 0xce9178,
-                    bold: false,
-                });
-                continue;
-            }
-            if ch.is_ascii_alphabetic() || ch == '-' {
-                let start = index;
-                index += 1;
-                while index < chars.len()
-                    && (chars[index].is_ascii_alphanumeric() || matches!(chars[index], '-' | '_'))
-                {
-                    index += 1;
-                }
-                let token: String = chars[start..index].iter().collect();
-                let is_attr = index < chars.len() && chars[index] == '=';
-                segments.push(HighlightSegment {
-                    text: token,
-                    color: if is_attr { 0x9cdcfe } else { 0xd8dee9 },
-                    bold: is_attr,
-                });
-                continue;
+    fn on_modifiers_changed(
+        &mut self,
+        event: &ModifiersChangedEvent,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if self.shift_held != event.modifiers.shift {
+            self.shift_held = event.modifiers.shift;
+            if self.mouse_protocol.is_active() {
+                cx.notify();
             }
-            segments.push(HighlightSegment {
-                text: ch.to_string(),
-                color: 0x8b949e,
-                bold: false,
-            });
-            index += 1;
         }
+    }
 
-        segments
+    /// Covers the output while a program tracks the mouse, so clicks, drags
+    /// and the wheel go to it instead of selecting or scrolling.
+    fn render_mouse_tracking_layer(&self, cx: &Context<Self>) -> Option<Div> {
+        if !self.tracks_mouse() {
+            return None;
+        }
+        Some(
+            div()
+                .absolute()
+                .inset_0()
+                .occlude()
+                .cursor(CursorStyle::Arrow)
+                .on_any_mouse_down(cx.listener(Self::on_tracked_mouse_down))
+                .capture_any_mouse_up(cx.listener(Self::on_tracked_mouse_up))
+                .on_mouse_up_out(MouseButton::Left, cx.listener(Self::on_tracked_mouse_up))
+                .on_mouse_move(cx.listener(Self::on_tracked_mouse_move))
+                .on_scroll_wheel(cx.listener(Self::on_tracked_scroll_wheel)),
+        )
     }
 
-    fn highlight_code_like_line(line: &str, language: PreviewLanguage) -> Vec<HighlightSegment> {
-        let comment_prefix = match language {
-            PreviewLanguage::Rust | PreviewLanguage::JavaScript | PreviewLanguage::TypeScript => {
-                Some("//")
+    fn on_preview_line_mouse_down_at(
+        &mut self,
+        line_index: usize,
+        event: &MouseDownEvent,
+        cx: &mut Context<Self>,
+    ) {
+        self.focus_preview();
+        if self
+            .preview_search_match
+            .as_ref()
+            .is_some_and(|search_match| search_match.line_index != line_index)
+        {
+            self.preview_search_match = None;
+        }
+        if event.modifiers.shift {
+            if self.preview_selection_anchor.is_none() {
+                self.preview_selection_anchor =
+                    Some(self.preview_active_line.unwrap_or(line_index));
             }
-            PreviewLanguage::Toml
-            | PreviewLanguage::Yaml
-            | PreviewLanguage::Shell
-            | PreviewLanguage::Python
-            | PreviewLanguage::Dockerfile => Some("#"),
-            PreviewLanguage::Sql => Some("--"),
-            _ => None,
-        };
+            self.preview_selection_head = Some(line_index);
+        } else {
+            self.preview_selection_anchor = Some(line_index);
+            self.preview_selection_head = Some(line_index);
+        }
+        self.preview_active_line = Some(line_index);
+        self.preview_selecting = true;
+        cx.notify();
+        cx.stop_propagation();
+    }
 
-        if let Some(prefix) = comment_prefix
-            && let Some(index) = line.find(prefix)
-        {
-            let mut head = Self::highlight_code_like_line(&line[..index], language);
-            head.push(HighlightSegment {
-                text: line[index..].to_string(),
-                color: 0x6a9955,
-                bold: false,
-            });
-            return head;
+    fn on_preview_line_mouse_move_at(
+        &mut self,
+        line_index: usize,
+        event: &MouseMoveEvent,
+        cx: &mut Context<Self>,
+    ) {
+        if !self.preview_selecting || !event.dragging() {
+            return;
         }
+        self.preview_selection_head = Some(line_index);
+        self.preview_active_line = Some(line_index);
+        cx.notify();
+    }
 
-        let keywords: &[&str] = match language {
-            PreviewLanguage::Rust => &[
-                "fn", "let", "mut", "pub", "struct", "enum", "impl", "match", "if", "else", "use",
-                "mod", "self", "Self", "return",
-            ],
-            PreviewLanguage::Json => &["true", "false", "null"],
-            PreviewLanguage::Toml | PreviewLanguage::Yaml => &["true", "false"],
-            PreviewLanguage::Shell | PreviewLanguage::Dockerfile => &[
-                "if",
-                "then",
-                "else",
-                "fi",
-                "for",
-                "do",
-                "done",
-                "case",
-                "from",
-                "run",
-                "copy",
-                "workdir",
-                "env",
-                "cmd",
-                "entrypoint",
-                "expose",
-                "arg",
-            ],
-            PreviewLanguage::JavaScript
-            | PreviewLanguage::TypeScript
-            | PreviewLanguage::JavaScriptReact
-            | PreviewLanguage::TypeScriptReact => &[
-                "const",
-                "let",
-                "function",
-                "return",
-                "if",
-                "else",
-                "import",
-                "from",
-                "export",
-                "class",
-                "extends",
-                "interface",
-                "type",
-                "async",
-                "await",
-                "props",
-            ],
-            PreviewLanguage::Css => &[
-                "display",
-                "position",
-                "color",
-                "background",
-                "padding",
-                "margin",
-                "gap",
-                "flex",
-                "grid",
-                "width",
-                "height",
-            ],
-            PreviewLanguage::Sql => &[
-                "select", "from", "where", "insert", "into", "update", "delete", "join", "left",
-                "right", "inner", "outer", "group", "by", "order", "limit", "and", "or", "as",
-                "on",
-            ],
-            PreviewLanguage::Python => &[
-                "def", "class", "return", "if", "elif", "else", "for", "while", "in", "import",
-                "from", "try", "except", "with", "as", "pass", "None", "True", "False",
-            ],
-            PreviewLanguage::Go => &[
-                "func",
-                "package",
-                "import",
-                "return",
-                "if",
-                "else",
-                "for",
-                "range",
-                "go",
-                "defer",
-                "struct",
-                "type",
-                "interface",
-                "map",
-                "var",
-                "const",
-            ],
-            PreviewLanguage::Markdown | PreviewLanguage::PlainText => &[],
-            PreviewLanguage::Html => &[],
+    fn on_preview_mouse_up(
+        &mut self,
+        _event: &MouseUpEvent,
+        _window: &mut Window,
+        _cx: &mut Context<Self>,
+    ) {
+        self.preview_selecting = false;
+    }
+
+    fn on_toggle_file_preview_mode(
+        &mut self,
+        _event: &MouseDownEvent,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.toggle_file_preview_mode(cx);
+        cx.stop_propagation();
+    }
+
+    fn on_preview_terminal_resize_mouse_down(
+        &mut self,
+        event: &MouseDownEvent,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let y: f32 = event.position.y.into();
+        self.preview_terminal_resize_dragging = true;
+        self.preview_terminal_resize_start_y = Some(y);
+        self.preview_terminal_resize_start_height = self.preview_terminal_height;
+        cx.notify();
+        cx.stop_propagation();
+    }
+
+    fn on_preview_terminal_resize_mouse_move(
+        &mut self,
+        event: &MouseMoveEvent,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if !self.preview_terminal_resize_dragging || !event.dragging() {
+            return;
+        }
+
+        let Some(start_y) = self.preview_terminal_resize_start_y else {
+            return;
         };
 
-        let mut segments = Vec::new();
-        let chars: Vec<char> = line.chars().collect();
-        let mut index = 0;
-        while index < chars.len() {
-            let ch = chars[index];
-            if ch.is_whitespace() {
-                let start = index;
-                while index < chars.len() && chars[index].is_whitespace() {
-                    index += 1;
+        let current_y: f32 = event.position.y.into();
+        let delta = start_y - current_y;
+        let next_height =
+            Self::clamp_preview_terminal_height(self.preview_terminal_resize_start_height + delta);
+        if (self.preview_terminal_height - next_height).abs() > f32::EPSILON {
+            self.preview_terminal_height = next_height;
+            cx.notify();
+        }
+    }
+
+    fn on_preview_terminal_resize_mouse_up(
+        &mut self,
+        _event: &MouseUpEvent,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if self.preview_terminal_resize_dragging {
+            self.preview_terminal_resize_dragging = false;
+            self.preview_terminal_resize_start_y = None;
+            cx.notify();
+        }
+    }
+
+    fn copy_selected_preview(&mut self, cx: &mut Context<Self>) -> bool {
+        let Some(text) = self.selected_preview_text() else {
+            return false;
+        };
+        cx.write_to_clipboard(ClipboardItem::new_string(text));
+        true
+    }
+
+    fn select_all_preview(&mut self) -> bool {
+        let count = self.preview_line_count();
+        if count == 0 {
+            return false;
+        }
+        self.preview_selection_anchor = Some(0);
+        self.preview_selection_head = Some(count.saturating_sub(1));
+        self.preview_active_line = Some(count.saturating_sub(1));
+        true
+    }
+
+    fn build_preview_search_match(
+        line_number: usize,
+        query: &str,
+        line_count: usize,
+    ) -> Option<(usize, String)> {
+        let trimmed = query.trim();
+        if line_number == 0 || trimmed.is_empty() || line_count == 0 {
+            return None;
+        }
+
+        Some((
+            line_number.saturating_sub(1).min(line_count - 1),
+            trimmed.to_string(),
+        ))
+    }
+
+    fn apply_preview_search_match_segments(
+        segments: &[HighlightSegment],
+        query: &str,
+    ) -> Vec<PreviewSearchMatchSegment> {
+        let trimmed = query.trim();
+        if trimmed.is_empty() {
+            return segments
+                .iter()
+                .map(|segment| PreviewSearchMatchSegment {
+                    text: segment.text.clone(),
+                    color: segment.color,
+                    bold: segment.bold,
+                    matched: false,
+                })
+                .collect();
+        }
+
+        let mut rendered = Vec::new();
+        let query_lower = trimmed.to_ascii_lowercase();
+
+        for segment in segments {
+            if segment.text.is_empty() {
+                continue;
+            }
+
+            let text = segment.text.as_str();
+            let text_lower = text.to_ascii_lowercase();
+            let mut search_from = 0usize;
+
+            while let Some(found_at) = text_lower[search_from..].find(&query_lower) {
+                let match_start = search_from + found_at;
+                let match_end = match_start + trimmed.len();
+
+                if match_start > search_from {
+                    rendered.push(PreviewSearchMatchSegment {
+                        text: text[search_from..match_start].to_string(),
+                        color: segment.color,
+                        bold: segment.bold,
+                        matched: false,
+                    });
                 }
-                segments.push(HighlightSegment {
-                    text: chars[start..index].iter().collect(),
-                    color: 0xd8dee9,
-                    bold: false,
+
+                rendered.push(PreviewSearchMatchSegment {
+                    text: text[match_start..match_end].to_string(),
+                    color: segment.color,
+                    bold: segment.bold,
+                    matched: true,
+                });
+                search_from = match_end;
+            }
+
+            if search_from < text.len() {
+                rendered.push(PreviewSearchMatchSegment {
+                    text: text[search_from..].to_string(),
+                    color: segment.color,
+                    bold: segment.bold,
+                    matched: false,
                 });
-                continue;
             }
+        }
+
+        if rendered.is_empty() {
+            segments
+                .iter()
+                .map(|segment| PreviewSearchMatchSegment {
+                    text: segment.text.clone(),
+                    color: segment.color,
+                    bold: segment.bold,
+                    matched: false,
+                })
+                .collect()
+        } else {
+            rendered
+        }
+    }
+
+    fn move_preview_selection_by(&mut self, delta: isize, extend: bool) -> bool {
+        let count = self.preview_line_count();
+        if count == 0 {
+            return false;
+        }
+
+        let (active_line, selection_anchor, selection_head) = Self::move_linear_selection(
+            count,
+            self.preview_active_line,
+            self.preview_selection_anchor,
+            self.preview_selection_head,
+            delta,
+            extend,
+        );
+        self.focus_preview();
+        self.preview_active_line = active_line;
+        self.preview_selection_anchor = selection_anchor;
+        self.preview_selection_head = selection_head;
+        true
+    }
+
+    fn move_preview_selection_to_edge(&mut self, to_end: bool, extend: bool) -> bool {
+        let count = self.preview_line_count();
+        if count == 0 {
+            return false;
+        }
+
+        let target = if to_end { count.saturating_sub(1) } else { 0 };
+        let current = self
+            .preview_active_line
+            .unwrap_or(target)
+            .min(count.saturating_sub(1));
+        self.focus_preview();
+        self.preview_active_line = Some(target);
+        if extend {
+            if self.preview_selection_anchor.is_none() {
+                self.preview_selection_anchor = Some(current);
+            }
+            self.preview_selection_head = Some(target);
+        } else {
+            self.preview_selection_anchor = Some(target);
+            self.preview_selection_head = Some(target);
+        }
+        true
+    }
+
+    fn normalize_linear_selection(
+        anchor: Option<usize>,
+        head: Option<usize>,
+    ) -> Option<(usize, usize)> {
+        let anchor = anchor?;
+        let head = head?;
+        if anchor == head {
+            return None;
+        }
+        Some((anchor.min(head), anchor.max(head)))
+    }
+
+    fn selected_text_from_lines(
+        lines: &[String],
+        anchor: Option<usize>,
+        head: Option<usize>,
+    ) -> Option<String> {
+        let (start, end) = Self::normalize_linear_selection(anchor, head)?;
+        if start >= lines.len() {
+            return None;
+        }
+        let end = end.min(lines.len().saturating_sub(1));
+        if start > end {
+            return None;
+        }
+        Some(lines[start..=end].join("\n"))
+    }
+
+    fn move_linear_selection(
+        count: usize,
+        active_line: Option<usize>,
+        selection_anchor: Option<usize>,
+        selection_head: Option<usize>,
+        delta: isize,
+        extend: bool,
+    ) -> (Option<usize>, Option<usize>, Option<usize>) {
+        if count == 0 {
+            return (active_line, selection_anchor, selection_head);
+        }
+
+        let current = active_line.unwrap_or(0).min(count.saturating_sub(1));
+        let next = if delta < 0 {
+            current.saturating_sub(delta.unsigned_abs())
+        } else {
+            (current + delta as usize).min(count.saturating_sub(1))
+        };
+
+        if extend {
+            let anchor = selection_anchor.or(Some(current));
+            (Some(next), anchor, Some(next))
+        } else {
+            (Some(next), Some(next), Some(next))
+        }
+    }
+
+    fn handle_preview_key(&mut self, event: &KeyDownEvent, cx: &mut Context<Self>) -> bool {
+        if !self.preview_focus || self.file_preview.is_none() {
+            return false;
+        }
+
+        let ctrl = event.keystroke.modifiers.control;
+        let shift = event.keystroke.modifiers.shift;
+        match event.keystroke.key.as_str() {
+            "c" if ctrl => {
+                self.copy_selected_preview(cx);
+                true
+            }
+            "a" if ctrl => {
+                if self.select_all_preview() {
+                    cx.notify();
+                }
+                true
+            }
+            "up" | "arrowup" => {
+                if self.move_preview_selection_by(-1, shift) {
+                    cx.notify();
+                }
+                true
+            }
+            "down" | "arrowdown" => {
+                if self.move_preview_selection_by(1, shift) {
+                    cx.notify();
+                }
+                true
+            }
+            "left" | "arrowleft" => {
+                if self.move_preview_selection_by(-1, shift) {
+                    cx.notify();
+                }
+                true
+            }
+            "right" | "arrowright" => {
+                if self.move_preview_selection_by(1, shift) {
+                    cx.notify();
+                }
+                true
+            }
+            "home" => {
+                if self.move_preview_selection_to_edge(false, shift) {
+                    cx.notify();
+                }
+                true
+            }
+            "end" => {
+                if self.move_preview_selection_to_edge(true, shift) {
+                    cx.notify();
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn format_path(path: &Path) -> String {
+        let home = std::env::var("USERPROFILE")
+            .or_else(|_| std::env::var("HOME"))
+            .ok()
+            .map(PathBuf::from);
+
+        if let Some(home_path) = home {
+            if let Ok(stripped) = path.strip_prefix(&home_path) {
+                let stripped = stripped.to_string_lossy();
+                if stripped.is_empty() {
+                    return "~".to_string();
+                }
+                let sep = if cfg!(windows) { "\\" } else { "/" };
+                return format!("~{}{}", sep, stripped);
+            }
+        }
+
+        let mut out = path.to_string_lossy().to_string();
+        if !cfg!(windows) {
+            out = out.replace('\\', "/");
+        }
+        out
+    }
+
+    pub fn new_welcome(cx: &mut Context<Self>, recent: Vec<RecentEntry>) -> Self {
+        let mut view = Self::new_base(cx);
+        let welcome = cx.new(|cx| WelcomeView::with_recent(cx, recent));
+        cx.subscribe(&welcome, |_, _welcome, event: &OpenRepositoryEvent, cx| {
+            cx.emit(TabViewEvent::OpenRepository(event.path.clone()));
+        })
+        .detach();
+        cx.subscribe(&welcome, |_, _welcome, event: &CreateProjectEvent, cx| {
+            cx.emit(TabViewEvent::CreateProject {
+                prompt: event.prompt.clone(),
+                parent: event.parent.clone(),
+            });
+        })
+        .detach();
+        cx.subscribe(&welcome, |_, _welcome, event: &CloneRepositoryEvent, cx| {
+            cx.emit(TabViewEvent::CloneRepository {
+                url: event.url.clone(),
+                parent: event.parent.clone(),
+            });
+        })
+        .detach();
+        view.mode = TabViewMode::Welcome(welcome);
+        view
+    }
+
+    pub fn new_settings(cx: &mut Context<Self>) -> Self {
+        let mut view = Self::new_base(cx);
+        let settings = cx.new(|cx| SettingsView::new(cx));
+        view.mode = TabViewMode::Settings(settings);
+        view
+    }
+
+    /// A read-only tab that plays back an asciicast recording.
+    pub fn new_replay(cx: &mut Context<Self>, path: PathBuf) -> Self {
+        let mut view = Self::new_base(cx);
+        view.mode = TabViewMode::Terminal;
+        view.input_visible = false;
+        match Cast::load(&path) {
+            Ok(cast) => {
+                view.replay = Some(ReplayState {
+                    cast: Arc::new(cast),
+                    path,
+                    next_event: 0,
+                    position: 0.0,
+                    speed: 1.0,
+                    playing: true,
+                    generation: 0,
+                });
+                view.restart_replay(cx);
+            }
+            Err(err) => {
+                view.append_output(&format!("Failed to load recording: {err:#}\n"), cx);
+            }
+        }
+        view
+    }
+
+    pub fn new_agent(cx: &mut Context<Self>) -> Self {
+        let mut view = Self::new_base(cx);
+        let agent = cx.new(|cx| AgentView::new(cx));
+        view.mode = TabViewMode::Agent(agent);
+        view
+    }
+
+    fn new_base(cx: &mut Context<Self>) -> Self {
+        let history_store = Self::load_initial_history();
+        shell::load_in_background();
+        let last_path_var = std::env::var("PATH").unwrap_or_default();
+        let base_terminal_cwd = launch::default_base_terminal_cwd();
+        let agent_rows = load_effective_agent_rows(ConflictPolicy::LocalWins).unwrap_or_default();
+        let agent_selected_key = agent_rows.first().map(|row| row.agent_key.clone());
+        let model_options_loading = agent_selected_key.is_some();
+        let runtime_preferences =
+            Arc::new(Mutex::new(RuntimePreferences::load().unwrap_or_default()));
+        let registry_manifests = storage::app_root()
+            .ok()
+            .and_then(|app_root| load_cached_registry(&app_root).ok().flatten())
+            .map(|cached| cached.manifests)
+            .unwrap_or_default();
+        Self {
+            blocks: Vec::new(),
+            pty: None,
+            focus_handle: cx.focus_handle(),
+            input: String::new(),
+            cursor: 0,
+            history_store,
+            history_filter: HistoryFilter::All,
+            running_command: None,
+            secure_input: None,
+            pending_guard: None,
+            guard_cache: GuardCache::default(),
+            shell_reports_status: false,
+            ansi_parser: AnsiParser::default(),
+            editor: ModalEditor::default(),
+            history_open: false,
+            history_index: 0,
+            history_items: Vec::new(),
+            suggestions: Vec::new(),
+            suggest_index: 0,
+            suggestion_menu_open: false,
+            completion_engine: Arc::new(CompletionEngine::load()),
+            completion_generators_running: false,
+            selection: None,
+            selection_anchor: None,
+            extra_cursors: Vec::new(),
+            path_commands: Self::load_path_commands(),
+            last_path_scan: Instant::now(),
+            last_path_var,
+            current_path: Self::format_path(&base_terminal_cwd),
+            git_status: get_git_status(&base_terminal_cwd),
+            auto_focus: true,
+            pending_echo: None,
+            scroll_handle: ScrollHandle::new(),
+            preview_scroll_handle: ScrollHandle::new(),
+            preview_code_scroll_handle: UniformListScrollHandle::new(),
+            input_visible: true,
+            overlay: None,
+            needs_git_refresh: false,
+            mode: TabViewMode::Terminal,
+            last_line_incomplete: false,
+            total_output_lines: 0,
+            follow_output: true,
+            last_scroll_to_bottom_at: Instant::now(),
+            input_mode: InputMode::Terminal,
+            agent_rows,
+            agent_selected_key,
+            agent_client: None,
+            agent_client_key: None,
+            agent_busy: false,
+            agent_needs_auth: false,
+            runtime_preferences,
+            registry_manifests,
+            discovered_models: Vec::new(),
+            model_options_loading,
+            selected_model_override: None,
+            selected_block: None,
+            block_nav_active: false,
+            block_selection: BTreeSet::new(),
+            block_nav_status: None,
+            bell_flashes: 0,
+            mouse_protocol: MouseProtocol::default(),
+            last_mouse_cell: None,
+            shift_held: false,
+            output_selection_anchor: None,
+            output_selection_head: None,
+            output_selecting: false,
+            output_selection_kind: OutputSelectionKind::Lines,
+            output_text_left: Rc::new(Cell::new(0.0)),
+            output_row_height: Rc::new(Cell::new(OUTPUT_LINE_HEIGHT)),
+            file_preview: None,
+            block_diff: None,
+            watch: None,
+            watch_generation: 0,
+            runbook: None,
+            dev_servers: Vec::new(),
+            dev_server_polling: false,
+            problem_groups: Vec::new(),
+            process_panel: None,
+            process_panel_generation: 0,
+            tab_dev_server: None,
+            recording: None,
+            recording_status: None,
+            replay: None,
+            replay_generation: 0,
+            preview_search_match: None,
+            preview_focus: false,
+            preview_selection_anchor: None,
+            preview_selection_head: None,
+            preview_selecting: false,
+            preview_active_line: None,
+            file_preview_mode: FilePreviewMode::Code,
+            preview_terminal_height: DEFAULT_PREVIEW_TERMINAL_HEIGHT,
+            preview_terminal_resize_dragging: false,
+            preview_terminal_resize_start_y: None,
+            preview_terminal_resize_start_height: DEFAULT_PREVIEW_TERMINAL_HEIGHT,
+        }
+    }
+
+    fn build_file_preview_state(path: &Path, root: Option<&Path>) -> FilePreviewState {
+        let relative_label = root
+            .and_then(|root| path.strip_prefix(root).ok())
+            .map(Self::format_path)
+            .unwrap_or_else(|| Self::format_path(path));
+        let language = Self::detect_preview_language(path);
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+
+        let image_extensions = [
+            "png", "jpg", "jpeg", "gif", "webp", "bmp", "ico", "svg", "avif", "tif", "tiff",
+        ];
+
+        let kind = if extension
+            .as_deref()
+            .map(|ext| image_extensions.contains(&ext))
+            .unwrap_or(false)
+        {
+            FilePreviewKind::Image
+        } else {
+            match std::fs::read(path) {
+                Ok(bytes) if bytes.len() > MAX_TEXT_PREVIEW_BYTES => FilePreviewKind::Unsupported {
+                    message: format!(
+                        "File too large to preview inline ({} KB limit).",
+                        MAX_TEXT_PREVIEW_BYTES / 1024
+                    ),
+                },
+                Ok(bytes) if bytes.contains(&0) => FilePreviewKind::Unsupported {
+                    message: "Binary file preview is not supported yet.".into(),
+                },
+                Ok(bytes) => match String::from_utf8(bytes) {
+                    Ok(contents) => {
+                        let lines =
+                            Arc::new(contents.lines().map(|line| line.to_string()).collect());
+                        FilePreviewKind::Text { contents, lines }
+                    }
+                    Err(_) => FilePreviewKind::Unsupported {
+                        message: "This file is not valid UTF-8 text.".into(),
+                    },
+                },
+                Err(err) => FilePreviewKind::Unsupported {
+                    message: format!("Failed to read file: {err}"),
+                },
+            }
+        };
+
+        FilePreviewState {
+            path: path.to_path_buf(),
+            relative_label,
+            language,
+            kind,
+        }
+    }
+
+    fn detect_preview_language(path: &Path) -> PreviewLanguage {
+        let file_name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| name.to_ascii_lowercase())
+            .unwrap_or_default();
+
+        match file_name.as_str() {
+            "cargo.toml" | "agents.json" | "repo.config.json" | "registry-sample.json" => {
+                return if file_name.ends_with(".toml") {
+                    PreviewLanguage::Toml
+                } else {
+                    PreviewLanguage::Json
+                };
+            }
+            "dockerfile" => {
+                return PreviewLanguage::Dockerfile;
+            }
+            ".gitignore" | ".dockerignore" | ".eslintignore" | ".prettierignore" => {
+                return PreviewLanguage::PlainText;
+            }
+            _ => {}
+        }
+
+        match path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("rs") => PreviewLanguage::Rust,
+            Some("md") => PreviewLanguage::Markdown,
+            Some("json") => PreviewLanguage::Json,
+            Some("toml") => PreviewLanguage::Toml,
+            Some("yaml") | Some("yml") => PreviewLanguage::Yaml,
+            Some("sh") | Some("bash") | Some("zsh") => PreviewLanguage::Shell,
+            Some("js") => PreviewLanguage::JavaScript,
+            Some("jsx") => PreviewLanguage::JavaScriptReact,
+            Some("ts") => PreviewLanguage::TypeScript,
+            Some("tsx") => PreviewLanguage::TypeScriptReact,
+            Some("html") | Some("htm") => PreviewLanguage::Html,
+            Some("css") | Some("scss") | Some("less") => PreviewLanguage::Css,
+            Some("sql") => PreviewLanguage::Sql,
+            Some("py") => PreviewLanguage::Python,
+            Some("go") => PreviewLanguage::Go,
+            _ => PreviewLanguage::PlainText,
+        }
+    }
+
+    fn preview_language_label(language: PreviewLanguage) -> &'static str {
+        match language {
+            PreviewLanguage::Rust => "Rust",
+            PreviewLanguage::Markdown => "Markdown",
+            PreviewLanguage::Json => "JSON",
+            PreviewLanguage::Toml => "TOML",
+            PreviewLanguage::Yaml => "YAML",
+            PreviewLanguage::Shell => "Shell",
+            PreviewLanguage::JavaScript => "JavaScript",
+            PreviewLanguage::JavaScriptReact => "JSX",
+            PreviewLanguage::TypeScript => "TypeScript",
+            PreviewLanguage::TypeScriptReact => "TSX",
+            PreviewLanguage::Html => "HTML",
+            PreviewLanguage::Css => "CSS",
+            PreviewLanguage::Sql => "SQL",
+            PreviewLanguage::Python => "Python",
+            PreviewLanguage::Go => "Go",
+            PreviewLanguage::Dockerfile => "Dockerfile",
+            PreviewLanguage::PlainText => "Plain text",
+        }
+    }
+
+    fn highlight_line(line: &str, language: PreviewLanguage) -> Vec<HighlightSegment> {
+        match language {
+            PreviewLanguage::Markdown => Self::highlight_markdown_line(line),
+            PreviewLanguage::Html => Self::highlight_html_line(line),
+            PreviewLanguage::PlainText => vec![HighlightSegment {
+                text: line.to_string(),
+                color: 0xd8dee9,
+                bold: false,
+            }],
+            _ => Self::highlight_code_like_line(line, language),
+        }
+    }
+
+    fn highlight_markdown_line(line: &str) -> Vec<HighlightSegment> {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") {
+            return vec![HighlightSegment {
+                text: line.to_string(),
+                color: 0xe3b341,
+                bold: true,
+            }];
+        }
+        if trimmed.starts_with('#') {
+            return vec![HighlightSegment {
+                text: line.to_string(),
+                color: 0x79c0ff,
+                bold: true,
+            }];
+        }
+        if trimmed.starts_with("- ") || trimmed.starts_with("* ") {
+            return vec![
+                HighlightSegment {
+                    text: line.chars().take_while(|ch| ch.is_whitespace()).collect(),
+                    color: 0xd8dee9,
+                    bold: false,
+                },
+                HighlightSegment {
+                    text: trimmed.chars().take(1).collect(),
+                    color: 0xe3b341,
+                    bold: true,
+                },
+                HighlightSegment {
+                    text: trimmed.chars().skip(1).collect(),
+                    color: 0xd8dee9,
+                    bold: false,
+                },
+            ];
+        }
+
+        vec![HighlightSegment {
+            text: line.to_string(),
+            color: 0xd8dee9,
+            bold: false,
+        }]
+    }
+
+    fn highlight_html_line(line: &str) -> Vec<HighlightSegment> {
+        if let Some(comment_start) = line.find("<!--")
+            && let Some(comment_end) = line[comment_start..].find("-->")
+        {
+            let comment_end = comment_start + comment_end + 3;
+            let mut head = Self::highlight_html_line(&line[..comment_start]);
+            head.push(HighlightSegment {
+                text: line[comment_start..comment_end].to_string(),
+                color: 0x6a9955,
+                bold: false,
+            });
+            if comment_end < line.len() {
+                head.extend(Self::highlight_html_line(&line[comment_end..]));
+            }
+            return head;
+        }
+
+        let mut segments = Vec::new();
+        let chars: Vec<char> = line.chars().collect();
+        let mut index = 0;
+        while index < chars.len() {
+            let ch = chars[index];
+            if ch == '<' {
+                let start = index;
+                index += 1;
+                while index < chars.len() && !chars[index].is_whitespace() && chars[index] != '>' {
+                    index += 1;
+                }
+                segments.push(HighlightSegment {
+                    text: chars[start..index].iter().collect(),
+                    color: 0x79c0ff,
+                    bold: true,
+                });
+                continue;
+            }
+            if ch == '"' || ch == '\'' {
+                let quote = ch;
+                let start = index;
+                index += 1;
+                while index < chars.len() {
+                    let current = chars[index];
+                    index += 1;
+                    if current == quote {
+                        break;
+                    }
+                }
+                segments.push(HighlightSegment {
+                    text: chars[start..index].iter().collect(),
+                    color: 0xce9178,
+                    bold: false,
+                });
+                continue;
+            }
+            if ch.is_ascii_alphabetic() || ch == '-' {
+                let start = index;
+                index += 1;
+                while index < chars.len()
+                    && (chars[index].is_ascii_alphanumeric() || matches!(chars[index], '-' | '_'))
+                {
+                    index += 1;
+                }
+                let token: String = chars[start..index].iter().collect();
+                let is_attr = index < chars.len() && chars[index] == '=';
+                segments.push(HighlightSegment {
+                    text: token,
+                    color: if is_attr { 0x9cdcfe } else { 0xd8dee9 },
+                    bold: is_attr,
+                });
+                continue;
+            }
+            segments.push(HighlightSegment {
+                text: ch.to_string(),
+                color: 0x8b949e,
+                bold: false,
+            });
+            index += 1;
+        }
+
+        segments
+    }
+
+    fn highlight_code_like_line(line: &str, language: PreviewLanguage) -> Vec<HighlightSegment> {
+        let comment_prefix = match language {
+            PreviewLanguage::Rust | PreviewLanguage::JavaScript | PreviewLanguage::TypeScript => {
+                Some("//")
+            }
+            PreviewLanguage::Toml
+            | PreviewLanguage::Yaml
+            | PreviewLanguage::Shell
+            | PreviewLanguage::Python
+            | PreviewLanguage::Dockerfile => Some("#"),
+            PreviewLanguage::Sql => Some("--"),
+            _ => None,
+        };
+
+        if let Some(prefix) = comment_prefix
+            && let Some(index) = line.find(prefix)
+        {
+            let mut head = Self::highlight_code_like_line(&line[..index], language);
+            head.push(HighlightSegment {
+                text: line[index..].to_string(),
+                color: 0x6a9955,
+                bold: false,
+            });
+            return head;
+        }
+
+        let keywords: &[&str] = match language {
+            PreviewLanguage::Rust => &[
+                "fn", "let", "mut", "pub", "struct", "enum", "impl", "match", "if", "else", "use",
+                "mod", "self", "Self", "return",
+            ],
+            PreviewLanguage::Json => &["true", "false", "null"],
+            PreviewLanguage::Toml | PreviewLanguage::Yaml => &["true", "false"],
+            PreviewLanguage::Shell | PreviewLanguage::Dockerfile => &[
+                "if",
+                "then",
+                "else",
+                "fi",
+                "for",
+                "do",
+                "done",
+                "case",
+                "from",
+                "run",
+                "copy",
+                "workdir",
+                "env",
+                "cmd",
+                "entrypoint",
+                "expose",
+                "arg",
+            ],
+            PreviewLanguage::JavaScript
+            | PreviewLanguage::TypeScript
+            | PreviewLanguage::JavaScriptReact
+            | PreviewLanguage::TypeScriptReact => &[
+                "const",
+                "let",
+                "function",
+                "return",
+                "if",
+                "else",
+                "import",
+                "from",
+                "export",
+                "class",
+                "extends",
+                "interface",
+                "type",
+                "async",
+                "await",
+                "props",
+            ],
+            PreviewLanguage::Css => &[
+                "display",
+                "position",
+                "color",
+                "background",
+                "padding",
+                "margin",
+                "gap",
+                "flex",
+                "grid",
+                "width",
+                "height",
+            ],
+            PreviewLanguage::Sql => &[
+                "select", "from", "where", "insert", "into", "update", "delete", "join", "left",
+                "right", "inner", "outer", "group", "by", "order", "limit", "and", "or", "as",
+                "on",
+            ],
+            PreviewLanguage::Python => &[
+                "def", "class", "return", "if", "elif", "else", "for", "while", "in", "import",
+                "from", "try", "except", "with", "as", "pass", "None", "True", "False",
+            ],
+            PreviewLanguage::Go => &[
+                "func",
+                "package",
+                "import",
+                "return",
+                "if",
+                "else",
+                "for",
+                "range",
+                "go",
+                "defer",
+                "struct",
+                "type",
+                "interface",
+                "map",
+                "var",
+                "const",
+            ],
+            PreviewLanguage::Markdown | PreviewLanguage::PlainText => &[],
+            PreviewLanguage::Html => &[],
+        };
+
+        let mut segments = Vec::new();
+        let chars: Vec<char> = line.chars().collect();
+        let mut index = 0;
+        while index < chars.len() {
+            let ch = chars[index];
+            if ch.is_whitespace() {
+                let start = index;
+                while index < chars.len() && chars[index].is_whitespace() {
+                    index += 1;
+                }
+                segments.push(HighlightSegment {
+                    text: chars[start..index].iter().collect(),
+                    color: 0xd8dee9,
+                    bold: false,
+                });
+                continue;
+            }
+
+            if ch == '"' || ch == '\'' {
+                let quote = ch;
+                let start = index;
+                index += 1;
+                while index < chars.len() {
+                    let current = chars[index];
+                    index += 1;
+                    if current == '\\' && index < chars.len() {
+                        index += 1;
+                        continue;
+                    }
+                    if current == quote {
+                        break;
+                    }
+                }
+                segments.push(HighlightSegment {
+                    text: chars[start..index].iter().collect(),
+                    color: 0xce9178,
+                    bold: false,
+                });
+                continue;
+            }
+
+            if ch.is_ascii_digit() {
+                let start = index;
+                index += 1;
+                while index < chars.len()
+                    && (chars[index].is_ascii_digit() || matches!(chars[index], '.' | '_'))
+                {
+                    index += 1;
+                }
+                segments.push(HighlightSegment {
+                    text: chars[start..index].iter().collect(),
+                    color: 0xb5cea8,
+                    bold: false,
+                });
+                continue;
+            }
+
+            if ch.is_ascii_alphabetic() || ch == '_' || ch == '$' {
+                let start = index;
+                index += 1;
+                while index < chars.len()
+                    && (chars[index].is_ascii_alphanumeric()
+                        || matches!(chars[index], '_' | '-' | '$'))
+                {
+                    index += 1;
+                }
+                let word: String = chars[start..index].iter().collect();
+                let lower_word = word.to_ascii_lowercase();
+                let is_keyword = keywords.iter().any(|keyword| *keyword == lower_word);
+                segments.push(HighlightSegment {
+                    text: word,
+                    color: if is_keyword { 0x79c0ff } else { 0xd8dee9 },
+                    bold: is_keyword,
+                });
+                continue;
+            }
+
+            segments.push(HighlightSegment {
+                text: ch.to_string(),
+                color: 0x8b949e,
+                bold: false,
+            });
+            index += 1;
+        }
+
+        segments
+    }
+
+    pub fn open_file_preview(&mut self, path: PathBuf, cx: &mut Context<Self>) {
+        if !matches!(self.mode, TabViewMode::Terminal) {
+            return;
+        }
+
+        let root = expand_tilde(&self.current_path);
+        self.block_diff = None;
+        self.file_preview = Some(Self::build_file_preview_state(&path, Some(root.as_path())));
+        self.preview_scroll_handle
+            .set_offset(point(px(0.0), px(0.0)));
+        self.preview_code_scroll_handle
+            .0
+            .borrow()
+            .base_handle
+            .set_offset(point(px(0.0), px(0.0)));
+        self.preview_terminal_resize_dragging = false;
+        self.preview_terminal_resize_start_y = None;
+        self.preview_terminal_resize_start_height = self.preview_terminal_height;
+        self.preview_search_match = None;
+        self.preview_active_line = Some(0);
+        self.clear_preview_selection();
+        self.focus_preview();
+        self.file_preview_mode = FilePreviewMode::Code;
+        self.load_runbook();
+        cx.notify();
+    }
+
+    pub fn open_file_preview_at_search_result(
+        &mut self,
+        path: PathBuf,
+        line_number: usize,
+        query: String,
+        cx: &mut Context<Self>,
+    ) {
+        if !matches!(self.mode, TabViewMode::Terminal) {
+            return;
+        }
+
+        let root = expand_tilde(&self.current_path);
+        let preview = Self::build_file_preview_state(&path, Some(root.as_path()));
+        let line_count = match &preview.kind {
+            FilePreviewKind::Text { lines, .. } => lines.len(),
+            _ => 0,
+        };
+        let preview_search_match =
+            Self::build_preview_search_match(line_number, &query, line_count)
+                .map(|(line_index, query)| PreviewSearchMatch { line_index, query });
+        let active_line = preview_search_match
+            .as_ref()
+            .map(|search_match| search_match.line_index)
+            .unwrap_or_else(|| {
+                line_number
+                    .saturating_sub(1)
+                    .min(line_count.saturating_sub(1))
+            });
+
+        self.block_diff = None;
+        self.file_preview = Some(preview);
+        self.preview_scroll_handle
+            .set_offset(point(px(0.0), px(0.0)));
+        self.preview_code_scroll_handle
+            .scroll_to_item_strict(active_line, ScrollStrategy::Center);
+        self.preview_terminal_resize_dragging = false;
+        self.preview_terminal_resize_start_y = None;
+        self.preview_terminal_resize_start_height = self.preview_terminal_height;
+        self.preview_search_match = preview_search_match;
+        self.preview_active_line = Some(active_line);
+        self.clear_preview_selection();
+        self.focus_preview();
+        self.file_preview_mode = FilePreviewMode::Code;
+        self.load_runbook();
+        cx.notify();
+    }
+
+    fn close_file_preview(&mut self, cx: &mut Context<Self>) {
+        self.file_preview = None;
+        self.preview_scroll_handle
+            .set_offset(point(px(0.0), px(0.0)));
+        self.preview_code_scroll_handle
+            .0
+            .borrow()
+            .base_handle
+            .set_offset(point(px(0.0), px(0.0)));
+        self.preview_terminal_resize_dragging = false;
+        self.preview_terminal_resize_start_y = None;
+        self.preview_terminal_resize_start_height = self.preview_terminal_height;
+        self.preview_search_match = None;
+        self.preview_active_line = None;
+        self.clear_preview_selection();
+        self.blur_preview();
+        self.file_preview_mode = FilePreviewMode::Code;
+        cx.notify();
+    }
+
+    fn toggle_file_preview_mode(&mut self, cx: &mut Context<Self>) {
+        let Some(preview) = self.file_preview.as_ref() else {
+            return;
+        };
+        if !Self::preview_supports_rendered_markdown(preview) {
+            return;
+        }
+
+        self.file_preview_mode = match self.file_preview_mode {
+            FilePreviewMode::Code => FilePreviewMode::Preview,
+            FilePreviewMode::Preview => FilePreviewMode::Code,
+        };
+        self.preview_selecting = false;
+        cx.notify();
+    }
+
+    fn render_preview_code_row(
+        language: PreviewLanguage,
+        selection: Option<(usize, usize)>,
+        active_line: Option<usize>,
+        persistent_match: Option<&PreviewSearchMatch>,
+        line: &str,
+        index: usize,
+        view_handle: &WeakEntity<Self>,
+    ) -> Div {
+        let segments = Self::highlight_line(line, language);
+        let rendered_segments = persistent_match
+            .filter(|search_match| search_match.line_index == index)
+            .map(|search_match| {
+                Self::apply_preview_search_match_segments(&segments, &search_match.query)
+            })
+            .unwrap_or_else(|| {
+                segments
+                    .iter()
+                    .map(|segment| PreviewSearchMatchSegment {
+                        text: segment.text.clone(),
+                        color: segment.color,
+                        bold: segment.bold,
+                        matched: false,
+                    })
+                    .collect::<Vec<_>>()
+            });
+        let is_selected = selection
+            .map(|(start, end)| index >= start && index <= end)
+            .unwrap_or(false);
+        let is_active_line = active_line == Some(index);
+
+        let mut row = div()
+            .h(px(22.0))
+            .min_h(px(22.0))
+            .flex()
+            .items_center()
+            .gap(px(12.0))
+            .cursor(CursorStyle::IBeam)
+            .child(
+                div()
+                    .w(px(44.0))
+                    .text_size(px(11.0))
+                    .text_color(rgb(if is_selected { 0xbdd9ff } else { 0x5f6b7a }))
+                    .font_family("Cascadia Code")
+                    .child((index + 1).to_string()),
+            )
+            .child(
+                div()
+                    .flex_1()
+                    .flex()
+                    .items_center()
+                    .gap(px(0.0))
+                    .overflow_hidden()
+                    .text_size(px(12.0))
+                    .font_family("Cascadia Code")
+                    .whitespace_nowrap()
+                    .children(if rendered_segments.is_empty() {
+                        vec![
+                            div()
+                                .text_color(rgb(0xd8dee9))
+                                .child(" ")
+                                .into_any_element(),
+                        ]
+                    } else {
+                        rendered_segments
+                            .into_iter()
+                            .map(|segment| {
+                                let mut span =
+                                    div().text_color(rgb(segment.color)).child(segment.text);
+                                if segment.matched {
+                                    span = span.px(px(1.0)).rounded(px(3.0)).bg(rgb(0x5c4a16));
+                                }
+                                if segment.bold {
+                                    span = span.font_weight(FontWeight::BOLD);
+                                }
+                                span.into_any_element()
+                            })
+                            .collect::<Vec<_>>()
+                    }),
+            );
+
+        let mouse_down_handle = view_handle.clone();
+        row = row.on_mouse_down(MouseButton::Left, move |event, _window, cx| {
+            let _ = mouse_down_handle.update(cx, |view, cx| {
+                view.on_preview_line_mouse_down_at(index, event, cx);
+            });
+        });
+
+        let mouse_move_handle = view_handle.clone();
+        row = row.on_mouse_move(move |event, _window, cx| {
+            let _ = mouse_move_handle.update(cx, |view, cx| {
+                view.on_preview_line_mouse_move_at(index, event, cx);
+            });
+        });
+
+        if is_selected {
+            row = row.bg(rgb(0x1a2f4a)).rounded(px(4.0));
+        } else if is_active_line {
+            row = row.bg(rgb(0x122033)).rounded(px(4.0));
+        }
+
+        row
+    }
+
+    pub fn set_recent(&mut self, recent: Vec<RecentEntry>, cx: &mut Context<Self>) {
+        if let TabViewMode::Welcome(ref welcome) = self.mode {
+            let _ = welcome.update(cx, |view, cx| {
+                view.set_recent(recent, cx);
+            });
+        }
+    }
+
+    pub fn set_settings_section(&mut self, section: &str, cx: &mut Context<Self>) {
+        if let TabViewMode::Settings(ref settings) = self.mode {
+            let _ = settings.update(cx, |view, cx| {
+                view.set_active_section(section, cx);
+            });
+        }
+    }
+
+    fn render_file_preview(&self, preview: &FilePreviewState, cx: &Context<Self>) -> Div {
+        let file_name = preview
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| preview.path.to_string_lossy().to_string());
+        let close_handle = cx.entity().downgrade();
+        let language_label = Self::preview_language_label(preview.language);
+        let supports_markdown_preview = Self::preview_supports_rendered_markdown(preview);
+        let show_rendered_preview =
+            supports_markdown_preview && self.file_preview_mode == FilePreviewMode::Preview;
+        let preview_view_handle = cx.entity().downgrade();
+        let markdown_toggle: AnyElement = if supports_markdown_preview {
+            div()
+                .size(px(28.0))
+                .rounded(px(6.0))
+                .flex()
+                .items_center()
+                .justify_center()
+                .bg(if show_rendered_preview {
+                    rgb(0x162235)
+                } else {
+                    rgb(0x121212)
+                })
+                .border_1()
+                .border_color(if show_rendered_preview {
+                    rgb(0x2f4f7a)
+                } else {
+                    rgb(0x2a2a2a)
+                })
+                .hover(|style| style.bg(rgb(0x1a1f2a)).border_color(rgb(0x3a3a3a)))
+                .child(
+                    lucide_icon(
+                        if show_rendered_preview {
+                            Icon::EyeOff
+                        } else {
+                            Icon::Eye
+                        },
+                        14.0,
+                        if show_rendered_preview {
+                            0x8fb7ff
+                        } else {
+                            0x9a9a9a
+                        },
+                    )
+                    .cursor(CursorStyle::PointingHand),
+                )
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(Self::on_toggle_file_preview_mode),
+                )
+                .into_any_element()
+        } else {
+            div().into_any_element()
+        };
+        let runbook = self
+            .runbook
+            .as_ref()
+            .filter(|runbook| show_rendered_preview && runbook.path == preview.path);
+        let run_all_button: AnyElement = match runbook {
+            Some(runbook) if !self.runbook_fences().is_empty() => {
+                let busy = runbook.is_busy();
+                div()
+                    .h(px(28.0))
+                    .px(px(10.0))
+                    .rounded(px(6.0))
+                    .flex()
+                    .items_center()
+                    .gap(px(6.0))
+                    .bg(rgb(0x121212))
+                    .border_1()
+                    .border_color(rgb(0x2a2a2a))
+                    .hover(|style| style.bg(rgb(0x1a1f2a)).border_color(rgb(0x3a3a3a)))
+                    .cursor(CursorStyle::PointingHand)
+                    .text_size(px(11.0))
+                    .text_color(rgb(0xcfd8e3))
+                    .child(lucide_icon(
+                        if busy { Icon::Square } else { Icon::ListChecks },
+                        13.0,
+                        0x9a9a9a,
+                    ))
+                    .child(if busy { "Stop" } else { "Run all" })
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |view, _event: &MouseDownEvent, _window, cx| {
+                            cx.stop_propagation();
+                            if busy {
+                                view.stop_runbook(cx);
+                            } else {
+                                let fences = view.runbook_fences();
+                                view.run_runbook_fences(fences, cx);
+                            }
+                        }),
+                    )
+                    .into_any_element()
+            }
+            _ => div().into_any_element(),
+        };
+        let runbook_bar: AnyElement = match runbook {
+            Some(runbook) => self.render_runbook_bar(runbook).into_any_element(),
+            None => div().into_any_element(),
+        };
+        let is_recording = preview
+            .path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("cast"));
+        let replay_button: AnyElement = if is_recording {
+            let recording = preview.path.clone();
+            div()
+                .size(px(28.0))
+                .rounded(px(6.0))
+                .flex()
+                .items_center()
+                .justify_center()
+                .bg(rgb(0x121212))
+                .border_1()
+                .border_color(rgb(0x2a2a2a))
+                .hover(|style| style.bg(rgb(0x1a1f2a)).border_color(rgb(0x3a3a3a)))
+                .cursor(CursorStyle::PointingHand)
+                .child(lucide_icon(Icon::Play, 14.0, 0x9a9a9a))
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |_view, _event: &MouseDownEvent, _window, cx| {
+                        cx.stop_propagation();
+                        cx.emit(TabViewEvent::ReplayRecording(recording.clone()));
+                    }),
+                )
+                .into_any_element()
+        } else {
+            div().into_any_element()
+        };
+
+        let body: AnyElement = if show_rendered_preview {
+            match &preview.kind {
+                FilePreviewKind::Text { contents, .. } => div()
+                    .id("file_preview_markdown")
+                    .track_scroll(&self.preview_scroll_handle)
+                    .flex()
+                    .flex_col()
+                    .on_mouse_down(MouseButton::Left, cx.listener(Self::on_preview_focus))
+                    .on_mouse_up(MouseButton::Left, cx.listener(Self::on_preview_mouse_up))
+                    .on_mouse_up_out(MouseButton::Left, cx.listener(Self::on_preview_mouse_up))
+                    .overflow_y_scroll()
+                    .scrollbar_width(px(12.0))
+                    .flex_1()
+                    .min_h(px(0.0))
+                    .min_w(px(0.0))
+                    .p(px(20.0))
+                    .child(
+                        div()
+                            .max_w(px(960.0))
+                            .min_h(px(0.0))
+                            .min_w(px(0.0))
+                            .pr(px(24.0))
+                            .child(self.render_runbook_markdown(contents, cx)),
+                    )
+                    .into_any_element(),
+                _ => div().into_any_element(),
+            }
+        } else {
+            match &preview.kind {
+                FilePreviewKind::Text { lines, .. } => {
+                    let lines = Arc::clone(lines);
+                    let selection = self.normalize_preview_selection();
+                    let language = preview.language;
+                    let active_line = self.preview_active_line;
+                    let persistent_match = self.preview_search_match.clone();
+                    div()
+                        .id("file_preview_text")
+                        .flex()
+                        .flex_col()
+                        .on_mouse_down(MouseButton::Left, cx.listener(Self::on_preview_focus))
+                        .on_mouse_up(MouseButton::Left, cx.listener(Self::on_preview_mouse_up))
+                        .on_mouse_up_out(MouseButton::Left, cx.listener(Self::on_preview_mouse_up))
+                        .flex_1()
+                        .min_h(px(0.0))
+                        .min_w(px(0.0))
+                        .p(px(16.0))
+                        .child(
+                            uniform_list("file_preview_text_list", lines.len(), {
+                                let lines = Arc::clone(&lines);
+                                let view_handle = preview_view_handle.clone();
+                                move |range, _window, _cx| {
+                                    range
+                                        .map(|index| {
+                                            Self::render_preview_code_row(
+                                                language,
+                                                selection,
+                                                active_line,
+                                                persistent_match.as_ref(),
+                                                &lines[index],
+                                                index,
+                                                &view_handle,
+                                            )
+                                        })
+                                        .collect::<Vec<_>>()
+                                }
+                            })
+                            .track_scroll(self.preview_code_scroll_handle.clone())
+                            .w_full()
+                            .h_full(),
+                        )
+                        .into_any_element()
+                }
+                FilePreviewKind::Image => div()
+                    .id("file_preview_image")
+                    .track_scroll(&self.preview_scroll_handle)
+                    .flex()
+                    .flex_col()
+                    .overflow_y_scroll()
+                    .scrollbar_width(px(12.0))
+                    .flex_1()
+                    .min_h(px(0.0))
+                    .min_w(px(0.0))
+                    .p(px(20.0))
+                    .child(
+                        div()
+                            .flex()
+                            .items_center()
+                            .justify_center()
+                            .min_h(px(0.0))
+                            .child(
+                                img(preview.path.clone())
+                                    .object_fit(ObjectFit::Contain)
+                                    .with_fallback(|| {
+                                        div()
+                                            .text_size(px(12.0))
+                                            .text_color(rgb(0xff7b72))
+                                            .child("Failed to render image.")
+                                            .into_any_element()
+                                    }),
+                            ),
+                    )
+                    .into_any_element(),
+                FilePreviewKind::Unsupported { message } => div()
+                    .flex()
+                    .flex_1()
+                    .min_h(px(0.0))
+                    .items_center()
+                    .justify_center()
+                    .child(
+                        div()
+                            .text_size(px(12.0))
+                            .text_color(rgb(0x8a97a8))
+                            .child(message.clone()),
+                    )
+                    .into_any_element(),
+            }
+        };
+
+        div()
+            .flex()
+            .flex_col()
+            .flex_1()
+            .min_h(px(0.0))
+            .min_w(px(0.0))
+            .overflow_hidden()
+            .bg(rgb(0x0a0a0a))
+            .child(
+                div()
+                    .flex()
+                    .flex_none()
+                    .items_center()
+                    .justify_between()
+                    .px(px(16.0))
+                    .py(px(12.0))
+                    .border_b_1()
+                    .border_color(rgb(0x1f1f1f))
+                    .child(
+                        div()
+                            .flex()
+                            .flex_col()
+                            .gap(px(4.0))
+                            .child(
+                                div()
+                                    .text_size(px(13.0))
+                                    .text_color(rgb(0xf0f0f0))
+                                    .child(file_name),
+                            )
+                            .child(
+                                div()
+                                    .text_size(px(11.0))
+                                    .text_color(rgb(0x7f8b99))
+                                    .child(preview.relative_label.clone()),
+                            ),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .items_center()
+                            .gap(px(8.0))
+                            .child(run_all_button)
+                            .child(replay_button)
+                            .child(markdown_toggle)
+                            .child(
+                                div()
+                                    .px(px(8.0))
+                                    .py(px(4.0))
+                                    .rounded(px(999.0))
+                                    .bg(rgb(0x121826))
+                                    .border_1()
+                                    .border_color(rgb(0x26324d))
+                                    .text_size(px(11.0))
+                                    .text_color(rgb(0x8fb7ff))
+                                    .child(language_label),
+                            ),
+                    )
+                    .child(
+                        div()
+                            .size(px(28.0))
+                            .rounded(px(6.0))
+                            .flex()
+                            .items_center()
+                            .justify_center()
+                            .bg(rgb(0x121212))
+                            .border_1()
+                            .border_color(rgb(0x2a2a2a))
+                            .hover(|style| style.bg(rgb(0x1a1a1a)).border_color(rgb(0x3a3a3a)))
+                            .child(
+                                lucide_icon(Icon::X, 14.0, 0x9a9a9a)
+                                    .cursor(CursorStyle::PointingHand),
+                            )
+                            .on_mouse_down(MouseButton::Left, move |_event, _window, cx| {
+                                cx.stop_propagation();
+                                let _ = close_handle.update(cx, |view, cx| {
+                                    view.close_file_preview(cx);
+                                });
+                            }),
+                    ),
+            )
+            .child(runbook_bar)
+            .child(body)
+    }
+
+    /// Starts tracking the local server URLs in `lines`, which were just
+    /// added to the current block.
+    fn detect_dev_servers(&mut self, lines: &[String], cx: &mut Context<Self>) {
+        if self.pty.is_none() {
+            return;
+        }
+        let block_index = self
+            .running_command
+            .as_ref()
+            .and_then(|running| running.block_index)
+            .or_else(|| self.blocks.len().checked_sub(1));
+        let mut found = false;
+        for line in lines {
+            for url in dev_server::find_server_urls(line) {
+                if self
+                    .dev_servers
+                    .iter()
+                    .any(|server| server.port == url.port)
+                {
+                    continue;
+                }
+                self.dev_servers.push(DevServer {
+                    url: url.url,
+                    port: url.port,
+                    block_index,
+                    pid: None,
+                    confirmed: false,
+                    assumed: false,
+                    stopping: false,
+                    checks_left: DEV_SERVER_CHECKS,
+                });
+                found = true;
+            }
+        }
+        if found {
+            self.poll_dev_servers(cx);
+        }
+    }
+
+    /// Checks the listening ports of the shell's processes until no server
+    /// is left to track.
+    fn poll_dev_servers(&mut self, cx: &mut Context<Self>) {
+        if self.dev_server_polling {
+            return;
+        }
+        self.dev_server_polling = true;
+        cx.spawn(|view: WeakEntity<Self>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                loop {
+                    cx.background_executor()
+                        .timer(DEV_SERVER_POLL_INTERVAL)
+                        .await;
+                    let Ok(shell) = view.update(&mut cx, |view, _| view.pty.as_ref()?.process_id())
+                    else {
+                        break;
+                    };
+                    let ports = cx
+                        .background_executor()
+                        .spawn(async move { shell.and_then(dev_server::listening_ports) })
+                        .await;
+                    let keep_polling = view
+                        .update(&mut cx, |view, cx| view.apply_listening_ports(ports, cx))
+                        .unwrap_or(false);
+                    if !keep_polling {
+                        break;
+                    }
+                }
+            }
+        })
+        .detach();
+    }
+
+    /// Confirms servers whose port is listening and drops those that
+    /// stopped. Without port information every URL counts as a server, and
+    /// polling stops since it would learn nothing more.
+    fn apply_listening_ports(
+        &mut self,
+        ports: Option<Vec<ListeningPort>>,
+        cx: &mut Context<Self>,
+    ) -> bool {
+        let has_ports = ports.is_some();
+        match ports {
+            Some(ports) => self.dev_servers.retain_mut(|server| {
+                match ports.iter().find(|port| port.port == server.port) {
+                    Some(port) => {
+                        server.confirmed = true;
+                        server.pid = Some(port.pid);
+                        true
+                    }
+                    None if server.confirmed => false,
+                    None => {
+                        server.checks_left = server.checks_left.saturating_sub(1);
+                        server.checks_left > 0
+                    }
+                }
+            }),
+            None => {
+                // A URL whose command already finished has nothing to show.
+                let running_block = self
+                    .running_command
+                    .as_ref()
+                    .and_then(|running| running.block_index);
+                self.dev_servers.retain_mut(|server| {
+                    if server.confirmed {
+                        return true;
+                    }
+                    let running = running_block.is_some() && server.block_index == running_block;
+                    server.confirmed = running;
+                    server.assumed = running;
+                    running
+                });
+            }
+        }
+        self.sync_tab_dev_server(cx);
+        cx.notify();
+        self.dev_server_polling = has_ports
+            && !self.dev_servers.is_empty()
+            && self
+                .pty
+                .as_ref()
+                .is_some_and(|pty| pty.process_id().is_some());
+        self.dev_server_polling
+    }
+
+    fn sync_tab_dev_server(&mut self, cx: &mut Context<Self>) {
+        let url = self
+            .dev_servers
+            .iter()
+            .find(|server| server.confirmed)
+            .map(|server| server.url.clone());
+        if url != self.tab_dev_server {
+            self.tab_dev_server = url.clone();
+            cx.emit(TabViewEvent::DevServerChanged(url));
+        }
+    }
+
+    fn stop_dev_server(&mut self, port: u16, cx: &mut Context<Self>) {
+        let Some(server) = self
+            .dev_servers
+            .iter_mut()
+            .find(|server| server.port == port)
+        else {
+            return;
+        };
+        if let Some(pid) = server.pid
+            && send_signal(pid, ProcessSignal::Terminate).is_ok()
+        {
+            server.stopping = true;
+        }
+        cx.notify();
+    }
+
+    fn render_dev_server_chip(&self, server: &DevServer, cx: &Context<Self>) -> Div {
+        let label = server
+            .url
+            .split_once("://")
+            .map_or(server.url.as_str(), |(_, rest)| rest)
+            .trim_end_matches('/')
+            .to_string();
+        let icon_button = |icon: Icon| {
+            div()
+                .flex_none()
+                .px(px(4.0))
+                .py(px(2.0))
+                .rounded(px(4.0))
+                .hover(|this| this.bg(rgb(0x1d3a2a)))
+                .cursor(CursorStyle::PointingHand)
+                .child(lucide_icon(icon, 11.0, 0x9fdcb4))
+        };
+        let open_url = server.url.clone();
+        let copy_url = server.url.clone();
+        let port = server.port;
+        div()
+            .flex()
+            .flex_none()
+            .items_center()
+            .gap(px(2.0))
+            .pl(px(6.0))
+            .pr(px(2.0))
+            .py(px(1.0))
+            .rounded(px(5.0))
+            .bg(rgb(0x10251a))
+            .border_1()
+            .border_color(rgb(0x2b6b45))
+            .text_size(px(11.0))
+            .text_color(rgb(0x9fdcb4))
+            .child(
+                div()
+                    .flex()
+                    .items_center()
+                    .gap(px(4.0))
+                    .cursor(CursorStyle::PointingHand)
+                    .child(lucide_icon(Icon::Globe, 11.0, 0x7ee787))
+                    .child(if server.stopping {
+                        format!("{label} · stopping")
+                    } else {
+                        label
+                    })
+                    .on_mouse_down(MouseButton::Left, move |_event, _window, cx| {
+                        cx.stop_propagation();
+                        let _ = webbrowser::open(&open_url);
+                    }),
+            )
+            .child(icon_button(Icon::Copy).on_mouse_down(
+                MouseButton::Left,
+                move |_event, _window, cx| {
+                    cx.stop_propagation();
+                    cx.write_to_clipboard(ClipboardItem::new_string(copy_url.clone()));
+                },
+            ))
+            .children(
+                server
+                    .pid
+                    .filter(|_| !server.stopping && !server.assumed)
+                    .map(|_| {
+                        icon_button(Icon::Square).on_mouse_down(
+                            MouseButton::Left,
+                            cx.listener(move |view, _event: &MouseDownEvent, _window, cx| {
+                                cx.stop_propagation();
+                                view.stop_dev_server(port, cx);
+                            }),
+                        )
+                    }),
+            )
+    }
+
+    fn toggle_process_panel(&mut self, cx: &mut Context<Self>) {
+        if self.process_panel.take().is_some() {
+            cx.notify();
+            return;
+        }
+        self.process_panel_generation += 1;
+        let generation = self.process_panel_generation;
+        self.process_panel = Some(ProcessPanel {
+            processes: Vec::new(),
+            sampler: ProcessSampler::default(),
+            generation,
+            message: None,
+        });
+        cx.notify();
+
+        cx.spawn(move |view: WeakEntity<Self>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                loop {
+                    let Ok(Some((shell, mut sampler))) = view.update(&mut cx, |view, _| {
+                        let panel = view
+                            .process_panel
+                            .as_ref()
+                            .filter(|panel| panel.generation == generation)?;
+                        let shell = view.pty.as_ref().and_then(|pty| pty.process_id());
+                        Some((shell, panel.sampler.clone()))
+                    }) else {
+                        break;
+                    };
+                    let (sampler, processes) = cx
+                        .background_executor()
+                        .spawn(async move {
+                            let processes = shell.and_then(|shell| sampler.sample(shell));
+                            (sampler, processes)
+                        })
+                        .await;
+                    let updated = view.update(&mut cx, |view, cx| {
+                        let Some(panel) = view
+                            .process_panel
+                            .as_mut()
+                            .filter(|panel| panel.generation == generation)
+                        else {
+                            return false;
+                        };
+                        panel.sampler = sampler;
+                        match processes {
+                            Some(processes) => panel.processes = processes,
+                            None => {
+                                panel.processes.clear();
+                                panel.message = Some(
+                                    "Process details need /proc, which this system does not have."
+                                        .to_string(),
+                                );
+                            }
+                        }
+                        cx.notify();
+                        true
+                    });
+                    if !updated.unwrap_or(false) {
+                        break;
+                    }
+                    cx.background_executor().timer(PROCESS_POLL_INTERVAL).await;
+                }
+            }
+        })
+        .detach();
+    }
+
+    fn signal_process(&mut self, pid: u32, signal: ProcessSignal, cx: &mut Context<Self>) {
+        let result = send_signal(pid, signal);
+        if let Some(panel) = self.process_panel.as_mut() {
+            panel.message = result
+                .err()
+                .map(|err| format!("Failed to send SIG{} to {pid}: {err:#}", signal.label()));
+        }
+        cx.notify();
+    }
+
+    /// Whether block `index` holds the job the terminal is busy with.
+    fn runs_foreground_job(&self, index: usize) -> bool {
+        let block = match self.running_command.as_ref() {
+            Some(running) => running.block_index,
+            // Without the shell integration a command runs until the prompt
+            // is back.
+            None if !self.input_visible && self.replay.is_none() => {
+                self.blocks.len().checked_sub(1)
+            }
+            None => None,
+        };
+        block == Some(index)
+            && self
+                .pty
+                .as_ref()
+                .is_some_and(|pty| pty.has_foreground_job().unwrap_or(true))
+    }
+
+    fn render_process_panel(&self, panel: &ProcessPanel, cx: &Context<Self>) -> Div {
+        let header = div()
+            .flex()
+            .items_center()
+            .gap(px(8.0))
+            .child(lucide_icon(Icon::Activity, 13.0, 0x8eb8ff))
+            .child(
+                div()
+                    .text_size(px(12.0))
+                    .text_color(rgb(0xe6e6e6))
+                    .child("Processes"),
+            )
+            .child(
+                div()
+                    .flex_1()
+                    .text_size(px(11.0))
+                    .text_color(rgb(0x7f8b99))
+                    .child(match panel.processes.len() {
+                        0 => String::new(),
+                        1 => "shell only".to_string(),
+                        count => format!("{} under the shell", count - 1),
+                    }),
+            )
+            .child(
+                div()
+                    .flex_none()
+                    .cursor(CursorStyle::PointingHand)
+                    .child(lucide_icon(Icon::X, 13.0, 0x9a9a9a))
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|view, _event: &MouseDownEvent, _window, cx| {
+                            cx.stop_propagation();
+                            view.toggle_process_panel(cx);
+                        }),
+                    ),
+            );
+
+        let column = |width: f32, text: String| {
+            div()
+                .flex_none()
+                .w(px(width))
+                .text_right()
+                .text_color(rgb(0x9a9a9a))
+                .child(text)
+        };
+        let rows = panel.processes.iter().map(|process| {
+            let pid = process.pid;
+            let signals = (process.depth > 0).then(|| {
+                div()
+                    .flex()
+                    .flex_none()
+                    .gap(px(4.0))
+                    .children(ProcessSignal::ALL.into_iter().map(|signal| {
+                        div()
+                            .px(px(5.0))
+                            .rounded(px(4.0))
+                            .border_1()
+                            .border_color(rgb(0x2a2a2a))
+                            .text_size(px(10.0))
+                            .text_color(if signal == ProcessSignal::Kill {
+                                rgb(0xff7b72)
+                            } else {
+                                rgb(0xb8b8b8)
+                            })
+                            .cursor(CursorStyle::PointingHand)
+                            .hover(|this| this.bg(rgb(0x242424)))
+                            .child(signal.label())
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(move |view, _event: &MouseDownEvent, _window, cx| {
+                                    cx.stop_propagation();
+                                    view.signal_process(pid, signal, cx);
+                                }),
+                            )
+                    }))
+            });
+            div()
+                .flex()
+                .items_center()
+                .gap(px(10.0))
+                .py(px(2.0))
+                .font_family("Cascadia Code")
+                .text_size(px(11.0))
+                .child(column(56.0, pid.to_string()))
+                .child(
+                    div()
+                        .flex_1()
+                        .min_w(px(0.0))
+                        .pl(px(process.depth as f32 * 14.0))
+                        .text_color(rgb(0xdddddd))
+                        .truncate()
+                        .child(self.secret_scanner().mask(&process.command).into_owned()),
+                )
+                .child(column(52.0, format!("{:.1}%", process.cpu_percent)))
+                .child(column(72.0, format_memory(process.memory_bytes)))
+                .child(column(72.0, format_command_duration(process.runtime)))
+                .child(div().flex_none().w(px(112.0)).children(signals))
+        });
+
+        div()
+            .flex()
+            .flex_col()
+            .gap(px(6.0))
+            .px(px(12.0))
+            .py(px(8.0))
+            .rounded(px(10.0))
+            .bg(rgb(0x141414))
+            .border_1()
+            .border_color(rgb(0x2a2a2a))
+            .child(header)
+            .children(panel.message.clone().map(|message| {
+                div()
+                    .text_size(px(11.0))
+                    .text_color(rgb(0xffb4a8))
+                    .child(message)
+            }))
+            .child(
+                div()
+                    .id("process_panel_rows")
+                    .flex()
+                    .flex_col()
+                    .max_h(px(200.0))
+                    .overflow_y_scroll()
+                    .children(rows),
+            )
+    }
 
-            if ch == '"' || ch == '\'' {
-                let quote = ch;
-                let start = index;
-                index += 1;
-                while index < chars.len() {
-                    let current = chars[index];
-                    index += 1;
-                    if current == '\\' && index < chars.len() {
-                        index += 1;
-                        continue;
-                    }
-                    if current == quote {
-                        break;
-                    }
-                }
-                segments.push(HighlightSegment {
-                    text: chars[start..index].iter().collect(),
-                    color: 0xce9178,
-                    bold: false,
-                });
-                continue;
-            }
+    /// Keeps the runbook of the previewed Markdown file, loading its saved
+    /// results when another file is opened.
+    fn load_runbook(&mut self) {
+        let Some(path) = self
+            .file_preview
+            .as_ref()
+            .filter(|preview| Self::preview_supports_rendered_markdown(preview))
+            .map(|preview| preview.path.clone())
+        else {
+            return;
+        };
+        if self
+            .runbook
+            .as_ref()
+            .is_some_and(|runbook| runbook.path == path || runbook.is_busy())
+        {
+            return;
+        }
+        self.runbook = Some(RunbookState::new(path));
+    }
 
-            if ch.is_ascii_digit() {
-                let start = index;
-                index += 1;
-                while index < chars.len()
-                    && (chars[index].is_ascii_digit() || matches!(chars[index], '.' | '_'))
+    /// The runnable fences of the previewed file, as key and command.
+    fn runbook_fences(&self) -> Vec<(String, String)> {
+        let Some(FilePreviewState {
+            kind: FilePreviewKind::Text { contents, .. },
+            ..
+        }) = &self.file_preview
+        else {
+            return Vec::new();
+        };
+        parse_markdown_blocks(contents)
+            .into_iter()
+            .filter_map(|block| match block {
+                MarkdownBlock::CodeBlock { language, lines }
+                    if runbook::is_runnable(language.as_deref()) =>
                 {
-                    index += 1;
+                    Some((
+                        runbook::fence_key(&lines),
+                        runbook::fence_command(language.as_deref(), &lines),
+                    ))
                 }
-                segments.push(HighlightSegment {
-                    text: chars[start..index].iter().collect(),
-                    color: 0xb5cea8,
-                    bold: false,
-                });
-                continue;
-            }
+                _ => None,
+            })
+            .filter(|(_, command)| !command.is_empty())
+            .collect()
+    }
 
-            if ch.is_ascii_alphabetic() || ch == '_' || ch == '$' {
-                let start = index;
-                index += 1;
-                while index < chars.len()
-                    && (chars[index].is_ascii_alphanumeric()
-                        || matches!(chars[index], '_' | '-' | '$'))
-                {
-                    index += 1;
+    /// Runs `fences` in order, stopping at the first failure. Asks for
+    /// their variables first.
+    fn run_runbook_fences(&mut self, fences: Vec<(String, String)>, cx: &mut Context<Self>) {
+        let Some(runbook) = self.runbook.as_mut() else {
+            return;
+        };
+        if fences.is_empty() || runbook.is_busy() {
+            return;
+        }
+        runbook.error = None;
+        let mut names: Vec<String> = Vec::new();
+        for (_, command) in &fences {
+            for name in runbook::variables(command) {
+                if !names.contains(&name) {
+                    names.push(name);
                 }
-                let word: String = chars[start..index].iter().collect();
-                let lower_word = word.to_ascii_lowercase();
-                let is_keyword = keywords.iter().any(|keyword| *keyword == lower_word);
-                segments.push(HighlightSegment {
-                    text: word,
-                    color: if is_keyword { 0x79c0ff } else { 0xd8dee9 },
-                    bold: is_keyword,
-                });
-                continue;
             }
-
-            segments.push(HighlightSegment {
-                text: ch.to_string(),
-                color: 0x8b949e,
-                bold: false,
-            });
-            index += 1;
         }
-
-        segments
-    }
-
-    pub fn open_file_preview(&mut self, path: PathBuf, cx: &mut Context<Self>) {
-        if !matches!(self.mode, TabViewMode::Terminal) {
+        if let Some(first) = names.first() {
+            let value = runbook.results.variables.get(first).cloned();
+            runbook.prompt = Some(RunbookPrompt {
+                value: value.unwrap_or_default(),
+                names,
+                index: 0,
+                fences,
+            });
+            cx.notify();
             return;
         }
-
-        let root = expand_tilde(&self.current_path);
-        self.file_preview = Some(Self::build_file_preview_state(&path, Some(root.as_path())));
-        self.preview_scroll_handle
-            .set_offset(point(px(0.0), px(0.0)));
-        self.preview_code_scroll_handle
-            .0
-            .borrow()
-            .base_handle
-            .set_offset(point(px(0.0), px(0.0)));
-        self.preview_terminal_resize_dragging = false;
-        self.preview_terminal_resize_start_y = None;
-        self.preview_terminal_resize_start_height = self.preview_terminal_height;
-        self.preview_search_match = None;
-        self.preview_active_line = Some(0);
-        self.clear_preview_selection();
-        self.focus_preview();
-        self.file_preview_mode = FilePreviewMode::Code;
+        runbook.queue = fences.into();
+        self.run_next_runbook_fence(cx);
         cx.notify();
     }
 
-    pub fn open_file_preview_at_search_result(
-        &mut self,
-        path: PathBuf,
-        line_number: usize,
-        query: String,
-        cx: &mut Context<Self>,
-    ) {
-        if !matches!(self.mode, TabViewMode::Terminal) {
+    /// Sends the next fence of the queue once the shell is at an empty
+    /// prompt.
+    fn run_next_runbook_fence(&mut self, cx: &mut Context<Self>) {
+        let idle = self.is_at_empty_prompt();
+        let Some(runbook) = self.runbook.as_mut() else {
+            return;
+        };
+        if runbook.pending.is_some() || runbook.running.is_some() || runbook.queue.is_empty() {
+            return;
+        }
+        if !idle {
+            if self.running_command.is_none() && self.input_visible {
+                runbook.queue.clear();
+                runbook.error =
+                    Some("Clear the command line of the terminal, then run again.".to_string());
+                cx.notify();
+            }
             return;
         }
+        let Some((key, command)) = runbook.queue.pop_front() else {
+            return;
+        };
+        let command = runbook::fill_variables(&command, &runbook.results.variables);
+        runbook.pending = Some((key, command.clone()));
+        self.run_command(command, cx);
+    }
 
-        let root = expand_tilde(&self.current_path);
-        let preview = Self::build_file_preview_state(&path, Some(root.as_path()));
-        let line_count = match &preview.kind {
-            FilePreviewKind::Text { lines, .. } => lines.len(),
-            _ => 0,
+    /// Saves the output of the fence running in block `index`. Run all goes
+    /// on only after a success.
+    fn finish_runbook_fence(&mut self, index: usize, exit_code: Option<i32>) {
+        let Some(runbook) = self.runbook.as_mut() else {
+            return;
         };
-        let preview_search_match =
-            Self::build_preview_search_match(line_number, &query, line_count)
-                .map(|(line_index, query)| PreviewSearchMatch { line_index, query });
-        let active_line = preview_search_match
+        if runbook
+            .running
             .as_ref()
-            .map(|search_match| search_match.line_index)
-            .unwrap_or(0);
-
-        self.file_preview = Some(preview);
-        self.preview_scroll_handle
-            .set_offset(point(px(0.0), px(0.0)));
-        self.preview_code_scroll_handle
-            .scroll_to_item_strict(active_line, ScrollStrategy::Center);
-        self.preview_terminal_resize_dragging = false;
-        self.preview_terminal_resize_start_y = None;
-        self.preview_terminal_resize_start_height = self.preview_terminal_height;
-        self.preview_search_match = preview_search_match;
-        self.preview_active_line = Some(active_line);
-        self.clear_preview_selection();
-        self.focus_preview();
-        self.file_preview_mode = FilePreviewMode::Code;
-        cx.notify();
+            .is_none_or(|(_, running)| *running != index)
+        {
+            return;
+        }
+        let Some((key, _)) = runbook.running.take() else {
+            return;
+        };
+        let Some(block) = self.blocks.get(index) else {
+            return;
+        };
+        let skip = block
+            .output_lines
+            .len()
+            .saturating_sub(MAX_SAVED_OUTPUT_LINES);
+        // Masked as a whole, so a key that began before the kept lines is
+        // still hidden.
+        let output_lines = self.masked_lines(&block.output_lines).split_off(skip);
+        let run = FenceRun {
+            command: block.command.clone(),
+            output_lines,
+            exit_code,
+            finished_at: chrono::Utc::now().timestamp(),
+        };
+        let Some(runbook) = self.runbook.as_mut() else {
+            return;
+        };
+        runbook.results.runs.insert(key, run);
+        if exit_code != Some(0) {
+            runbook.queue.clear();
+        }
+        if let Err(err) = runbook.results.save(&runbook.path) {
+            runbook.error = Some(format!("Failed to save results: {err:#}"));
+        }
     }
 
-    fn close_file_preview(&mut self, cx: &mut Context<Self>) {
-        self.file_preview = None;
-        self.preview_scroll_handle
-            .set_offset(point(px(0.0), px(0.0)));
-        self.preview_code_scroll_handle
-            .0
-            .borrow()
-            .base_handle
-            .set_offset(point(px(0.0), px(0.0)));
-        self.preview_terminal_resize_dragging = false;
-        self.preview_terminal_resize_start_y = None;
-        self.preview_terminal_resize_start_height = self.preview_terminal_height;
-        self.preview_search_match = None;
-        self.preview_active_line = None;
-        self.clear_preview_selection();
-        self.blur_preview();
-        self.file_preview_mode = FilePreviewMode::Code;
+    fn stop_runbook(&mut self, cx: &mut Context<Self>) {
+        if let Some(runbook) = self.runbook.as_mut() {
+            runbook.queue.clear();
+            runbook.pending = None;
+            runbook.prompt = None;
+        }
         cx.notify();
     }
 
-    fn toggle_file_preview_mode(&mut self, cx: &mut Context<Self>) {
-        let Some(preview) = self.file_preview.as_ref() else {
-            return;
+    fn handle_runbook_prompt_key(&mut self, event: &KeyDownEvent, cx: &mut Context<Self>) -> bool {
+        let Some(runbook) = self.runbook.as_mut() else {
+            return false;
         };
-        if !Self::preview_supports_rendered_markdown(preview) {
-            return;
-        }
-
-        self.file_preview_mode = match self.file_preview_mode {
-            FilePreviewMode::Code => FilePreviewMode::Preview,
-            FilePreviewMode::Preview => FilePreviewMode::Code,
+        let Some(prompt) = runbook.prompt.as_mut() else {
+            return false;
         };
-        self.preview_selecting = false;
+        let modifiers = event.keystroke.modifiers;
+        let key = event.keystroke.key.as_str();
+        match key {
+            "enter" | "return" | "numpadenter" => {
+                let name = prompt.names[prompt.index].clone();
+                let value = std::mem::take(&mut prompt.value);
+                prompt.index += 1;
+                let next = prompt.names.get(prompt.index).cloned();
+                runbook.results.variables.insert(name, value);
+                match next {
+                    Some(next) => {
+                        let value = runbook.results.variables.get(&next).cloned();
+                        if let Some(prompt) = runbook.prompt.as_mut() {
+                            prompt.value = value.unwrap_or_default();
+                        }
+                    }
+                    None => {
+                        if let Some(prompt) = runbook.prompt.take() {
+                            runbook.queue = prompt.fences.into();
+                        }
+                        self.run_next_runbook_fence(cx);
+                    }
+                }
+            }
+            "escape" => runbook.prompt = None,
+            "backspace" => {
+                prompt.value.pop();
+            }
+            _ if (modifiers.control && key.eq_ignore_ascii_case("v"))
+                || (modifiers.shift && key.eq_ignore_ascii_case("insert")) =>
+            {
+                if let Some(text) = cx.read_from_clipboard().and_then(|item| item.text()) {
+                    prompt
+                        .value
+                        .push_str(text.lines().next().unwrap_or_default());
+                }
+            }
+            _ if modifiers.control && key.eq_ignore_ascii_case("u") => prompt.value.clear(),
+            _ if modifiers.control || modifiers.platform => return false,
+            _ => {
+                let text = match key {
+                    "space" => Some(" "),
+                    _ => event.keystroke.key_char.as_deref(),
+                };
+                if let Some(text) = text.filter(|text| !text.chars().any(char::is_control)) {
+                    prompt.value.push_str(text);
+                }
+            }
+        }
         cx.notify();
+        true
     }
 
-    fn render_preview_code_row(
-        language: PreviewLanguage,
-        selection: Option<(usize, usize)>,
-        active_line: Option<usize>,
-        persistent_match: Option<&PreviewSearchMatch>,
-        line: &str,
-        index: usize,
-        view_handle: &WeakEntity<Self>,
+    /// The variable prompt or the last error of the runbook.
+    fn render_runbook_bar(&self, runbook: &RunbookState) -> Div {
+        let bar = div()
+            .flex()
+            .flex_none()
+            .items_center()
+            .gap(px(10.0))
+            .px(px(16.0))
+            .py(px(8.0))
+            .border_b_1()
+            .border_color(rgb(0x1f1f1f))
+            .text_size(px(12.0));
+        if let Some(prompt) = &runbook.prompt {
+            return bar
+                .bg(rgb(0x101826))
+                .child(lucide_icon(Icon::Variable, 14.0, 0x8fb7ff))
+                .child(
+                    div()
+                        .flex_none()
+                        .text_color(rgb(0x8aa1b3))
+                        .child(format!("{} =", prompt.names[prompt.index])),
+                )
+                .child(
+                    div()
+                        .flex()
+                        .flex_1()
+                        .min_w(px(0.0))
+                        .px(px(8.0))
+                        .py(px(4.0))
+                        .rounded(px(6.0))
+                        .bg(rgb(0x0b0f16))
+                        .border_1()
+                        .border_color(rgb(0x2f4f7a))
+                        .font_family("Cascadia Code")
+                        .text_color(rgb(0xe6e6e6))
+                        .child(prompt.value.clone())
+                        .child(div().w(px(1.0)).h(px(14.0)).bg(rgb(0x8fb7ff))),
+                )
+                .child(
+                    div()
+                        .flex_none()
+                        .text_size(px(11.0))
+                        .text_color(rgb(0x5c6b7a))
+                        .child(format!(
+                            "{} of {} · Enter to continue, Esc to cancel",
+                            prompt.index + 1,
+                            prompt.names.len()
+                        )),
+                );
+        }
+        match &runbook.error {
+            Some(error) => bar
+                .bg(rgb(0x1f1212))
+                .text_color(rgb(0xffb4a8))
+                .child(lucide_icon(Icon::TriangleAlert, 14.0, 0xff7b72))
+                .child(error.clone()),
+            None => div(),
+        }
+    }
+
+    /// The rendered Markdown of the preview, with a run button and the last
+    /// output under each shell fence.
+    fn render_runbook_markdown(&self, contents: &str, cx: &Context<Self>) -> Div {
+        let Some(runbook) = self.runbook.as_ref().filter(|runbook| {
+            self.file_preview
+                .as_ref()
+                .is_some_and(|preview| preview.path == runbook.path)
+        }) else {
+            return render_markdown_response_content(contents, false);
+        };
+        let blocks = parse_markdown_blocks(contents);
+        let base_color: Hsla = rgb(0xd8e6f2).into();
+        div()
+            .flex_col()
+            .gap(px(8.0))
+            .children(blocks.into_iter().map(|block| match block {
+                MarkdownBlock::CodeBlock { language, lines }
+                    if runbook::is_runnable(language.as_deref()) =>
+                {
+                    self.render_runbook_fence(runbook, language, lines, cx)
+                }
+                block => render_markdown_block(block, base_color),
+            }))
+    }
+
+    fn render_runbook_fence(
+        &self,
+        runbook: &RunbookState,
+        language: Option<String>,
+        lines: Vec<String>,
+        cx: &Context<Self>,
     ) -> Div {
-        let segments = Self::highlight_line(line, language);
-        let rendered_segments = persistent_match
-            .filter(|search_match| search_match.line_index == index)
-            .map(|search_match| {
-                Self::apply_preview_search_match_segments(&segments, &search_match.query)
-            })
-            .unwrap_or_else(|| {
-                segments
-                    .iter()
-                    .map(|segment| PreviewSearchMatchSegment {
-                        text: segment.text.clone(),
-                        color: segment.color,
-                        bold: segment.bold,
-                        matched: false,
-                    })
-                    .collect::<Vec<_>>()
-            });
-        let is_selected = selection
-            .map(|(start, end)| index >= start && index <= end)
-            .unwrap_or(false);
-        let is_active_line = active_line == Some(index);
+        let key = runbook::fence_key(&lines);
+        let command = runbook::fence_command(language.as_deref(), &lines);
+        let running = runbook
+            .running
+            .as_ref()
+            .filter(|(running, _)| *running == key)
+            .and_then(|(_, index)| self.blocks.get(*index));
+        let waiting = runbook
+            .pending
+            .as_ref()
+            .is_some_and(|(pending, _)| *pending == key)
+            || runbook.queue.iter().any(|(queued, _)| *queued == key);
+        let last_run = runbook.results.runs.get(&key);
+
+        let (status, status_color) = if running.is_some() {
+            ("Running…".to_string(), 0x8fb7ff)
+        } else if waiting {
+            ("Queued".to_string(), 0x8a97a8)
+        } else if let Some(run) = last_run {
+            let when = chrono::DateTime::from_timestamp(run.finished_at, 0)
+                .map(|time| {
+                    time.with_timezone(&chrono::Local)
+                        .format("%Y-%m-%d %H:%M")
+                        .to_string()
+                })
+                .unwrap_or_default();
+            match run.exit_code {
+                Some(0) => (format!("Passed · {when}"), 0x7ee787),
+                Some(code) => (format!("Exit {code} · {when}"), 0xff7b72),
+                None => (format!("Finished · {when}"), 0x8a97a8),
+            }
+        } else {
+            (String::new(), 0x8a97a8)
+        };
 
-        let mut row = div()
-            .h(px(22.0))
-            .min_h(px(22.0))
+        let mut header = div()
             .flex()
             .items_center()
-            .gap(px(12.0))
-            .cursor(CursorStyle::IBeam)
+            .gap(px(8.0))
             .child(
                 div()
-                    .w(px(44.0))
-                    .text_size(px(11.0))
-                    .text_color(rgb(if is_selected { 0xbdd9ff } else { 0x5f6b7a }))
-                    .font_family("Cascadia Code")
-                    .child((index + 1).to_string()),
+                    .text_size(px(10.0))
+                    .text_color(rgb(0x8aa1b3))
+                    .child(language.unwrap_or_default()),
             )
             .child(
                 div()
-                    .flex_1()
+                    .ml_auto()
+                    .text_size(px(10.0))
+                    .text_color(rgb(status_color))
+                    .child(status),
+            );
+        // A transcript without prompts has nothing to run.
+        if !command.is_empty() {
+            header = header.child(
+                div()
                     .flex()
                     .items_center()
-                    .gap(px(0.0))
-                    .overflow_hidden()
-                    .text_size(px(12.0))
-                    .font_family("Cascadia Code")
-                    .whitespace_nowrap()
-                    .children(if rendered_segments.is_empty() {
-                        vec![
-                            div()
-                                .text_color(rgb(0xd8dee9))
-                                .child(" ")
-                                .into_any_element(),
-                        ]
-                    } else {
-                        rendered_segments
-                            .into_iter()
-                            .map(|segment| {
-                                let mut span =
-                                    div().text_color(rgb(segment.color)).child(segment.text);
-                                if segment.matched {
-                                    span = span.px(px(1.0)).rounded(px(3.0)).bg(rgb(0x5c4a16));
-                                }
-                                if segment.bold {
-                                    span = span.font_weight(FontWeight::BOLD);
-                                }
-                                span.into_any_element()
-                            })
-                            .collect::<Vec<_>>()
-                    }),
+                    .gap(px(4.0))
+                    .px(px(8.0))
+                    .py(px(2.0))
+                    .rounded(px(4.0))
+                    .bg(rgb(0x162235))
+                    .border_1()
+                    .border_color(rgb(0x2f4f7a))
+                    .text_size(px(11.0))
+                    .text_color(rgb(0x8fb7ff))
+                    .cursor(CursorStyle::PointingHand)
+                    .hover(|style| style.bg(rgb(0x1d2d45)))
+                    .child(lucide_icon(Icon::Play, 11.0, 0x8fb7ff))
+                    .child("Run")
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |view, _event: &MouseDownEvent, _window, cx| {
+                            cx.stop_propagation();
+                            view.run_runbook_fences(vec![(key.clone(), command.clone())], cx);
+                        }),
+                    ),
             );
-
-        let mouse_down_handle = view_handle.clone();
-        row = row.on_mouse_down(MouseButton::Left, move |event, _window, cx| {
-            let _ = mouse_down_handle.update(cx, |view, cx| {
-                view.on_preview_line_mouse_down_at(index, event, cx);
-            });
-        });
-
-        let mouse_move_handle = view_handle.clone();
-        row = row.on_mouse_move(move |event, _window, cx| {
-            let _ = mouse_move_handle.update(cx, |view, cx| {
-                view.on_preview_line_mouse_move_at(index, event, cx);
-            });
-        });
-
-        if is_selected {
-            row = row.bg(rgb(0x1a2f4a)).rounded(px(4.0));
-        } else if is_active_line {
-            row = row.bg(rgb(0x122033)).rounded(px(4.0));
         }
 
-        row
-    }
-
-    pub fn set_recent(&mut self, recent: Vec<RecentEntry>, cx: &mut Context<Self>) {
-        if let TabViewMode::Welcome(ref welcome) = self.mode {
-            let _ = welcome.update(cx, |view, cx| {
-                view.set_recent(recent, cx);
-            });
-        }
-    }
+        let output: Vec<String> = match (running, last_run) {
+            (Some(block), _) => {
+                let skip = block
+                    .output_lines
+                    .len()
+                    .saturating_sub(MAX_SAVED_OUTPUT_LINES);
+                self.masked_lines(&block.output_lines).split_off(skip)
+            }
+            (None, Some(run)) => run.output_lines.clone(),
+            (None, None) => Vec::new(),
+        };
+        let output = (!output.is_empty()).then(|| {
+            div()
+                .flex_col()
+                .mt(px(4.0))
+                .pt(px(6.0))
+                .border_t_1()
+                .border_color(rgb(0x253545))
+                .text_size(px(12.0))
+                .text_color(rgb(0xaab4bf))
+                .children(output.into_iter().map(|line| {
+                    if line.is_empty() {
+                        div().h(px(12.0))
+                    } else {
+                        div().min_w(px(0.0)).child(line)
+                    }
+                }))
+        });
 
-    pub fn set_settings_section(&mut self, section: &str, cx: &mut Context<Self>) {
-        if let TabViewMode::Settings(ref settings) = self.mode {
-            let _ = settings.update(cx, |view, cx| {
-                view.set_active_section(section, cx);
-            });
-        }
+        div()
+            .flex_col()
+            .gap(px(2.0))
+            .px(px(10.0))
+            .py(px(8.0))
+            .rounded(px(6.0))
+            .bg(rgb(0x111820))
+            .border_1()
+            .border_color(if running.is_some() {
+                rgb(0x2f4f7a)
+            } else {
+                rgb(0x253545)
+            })
+            .font_family("Cascadia Code")
+            .text_color(rgb(0xcfd8e3))
+            .child(header)
+            .children(lines.into_iter().map(|line| div().child(line)))
+            .children(output)
     }
 
-    fn render_file_preview(&self, preview: &FilePreviewState, cx: &Context<Self>) -> Div {
-        let file_name = preview
-            .path
-            .file_name()
-            .map(|name| name.to_string_lossy().to_string())
-            .unwrap_or_else(|| preview.path.to_string_lossy().to_string());
-        let close_handle = cx.entity().downgrade();
-        let language_label = Self::preview_language_label(preview.language);
-        let supports_markdown_preview = Self::preview_supports_rendered_markdown(preview);
-        let show_rendered_preview =
-            supports_markdown_preview && self.file_preview_mode == FilePreviewMode::Preview;
-        let preview_view_handle = cx.entity().downgrade();
-        let markdown_toggle: AnyElement = if supports_markdown_preview {
+    fn render_block_diff(&self, diff: &BlockDiffState, cx: &Context<Self>) -> Div {
+        let (removed, added) = block_diff::change_counts(&diff.lines);
+        let chip = |label: &'static str, on: bool| {
             div()
-                .size(px(28.0))
+                .flex_none()
+                .px(px(8.0))
+                .py(px(4.0))
                 .rounded(px(6.0))
-                .flex()
-                .items_center()
-                .justify_center()
-                .bg(if show_rendered_preview {
-                    rgb(0x162235)
-                } else {
-                    rgb(0x121212)
-                })
+                .bg(if on { rgb(0x162235) } else { rgb(0x121212) })
                 .border_1()
-                .border_color(if show_rendered_preview {
-                    rgb(0x2f4f7a)
-                } else {
-                    rgb(0x2a2a2a)
-                })
-                .hover(|style| style.bg(rgb(0x1a1f2a)).border_color(rgb(0x3a3a3a)))
-                .child(
-                    lucide_icon(
-                        if show_rendered_preview {
-                            Icon::EyeOff
-                        } else {
-                            Icon::Eye
-                        },
-                        14.0,
-                        if show_rendered_preview {
-                            0x8fb7ff
-                        } else {
-                            0x9a9a9a
-                        },
-                    )
-                    .cursor(CursorStyle::PointingHand),
-                )
-                .on_mouse_down(
-                    MouseButton::Left,
-                    cx.listener(Self::on_toggle_file_preview_mode),
-                )
-                .into_any_element()
-        } else {
-            div().into_any_element()
+                .border_color(if on { rgb(0x2f4f7a) } else { rgb(0x2a2a2a) })
+                .text_size(px(11.0))
+                .text_color(if on { rgb(0x8fb7ff) } else { rgb(0x9a9a9a) })
+                .cursor(CursorStyle::PointingHand)
+                .hover(|style| style.border_color(rgb(0x3a3a3a)))
+                .child(label)
         };
-
-        let body: AnyElement = if show_rendered_preview {
-            match &preview.kind {
-                FilePreviewKind::Text { contents, .. } => div()
-                    .id("file_preview_markdown")
-                    .track_scroll(&self.preview_scroll_handle)
-                    .flex()
-                    .flex_col()
-                    .on_mouse_down(MouseButton::Left, cx.listener(Self::on_preview_focus))
-                    .on_mouse_up(MouseButton::Left, cx.listener(Self::on_preview_mouse_up))
-                    .on_mouse_up_out(MouseButton::Left, cx.listener(Self::on_preview_mouse_up))
-                    .overflow_y_scroll()
-                    .scrollbar_width(px(12.0))
-                    .flex_1()
-                    .min_h(px(0.0))
-                    .min_w(px(0.0))
-                    .p(px(20.0))
-                    .child(
-                        div()
-                            .max_w(px(960.0))
-                            .min_h(px(0.0))
-                            .min_w(px(0.0))
-                            .pr(px(24.0))
-                            .child(render_markdown_response_content(contents, false)),
-                    )
-                    .into_any_element(),
-                _ => div().into_any_element(),
-            }
-        } else {
-            match &preview.kind {
-                FilePreviewKind::Text { lines, .. } => {
-                    let lines = Arc::clone(lines);
-                    let selection = self.normalize_preview_selection();
-                    let language = preview.language;
-                    let active_line = self.preview_active_line;
-                    let persistent_match = self.preview_search_match.clone();
-                    div()
-                        .id("file_preview_text")
-                        .flex()
-                        .flex_col()
-                        .on_mouse_down(MouseButton::Left, cx.listener(Self::on_preview_focus))
-                        .on_mouse_up(MouseButton::Left, cx.listener(Self::on_preview_mouse_up))
-                        .on_mouse_up_out(MouseButton::Left, cx.listener(Self::on_preview_mouse_up))
-                        .flex_1()
-                        .min_h(px(0.0))
-                        .min_w(px(0.0))
-                        .p(px(16.0))
-                        .child(
-                            uniform_list("file_preview_text_list", lines.len(), {
-                                let lines = Arc::clone(&lines);
-                                let view_handle = preview_view_handle.clone();
-                                move |range, _window, _cx| {
-                                    range
-                                        .map(|index| {
-                                            Self::render_preview_code_row(
-                                                language,
-                                                selection,
-                                                active_line,
-                                                persistent_match.as_ref(),
-                                                &lines[index],
-                                                index,
-                                                &view_handle,
-                                            )
-                                        })
-                                        .collect::<Vec<_>>()
-                                }
+        let layout_chip = |label: &'static str, layout: DiffLayout| {
+            chip(label, diff.layout == layout).on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |view, _event: &MouseDownEvent, _window, cx| {
+                    cx.stop_propagation();
+                    view.update_block_diff(cx, |diff| diff.layout = layout);
+                }),
+            )
+        };
+        let option_chip = |label: &'static str, on: bool, toggle: fn(&mut DiffOptions)| {
+            chip(label, on).on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |view, _event: &MouseDownEvent, _window, cx| {
+                    cx.stop_propagation();
+                    view.update_block_diff(cx, |diff| toggle(&mut diff.options));
+                }),
+            )
+        };
+        let title = |label: &str, title: &str| {
+            div()
+                .flex()
+                .gap(px(6.0))
+                .min_w(px(0.0))
+                .text_size(px(12.0))
+                .child(
+                    div()
+                        .flex_none()
+                        .text_color(rgb(0x7f8b99))
+                        .child(label.to_string()),
+                )
+                .child(
+                    div()
+                        .min_w(px(0.0))
+                        .truncate()
+                        .text_color(rgb(0xf0f0f0))
+                        .child(title.to_string()),
+                )
+        };
+
+        let left = Arc::clone(&diff.left);
+        let right = Arc::clone(&diff.right);
+        let body = if diff
+            .lines
+            .iter()
+            .all(|line| matches!(line, DiffLine::Same { .. }))
+        {
+            div()
+                .flex()
+                .flex_1()
+                .min_h(px(0.0))
+                .items_center()
+                .justify_center()
+                .text_size(px(12.0))
+                .text_color(rgb(0x8a97a8))
+                .child("The outputs are the same.")
+        } else {
+            let list = match diff.layout {
+                DiffLayout::SideBySide => {
+                    let rows = Arc::clone(&diff.rows);
+                    uniform_list("block_diff_rows", rows.len(), move |range, _window, _cx| {
+                        range
+                            .map(|index| {
+                                Self::render_side_by_side_diff_row(rows[index], &left, &right)
                             })
-                            .track_scroll(self.preview_code_scroll_handle.clone())
-                            .w_full()
-                            .h_full(),
-                        )
-                        .into_any_element()
+                            .collect::<Vec<_>>()
+                    })
                 }
-                FilePreviewKind::Image => div()
-                    .id("file_preview_image")
-                    .track_scroll(&self.preview_scroll_handle)
-                    .flex()
-                    .flex_col()
-                    .overflow_y_scroll()
-                    .scrollbar_width(px(12.0))
-                    .flex_1()
-                    .min_h(px(0.0))
-                    .min_w(px(0.0))
-                    .p(px(20.0))
-                    .child(
-                        div()
-                            .flex()
-                            .items_center()
-                            .justify_center()
-                            .min_h(px(0.0))
-                            .child(
-                                img(preview.path.clone())
-                                    .object_fit(ObjectFit::Contain)
-                                    .with_fallback(|| {
-                                        div()
-                                            .text_size(px(12.0))
-                                            .text_color(rgb(0xff7b72))
-                                            .child("Failed to render image.")
-                                            .into_any_element()
-                                    }),
-                            ),
-                    )
-                    .into_any_element(),
-                FilePreviewKind::Unsupported { message } => div()
-                    .flex()
-                    .flex_1()
-                    .min_h(px(0.0))
-                    .items_center()
-                    .justify_center()
-                    .child(
-                        div()
-                            .text_size(px(12.0))
-                            .text_color(rgb(0x8a97a8))
-                            .child(message.clone()),
+                DiffLayout::Unified => {
+                    let lines = Arc::clone(&diff.lines);
+                    uniform_list(
+                        "block_diff_lines",
+                        lines.len(),
+                        move |range, _window, _cx| {
+                            range
+                                .map(|index| {
+                                    Self::render_unified_diff_line(lines[index], &left, &right)
+                                })
+                                .collect::<Vec<_>>()
+                        },
                     )
-                    .into_any_element(),
-            }
+                }
+            };
+            div()
+                .flex()
+                .flex_col()
+                .flex_1()
+                .min_h(px(0.0))
+                .min_w(px(0.0))
+                .p(px(16.0))
+                .child(
+                    list.track_scroll(self.preview_code_scroll_handle.clone())
+                        .w_full()
+                        .h_full(),
+                )
         };
 
         div()
@@ -2575,6 +5377,7 @@ impl TabView {
                     .flex_none()
                     .items_center()
                     .justify_between()
+                    .gap(px(12.0))
                     .px(px(16.0))
                     .py(px(12.0))
                     .border_b_1()
@@ -2583,65 +5386,155 @@ impl TabView {
                         div()
                             .flex()
                             .flex_col()
+                            .flex_1()
+                            .min_w(px(0.0))
                             .gap(px(4.0))
-                            .child(
-                                div()
-                                    .text_size(px(13.0))
-                                    .text_color(rgb(0xf0f0f0))
-                                    .child(file_name),
-                            )
-                            .child(
-                                div()
-                                    .text_size(px(11.0))
-                                    .text_color(rgb(0x7f8b99))
-                                    .child(preview.relative_label.clone()),
-                            ),
+                            .child(title("Before", &diff.left_title))
+                            .child(title("After", &diff.right_title)),
                     )
                     .child(
                         div()
                             .flex()
+                            .flex_none()
                             .items_center()
                             .gap(px(8.0))
-                            .child(markdown_toggle)
                             .child(
                                 div()
-                                    .px(px(8.0))
-                                    .py(px(4.0))
-                                    .rounded(px(999.0))
-                                    .bg(rgb(0x121826))
-                                    .border_1()
-                                    .border_color(rgb(0x26324d))
                                     .text_size(px(11.0))
-                                    .text_color(rgb(0x8fb7ff))
-                                    .child(language_label),
-                            ),
-                    )
-                    .child(
-                        div()
-                            .size(px(28.0))
-                            .rounded(px(6.0))
-                            .flex()
-                            .items_center()
-                            .justify_center()
-                            .bg(rgb(0x121212))
-                            .border_1()
-                            .border_color(rgb(0x2a2a2a))
-                            .hover(|style| style.bg(rgb(0x1a1a1a)).border_color(rgb(0x3a3a3a)))
-                            .child(
-                                lucide_icon(Icon::X, 14.0, 0x9a9a9a)
-                                    .cursor(CursorStyle::PointingHand),
+                                    .font_family("Cascadia Code")
+                                    .flex()
+                                    .gap(px(6.0))
+                                    .child(
+                                        div()
+                                            .text_color(rgb(0xff7b72))
+                                            .child(format!("-{removed}")),
+                                    )
+                                    .child(
+                                        div().text_color(rgb(0x8bd06f)).child(format!("+{added}")),
+                                    ),
                             )
-                            .on_mouse_down(MouseButton::Left, move |_event, _window, cx| {
-                                cx.stop_propagation();
-                                let _ = close_handle.update(cx, |view, cx| {
-                                    view.close_file_preview(cx);
-                                });
-                            }),
+                            .child(layout_chip("Side by side", DiffLayout::SideBySide))
+                            .child(layout_chip("Unified", DiffLayout::Unified))
+                            .child(option_chip(
+                                "Ignore whitespace",
+                                diff.options.ignore_whitespace,
+                                |options| options.ignore_whitespace = !options.ignore_whitespace,
+                            ))
+                            .child(option_chip(
+                                "Ignore timestamps",
+                                diff.options.ignore_timestamps,
+                                |options| options.ignore_timestamps = !options.ignore_timestamps,
+                            ))
+                            .child(
+                                div()
+                                    .size(px(28.0))
+                                    .rounded(px(6.0))
+                                    .flex()
+                                    .items_center()
+                                    .justify_center()
+                                    .bg(rgb(0x121212))
+                                    .border_1()
+                                    .border_color(rgb(0x2a2a2a))
+                                    .hover(|style| {
+                                        style.bg(rgb(0x1a1a1a)).border_color(rgb(0x3a3a3a))
+                                    })
+                                    .child(
+                                        lucide_icon(Icon::X, 14.0, 0x9a9a9a)
+                                            .cursor(CursorStyle::PointingHand),
+                                    )
+                                    .on_mouse_down(
+                                        MouseButton::Left,
+                                        cx.listener(
+                                            |view, _event: &MouseDownEvent, _window, cx| {
+                                                cx.stop_propagation();
+                                                view.block_diff = None;
+                                                cx.notify();
+                                            },
+                                        ),
+                                    ),
+                            ),
                     ),
             )
             .child(body)
     }
 
+    /// One side of a diff row: a line number and the line, or a blank.
+    fn render_diff_cell(line: Option<(usize, &str)>, changed: Option<(u32, u32)>) -> Div {
+        let (bg, color) = changed.unwrap_or((0x0a0a0a, 0xbdbdbd));
+        div()
+            .flex()
+            .flex_1()
+            .min_w(px(0.0))
+            .h_full()
+            .items_center()
+            .gap(px(8.0))
+            .overflow_hidden()
+            .bg(rgb(if line.is_some() { bg } else { 0x101010 }))
+            .child(
+                div()
+                    .flex_none()
+                    .w(px(40.0))
+                    .text_size(px(11.0))
+                    .text_color(rgb(0x5f6b7a))
+                    .child(
+                        line.map(|(index, _)| (index + 1).to_string())
+                            .unwrap_or_default(),
+                    ),
+            )
+            .child(
+                div()
+                    .min_w(px(0.0))
+                    .text_color(rgb(color))
+                    .whitespace_nowrap()
+                    .child(line.map(|(_, text)| text.to_string()).unwrap_or_default()),
+            )
+    }
+
+    fn render_side_by_side_diff_row(row: DiffRow, left: &[String], right: &[String]) -> Div {
+        let (left_line, right_line, changed) = match row {
+            DiffRow::Same { left, right } => (Some(left), Some(right), false),
+            DiffRow::Changed { left, right } => (left, right, true),
+        };
+        div()
+            .h(px(20.0))
+            .flex()
+            .gap(px(2.0))
+            .font_family("Cascadia Code")
+            .text_size(px(12.0))
+            .child(Self::render_diff_cell(
+                left_line.map(|index| (index, left[index].as_str())),
+                changed.then_some((0x2d1517, 0xffa3a3)),
+            ))
+            .child(Self::render_diff_cell(
+                right_line.map(|index| (index, right[index].as_str())),
+                changed.then_some((0x12261a, 0x9be28a)),
+            ))
+    }
+
+    fn render_unified_diff_line(line: DiffLine, left: &[String], right: &[String]) -> Div {
+        let (marker, number, text, changed) = match line {
+            DiffLine::Same { right: index, .. } => (" ", index, &right[index], None),
+            DiffLine::Removed(index) => ("-", index, &left[index], Some((0x2d1517, 0xffa3a3))),
+            DiffLine::Added(index) => ("+", index, &right[index], Some((0x12261a, 0x9be28a))),
+        };
+        div()
+            .h(px(20.0))
+            .flex()
+            .font_family("Cascadia Code")
+            .text_size(px(12.0))
+            .child(
+                div()
+                    .flex_none()
+                    .w(px(16.0))
+                    .text_color(rgb(changed.map_or(0x5f6b7a, |(_, color)| color)))
+                    .child(marker),
+            )
+            .child(Self::render_diff_cell(
+                Some((number, text.as_str())),
+                changed,
+            ))
+    }
+
     fn render_terminal_panel(&mut self, window: &mut Window, cx: &mut Context<Self>) -> Div {
         let _ = window;
         let mut panel = div()
@@ -2673,21 +5566,22 @@ impl TabView {
                             .font_family("Cascadia Code")
                             .text_size(px(13.0))
                             .text_color(rgb(0xcccccc))
-                            .child({
+                            // Blocks are direct children so the scroll handle
+                            // can tell which one is at the top.
+                            .children({
                                 let active_index = self.blocks.len().saturating_sub(1);
-                                let blocks: Vec<Div> = self
-                                    .blocks
+                                self.blocks
                                     .iter()
                                     .enumerate()
                                     .map(|(i, block)| self.render_block(block, i, active_index, cx))
-                                    .collect();
-                                div()
-                                    .flex_col()
-                                    .gap(px(0.0))
-                                    .min_h(px(0.0))
-                                    .children(blocks)
+                                    .collect::<Vec<Div>>()
                             }),
                     )
+                    .children(self.render_mouse_tracking_layer(cx))
+                    .children(self.render_sticky_block_header(cx))
+                    .children(self.render_fold_controls(cx))
+                    .children(self.render_bell_flash())
+                    .children(self.render_selection_actions(cx))
                     .child(if self.follow_output {
                         div()
                     } else {
@@ -2696,11 +5590,64 @@ impl TabView {
             )
             .child(self.render_overlay(cx));
 
-        if self.input_visible {
+        if let Some(watch) = &self.watch {
+            panel = panel.child(
+                div()
+                    .flex_none()
+                    .px(px(16.0))
+                    .pb(px(8.0))
+                    .child(self.render_watch_bar(watch, cx)),
+            );
+        }
+        if self.block_nav_active {
+            panel = panel.child(
+                div()
+                    .flex_none()
+                    .px(px(16.0))
+                    .pb(px(8.0))
+                    .child(self.render_block_nav_bar()),
+            );
+        }
+        if let Some(processes) = &self.process_panel {
+            panel = panel.child(
+                div()
+                    .flex_none()
+                    .px(px(16.0))
+                    .pb(px(8.0))
+                    .child(self.render_process_panel(processes, cx)),
+            );
+        }
+        if let Some((status, path)) = &self.recording_status {
+            panel = panel.child(
+                div()
+                    .flex_none()
+                    .px(px(16.0))
+                    .pb(px(8.0))
+                    .child(self.render_recording_status(status, path.clone(), cx)),
+            );
+        }
+        if let Some(replay) = &self.replay {
+            panel = panel.child(
+                div()
+                    .flex_none()
+                    .px(px(16.0))
+                    .pb(px(12.0))
+                    .child(self.render_replay_bar(replay, cx)),
+            );
+        } else if let Some(secure) = &self.secure_input {
+            panel = panel.child(
+                div()
+                    .flex_none()
+                    .px(px(16.0))
+                    .pb(px(12.0))
+                    .child(Self::render_secure_input_bar(secure)),
+            );
+        } else if self.input_visible {
             panel = panel.child(
                 div()
                     .flex_none()
-                    .child(self.render_history_menu_container())
+                    .child(self.render_history_menu_container(cx))
+                    .child(self.render_suggestion_menu_container())
                     .child(
                         div()
                             .px(px(16.0))
@@ -2714,7 +5661,14 @@ impl TabView {
     }
 
     fn render_terminal_workspace(&mut self, window: &mut Window, cx: &mut Context<Self>) -> Div {
-        if let Some(preview) = self.file_preview.clone() {
+        let preview = if let Some(diff) = &self.block_diff {
+            Some(self.render_block_diff(diff, cx))
+        } else {
+            self.file_preview
+                .clone()
+                .map(|preview| self.render_file_preview(&preview, cx))
+        };
+        if let Some(preview) = preview {
             let terminal_height = Self::clamp_preview_terminal_height(self.preview_terminal_height);
             div()
                 .flex()
@@ -2739,7 +5693,7 @@ impl TabView {
                         .min_h(px(0.0))
                         .min_w(px(0.0))
                         .overflow_hidden()
-                        .child(self.render_file_preview(&preview, cx)),
+                        .child(preview),
                 )
                 .child(
                     div()
@@ -2798,8 +5752,8 @@ impl TabView {
         }
 
         let cwd = path.or_else(|| std::env::current_dir().ok());
-        let (pty, reader) =
-            TerminalPty::new_in_path(80, 24, cwd.as_deref()).expect("failed to create PTY");
+        let (pty, reader) = TerminalPty::new_in_path(TERMINAL_COLS, TERMINAL_ROWS, cwd.as_deref())
+            .expect("failed to create PTY");
         self.pty = Some(pty);
         self.current_path = cwd
             .as_ref()
@@ -2808,6 +5762,8 @@ impl TabView {
         self.git_status = cwd.as_ref().and_then(|path| get_git_status(path));
         self.blocks.clear();
         self.selected_block = None;
+        self.exit_block_nav();
+        self.mouse_protocol = MouseProtocol::default();
         self.clear_output_selection();
         self.total_output_lines = 0;
         self.input.clear();
@@ -2816,6 +5772,7 @@ impl TabView {
         self.history_items.clear();
         self.suggestions.clear();
         self.suggest_index = 0;
+        self.suggestion_menu_open = false;
         self.selection = None;
         self.selection_anchor = None;
         self.pending_echo = None;
@@ -2880,24 +5837,51 @@ impl TabView {
         if let Some(prompt) = Self::normalize_initial_terminal_command(Some(&prompt)) {
             self.run_agent_prompt(prompt, cx);
         }
-    }
-
-    fn on_key_down(&mut self, event: &KeyDownEvent, _window: &mut Window, cx: &mut Context<Self>) {
-        if self.handle_overlay_key(event, cx) {
+    }
+
+    fn on_key_down(&mut self, event: &KeyDownEvent, window: &mut Window, cx: &mut Context<Self>) {
+        if self.handle_overlay_key(event, cx) {
+            cx.stop_propagation();
+            return;
+        }
+        if self.handle_runbook_prompt_key(event, cx) {
+            cx.stop_propagation();
+            return;
+        }
+        if self.handle_preview_key(event, cx) {
+            cx.stop_propagation();
+            return;
+        }
+        if self.handle_secure_input_key(event, cx) {
+            cx.stop_propagation();
+            return;
+        }
+        if self.handle_guard_key(event, cx) {
             cx.stop_propagation();
             return;
         }
-        if self.handle_preview_key(event, cx) {
+        if self.handle_block_nav_key(event, cx) {
+            cx.stop_propagation();
+            return;
+        }
+        if self.input_visible && self.handle_multi_cursor_key(event, window, cx) {
             cx.stop_propagation();
             return;
         }
         let ctrl = event.keystroke.modifiers.control;
         let shift = event.keystroke.modifiers.shift;
         if ctrl && shift && event.keystroke.key.eq_ignore_ascii_case("c") {
-            self.copy_selected_output(cx);
+            self.copy_selected_output(event.keystroke.modifiers.alt, cx);
             cx.stop_propagation();
             return;
         }
+        if self.replay.is_some() {
+            if event.keystroke.key == "space" {
+                self.toggle_replay_playing(cx);
+                cx.stop_propagation();
+            }
+            return;
+        }
         if ctrl && event.keystroke.key.eq_ignore_ascii_case("i") {
             self.toggle_input_mode(cx);
             cx.stop_propagation();
@@ -2913,6 +5897,10 @@ impl TabView {
             cx.stop_propagation();
             return;
         }
+        if self.input_visible && self.handle_editor_key(event, cx) {
+            cx.stop_propagation();
+            return;
+        }
         if ctrl && event.keystroke.key.eq_ignore_ascii_case("a") {
             self.select_all_input();
             cx.notify();
@@ -2930,7 +5918,7 @@ impl TabView {
                     .replace('\n', " ");
                 if !paste.is_empty() {
                     self.insert_text(&paste);
-                    self.refresh_suggestions();
+                    self.refresh_suggestions(cx);
                     self.refresh_history_menu();
                     cx.notify();
                 }
@@ -2939,12 +5927,26 @@ impl TabView {
             return;
         }
 
+        if self.input_visible && ctrl && event.keystroke.key.eq_ignore_ascii_case("r") {
+            self.open_history_search(cx);
+            cx.stop_propagation();
+            return;
+        }
+
+        if self.history_open && ctrl && event.keystroke.key.eq_ignore_ascii_case("f") {
+            self.cycle_history_filter(None);
+            cx.notify();
+            cx.stop_propagation();
+            return;
+        }
+
         if self.input_mode == InputMode::Terminal && ctrl && event.keystroke.key.len() == 1 {
             if let Some(ref mut pty) = self.pty {
                 let key = event.keystroke.key.as_bytes()[0];
                 if key.is_ascii_alphabetic() {
                     let code = key.to_ascii_lowercase() - b'a' + 1;
                     let _ = pty.write(&[code]);
+                    self.record(CastEventKind::Input, &char::from(code).to_string());
                     cx.stop_propagation();
                     return;
                 }
@@ -2967,7 +5969,7 @@ impl TabView {
             }
             "backspace" => {
                 if self.delete_selection_if_any() {
-                    self.refresh_suggestions();
+                    self.refresh_suggestions(cx);
                     self.refresh_history_menu();
                     cx.notify();
                     cx.stop_propagation();
@@ -2975,7 +5977,7 @@ impl TabView {
                 }
                 if !self.input.is_empty() {
                     self.pop_char_before_cursor();
-                    self.refresh_suggestions();
+                    self.refresh_suggestions(cx);
                     self.refresh_history_menu();
                     cx.notify();
                 }
@@ -2983,14 +5985,14 @@ impl TabView {
             }
             "space" => {
                 self.insert_text(" ");
-                self.refresh_suggestions();
+                self.refresh_suggestions(cx);
                 self.refresh_history_menu();
                 cx.notify();
                 cx.stop_propagation();
             }
             "tab" => {
                 if self.has_suggestion() {
-                    self.accept_suggestion();
+                    self.accept_suggestion(cx);
                     cx.notify();
                 }
                 cx.stop_propagation();
@@ -3041,7 +6043,7 @@ impl TabView {
                     }
                     self.clear_selection();
                 } else if self.has_suggestion() {
-                    self.accept_suggestion();
+                    self.accept_suggestion(cx);
                 } else {
                     self.move_cursor_right();
                 }
@@ -3064,17 +6066,32 @@ impl TabView {
                 cx.stop_propagation();
             }
             "up" | "arrowup" => {
-                self.open_or_step_history(true);
+                if self.suggestion_menu_open && !self.history_open {
+                    self.step_suggestion_menu(true);
+                } else {
+                    self.open_or_step_history(true);
+                }
                 cx.notify();
             }
             "down" | "arrowdown" => {
-                self.open_or_step_history(false);
+                if self.suggestion_menu_open && !self.history_open {
+                    self.step_suggestion_menu(false);
+                } else {
+                    self.open_or_step_history(false);
+                }
                 cx.notify();
             }
             "escape" => {
+                if self.suggestion_menu_open {
+                    self.suggestion_menu_open = false;
+                    cx.notify();
+                    cx.stop_propagation();
+                    return;
+                }
                 if !self.input_visible {
                     if let Some(ref mut pty) = self.pty {
                         let _ = pty.write(&[3]);
+                        self.record(CastEventKind::Input, "\x03");
                     }
                     cx.stop_propagation();
                 }
@@ -3083,7 +6100,7 @@ impl TabView {
                 if let Some(text) = event.keystroke.key_char.as_deref() {
                     if !text.is_empty() {
                         self.insert_text(text);
-                        self.refresh_suggestions();
+                        self.refresh_suggestions(cx);
                         self.refresh_history_menu();
                         cx.notify();
                         cx.stop_propagation();
@@ -3091,7 +6108,7 @@ impl TabView {
                 } else if event.keystroke.key.len() == 1 {
                     let key = event.keystroke.key.clone();
                     self.insert_text(&key);
-                    self.refresh_suggestions();
+                    self.refresh_suggestions(cx);
                     self.refresh_history_menu();
                     cx.notify();
                     cx.stop_propagation();
@@ -3100,6 +6117,398 @@ impl TabView {
         }
     }
 
+    /// Sends keys of a password answer straight to the PTY. Nothing reaches
+    /// the input editor, so nothing can end up in history.
+    fn handle_secure_input_key(&mut self, event: &KeyDownEvent, cx: &mut Context<Self>) -> bool {
+        let Some(secure) = self.secure_input.as_mut() else {
+            return false;
+        };
+        let Some(pty) = self.pty.as_mut() else {
+            self.secure_input = None;
+            return false;
+        };
+        let modifiers = event.keystroke.modifiers;
+        let key = event.keystroke.key.as_str();
+        match key {
+            "enter" | "return" | "numpadenter" => {
+                let _ = pty.write(b"\r");
+                self.secure_input = None;
+            }
+            "escape" => {
+                let _ = pty.write(&[3]);
+                self.secure_input = None;
+            }
+            "backspace" => {
+                if secure.typed > 0 {
+                    let _ = pty.write(&[0x7f]);
+                    secure.typed -= 1;
+                }
+            }
+            _ if (modifiers.control && key.eq_ignore_ascii_case("v"))
+                || (modifiers.shift && key.eq_ignore_ascii_case("insert")) =>
+            {
+                if let Some(text) = cx.read_from_clipboard().and_then(|item| item.text()) {
+                    let line = text.lines().next().unwrap_or_default();
+                    let _ = pty.write(line.as_bytes());
+                    secure.typed += line.chars().count();
+                }
+            }
+            _ if modifiers.control && key.len() == 1 => {
+                let byte = key.as_bytes()[0];
+                if byte.is_ascii_alphabetic() {
+                    let _ = pty.write(&[byte.to_ascii_lowercase() - b'a' + 1]);
+                    match byte.to_ascii_lowercase() {
+                        b'c' | b'd' => self.secure_input = None,
+                        b'u' => secure.typed = 0,
+                        _ => {}
+                    }
+                }
+            }
+            _ => {
+                let text = match key {
+                    "space" => Some(" "),
+                    _ => event.keystroke.key_char.as_deref(),
+                };
+                if let Some(text) = text.filter(|text| !text.chars().any(char::is_control)) {
+                    let _ = pty.write(text.as_bytes());
+                    secure.typed += text.chars().count();
+                }
+            }
+        }
+        cx.notify();
+        true
+    }
+
+    /// Enter runs the command the guard stopped and Esc drops the warning.
+    /// Any other key also drops it, and goes on to edit the command.
+    fn handle_guard_key(&mut self, event: &KeyDownEvent, cx: &mut Context<Self>) -> bool {
+        if self.pending_guard.is_none() {
+            return false;
+        }
+        match event.keystroke.key.as_str() {
+            "enter" | "return" | "numpadenter" => {
+                self.confirm_guarded_command(cx);
+                true
+            }
+            "escape" => {
+                self.pending_guard = None;
+                cx.notify();
+                true
+            }
+            _ => {
+                self.pending_guard = None;
+                false
+            }
+        }
+    }
+
+    fn confirm_guarded_command(&mut self, cx: &mut Context<Self>) {
+        if let Some(pending) = self.pending_guard.take() {
+            self.execute_command(pending.command, cx);
+        }
+    }
+
+    /// The rules `command` trips for the user's settings and the project
+    /// the tab is in.
+    fn guard_matches(&mut self, command: &str) -> Vec<GuardMatch> {
+        let settings = TerminalSettings::current();
+        let guard = self
+            .guard_cache
+            .guard(&settings.command_guard, &expand_tilde(&self.current_path));
+        if guard.is_empty() {
+            return Vec::new();
+        }
+        let branch = self.git_status.as_ref().map(|status| status.branch.clone());
+        guard.check(command, &GuardContext::for_command(command, branch))
+    }
+
+    /// Enters secure input when a running command leaves a password prompt
+    /// on an unfinished line, by its wording or because the terminal stopped
+    /// echoing. Leaves it once the shell prompt is back.
+    fn update_secure_input(&mut self) {
+        if self.input_visible {
+            self.secure_input = None;
+            return;
+        }
+        if self.secure_input.is_some() || !self.last_line_incomplete {
+            return;
+        }
+        let Some(prompt) = self
+            .blocks
+            .last()
+            .and_then(|block| block.output_lines.last())
+        else {
+            return;
+        };
+        let reads_secret = self.pty.as_ref().is_some_and(|pty| pty.reads_secret());
+        if reads_secret || is_password_prompt(prompt) {
+            self.secure_input = Some(SecureInput {
+                prompt: prompt.trim().to_string(),
+                typed: 0,
+            });
+        }
+    }
+
+    /// Adds cursors and applies typing, deletion, paste and arrow moves at
+    /// every cursor. Any other key drops the extra cursors and is handled as
+    /// usual.
+    fn handle_multi_cursor_key(
+        &mut self,
+        event: &KeyDownEvent,
+        window: &Window,
+        cx: &mut Context<Self>,
+    ) -> bool {
+        let modifiers = event.keystroke.modifiers;
+        let key = event.keystroke.key.as_str();
+        let vertical = match key {
+            "up" | "arrowup" => Some(true),
+            "down" | "arrowdown" => Some(false),
+            _ => None,
+        };
+        if let Some(up) = vertical
+            && modifiers.control
+            && modifiers.shift
+        {
+            self.add_cursor_vertically(up, Self::input_wrap_chars(window));
+            self.history_open = false;
+            cx.notify();
+            return true;
+        }
+        if modifiers.control
+            && !modifiers.shift
+            && key.eq_ignore_ascii_case("d")
+            && self.add_next_occurrence()
+        {
+            self.history_open = false;
+            cx.notify();
+            return true;
+        }
+        if self.extra_cursors.is_empty() {
+            return false;
+        }
+
+        let paste = (modifiers.control && key.eq_ignore_ascii_case("v"))
+            || (modifiers.shift && key.eq_ignore_ascii_case("insert"));
+        let pasted;
+        let edit = match key {
+            "escape" => {
+                self.extra_cursors.clear();
+                cx.notify();
+                return true;
+            }
+            "left" | "arrowleft" | "right" | "arrowright"
+                if !modifiers.control && !modifiers.shift =>
+            {
+                self.move_all_cursors(matches!(key, "right" | "arrowright"));
+                cx.notify();
+                return true;
+            }
+            "backspace" => Some(MultiEdit::DeleteBackward),
+            "delete" => Some(MultiEdit::DeleteForward),
+            "space" if !modifiers.control => Some(MultiEdit::Insert(" ")),
+            _ if paste => {
+                pasted = cx
+                    .read_from_clipboard()
+                    .and_then(|item| item.text())
+                    .map(|text| {
+                        text.replace("\r\n", "\n")
+                            .replace('\r', "\n")
+                            .replace('\n', " ")
+                    })
+                    .unwrap_or_default();
+                Some(MultiEdit::Insert(&pasted))
+            }
+            _ if modifiers.control || modifiers.alt || modifiers.platform => None,
+            _ => event
+                .keystroke
+                .key_char
+                .as_deref()
+                .filter(|text| !text.is_empty() && !text.chars().any(char::is_control))
+                .map(MultiEdit::Insert),
+        };
+        let Some(edit) = edit else {
+            self.extra_cursors.clear();
+            return false;
+        };
+        self.edit_at_all_cursors(edit);
+        self.refresh_suggestions(cx);
+        self.refresh_history_menu();
+        cx.notify();
+        true
+    }
+
+    fn input_cursor_ranges(&self) -> Vec<CursorRange> {
+        let primary = match self.selection {
+            Some((anchor, head)) if anchor != head => CursorRange { anchor, head },
+            _ => CursorRange::caret(self.cursor),
+        };
+        let mut ranges = vec![primary];
+        for range in &self.extra_cursors {
+            if !ranges
+                .iter()
+                .any(|known| known.start() == range.start() && known.end() == range.end())
+            {
+                ranges.push(*range);
+            }
+        }
+        ranges
+    }
+
+    fn set_cursor_ranges(&mut self, mut ranges: Vec<CursorRange>) {
+        let len = self.input.chars().count();
+        for range in &mut ranges {
+            range.anchor = range.anchor.min(len);
+            range.head = range.head.min(len);
+        }
+        let primary = ranges.remove(0);
+        self.cursor = primary.head;
+        if primary.is_empty() {
+            self.clear_selection();
+        } else {
+            self.set_selection_from_anchor(primary.anchor, primary.head);
+        }
+        self.extra_cursors.clear();
+        for range in ranges {
+            if range != primary && !self.extra_cursors.contains(&range) {
+                self.extra_cursors.push(range);
+            }
+        }
+    }
+
+    fn edit_at_all_cursors(&mut self, edit: MultiEdit) {
+        let ranges = self.input_cursor_ranges();
+        let carets = TextEditState::edit_at_ranges(&mut self.input, &ranges, edit);
+        self.set_cursor_ranges(carets.into_iter().map(CursorRange::caret).collect());
+    }
+
+    fn move_all_cursors(&mut self, right: bool) {
+        let len = self.input.chars().count();
+        let ranges = self
+            .input_cursor_ranges()
+            .into_iter()
+            .map(|range| {
+                CursorRange::caret(match (range.is_empty(), right) {
+                    (false, false) => range.start(),
+                    (false, true) => range.end(),
+                    (true, false) => range.head.saturating_sub(1),
+                    (true, true) => (range.head + 1).min(len),
+                })
+            })
+            .collect();
+        self.set_cursor_ranges(ranges);
+    }
+
+    /// Adds a caret one wrapped line above the topmost cursor or below the
+    /// bottommost one, in the same column.
+    fn add_cursor_vertically(&mut self, up: bool, wrap_chars: usize) {
+        if wrap_chars == 0 {
+            return;
+        }
+        let rows = wrap_ranges(&self.input, wrap_chars);
+        let chars: Vec<char> = self.input.chars().collect();
+        let heads = self
+            .input_cursor_ranges()
+            .into_iter()
+            .map(|range| range.head);
+        let Some(from) = (if up { heads.min() } else { heads.max() }) else {
+            return;
+        };
+        let row = input_row_at(&rows, from);
+        let target_row = if up {
+            row.checked_sub(1)
+        } else {
+            Some(row + 1).filter(|row| *row < rows.len())
+        };
+        let Some(target_row) = target_row else {
+            return;
+        };
+        // Keep the display column, not the char count, across rows.
+        let column = str_width(&chars[rows[row].start..from].iter().collect::<String>());
+        let target = &rows[target_row];
+        let target_text: String = chars[target.clone()].iter().collect();
+        // The end of a row that is not the last belongs to the next one.
+        let column = if target_row + 1 < rows.len() {
+            column.min(str_width(&target_text).saturating_sub(1))
+        } else {
+            column
+        };
+        let mut ranges = self.input_cursor_ranges();
+        ranges.push(CursorRange::caret(
+            target.start + offset_at_column(&target_text, column),
+        ));
+        self.set_cursor_ranges(ranges);
+    }
+
+    /// Ctrl+D: adds a cursor selecting the next occurrence of the selected
+    /// text. Returns false without a selection, so the key goes on to the
+    /// editing mode, such as Emacs delete-char, or to the shell as EOF.
+    fn add_next_occurrence(&mut self) -> bool {
+        let chars: Vec<char> = self.input.chars().collect();
+        let Some((start, end)) = occurrence_needle(self.selection) else {
+            return false;
+        };
+        let needle = &chars[start..end.min(chars.len())];
+        let mut ranges = self.input_cursor_ranges();
+        let after = ranges.iter().map(CursorRange::end).max().unwrap_or(end);
+        let free = |at: usize| {
+            ranges
+                .iter()
+                .all(|range| at + needle.len() <= range.start() || at >= range.end())
+        };
+        let found = (after..chars.len())
+            .chain(0..after)
+            .find(|&at| chars[at..].starts_with(needle) && free(at));
+        if let Some(at) = found {
+            ranges.push(CursorRange {
+                anchor: at,
+                head: at + needle.len(),
+            });
+            self.set_cursor_ranges(ranges);
+        }
+        true
+    }
+
+    /// Routes a key through the Emacs or Vi bindings of the command input.
+    /// Returns false when the key is not an editor binding.
+    fn handle_editor_key(&mut self, event: &KeyDownEvent, cx: &mut Context<Self>) -> bool {
+        self.editor
+            .set_mode(TerminalSettings::current().editing_mode);
+        let ctrl = event.keystroke.modifiers.control;
+        let key = event.keystroke.key.as_str();
+        if (ctrl && key.eq_ignore_ascii_case("r"))
+            || (self.history_open && ctrl && key.eq_ignore_ascii_case("f"))
+            || (key == "escape" && self.suggestion_menu_open)
+        {
+            return false;
+        }
+        let action = self.editor.handle_key(
+            EditKey {
+                key,
+                text: event.keystroke.key_char.as_deref(),
+                ctrl,
+                alt: event.keystroke.modifiers.alt,
+            },
+            EditBuffer {
+                text: &mut self.input,
+                cursor: &mut self.cursor,
+                selection: &mut self.selection,
+                anchor: &mut self.selection_anchor,
+            },
+        );
+        match action {
+            EditAction::Ignored => return false,
+            EditAction::Moved => self.history_open = false,
+            EditAction::Changed => {
+                self.refresh_suggestions(cx);
+                self.refresh_history_menu();
+            }
+            EditAction::HistoryPrevious => self.open_or_step_history(true),
+            EditAction::HistoryNext => self.open_or_step_history(false),
+        }
+        cx.notify();
+        true
+    }
+
     fn on_focus_input(
         &mut self,
         _event: &MouseDownEvent,
@@ -3233,6 +6642,48 @@ impl TabView {
             return false;
         };
 
+        if matches!(overlay, Overlay::History(_)) {
+            let modifiers = event.keystroke.modifiers;
+            match event.keystroke.key.as_str() {
+                "r" if modifiers.control => {
+                    Self::overlay_move_selection_down(overlay);
+                    cx.notify();
+                    return true;
+                }
+                "enter" | "return" | "numpadenter" if modifiers.control => {
+                    self.accept_history_search(true, cx);
+                    return true;
+                }
+                "delete" if modifiers.shift => {
+                    self.delete_history_search_selection();
+                    cx.notify();
+                    return true;
+                }
+                _ => {}
+            }
+        }
+
+        if Self::overlay_has_search_input(overlay) {
+            let modifiers = event.keystroke.modifiers;
+            let action = Self::overlay_query_mut(overlay).handle_edit_key(EditKey {
+                key: event.keystroke.key.as_str(),
+                text: event.keystroke.key_char.as_deref(),
+                ctrl: modifiers.control,
+                alt: modifiers.alt,
+            });
+            match action {
+                EditAction::Ignored => {}
+                EditAction::Moved => {}
+                EditAction::Changed => Self::overlay_refilter(overlay),
+                EditAction::HistoryPrevious => Self::overlay_move_selection_up(overlay),
+                EditAction::HistoryNext => Self::overlay_move_selection_down(overlay),
+            }
+            if action != EditAction::Ignored {
+                cx.notify();
+                return true;
+            }
+        }
+
         match event.keystroke.key.as_str() {
             "escape" => {
                 self.overlay = None;
@@ -3245,6 +6696,7 @@ impl TabView {
                     Overlay::Branch(picker) => picker.query.select_all(),
                     Overlay::Agent(picker) => picker.query.select_all(),
                     Overlay::Model(picker) => picker.query.select_all(),
+                    Overlay::History(picker) => picker.query.select_all(),
                 }
                 cx.notify();
                 return true;
@@ -3288,6 +6740,9 @@ impl TabView {
                         Overlay::Model(picker) => {
                             picker.query.move_home(event.keystroke.modifiers.shift)
                         }
+                        Overlay::History(picker) => {
+                            picker.query.move_home(event.keystroke.modifiers.shift)
+                        }
                     }
                 } else {
                     Self::overlay_select_first(overlay);
@@ -3310,6 +6765,9 @@ impl TabView {
                         Overlay::Model(picker) => {
                             picker.query.move_end(event.keystroke.modifiers.shift)
                         }
+                        Overlay::History(picker) => {
+                            picker.query.move_end(event.keystroke.modifiers.shift)
+                        }
                     }
                 } else {
                     Self::overlay_select_last(overlay);
@@ -3331,6 +6789,9 @@ impl TabView {
                     Overlay::Model(picker) => {
                         picker.query.move_left(event.keystroke.modifiers.shift)
                     }
+                    Overlay::History(picker) => {
+                        picker.query.move_left(event.keystroke.modifiers.shift)
+                    }
                 }
                 cx.notify();
                 return true;
@@ -3349,6 +6810,9 @@ impl TabView {
                     Overlay::Model(picker) => {
                         picker.query.move_right(event.keystroke.modifiers.shift)
                     }
+                    Overlay::History(picker) => {
+                        picker.query.move_right(event.keystroke.modifiers.shift)
+                    }
                 }
                 cx.notify();
                 return true;
@@ -3397,6 +6861,7 @@ impl TabView {
             Overlay::Branch(_) => PickerKind::Branch,
             Overlay::Agent(_) => PickerKind::Agent,
             Overlay::Model(_) => PickerKind::Model,
+            Overlay::History(_) => PickerKind::History,
         }
     }
 
@@ -3410,6 +6875,7 @@ impl TabView {
             Overlay::Model(picker) => {
                 picker_has_search_input(PickerKind::Model, picker.all_options.len())
             }
+            Overlay::History(_) => true,
         }
     }
 
@@ -3435,6 +6901,11 @@ impl TabView {
                     picker.selected -= 1;
                 }
             }
+            Overlay::History(picker) => {
+                if picker.selected > 0 {
+                    picker.selected -= 1;
+                }
+            }
         }
     }
 
@@ -3460,6 +6931,11 @@ impl TabView {
                     picker.selected += 1;
                 }
             }
+            Overlay::History(picker) => {
+                if picker.selected + 1 < picker.matches.len() {
+                    picker.selected += 1;
+                }
+            }
         }
     }
 
@@ -3469,6 +6945,7 @@ impl TabView {
             Overlay::Branch(picker) => picker.selected = 0,
             Overlay::Agent(picker) => picker.selected = 0,
             Overlay::Model(picker) => picker.selected = 0,
+            Overlay::History(picker) => picker.selected = 0,
         }
     }
 
@@ -3486,6 +6963,44 @@ impl TabView {
             Overlay::Model(picker) => {
                 picker.selected = picker.options.len().saturating_sub(1);
             }
+            Overlay::History(picker) => {
+                picker.selected = picker.matches.len().saturating_sub(1);
+            }
+        }
+    }
+
+    fn overlay_query_mut(overlay: &mut Overlay) -> &mut PickerQueryState {
+        match overlay {
+            Overlay::Path(picker) => &mut picker.query,
+            Overlay::Branch(picker) => &mut picker.query,
+            Overlay::Agent(picker) => &mut picker.query,
+            Overlay::Model(picker) => &mut picker.query,
+            Overlay::History(picker) => &mut picker.query,
+        }
+    }
+
+    fn overlay_refilter(overlay: &mut Overlay) {
+        match overlay {
+            Overlay::Path(picker) => {
+                picker.selected = 0;
+                Self::populate_path_picker(picker);
+            }
+            Overlay::Branch(picker) => {
+                picker.selected = 0;
+                Self::filter_branch_picker(picker);
+            }
+            Overlay::Agent(picker) => {
+                picker.selected = 0;
+                Self::filter_agent_picker(picker);
+            }
+            Overlay::Model(picker) => {
+                picker.selected = 0;
+                Self::filter_model_picker(picker);
+            }
+            Overlay::History(picker) => {
+                picker.selected = 0;
+                Self::filter_history_search(picker);
+            }
         }
     }
 
@@ -3511,6 +7026,11 @@ impl TabView {
                 picker.selected = 0;
                 Self::filter_model_picker(picker);
             }
+            Overlay::History(picker) => {
+                picker.query.insert_text(&ch.to_string());
+                picker.selected = 0;
+                Self::filter_history_search(picker);
+            }
         }
     }
 
@@ -3536,6 +7056,11 @@ impl TabView {
                 picker.selected = 0;
                 Self::filter_model_picker(picker);
             }
+            Overlay::History(picker) => {
+                picker.query.pop_char_before_cursor();
+                picker.selected = 0;
+                Self::filter_history_search(picker);
+            }
         }
     }
 
@@ -3561,6 +7086,11 @@ impl TabView {
                 picker.selected = 0;
                 Self::filter_model_picker(picker);
             }
+            Overlay::History(picker) => {
+                picker.query.insert_text(text);
+                picker.selected = 0;
+                Self::filter_history_search(picker);
+            }
         }
     }
 
@@ -3586,6 +7116,11 @@ impl TabView {
                 picker.selected = 0;
                 Self::filter_model_picker(picker);
             }
+            Overlay::History(picker) => {
+                picker.query.delete_char_after_cursor();
+                picker.selected = 0;
+                Self::filter_history_search(picker);
+            }
         }
     }
 
@@ -3626,7 +7161,7 @@ impl TabView {
         let mut handled = false;
         for ch in text.chars() {
             match Self::overlay_kind(overlay) {
-                PickerKind::Path | PickerKind::Branch => {
+                PickerKind::Path | PickerKind::Branch | PickerKind::History => {
                     Self::overlay_query_push(overlay, ch);
                     handled = true;
                 }
@@ -3751,7 +7286,94 @@ impl TabView {
         }
     }
 
+    fn filter_history_search(picker: &mut HistorySearchState) {
+        picker.matches = match picker.store.lock() {
+            Ok(store) => store.search(
+                &picker.query.text,
+                &HistoryQuery {
+                    prefix: "",
+                    cwd: Some(&picker.cwd),
+                    filter: HistoryFilter::All,
+                    now: now_timestamp(),
+                    limit: MAX_HISTORY_SEARCH_RESULTS,
+                },
+            ),
+            Err(_) => Vec::new(),
+        };
+        if picker.selected >= picker.matches.len() {
+            picker.selected = picker.matches.len().saturating_sub(1);
+        }
+    }
+
+    fn open_history_search(&mut self, cx: &mut Context<Self>) {
+        let mut query = PickerQueryState::default();
+        query.insert_text(&self.input);
+        let mut picker = HistorySearchState {
+            store: self.history_store.clone(),
+            cwd: expand_tilde(&self.current_path)
+                .to_string_lossy()
+                .to_string(),
+            query,
+            matches: Vec::new(),
+            selected: 0,
+        };
+        Self::filter_history_search(&mut picker);
+        self.history_open = false;
+        self.suggestion_menu_open = false;
+        self.overlay = Some(Overlay::History(picker));
+        cx.notify();
+    }
+
+    /// Puts the selected command in the input, and submits it when `run` is
+    /// set.
+    fn accept_history_search(&mut self, run: bool, cx: &mut Context<Self>) {
+        let Some(Overlay::History(picker)) = self.overlay.take() else {
+            return;
+        };
+        let Some(found) = picker.matches.get(picker.selected) else {
+            cx.notify();
+            return;
+        };
+        self.input = found.ranked.command.clone();
+        self.cursor = self.input.chars().count();
+        self.clear_selection();
+        if run {
+            self.commit_input(cx);
+        } else {
+            self.refresh_suggestions(cx);
+            cx.notify();
+        }
+    }
+
+    fn delete_history_search_selection(&mut self) {
+        let Some(Overlay::History(picker)) = self.overlay.as_mut() else {
+            return;
+        };
+        let Some(command) = picker
+            .matches
+            .get(picker.selected)
+            .map(|found| found.ranked.command.clone())
+        else {
+            return;
+        };
+        if let Ok(mut store) = picker.store.lock() {
+            store.delete_command(&command);
+        }
+        Self::filter_history_search(picker);
+    }
+
+    fn on_history_search_select(&mut self, index: usize, cx: &mut Context<Self>) {
+        if let Some(Overlay::History(ref mut picker)) = self.overlay {
+            picker.selected = index;
+        }
+        self.accept_history_search(false, cx);
+    }
+
     fn accept_overlay_selection(&mut self, cx: &mut Context<Self>) {
+        if matches!(self.overlay, Some(Overlay::History(_))) {
+            self.accept_history_search(false, cx);
+            return;
+        }
         let Some(overlay) = self.overlay.take() else {
             return;
         };
@@ -3827,9 +7449,507 @@ impl TabView {
                 self.overlay = None;
                 cx.notify();
             }
+            Overlay::History(_) => {}
         }
     }
 
+    fn render_block_nav_bar(&self) -> Div {
+        let focused = self
+            .selected_block
+            .and_then(|index| Some((index, self.blocks.get(index)?)));
+        let summary = focused
+            .map(|(index, block)| {
+                let running = self
+                    .running_command
+                    .as_ref()
+                    .is_some_and(|running| running.block_index == Some(index));
+                Self::block_summary(block, index, self.blocks.len(), running)
+            })
+            .unwrap_or_default();
+        let selected = match self.block_selection.len() {
+            0 => None,
+            1 => Some("1 block selected".to_string()),
+            count => Some(format!("{count} blocks selected")),
+        };
+        div()
+            .flex()
+            .flex_col()
+            .gap(px(4.0))
+            .px(px(12.0))
+            .py(px(8.0))
+            .rounded(px(10.0))
+            .bg(rgb(0x111827))
+            .border_1()
+            .border_color(rgb(0x3f669c))
+            .child(
+                div()
+                    .flex()
+                    .items_center()
+                    .gap(px(8.0))
+                    .child(lucide_icon(Icon::Rows3, 14.0, 0x8eb8ff))
+                    .child(
+                        div()
+                            .flex_1()
+                            .min_w(px(0.0))
+                            .text_size(px(13.0))
+                            .text_color(rgb(0xe6e6e6))
+                            .truncate()
+                            .child(summary),
+                    )
+                    .children(selected.map(|selected| {
+                        div()
+                            .flex_none()
+                            .text_size(px(11.0))
+                            .text_color(rgb(0x8eb8ff))
+                            .child(selected)
+                    }))
+                    .children(self.block_nav_status.clone().map(|status| {
+                        div()
+                            .flex_none()
+                            .text_size(px(11.0))
+                            .text_color(rgb(0x7ee787))
+                            .child(status)
+                    })),
+            )
+            .child(
+                div()
+                    .text_size(px(11.0))
+                    .text_color(rgb(0x7f7f7f))
+                    .truncate()
+                    .child(
+                        "↑↓ move · Shift select range · Space select · c command · o output · y both · d diff · e previous error · t scroll to top · ←→ fold · Enter reuse · Esc back to input",
+                    ),
+            )
+    }
+
+    fn render_recording_status(
+        &self,
+        status: &str,
+        path: Option<PathBuf>,
+        cx: &Context<Self>,
+    ) -> Div {
+        let link = |label: &'static str| {
+            div()
+                .flex_none()
+                .text_size(px(11.0))
+                .text_color(rgb(0x8eb8ff))
+                .cursor(CursorStyle::PointingHand)
+                .hover(|this| this.text_color(rgb(0xc4dcff)))
+                .child(label)
+        };
+        div()
+            .flex()
+            .items_center()
+            .gap(px(10.0))
+            .px(px(12.0))
+            .py(px(6.0))
+            .rounded(px(8.0))
+            .bg(rgb(0x141414))
+            .border_1()
+            .border_color(rgb(0x2a2a2a))
+            .child(lucide_icon(Icon::Film, 13.0, 0x9a9a9a))
+            .child(
+                div()
+                    .flex_1()
+                    .min_w(px(0.0))
+                    .text_size(px(11.0))
+                    .text_color(rgb(0xcfcfcf))
+                    .truncate()
+                    .child(status.to_string()),
+            )
+            .children(path.map(|path| {
+                link("Replay").on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |_view, _event: &MouseDownEvent, _window, cx| {
+                        cx.stop_propagation();
+                        cx.emit(TabViewEvent::ReplayRecording(path.clone()));
+                    }),
+                )
+            }))
+            .child(link("Dismiss").on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|view, _event: &MouseDownEvent, _window, cx| {
+                    cx.stop_propagation();
+                    view.recording_status = None;
+                    cx.notify();
+                }),
+            ))
+    }
+
+    fn render_replay_bar(&self, replay: &ReplayState, cx: &Context<Self>) -> Div {
+        let finished = replay.next_event >= replay.cast.events.len();
+        let button = |icon: Icon| {
+            div()
+                .flex()
+                .flex_none()
+                .items_center()
+                .justify_center()
+                .size(px(26.0))
+                .rounded(px(6.0))
+                .bg(rgb(0x141414))
+                .border_1()
+                .border_color(rgb(0x2a2a2a))
+                .hover(|this| this.bg(rgb(0x242424)).border_color(rgb(0x4a4a4a)))
+                .cursor(CursorStyle::PointingHand)
+                .child(lucide_icon(icon, 13.0, 0xdddddd))
+        };
+        let speeds = REPLAY_SPEEDS.into_iter().map(|speed| {
+            let on = replay.speed == speed;
+            div()
+                .flex_none()
+                .px(px(8.0))
+                .py(px(2.0))
+                .rounded(px(10.0))
+                .text_size(px(11.0))
+                .border_1()
+                .border_color(if on { rgb(0x3f669c) } else { rgb(0x2a2a2a) })
+                .bg(if on { rgb(0x1b283a) } else { rgb(0x141414) })
+                .text_color(if on { rgb(0x8eb8ff) } else { rgb(0x8a8a8a) })
+                .cursor(CursorStyle::PointingHand)
+                .child(format!("{speed}×"))
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |view, _event: &MouseDownEvent, _window, cx| {
+                        cx.stop_propagation();
+                        view.set_replay_speed(speed, cx);
+                    }),
+                )
+        });
+        let name = replay
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        div()
+            .flex()
+            .items_center()
+            .gap(px(10.0))
+            .px(px(12.0))
+            .py(px(8.0))
+            .rounded(px(10.0))
+            .bg(rgb(0x1a1a1a))
+            .border_1()
+            .border_color(rgb(0x2a2a2a))
+            .child(
+                button(if replay.playing {
+                    Icon::Pause
+                } else {
+                    Icon::Play
+                })
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(|view, _event: &MouseDownEvent, _window, cx| {
+                        cx.stop_propagation();
+                        view.toggle_replay_playing(cx);
+                    }),
+                ),
+            )
+            .child(button(Icon::RotateCcw).on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|view, _event: &MouseDownEvent, _window, cx| {
+                    cx.stop_propagation();
+                    view.restart_replay_from_start(cx);
+                }),
+            ))
+            .child(
+                div()
+                    .flex_1()
+                    .min_w(px(0.0))
+                    .flex()
+                    .flex_col()
+                    .child(
+                        div()
+                            .text_size(px(12.0))
+                            .text_color(rgb(0xe6e6e6))
+                            .truncate()
+                            .child(format!("Replaying {name}")),
+                    )
+                    .child(div().text_size(px(11.0)).text_color(rgb(0x7f8b99)).child(
+                        if finished {
+                            "Finished".to_string()
+                        } else {
+                            format!(
+                                "{} of {} · read-only, Space to pause",
+                                format_command_duration(Duration::from_secs_f64(replay.position)),
+                                format_command_duration(Duration::from_secs_f64(
+                                    replay.cast.duration()
+                                )),
+                            )
+                        },
+                    )),
+            )
+            .children(speeds)
+    }
+
+    fn render_watch_bar(&self, watch: &WatchState, cx: &Context<Self>) -> Div {
+        let chip = |label: String, on: bool| {
+            div()
+                .flex_none()
+                .px(px(8.0))
+                .py(px(2.0))
+                .rounded(px(10.0))
+                .text_size(px(11.0))
+                .border_1()
+                .border_color(if on { rgb(0x2b7a8f) } else { rgb(0x2a2a2a) })
+                .bg(if on { rgb(0x0e2a33) } else { rgb(0x141414) })
+                .text_color(if on { rgb(0xd6f3ff) } else { rgb(0x8a8a8a) })
+                .cursor(CursorStyle::PointingHand)
+                .child(label)
+        };
+        let triggers = std::iter::once(WatchTrigger::FileChanges)
+            .chain(WATCH_INTERVALS.into_iter().map(WatchTrigger::Interval))
+            .map(|trigger| {
+                let label = match trigger {
+                    WatchTrigger::FileChanges => "Files".to_string(),
+                    WatchTrigger::Interval(secs) => format!("{secs}s"),
+                };
+                chip(label, watch.trigger == trigger).on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |view, _event: &MouseDownEvent, _window, cx| {
+                        cx.stop_propagation();
+                        view.set_watch_trigger(trigger, cx);
+                    }),
+                )
+            });
+        let outputs = [
+            (WatchOutput::Replace, "Replace"),
+            (WatchOutput::Append, "Append"),
+        ]
+        .into_iter()
+        .map(|(output, label)| {
+            chip(label.to_string(), watch.output == output).on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |view, _event: &MouseDownEvent, _window, cx| {
+                    cx.stop_propagation();
+                    view.set_watch_output(output, cx);
+                }),
+            )
+        });
+        let runs = watch.runs.iter().map(|run| {
+            let number = run.number;
+            let color = match run.exit_code {
+                Some(0) => 0x7ee787,
+                Some(_) => 0xff7b72,
+                None => 0x9a9a9a,
+            };
+            div()
+                .flex_none()
+                .px(px(6.0))
+                .py(px(2.0))
+                .rounded(px(6.0))
+                .bg(rgb(0x141414))
+                .border_1()
+                .border_color(rgb(0x2a2a2a))
+                .hover(|this| this.border_color(rgb(0x4a4a4a)))
+                .text_size(px(11.0))
+                .text_color(rgb(color))
+                .cursor(CursorStyle::PointingHand)
+                .child(format!("#{number}"))
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |view, _event: &MouseDownEvent, _window, cx| {
+                        cx.stop_propagation();
+                        view.open_watch_run_diff(number, cx);
+                    }),
+                )
+        });
+        let status = if watch.due {
+            "Changes seen, waiting for the prompt"
+        } else {
+            match watch.trigger {
+                WatchTrigger::FileChanges => "Watching files",
+                WatchTrigger::Interval(_) => "Re-running on a timer",
+            }
+        };
+        let stop_index = watch.block_index;
+
+        div()
+            .flex()
+            .flex_col()
+            .gap(px(6.0))
+            .px(px(12.0))
+            .py(px(8.0))
+            .rounded(px(10.0))
+            .bg(rgb(0x0f1d21))
+            .border_1()
+            .border_color(rgb(0x2b7a8f))
+            .child(
+                div()
+                    .flex()
+                    .items_center()
+                    .gap(px(8.0))
+                    .child(lucide_icon(Icon::RefreshCw, 14.0, 0x7fd4e8))
+                    .child(
+                        div()
+                            .flex_1()
+                            .min_w(px(0.0))
+                            .text_size(px(13.0))
+                            .text_color(rgb(0xe6e6e6))
+                            .truncate()
+                            .child(self.secret_scanner().mask(&watch.command).into_owned()),
+                    )
+                    .child(
+                        div()
+                            .flex_none()
+                            .text_size(px(11.0))
+                            .text_color(rgb(0x7f8b99))
+                            .child(status),
+                    )
+                    .child(chip("Stop".to_string(), false).on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |view, _event: &MouseDownEvent, _window, cx| {
+                            cx.stop_propagation();
+                            match stop_index {
+                                Some(index) => view.toggle_watch(index, cx),
+                                None => {
+                                    view.watch = None;
+                                    cx.notify();
+                                }
+                            }
+                        }),
+                    )),
+            )
+            .child(
+                div()
+                    .flex()
+                    .flex_wrap()
+                    .items_center()
+                    .gap(px(6.0))
+                    .children(triggers)
+                    .child(div().w(px(8.0)))
+                    .children(outputs)
+                    .children((!watch.runs.is_empty()).then(|| {
+                        div()
+                            .ml(px(8.0))
+                            .text_size(px(11.0))
+                            .text_color(rgb(0x7f7f7f))
+                            .child("Earlier runs, click to compare:")
+                    }))
+                    .children(runs),
+            )
+    }
+
+    fn render_secure_input_bar(secure: &SecureInput) -> Div {
+        div()
+            .flex()
+            .items_center()
+            .gap(px(10.0))
+            .px(px(12.0))
+            .py(px(10.0))
+            .rounded(px(10.0))
+            .bg(rgb(0x141414))
+            .border_1()
+            .border_color(rgb(0x6b5a2a))
+            .child(lucide_icon(Icon::LockKeyhole, 16.0, 0xe0a458))
+            .child(
+                div()
+                    .flex_none()
+                    .text_size(px(13.0))
+                    .text_color(rgb(0xbdbdbd))
+                    .child(secure.prompt.clone()),
+            )
+            .child(
+                div()
+                    .flex_1()
+                    .min_w(px(0.0))
+                    .truncate()
+                    .text_size(px(15.0))
+                    .text_color(rgb(0xdddddd))
+                    .font_family("Cascadia Code")
+                    .child("•".repeat(secure.typed.min(64))),
+            )
+            .child(
+                div()
+                    .flex_none()
+                    .text_size(px(11.0))
+                    .text_color(rgb(0x7f7f7f))
+                    .child("Secure input · sent to the program, not saved to history"),
+            )
+    }
+
+    fn render_guard_confirmation(pending: &PendingGuard, cx: &Context<Self>) -> Div {
+        let button = |label: &'static str, hint: &'static str, danger: bool| {
+            div()
+                .flex()
+                .flex_none()
+                .items_center()
+                .gap(px(6.0))
+                .px(px(8.0))
+                .py(px(2.0))
+                .rounded(px(6.0))
+                .text_size(px(11.0))
+                .border_1()
+                .border_color(if danger { rgb(0x8f3b3b) } else { rgb(0x2a2a2a) })
+                .bg(if danger { rgb(0x3a1414) } else { rgb(0x141414) })
+                .text_color(if danger { rgb(0xffb4b4) } else { rgb(0xbdbdbd) })
+                .cursor(clickable_cursor())
+                .child(label)
+                .child(div().text_color(rgb(0x6f6f6f)).child(hint))
+        };
+        let reasons = pending.matches.iter().map(|found| {
+            div()
+                .flex()
+                .min_w(px(0.0))
+                .gap(px(6.0))
+                .text_size(px(11.0))
+                .child(
+                    div()
+                        .flex_none()
+                        .text_color(rgb(0xffb4b4))
+                        .child(found.rule.clone()),
+                )
+                .child(
+                    div()
+                        .min_w(px(0.0))
+                        .truncate()
+                        .text_color(rgb(0x8a8a8a))
+                        .child(found.reason.clone()),
+                )
+        });
+        div()
+            .flex()
+            .items_start()
+            .gap(px(8.0))
+            .px(px(8.0))
+            .py(px(6.0))
+            .rounded(px(8.0))
+            .bg(rgb(0x1f1212))
+            .border_1()
+            .border_color(rgb(0x5a2a2a))
+            .child(lucide_icon(Icon::TriangleAlert, 14.0, 0xff7b72))
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .flex_1()
+                    .min_w(px(0.0))
+                    .gap(px(2.0))
+                    .child(
+                        div()
+                            .text_size(px(12.0))
+                            .text_color(rgb(0xe6e6e6))
+                            .child("This command looks destructive. Run it anyway?"),
+                    )
+                    .children(reasons),
+            )
+            .child(button("Run anyway", "Enter", true).on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|view, _event: &MouseDownEvent, _window, cx| {
+                    view.confirm_guarded_command(cx);
+                    cx.stop_propagation();
+                }),
+            ))
+            .child(button("Cancel", "Esc", false).on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|view, _event: &MouseDownEvent, _window, cx| {
+                    view.pending_guard = None;
+                    cx.notify();
+                    cx.stop_propagation();
+                }),
+            ))
+    }
+
     fn render_input_bar(&mut self, window: &Window, cx: &Context<Self>) -> Div {
         let is_focused = self.focus_handle.is_focused(window);
         let action_button = |icon: Icon| {
@@ -3858,7 +7978,12 @@ impl TabView {
         let model_button_state = self.current_model_button_state();
         let input_wrap_chars = Self::input_wrap_chars(window);
         let input_line_count = Self::rendered_input_line_count(&self.input, input_wrap_chars);
+        let guard_height = self
+            .pending_guard
+            .as_ref()
+            .map_or(0.0, |pending| 20.0 + pending.matches.len() as f32 * 18.0);
         let input_bar_height = 84.0
+            + guard_height
             + (input_line_count.saturating_sub(1) as f32 * (INPUT_LINE_HEIGHT + INPUT_LINE_GAP));
 
         div()
@@ -3874,6 +7999,11 @@ impl TabView {
             .border_color(rgb(0x2a2a2a))
             .rounded(px(10.0))
             .on_mouse_down(gpui::MouseButton::Left, cx.listener(Self::on_focus_input))
+            .children(
+                self.pending_guard
+                    .as_ref()
+                    .map(|pending| Self::render_guard_confirmation(pending, cx)),
+            )
             .child(
                 // Meta row (path + git)
                 div()
@@ -4199,6 +8329,66 @@ impl TabView {
                             .flex_none()
                             .items_center()
                             .gap(px(10.0))
+                            .child(if let Some(label) = self.editor.indicator() {
+                                div()
+                                    .px(px(6.0))
+                                    .py(px(2.0))
+                                    .rounded(px(4.0))
+                                    .bg(rgb(0x1b283a))
+                                    .border_1()
+                                    .border_color(rgb(0x3f669c))
+                                    .text_size(px(10.0))
+                                    .text_color(rgb(0x8eb8ff))
+                                    .child(label)
+                            } else {
+                                div()
+                            })
+                            .child(
+                                if let Some(recording) = &self.recording {
+                                    div()
+                                        .flex()
+                                        .flex_none()
+                                        .items_center()
+                                        .gap(px(6.0))
+                                        .h(px(24.0))
+                                        .px(px(8.0))
+                                        .rounded(px(6.0))
+                                        .bg(rgb(0x2a1515))
+                                        .border_1()
+                                        .border_color(rgb(0x7a2b2b))
+                                        .cursor(CursorStyle::PointingHand)
+                                        .child(
+                                            div().size(px(8.0)).rounded(px(4.0)).bg(rgb(0xff5f56)),
+                                        )
+                                        .child(
+                                            div()
+                                                .text_size(px(10.0))
+                                                .text_color(rgb(0xffa3a3))
+                                                .child(format!(
+                                                    "REC {}",
+                                                    format_command_duration(
+                                                        recording.recorder.elapsed()
+                                                    )
+                                                )),
+                                        )
+                                } else {
+                                    action_button(Icon::Circle)
+                                }
+                                .on_mouse_down(
+                                    gpui::MouseButton::Left,
+                                    cx.listener(|view, _event: &MouseDownEvent, _window, cx| {
+                                        cx.stop_propagation();
+                                        view.toggle_recording(cx);
+                                    }),
+                                ),
+                            )
+                            .child(action_button(Icon::Activity).on_mouse_down(
+                                gpui::MouseButton::Left,
+                                cx.listener(|view, _event: &MouseDownEvent, _window, cx| {
+                                    cx.stop_propagation();
+                                    view.toggle_process_panel(cx);
+                                }),
+                            ))
                             .child(action_button(Icon::Clipboard).on_mouse_down(
                                 gpui::MouseButton::Left,
                                 cx.listener(Self::on_copy_output),
@@ -4212,7 +8402,7 @@ impl TabView {
 
     fn render_input_text(&self, is_focused: bool, input_wrap_chars: usize) -> Div {
         let show_placeholder = self.input.is_empty();
-        let input_wraps = self.input.chars().count() > input_wrap_chars;
+        let input_wraps = str_width(&self.input) > input_wrap_chars;
         let ghost = if input_wraps {
             String::new()
         } else {
@@ -4234,15 +8424,42 @@ impl TabView {
                 })
         };
 
-        let text_normal = |text: String| {
+        let text_colored = |text: String, color: u32| {
             div()
                 .min_w(px(0.0))
                 .text_size(px(15.0))
-                .text_color(rgb(0xdddddd))
+                .text_color(rgb(color))
                 .font_family("Cascadia Code")
                 .truncate()
                 .child(text)
         };
+        let text_normal = |text: String| text_colored(text, HighlightKind::Plain.color());
+
+        // Chars `start..end` of the input, colored by shell syntax.
+        let chars: Vec<char> = self.input.chars().collect();
+        let spans = if self.input_mode == InputMode::Terminal {
+            highlight(&self.input, |command| self.is_known_command(command))
+        } else {
+            Vec::new()
+        };
+        let text_styled = |start: usize, end: usize| {
+            let end = end.min(chars.len());
+            let start = start.min(end);
+            if spans.is_empty() {
+                return text_normal(chars[start..end].iter().collect());
+            }
+            div().flex().flex_none().min_w(px(0.0)).children(
+                spans
+                    .iter()
+                    .filter(|span| span.start < end && span.end > start)
+                    .map(|span| {
+                        let text = chars[span.start.max(start)..span.end.min(end)]
+                            .iter()
+                            .collect();
+                        text_colored(text, span.kind.color())
+                    }),
+            )
+        };
 
         let text_selected = |text: String| {
             div()
@@ -4287,7 +8504,50 @@ impl TabView {
             return row;
         }
 
+        let rows = wrap_ranges(&self.input, input_wrap_chars);
+        let first_visible = |max_lines: usize| {
+            rows[Self::visible_input_lines(&rows, self.cursor, max_lines).start].start
+        };
+        if !self.extra_cursors.is_empty() {
+            let mut offset = first_visible(MAX_RENDERED_INPUT_LINES.max(1));
+            let lines = Self::multi_cursor_lines(
+                &self.input,
+                &self.input_cursor_ranges(),
+                input_wrap_chars,
+                MAX_RENDERED_INPUT_LINES,
+                self.cursor,
+            );
+            return div()
+                .flex()
+                .flex_col()
+                .flex_1()
+                .min_w(px(0.0))
+                .overflow_hidden()
+                .gap(px(INPUT_LINE_GAP))
+                .children(lines.into_iter().map(|segments| {
+                    div()
+                        .flex()
+                        .min_w(px(0.0))
+                        .overflow_hidden()
+                        .h(px(INPUT_LINE_HEIGHT))
+                        .gap(px(0.0))
+                        .children(segments.into_iter().map(|segment| match segment {
+                            InputSegment::Text(text) => {
+                                let start = offset;
+                                offset += text.chars().count();
+                                text_styled(start, offset)
+                            }
+                            InputSegment::Selected(text) => {
+                                offset += text.chars().count();
+                                text_selected(text)
+                            }
+                            InputSegment::Caret => caret(),
+                        }))
+                }));
+        }
+
         if input_wraps {
+            let mut offset = first_visible(MAX_RENDERED_INPUT_LINES);
             let lines = Self::input_visual_lines(
                 &self.input,
                 self.cursor,
@@ -4308,54 +8568,54 @@ impl TabView {
                         .overflow_hidden()
                         .h(px(INPUT_LINE_HEIGHT))
                         .gap(px(0.0));
+                    let start = offset;
+                    let split = start + line.left.chars().count();
+                    offset = split + line.right.chars().count();
                     if line.has_cursor {
                         line_row = line_row
-                            .child(text_normal(line.left))
+                            .child(text_styled(start, split))
                             .child(caret())
-                            .child(text_normal(line.right));
+                            .child(text_styled(split, offset));
                     } else {
-                        line_row =
-                            line_row.child(text_normal(format!("{}{}", line.left, line.right)));
+                        line_row = line_row.child(text_styled(start, offset));
                     }
                     line_row
                 }));
         } else if let Some((a, b)) = self.normalized_selection().filter(|(a, b)| a != b) {
-            let (pre, rest) = Self::split_at_index(&self.input, a);
-            let (sel, post) = Self::split_at_index(&rest, b.saturating_sub(a));
+            let (_, rest) = Self::split_at_index(&self.input, a);
+            let (sel, _) = Self::split_at_index(&rest, b.saturating_sub(a));
+            let len = chars.len();
 
             if self.cursor <= a {
-                let (pre_left, pre_right) = Self::split_at_index(&pre, self.cursor.min(a));
+                let cursor = self.cursor.min(a);
                 row = row
-                    .child(text_normal(pre_left))
+                    .child(text_styled(0, cursor))
                     .child(caret())
-                    .child(text_normal(pre_right))
+                    .child(text_styled(cursor, a))
                     .child(text_selected(sel))
-                    .child(text_normal(post));
+                    .child(text_styled(b, len));
             } else if self.cursor >= b {
-                let post_cursor = self.cursor.saturating_sub(b);
-                let (post_left, post_right) = Self::split_at_index(&post, post_cursor);
                 row = row
-                    .child(text_normal(pre))
+                    .child(text_styled(0, a))
                     .child(text_selected(sel))
-                    .child(text_normal(post_left))
+                    .child(text_styled(b, self.cursor))
                     .child(caret())
-                    .child(text_normal(post_right));
+                    .child(text_styled(self.cursor, len));
             } else {
                 let sel_cursor = self.cursor.saturating_sub(a);
                 let (sel_left, sel_right) = Self::split_at_index(&sel, sel_cursor);
                 row = row
-                    .child(text_normal(pre))
+                    .child(text_styled(0, a))
                     .child(text_selected(sel_left))
                     .child(caret())
                     .child(text_selected(sel_right))
-                    .child(text_normal(post));
+                    .child(text_styled(b, len));
             }
         } else {
-            let (left, right) = self.split_at_cursor();
             row = row
-                .child(text_normal(left))
+                .child(text_styled(0, self.cursor))
                 .child(caret())
-                .child(text_normal(right));
+                .child(text_styled(self.cursor, chars.len()));
             if show_ghost {
                 row = row.child(ghost_div);
             }
@@ -4697,6 +8957,13 @@ impl TabView {
                 agent_stream_line_index: None,
                 agent_response: None,
                 agent_response_line_count: 0,
+                exit_code: None,
+                duration: None,
+                secrets_revealed: false,
+                collapsed: false,
+                styled_lines: BTreeMap::new(),
+                tests: None,
+                private_keys: RefCell::default(),
             });
         }
         if !normalized.trim().is_empty() {
@@ -4769,6 +9036,13 @@ impl TabView {
                 agent_stream_line_index: None,
                 agent_response: None,
                 agent_response_line_count: 0,
+                exit_code: None,
+                duration: None,
+                secrets_revealed: false,
+                collapsed: false,
+                styled_lines: BTreeMap::new(),
+                tests: None,
+                private_keys: RefCell::default(),
             });
             self.selected_block = self.blocks.len().checked_sub(1);
             self.clear_output_selection();
@@ -4801,6 +9075,13 @@ impl TabView {
             agent_stream_line_index: None,
             agent_response: None,
             agent_response_line_count: 0,
+            exit_code: None,
+            duration: None,
+            secrets_revealed: false,
+            collapsed: false,
+            styled_lines: BTreeMap::new(),
+            tests: None,
+            private_keys: RefCell::default(),
         });
         self.selected_block = self.blocks.len().checked_sub(1);
         self.clear_output_selection();
@@ -4810,10 +9091,13 @@ impl TabView {
         self.agent_needs_auth = false;
         self.input.clear();
         self.cursor = 0;
+        self.editor.reset_line();
+        self.extra_cursors.clear();
         self.history_open = false;
         self.history_items.clear();
         self.suggestions.clear();
         self.suggest_index = 0;
+        self.suggestion_menu_open = false;
         self.clear_selection();
         self.overlay = None;
         cx.notify();
@@ -4837,6 +9121,7 @@ impl TabView {
         let session_cwd = expand_tilde(&self.current_path)
             .to_string_lossy()
             .to_string();
+        let prompt = self.secret_scanner().redact(&prompt).into_owned();
 
         let (tx, mut rx) = mpsc::unbounded::<AgentPromptEvent>();
         thread::spawn(move || {
@@ -5055,11 +9340,14 @@ impl TabView {
             if self.input_mode == InputMode::Terminal {
                 if let Some(ref mut pty) = self.pty {
                     let _ = pty.write(b"\r\n");
+                    self.record(CastEventKind::Input, "\r\n");
                 }
             }
             self.input.clear();
             self.cursor = 0;
             self.clear_selection();
+            self.editor.reset_line();
+            self.extra_cursors.clear();
             cx.notify();
             return;
         }
@@ -5116,20 +9404,71 @@ impl TabView {
         }
     }
 
+    /// Runs `command`, unless it trips a guard rule. Then the command stays
+    /// in the input and the input bar asks for confirmation.
     fn run_command(&mut self, command: String, cx: &mut Context<Self>) {
         let command = command.trim().to_string();
         if command.is_empty() {
             return;
         }
+        let matches = self.guard_matches(&command);
+        if matches.is_empty() {
+            self.execute_command(command, cx);
+            return;
+        }
+        if self.input.trim() != command {
+            self.input = command.clone();
+            self.cursor = self.input.chars().count();
+            self.clear_selection();
+            self.extra_cursors.clear();
+        }
+        self.input_visible = true;
+        self.overlay = None;
+        self.suggestion_menu_open = false;
+        self.history_open = false;
+        self.pending_guard = Some(PendingGuard { command, matches });
+        cx.notify();
+    }
+
+    fn execute_command(&mut self, command: String, cx: &mut Context<Self>) {
+        self.start_command(command, None, cx);
+
+        self.input.clear();
+        self.cursor = 0;
+        self.editor.reset_line();
+        self.extra_cursors.clear();
+        self.history_open = false;
+        self.history_items.clear();
+        self.suggestions.clear();
+        self.suggest_index = 0;
+        self.suggestion_menu_open = false;
+        self.clear_selection();
+        self.input_visible = false;
+        self.overlay = None;
+        cx.notify();
+    }
 
+    /// Sends `command` to the shell with a block for its output: a new one,
+    /// or `reuse`, whose earlier output is dropped.
+    fn start_command(&mut self, command: String, reuse: Option<usize>, cx: &mut Context<Self>) {
         self.follow_output = true;
         let lower = command.to_ascii_lowercase();
         self.needs_git_refresh =
             lower.starts_with("git checkout") || lower.starts_with("git switch");
 
-        self.push_history(&command);
+        // A command typed before the previous one reported its status (for
+        // example input to an interactive program) finishes it unknown.
+        self.finish_running_command(None, cx);
+        // Replayed commands are not the user's.
+        let history_id = if self.replay.is_some() {
+            None
+        } else {
+            self.push_history(&command)
+        };
+        self.record(CastEventKind::Marker, &command);
+        self.record(CastEventKind::Input, &format!("{command}\r\n"));
         self.pending_echo = Some(command.clone());
-        self.blocks.push(Block {
+        let block = Block {
             command: command.clone(),
             output_lines: Vec::new(),
             has_error: false,
@@ -5147,32 +9486,109 @@ impl TabView {
             agent_stream_line_index: None,
             agent_response: None,
             agent_response_line_count: 0,
+            exit_code: None,
+            duration: None,
+            secrets_revealed: false,
+            collapsed: false,
+            styled_lines: BTreeMap::new(),
+            tests: None,
+            private_keys: RefCell::default(),
+        };
+        let index = match reuse.filter(|index| *index < self.blocks.len()) {
+            Some(index) => {
+                let old = std::mem::replace(&mut self.blocks[index], block);
+                self.total_output_lines = self
+                    .total_output_lines
+                    .saturating_sub(old.output_lines.len());
+                index
+            }
+            None => {
+                self.blocks.push(block);
+                self.blocks.len() - 1
+            }
+        };
+        self.selected_block = Some(index);
+        if let Some(runbook) = self.runbook.as_mut()
+            && runbook
+                .pending
+                .as_ref()
+                .is_some_and(|(_, pending)| *pending == command)
+            && let Some((key, _)) = runbook.pending.take()
+        {
+            runbook.running = Some((key, index));
+        }
+        self.running_command = history_id.map(|history_id| RunningCommand {
+            history_id,
+            block_index: Some(index),
+            started_at: Instant::now(),
         });
-        self.selected_block = self.blocks.len().checked_sub(1);
         self.clear_output_selection();
         self.last_line_incomplete = false;
+        self.input_visible = false;
 
         if let Some(ref mut pty) = self.pty {
             let _ = pty.write(format!("{command}\r\n").as_bytes());
         }
-
-        self.input.clear();
-        self.cursor = 0;
-        self.history_open = false;
-        self.history_items.clear();
-        self.suggestions.clear();
-        self.suggest_index = 0;
-        self.clear_selection();
-        self.input_visible = false;
-        self.overlay = None;
-        cx.notify();
     }
 
-    fn render_history_menu(&self) -> Div {
-        if !self.history_open || self.history_items.is_empty() {
+    fn render_history_menu(&self, cx: &Context<Self>) -> Div {
+        if !self.history_open {
             return div();
         }
 
+        let chips = [
+            HistoryFilter::All,
+            HistoryFilter::Failed,
+            HistoryFilter::Succeeded,
+        ]
+        .into_iter()
+        .map(|filter| {
+            let is_active = filter == self.history_filter;
+            div()
+                .px(px(8.0))
+                .py(px(2.0))
+                .rounded(px(10.0))
+                .text_size(px(11.0))
+                .border_1()
+                .border_color(if is_active {
+                    rgb(0x2b7a8f)
+                } else {
+                    rgb(0x2a2a2a)
+                })
+                .bg(if is_active {
+                    rgb(0x0e2a33)
+                } else {
+                    rgb(0x141414)
+                })
+                .text_color(if is_active {
+                    rgb(0xd6f3ff)
+                } else {
+                    rgb(0x8a8a8a)
+                })
+                .cursor(CursorStyle::PointingHand)
+                .child(filter.label())
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |view, _event: &MouseDownEvent, _window, cx| {
+                        view.cycle_history_filter(Some(filter));
+                        cx.notify();
+                        cx.stop_propagation();
+                    }),
+                )
+        });
+        let header = div()
+            .flex()
+            .items_center()
+            .gap(px(6.0))
+            .children(chips)
+            .child(
+                div()
+                    .ml_auto()
+                    .text_size(px(10.0))
+                    .text_color(rgb(0x5c5c5c))
+                    .child("Ctrl+F to filter"),
+            );
+
         let max_items = 8usize.min(self.history_items.len());
         let items = self
             .history_items
@@ -5198,37 +9614,128 @@ impl TabView {
                     } else {
                         rgb(0x1a1a1a)
                     })
+                    .justify_between()
+                    .gap(px(16.0))
+                    .child(
+                        div()
+                            .flex_none()
+                            .text_size(px(12.0))
+                            .text_color(rgb(0xcccccc))
+                            .font_family("Cascadia Code")
+                            .child(item.display.clone()),
+                    )
+                    .child(
+                        div()
+                            .min_w(px(0.0))
+                            .truncate()
+                            .text_size(px(11.0))
+                            .text_color(rgb(0x7c7c7c))
+                            .child(item.description.clone().unwrap_or_default()),
+                    )
+            });
+        let empty = self.history_items.is_empty().then(|| {
+            div()
+                .px(px(10.0))
+                .py(px(6.0))
+                .text_size(px(12.0))
+                .text_color(rgb(0x7a7a7a))
+                .child(format!(
+                    "No {} commands match",
+                    self.history_filter.label().to_ascii_lowercase()
+                ))
+        });
+
+        div()
+            .flex()
+            .flex_col()
+            .gap(px(6.0))
+            .p(px(8.0))
+            .rounded(px(8.0))
+            .bg(rgb(0x0f0f0f))
+            .border_1()
+            .border_color(rgb(0x1f1f1f))
+            .child(header)
+            .children(items)
+            .children(empty)
+    }
+
+    fn render_suggestion_menu(&self) -> Div {
+        let items = self
+            .suggestions
+            .iter()
+            .take(MAX_SUGGESTION_MENU_ITEMS)
+            .enumerate()
+            .map(|(i, item)| {
+                let is_active = i == self.suggest_index;
+                div()
+                    .flex()
+                    .items_center()
+                    .justify_between()
+                    .gap(px(16.0))
+                    .px(px(10.0))
+                    .py(px(5.0))
+                    .rounded(px(6.0))
+                    .bg(if is_active {
+                        rgb(0x1f1f1f)
+                    } else {
+                        rgb(0x111111)
+                    })
+                    .border_1()
+                    .border_color(if is_active {
+                        rgb(0x2d2d2d)
+                    } else {
+                        rgb(0x1a1a1a)
+                    })
                     .child(
                         div()
+                            .flex_none()
                             .text_size(px(12.0))
                             .text_color(rgb(0xcccccc))
                             .font_family("Cascadia Code")
                             .child(item.display.clone()),
                     )
+                    .child(
+                        div()
+                            .min_w(px(0.0))
+                            .truncate()
+                            .text_size(px(11.0))
+                            .text_color(rgb(0x7c7c7c))
+                            .child(item.description.clone().unwrap_or_default()),
+                    )
             });
 
         div()
             .flex()
             .flex_col()
-            .gap(px(6.0))
+            .gap(px(4.0))
             .p(px(8.0))
             .rounded(px(8.0))
             .bg(rgb(0x0f0f0f))
             .border_1()
             .border_color(rgb(0x1f1f1f))
-            .child(div().h(px(2.0)))
             .children(items)
     }
 
-    fn render_history_menu_container(&self) -> Div {
-        if !self.history_open || self.history_items.is_empty() {
+    fn render_suggestion_menu_container(&self) -> Div {
+        if !self.suggestion_menu_open || self.history_open || self.suggestions.is_empty() {
+            return div().h(px(0.0));
+        }
+
+        div()
+            .px(px(16.0))
+            .pb(px(8.0))
+            .child(self.render_suggestion_menu())
+    }
+
+    fn render_history_menu_container(&self, cx: &Context<Self>) -> Div {
+        if !self.history_open {
             return div().h(px(0.0));
         }
 
         div()
             .px(px(16.0))
             .pb(px(8.0))
-            .child(self.render_history_menu())
+            .child(self.render_history_menu(cx))
     }
 
     fn render_overlay(&self, cx: &Context<Self>) -> Div {
@@ -5241,6 +9748,7 @@ impl TabView {
             Overlay::Branch(picker) => self.render_branch_picker(picker, cx),
             Overlay::Agent(picker) => self.render_agent_picker(picker, cx),
             Overlay::Model(picker) => self.render_model_picker(picker, cx),
+            Overlay::History(picker) => self.render_history_search(picker, cx),
         };
 
         div()
@@ -5506,6 +10014,135 @@ impl TabView {
             )
     }
 
+    fn render_history_search(&self, picker: &HistorySearchState, cx: &Context<Self>) -> Div {
+        let handle = cx.entity().downgrade();
+        let now = now_timestamp();
+
+        let items = picker.matches.iter().enumerate().map(|(i, found)| {
+            let is_active = i == picker.selected;
+            let command = &found.ranked.command;
+            let first_line = command.lines().next().unwrap_or_default();
+            let mut meta = Vec::new();
+            if let Some(cwd) = found.ranked.last_cwd.as_deref() {
+                meta.push(Self::format_path(Path::new(cwd)));
+            }
+            meta.push(format_relative_time(found.ranked.last_run, now));
+            div()
+                .flex()
+                .items_center()
+                .justify_between()
+                .gap(px(16.0))
+                .px(px(12.0))
+                .py(px(6.0))
+                .rounded(px(6.0))
+                .bg(if is_active {
+                    rgb(0x1f2a2f)
+                } else {
+                    rgb(0x1a1a1a)
+                })
+                .border_1()
+                .border_color(if is_active {
+                    rgb(0x27404a)
+                } else {
+                    rgb(0x1f1f1f)
+                })
+                .cursor(CursorStyle::PointingHand)
+                .child(
+                    div()
+                        .flex()
+                        .items_center()
+                        .gap(px(6.0))
+                        .min_w(px(0.0))
+                        .overflow_hidden()
+                        .child(render_highlighted_text(first_line, &found.positions, 0))
+                        .children(command.contains('\n').then(|| {
+                            div()
+                                .flex_none()
+                                .text_size(px(11.0))
+                                .text_color(rgb(0x6f6f6f))
+                                .child(format!("+{} lines", command.lines().count() - 1))
+                        })),
+                )
+                .child(
+                    div()
+                        .flex_none()
+                        .text_size(px(11.0))
+                        .text_color(rgb(0x7a7a7a))
+                        .child(meta.join("  ")),
+                )
+                .on_mouse_down(gpui::MouseButton::Left, {
+                    let handle = handle.clone();
+                    move |_event, _window, cx| {
+                        let _ = handle.update(cx, |view, cx| {
+                            view.on_history_search_select(i, cx);
+                        });
+                    }
+                })
+        });
+
+        let preview = picker.matches.get(picker.selected).map(|found| {
+            let mut offset = 0;
+            let lines = found.ranked.command.split('\n').map(|line| {
+                let row = render_highlighted_text(line, &found.positions, offset);
+                offset += line.chars().count() + 1;
+                row
+            });
+            div()
+                .flex()
+                .flex_col()
+                .gap(px(2.0))
+                .px(px(12.0))
+                .py(px(8.0))
+                .rounded(px(6.0))
+                .bg(rgb(0x111111))
+                .border_1()
+                .border_color(rgb(0x1f1f1f))
+                .max_h(px(140.0))
+                .overflow_hidden()
+                .children(lines)
+        });
+
+        let empty = picker.matches.is_empty().then(|| {
+            div()
+                .px(px(12.0))
+                .py(px(8.0))
+                .text_size(px(12.0))
+                .text_color(rgb(0x7a7a7a))
+                .child("No matching commands")
+        });
+
+        div()
+            .absolute()
+            .left(px(16.0))
+            .right(px(16.0))
+            .bottom(px(120.0))
+            .rounded(px(10.0))
+            .bg(rgb(0x171717))
+            .border_1()
+            .border_color(rgb(0x2a2a2a))
+            .p(px(10.0))
+            .flex()
+            .flex_col()
+            .gap(px(8.0))
+            .child(self.render_picker_query_input(&picker.query, "Search history..."))
+            .child(
+                div()
+                    .id("history_search_list")
+                    .flex_col()
+                    .gap(px(6.0))
+                    .max_h(px(260.0))
+                    .overflow_y_scroll()
+                    .children(items)
+                    .children(empty),
+            )
+            .children(preview)
+            .child(
+                div().text_size(px(10.0)).text_color(rgb(0x5c5c5c)).child(
+                    "Enter insert  Ctrl+Enter run  Shift+Delete remove  Ctrl+R next  Esc close",
+                ),
+            )
+    }
+
     fn render_agent_picker(&self, picker: &AgentPickerState, cx: &Context<Self>) -> Div {
         let handle = cx.entity().downgrade();
         let search_visible = picker_has_search_input(PickerKind::Agent, picker.all_options.len());
@@ -5752,20 +10389,176 @@ impl TabView {
         self.accept_overlay_selection(cx);
     }
 
-    fn push_history(&mut self, command: &str) {
-        if self.history.front().map(|c| c == command).unwrap_or(false) {
+    fn push_history(&mut self, command: &str) -> Option<u64> {
+        let cwd = expand_tilde(&self.current_path)
+            .to_string_lossy()
+            .to_string();
+        let git_branch = self.git_status.as_ref().map(|g| g.branch.clone());
+        let command = self.secret_scanner().redact(command);
+        let mut store = self.history_store.lock().ok()?;
+        Some(store.start(&command, Some(cwd), git_branch, now_timestamp()))
+    }
+
+    fn ranked_history(
+        &self,
+        prefix: &str,
+        filter: HistoryFilter,
+        limit: usize,
+    ) -> Vec<RankedCommand> {
+        let cwd = expand_tilde(&self.current_path)
+            .to_string_lossy()
+            .to_string();
+        let Ok(store) = self.history_store.lock() else {
+            return Vec::new();
+        };
+        store.ranked(&HistoryQuery {
+            prefix,
+            cwd: Some(&cwd),
+            filter,
+            now: now_timestamp(),
+            limit,
+        })
+    }
+
+    fn finish_running_command(&mut self, exit_code: Option<i32>, cx: &mut Context<Self>) {
+        let Some(running) = self.running_command.take() else {
             return;
+        };
+        let duration = running.started_at.elapsed();
+        if let Ok(mut store) = self.history_store.lock() {
+            store.finish(running.history_id, exit_code, duration);
+        }
+        if let Some(block) = running
+            .block_index
+            .and_then(|index| self.blocks.get_mut(index))
+        {
+            block.exit_code = exit_code;
+            block.duration = Some(duration);
+            if exit_code.is_some_and(|code| code != 0) {
+                block.has_error = true;
+            }
+            cx.emit(TabViewEvent::CommandFinished {
+                command: block.command.clone(),
+                exit_code,
+                duration,
+            });
+        }
+        if let Some(index) = running.block_index {
+            self.finish_runbook_fence(index, exit_code);
+            self.collect_problems(index, cx);
+            self.collect_test_results(index);
+            self.expire_assumed_dev_servers(index, cx);
         }
-        self.history.push_front(command.to_string());
-        if self.history.len() > 2000 {
-            self.history.pop_back();
+    }
+
+    /// Drops the servers shown without port information for the command in
+    /// block `index`, which has now finished.
+    fn expire_assumed_dev_servers(&mut self, index: usize, cx: &mut Context<Self>) {
+        let before = self.dev_servers.len();
+        self.dev_servers
+            .retain(|server| !(server.assumed && server.block_index == Some(index)));
+        if self.dev_servers.len() != before {
+            self.sync_tab_dev_server(cx);
+            cx.notify();
         }
-        if let Some(path) = self.history_file.clone() {
-            let command = command.to_string();
-            thread::spawn(move || {
-                let _ = Self::append_history_line(&path, &command);
+    }
+
+    fn collect_test_results(&mut self, index: usize) {
+        let Some(block) = self.blocks.get_mut(index) else {
+            return;
+        };
+        block.tests = TestRunner::detect(&block.command)
+            .and_then(|runner| parse_test_report(runner, &block.output_lines))
+            .map(|report| BlockTests {
+                open: report.failed > 0,
+                report,
+                expanded: HashSet::new(),
             });
+    }
+
+    fn toggle_test_failures(&mut self, index: usize, cx: &mut Context<Self>) {
+        if let Some(tests) = self
+            .blocks
+            .get_mut(index)
+            .and_then(|block| block.tests.as_mut())
+        {
+            tests.open = !tests.open;
+            cx.notify();
+        }
+    }
+
+    fn toggle_test_output(&mut self, index: usize, name: &str, cx: &mut Context<Self>) {
+        if let Some(tests) = self
+            .blocks
+            .get_mut(index)
+            .and_then(|block| block.tests.as_mut())
+        {
+            if !tests.expanded.remove(name) {
+                tests.expanded.insert(name.to_string());
+            }
+            cx.notify();
+        }
+    }
+
+    /// Runs the block's command again for `tests` only, or for its failures
+    /// when `tests` is `None`.
+    fn rerun_tests(&mut self, index: usize, tests: Option<Vec<String>>, cx: &mut Context<Self>) {
+        if self.running_command.is_some() {
+            return;
+        }
+        let Some(block) = self.blocks.get(index) else {
+            return;
+        };
+        let tests = tests.unwrap_or_else(|| {
+            block
+                .tests
+                .iter()
+                .flat_map(|tests| tests.report.failures())
+                .map(|test| test.name.clone())
+                .collect()
+        });
+        if let Some(command) = rerun_command(&block.command, &tests) {
+            self.run_command(command, cx);
+        }
+    }
+
+    /// Replaces the problems of the block's command with those in its
+    /// output.
+    fn collect_problems(&mut self, index: usize, cx: &mut Context<Self>) {
+        let Some(block) = self.blocks.get(index) else {
+            return;
+        };
+        let problems = parse_problems(&block.output_lines);
+        let known = self
+            .problem_groups
+            .iter()
+            .any(|group| group.command == block.command);
+        if problems.is_empty() && !known {
+            return;
+        }
+        let command = block.command.clone();
+        let cwd = expand_tilde(
+            block
+                .context
+                .as_ref()
+                .map_or(&self.current_path, |context| &context.cwd),
+        );
+        self.problem_groups.retain(|group| group.command != command);
+        if !problems.is_empty() {
+            self.problem_groups.insert(
+                0,
+                ProblemGroup {
+                    command,
+                    cwd,
+                    problems,
+                },
+            );
         }
+        cx.emit(TabViewEvent::ProblemsChanged);
+    }
+
+    pub fn problem_groups(&self) -> &[ProblemGroup] {
+        &self.problem_groups
     }
 
     fn inline_ghost_text(&self) -> String {
@@ -5785,12 +10578,12 @@ impl TabView {
         false
     }
 
-    fn accept_suggestion(&mut self) {
+    fn accept_suggestion(&mut self, cx: &mut Context<Self>) {
         if let Some(insert) = self.inline_ghost_insert() {
             if !insert.is_empty() {
                 self.insert_text(&insert);
                 self.clear_selection();
-                self.refresh_suggestions();
+                self.refresh_suggestions(cx);
                 self.refresh_history_menu();
                 return;
             }
@@ -5804,7 +10597,7 @@ impl TabView {
         } else {
             return;
         }
-        self.refresh_suggestions();
+        self.refresh_suggestions(cx);
         self.refresh_history_menu();
     }
 
@@ -5819,7 +10612,7 @@ impl TabView {
         self.clear_selection();
         self.history_open = false;
         self.history_items.clear();
-        self.refresh_suggestions();
+        self.refresh_suggestions(cx);
         cx.notify();
     }
 
@@ -5845,69 +10638,183 @@ impl TabView {
         }
     }
 
+    fn cycle_history_filter(&mut self, filter: Option<HistoryFilter>) {
+        self.history_filter = filter.unwrap_or_else(|| self.history_filter.next());
+        self.history_index = 0;
+        self.refresh_history_menu();
+    }
+
     fn refresh_history_menu(&mut self) {
         if !self.history_open {
             return;
         }
         let prefix = self.prefix_at_cursor();
         self.history_items = self
-            .history
-            .iter()
-            .filter(|cmd| cmd.starts_with(&prefix) && cmd.as_str() != prefix)
-            .take(8)
-            .map(|cmd| SuggestionItem {
-                display: cmd.clone(),
-                insert: cmd.clone(),
+            .ranked_history(&prefix, self.history_filter, 8)
+            .into_iter()
+            .map(|ranked| SuggestionItem {
+                description: Some(history_item_description(&ranked)),
+                display: ranked.command.clone(),
+                insert: ranked.command,
             })
             .collect();
-        if self.history_items.is_empty() {
+        // An empty filtered list keeps the menu open so the filter can be
+        // switched back.
+        if self.history_items.is_empty() && self.history_filter == HistoryFilter::All {
             self.history_open = false;
         } else {
-            self.history_index = self.history_index.min(self.history_items.len() - 1);
+            self.history_index = self
+                .history_index
+                .min(self.history_items.len().saturating_sub(1));
         }
     }
 
-    fn refresh_suggestions(&mut self) {
+    fn refresh_suggestions(&mut self, cx: &mut Context<Self>) {
         let prefix = self.prefix_at_cursor();
         if prefix.is_empty() {
             self.suggestions.clear();
             self.suggest_index = 0;
+            self.suggestion_menu_open = false;
             return;
         }
 
-        let mut history_items = Vec::new();
-        for cmd in self.history.iter() {
-            if cmd.starts_with(&prefix) && cmd.as_str() != prefix {
-                history_items.push(SuggestionItem {
-                    display: cmd.clone(),
-                    insert: cmd.clone(),
-                });
-            }
-        }
+        let history_items: Vec<SuggestionItem> = self
+            .ranked_history(&prefix, HistoryFilter::All, 0)
+            .into_iter()
+            .map(|ranked| SuggestionItem {
+                display: ranked.command.clone(),
+                insert: ranked.command,
+                description: None,
+            })
+            .collect();
 
         let token = self.current_token();
-        let mut path_items = Vec::new();
+        let mut argument_items = Vec::new();
         let mut command_items = Vec::new();
+        let spec_completion = if self.input_mode == InputMode::Terminal {
+            let cwd = expand_tilde(&self.current_path);
+            let (completion, pending) = self.completion_engine.complete_cached(&prefix, &cwd);
+            self.run_completion_generators(pending, cwd, cx);
+            completion
+        } else {
+            None
+        };
         if Self::is_path_token(&token) {
-            self.append_path_suggestions(&mut path_items);
-            path_items.sort_by(|a, b| a.display.cmp(&b.display));
+            self.append_path_suggestions(&mut argument_items);
+            argument_items.sort_by(|a, b| a.display.cmp(&b.display));
+        } else if let Some(completion) = spec_completion {
+            match completion {
+                SpecCompletion::Items { token_start, items } => {
+                    let (_, right) = self.split_at_cursor();
+                    let head = &prefix[..token_start];
+                    let separator = if right.starts_with(char::is_whitespace) {
+                        ""
+                    } else {
+                        " "
+                    };
+                    argument_items.extend(items.into_iter().map(|item| SuggestionItem {
+                        insert: format!("{head}{}{separator}{right}", item.value),
+                        display: item.value,
+                        description: item.description,
+                    }));
+                }
+                SpecCompletion::Paths { folders_only } => {
+                    self.append_path_suggestions(&mut argument_items);
+                    if folders_only {
+                        argument_items.retain(|item| item.display.ends_with(['/', '\\']));
+                    }
+                    argument_items.sort_by(|a, b| a.display.cmp(&b.display));
+                }
+            }
         } else if self.is_command_context() {
             self.maybe_refresh_path_commands();
+            // Aliases and functions shadow executables of the same name.
+            let shell_commands = shell::shell_commands();
+            for command in shell_commands {
+                if command.name.starts_with(&prefix) && command.name != prefix {
+                    command_items.push(SuggestionItem {
+                        display: command.name.clone(),
+                        insert: command.name.clone(),
+                        description: Some(command.description()),
+                    });
+                }
+            }
             for cmd in &self.path_commands {
+                if shell_commands.iter().any(|command| command.name == *cmd) {
+                    continue;
+                }
                 if cmd.starts_with(&prefix) && cmd.as_str() != prefix {
                     command_items.push(SuggestionItem {
                         display: cmd.clone(),
                         insert: cmd.clone(),
+                        description: self
+                            .completion_engine
+                            .spec_for(cmd)
+                            .and_then(|spec| spec.description.clone()),
                     });
                 }
             }
             command_items.sort_by(|a, b| a.display.cmp(&b.display));
         }
 
-        self.suggestions = Self::dedupe_suggestions(history_items, path_items, command_items);
+        self.suggestion_menu_open = argument_items
+            .iter()
+            .chain(&command_items)
+            .any(|item| item.description.is_some());
+        self.suggestions = Self::dedupe_suggestions(history_items, argument_items, command_items);
         self.suggest_index = 0;
     }
 
+    /// Runs completion generators on the background executor, then completes
+    /// the input again with their output. One batch runs at a time; whatever
+    /// is typed meanwhile is picked up by the refresh after it.
+    fn run_completion_generators(
+        &mut self,
+        generators: Vec<GeneratorSpec>,
+        cwd: PathBuf,
+        cx: &mut Context<Self>,
+    ) {
+        if generators.is_empty() || self.completion_generators_running {
+            return;
+        }
+        self.completion_generators_running = true;
+        let engine = self.completion_engine.clone();
+        cx.spawn(move |view: WeakEntity<Self>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                // A generator that panics must not leave generators off.
+                cx.background_executor()
+                    .spawn(async move {
+                        let _ = std::panic::catch_unwind(AssertUnwindSafe(|| {
+                            engine.run_generators(&generators, &cwd)
+                        }));
+                    })
+                    .await;
+                let _ = view.update(&mut cx, |view, cx| {
+                    view.completion_generators_running = false;
+                    view.refresh_suggestions(cx);
+                    cx.notify();
+                });
+            }
+        })
+        .detach();
+    }
+
+    fn step_suggestion_menu(&mut self, up: bool) {
+        if self.suggestions.is_empty() {
+            self.suggestion_menu_open = false;
+            return;
+        }
+        let last = self.suggestions.len().min(MAX_SUGGESTION_MENU_ITEMS) - 1;
+        self.suggest_index = if up {
+            self.suggest_index.checked_sub(1).unwrap_or(last)
+        } else if self.suggest_index >= last {
+            0
+        } else {
+            self.suggest_index + 1
+        };
+    }
+
     fn is_command_context(&self) -> bool {
         let token = self.current_token();
         if token.is_empty() {
@@ -5946,12 +10853,12 @@ impl TabView {
     }
 
     fn rendered_input_line_count(input: &str, wrap_chars: usize) -> usize {
-        let count = input.chars().count();
-        if count == 0 || wrap_chars == 0 {
+        if input.is_empty() || wrap_chars == 0 {
             return 1;
         }
-        let lines = (count + wrap_chars - 1) / wrap_chars;
-        lines.clamp(1, MAX_RENDERED_INPUT_LINES)
+        wrap_ranges(input, wrap_chars)
+            .len()
+            .clamp(1, MAX_RENDERED_INPUT_LINES)
     }
 
     fn input_text_viewport_height(line_count: usize) -> f32 {
@@ -5974,41 +10881,88 @@ impl TabView {
         }
 
         let chars = input.chars().collect::<Vec<_>>();
-        let total = chars.len();
-        let cursor = cursor.min(total);
-        let total_lines = (total + wrap_chars - 1) / wrap_chars;
-        let cursor_line = (cursor / wrap_chars).min(total_lines.saturating_sub(1));
+        let cursor = cursor.min(chars.len());
+        let rows = wrap_ranges(input, wrap_chars);
+        let cursor_row = input_row_at(&rows, cursor);
+
+        Self::visible_input_lines(&rows, cursor, max_lines)
+            .map(|line_index| {
+                let Range { start, end } = rows[line_index].clone();
+                if line_index == cursor_row {
+                    InputVisualLine {
+                        left: chars[start..cursor].iter().collect(),
+                        right: chars[cursor..end].iter().collect(),
+                        has_cursor: true,
+                    }
+                } else {
+                    InputVisualLine {
+                        left: chars[start..end].iter().collect(),
+                        right: String::new(),
+                        has_cursor: false,
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// The wrapped input rows the input bar shows: at most `max_lines`, kept
+    /// around the row holding char `focus`.
+    fn visible_input_lines(rows: &[Range<usize>], focus: usize, max_lines: usize) -> Range<usize> {
+        let total_lines = rows.len().max(1);
+        let focus_line = input_row_at(rows, focus);
         let start_line = if total_lines <= max_lines {
             0
         } else {
-            cursor_line
+            focus_line
                 .saturating_sub(max_lines / 2)
                 .min(total_lines - max_lines)
         };
-        let end_line = (start_line + max_lines).min(total_lines);
+        start_line..(start_line + max_lines).min(total_lines)
+    }
+
+    /// Wrapped input lines split into plain, selected and caret segments for
+    /// every cursor range. Shows up to `max_lines` lines around `focus`.
+    fn multi_cursor_lines(
+        input: &str,
+        ranges: &[CursorRange],
+        wrap_chars: usize,
+        max_lines: usize,
+        focus: usize,
+    ) -> Vec<Vec<InputSegment>> {
+        let chars = input.chars().collect::<Vec<_>>();
+        let rows = wrap_ranges(input, wrap_chars);
 
-        (start_line..end_line)
+        Self::visible_input_lines(&rows, focus, max_lines.max(1))
             .map(|line_index| {
-                let start = line_index * wrap_chars;
-                let end = ((line_index + 1) * wrap_chars).min(total);
-                let is_last_line = line_index == total_lines.saturating_sub(1);
-                let has_cursor =
-                    cursor >= start && (cursor < end || (is_last_line && cursor == end));
-                if has_cursor {
-                    let left = chars[start..cursor].iter().collect::<String>();
-                    let right = chars[cursor..end].iter().collect::<String>();
-                    InputVisualLine {
-                        left,
-                        right,
-                        has_cursor,
+                let Range { start, end } = rows[line_index].clone();
+                let is_last_line = line_index + 1 == rows.len();
+                let mut segments = Vec::new();
+                let mut run = String::new();
+                let mut run_selected = false;
+                for at in start..=end {
+                    let caret =
+                        ranges.iter().any(|range| range.head == at) && (at < end || is_last_line);
+                    let selected = at < end
+                        && ranges
+                            .iter()
+                            .any(|range| range.start() <= at && at < range.end());
+                    if (caret || selected != run_selected || at == end) && !run.is_empty() {
+                        let text = std::mem::take(&mut run);
+                        segments.push(if run_selected {
+                            InputSegment::Selected(text)
+                        } else {
+                            InputSegment::Text(text)
+                        });
                     }
-                } else {
-                    InputVisualLine {
-                        left: chars[start..end].iter().collect(),
-                        right: String::new(),
-                        has_cursor,
+                    if caret {
+                        segments.push(InputSegment::Caret);
+                    }
+                    if let Some(ch) = chars.get(at).filter(|_| at < end) {
+                        run_selected = selected;
+                        run.push(*ch);
                     }
                 }
+                segments
             })
             .collect()
     }
@@ -6075,11 +11029,11 @@ impl TabView {
     }
 
     fn current_token(&self) -> String {
-        self.prefix_at_cursor()
-            .split_whitespace()
-            .last()
-            .unwrap_or("")
-            .to_string()
+        let prefix = self.prefix_at_cursor();
+        if prefix.ends_with(char::is_whitespace) {
+            return String::new();
+        }
+        prefix.split_whitespace().last().unwrap_or("").to_string()
     }
 
     fn is_path_token(token: &str) -> bool {
@@ -6180,10 +11134,33 @@ impl TabView {
         self.history_open = false;
     }
 
+    /// Whether the shell can run `command`: a path to a file, something on
+    /// `PATH`, or an alias, function or builtin. Says yes while `PATH` has not
+    /// been scanned.
+    fn is_known_command(&self, command: &str) -> bool {
+        if command.contains('/') {
+            let path = expand_tilde(command);
+            let path = if path.is_absolute() {
+                path
+            } else {
+                expand_tilde(&self.current_path).join(path)
+            };
+            return path.exists();
+        }
+        self.path_commands.is_empty()
+            || self
+                .path_commands
+                .binary_search_by(|candidate| candidate.as_str().cmp(command))
+                .is_ok()
+            || shell::shell_commands()
+                .iter()
+                .any(|shell_command| shell_command.name == command)
+    }
+
     fn load_path_commands() -> Vec<String> {
         let mut set = HashSet::new();
         let mut out = Vec::new();
-        let path_var = std::env::var("PATH").unwrap_or_default();
+        let path_var = std::env::var_os("PATH").unwrap_or_default();
         let exts = if cfg!(windows) {
             std::env::var("PATHEXT").unwrap_or_else(|_| ".EXE;.CMD;.BAT;.COM".to_string())
         } else {
@@ -6195,11 +11172,11 @@ impl TabView {
             .map(|s| s.to_ascii_lowercase())
             .collect();
 
-        for dir in path_var.split(';') {
-            if dir.trim().is_empty() {
+        for dir in std::env::split_paths(&path_var) {
+            if dir.as_os_str().is_empty() {
                 continue;
             }
-            let entries = std::fs::read_dir(dir);
+            let entries = std::fs::read_dir(&dir);
             if entries.is_err() {
                 continue;
             }
@@ -6251,167 +11228,61 @@ impl TabView {
         self.path_commands = Self::load_path_commands();
     }
 
-    fn load_initial_history() -> (VecDeque<String>, Option<PathBuf>) {
-        let mut history = VecDeque::new();
-        let mut seen = HashSet::new();
-        let mut app_history_path = None;
-
-        if let Some(app_dir) = Self::app_data_dir() {
-            let app_dir = app_dir.join("orbitshell");
-            let _ = std::fs::create_dir_all(&app_dir);
-            let app_path = app_dir.join("history.txt");
-            app_history_path = Some(app_path.clone());
-            Self::load_history_from_file(&app_path, &mut history, &mut seen);
-        }
-
-        if cfg!(windows) {
-            if let Ok(appdata) = std::env::var("APPDATA") {
-                let windows_ps = PathBuf::from(&appdata)
-                    .join("Microsoft")
-                    .join("Windows")
-                    .join("PowerShell")
-                    .join("PSReadLine")
-                    .join("ConsoleHost_history.txt");
-                Self::load_history_from_file(&windows_ps, &mut history, &mut seen);
-
-                let pwsh_ps = PathBuf::from(&appdata)
-                    .join("Microsoft")
-                    .join("PowerShell")
-                    .join("PSReadLine")
-                    .join("ConsoleHost_history.txt");
-                Self::load_history_from_file(&pwsh_ps, &mut history, &mut seen);
-            }
-
-            #[cfg(windows)]
-            Self::load_cmd_doskey_history(&mut history, &mut seen);
-        } else {
-            Self::load_unix_shell_history(&mut history, &mut seen);
-        }
-
-        (history, app_history_path)
-    }
-
-    fn app_data_dir() -> Option<PathBuf> {
-        if let Ok(appdata) = std::env::var("APPDATA") {
-            return Some(PathBuf::from(appdata));
-        }
-        if let Ok(xdg) = std::env::var("XDG_DATA_HOME") {
-            return Some(PathBuf::from(xdg));
-        }
-        if let Ok(home) = std::env::var("HOME") {
-            return Some(PathBuf::from(home).join(".local").join("share"));
-        }
-        None
-    }
-
-    fn load_unix_shell_history(history: &mut VecDeque<String>, seen: &mut HashSet<String>) {
-        let Some(home) = std::env::var("HOME").ok().map(PathBuf::from) else {
-            return;
-        };
-
-        let bash = home.join(".bash_history");
-        Self::load_history_from_file(&bash, history, seen);
-
-        let zsh = home.join(".zsh_history");
-        Self::load_zsh_history(&zsh, history, seen);
-
-        let fish = home.join(".config").join("fish").join("fish_history");
-        Self::load_fish_history(&fish, history, seen);
-    }
-
-    fn load_zsh_history(path: &Path, history: &mut VecDeque<String>, seen: &mut HashSet<String>) {
-        let Ok(contents) = std::fs::read_to_string(path) else {
-            return;
-        };
-        load_zsh_history_contents(&contents, history, seen);
-    }
-
-    fn load_fish_history(path: &Path, history: &mut VecDeque<String>, seen: &mut HashSet<String>) {
-        let Ok(contents) = std::fs::read_to_string(path) else {
-            return;
-        };
-        load_fish_history_contents(&contents, history, seen);
-    }
-
-    #[cfg(windows)]
-    fn load_cmd_doskey_history(history: &mut VecDeque<String>, seen: &mut HashSet<String>) {
-        use std::process::Command;
-        let out = Command::new("cmd")
-            .args(["/c", "doskey", "/history"])
-            .output();
-        let Ok(out) = out else {
-            return;
-        };
-        if !out.status.success() {
-            return;
-        }
-        let text = String::from_utf8_lossy(&out.stdout);
-        for line in text.lines().rev() {
-            let l = line.trim();
-            if l.is_empty() {
-                continue;
-            }
-            if seen.insert(l.to_string()) {
-                history.push_front(l.to_string());
-            }
-        }
-    }
-
-    fn load_history_from_file(
-        path: &Path,
-        history: &mut VecDeque<String>,
-        seen: &mut HashSet<String>,
-    ) {
-        Self::load_history_from_file_with_reader(path, history, seen, |p| {
-            std::fs::read_to_string(p)
-        });
-    }
-
-    fn load_history_from_file_with_reader<F>(
-        path: &Path,
-        history: &mut VecDeque<String>,
-        seen: &mut HashSet<String>,
-        read_to_string: F,
-    ) where
-        F: for<'a> Fn(&'a Path) -> std::io::Result<String>,
-    {
-        let Ok(contents) = read_to_string(path) else {
-            return;
-        };
-        load_history_from_contents(&contents, history, seen);
-    }
-
-    fn append_history_line(path: &Path, command: &str) -> std::io::Result<()> {
-        use std::io::Write;
-        let mut file = std::fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(path)?;
-        writeln!(file, "{command}")?;
-        Ok(())
+    /// Opens the structured history shared by every tab. The first launch
+    /// imports the shell histories and the flat `history.txt` of older builds.
+    fn load_initial_history() -> Arc<Mutex<HistoryStore>> {
+        static STORE: OnceLock<Arc<Mutex<HistoryStore>>> = OnceLock::new();
+        STORE
+            .get_or_init(|| {
+                let store = default_store_path()
+                    .and_then(|path| HistoryStore::open(&path).ok())
+                    .map(|mut store| {
+                        let legacy = store
+                            .path()
+                            .and_then(|path| path.parent())
+                            .map(|dir| dir.join("history.txt"));
+                        store.import_once(|| {
+                            let (scanner, _) = TerminalSettings::current().secret_scanner();
+                            collect_shell_history(legacy.as_deref())
+                                .into_iter()
+                                .map(|mut imported| {
+                                    imported.command =
+                                        scanner.redact(&imported.command).into_owned();
+                                    imported
+                                })
+                                .collect()
+                        });
+                        store
+                    })
+                    .unwrap_or_else(HistoryStore::in_memory);
+                Arc::new(Mutex::new(store))
+            })
+            .clone()
     }
 
     fn dedupe_suggestions(
         history_items: Vec<SuggestionItem>,
-        path_items: Vec<SuggestionItem>,
+        argument_items: Vec<SuggestionItem>,
         command_items: Vec<SuggestionItem>,
     ) -> Vec<SuggestionItem> {
         let mut seen = HashSet::new();
         let mut out = Vec::new();
 
-        for item in history_items.into_iter() {
-            if seen.insert(item.insert.clone()) {
-                out.push(item);
-            }
-        }
-        for item in path_items.into_iter() {
-            if seen.insert(item.insert.clone()) {
-                out.push(item);
-            }
-        }
-        for item in command_items.into_iter() {
+        for item in history_items
+            .into_iter()
+            .chain(argument_items)
+            .chain(command_items)
+        {
             if seen.insert(item.insert.clone()) {
                 out.push(item);
+            } else if item.description.is_some()
+                && let Some(existing) = out
+                    .iter_mut()
+                    .find(|existing| existing.insert == item.insert)
+                && existing.description.is_none()
+            {
+                // A history hit keeps the description of the same command.
+                existing.description = item.description;
             }
         }
 
@@ -6421,9 +11292,6 @@ impl TabView {
     fn append_path_suggestions(&self, items: &mut Vec<SuggestionItem>) {
         let (left, right) = self.split_at_cursor();
         let token = self.current_token();
-        if token.is_empty() {
-            return;
-        }
 
         let (base, partial, sep) = split_path_token(&token);
         let base_dir = if base.is_empty() {
@@ -6444,7 +11312,7 @@ impl TabView {
                 Some(name) => name.to_string(),
                 None => continue,
             };
-            if !name.starts_with(&partial) {
+            if !name.starts_with(&partial) || (partial.is_empty() && name.starts_with('.')) {
                 continue;
             }
             let mut completed = if base.is_empty() {
@@ -6459,6 +11327,7 @@ impl TabView {
             items.push(SuggestionItem {
                 display: completed,
                 insert,
+                description: None,
             });
         }
     }
@@ -6476,6 +11345,13 @@ impl TabView {
                 agent_stream_line_index: None,
                 agent_response: None,
                 agent_response_line_count: 0,
+                exit_code: None,
+                duration: None,
+                secrets_revealed: false,
+                collapsed: false,
+                styled_lines: BTreeMap::new(),
+                tests: None,
+                private_keys: RefCell::default(),
             });
             self.selected_block = Some(0);
         }
@@ -6486,10 +11362,49 @@ impl TabView {
     }
 
     fn append_output(&mut self, chunk: &str, cx: &mut Context<Self>) {
-        let normalized = strip_ansi(chunk).replace("\r\n", "\n").replace('\r', "\n");
+        self.record(CastEventKind::Output, chunk);
+        let parsed = self.ansi_parser.feed(chunk);
+        let mut finished = None;
+        for event in parsed.events {
+            match event {
+                TerminalEvent::CommandFinished { exit_code } => {
+                    self.shell_reports_status = true;
+                    finished = Some(exit_code);
+                }
+                TerminalEvent::TitleChanged(title) => {
+                    cx.emit(TabViewEvent::TitleChanged(title));
+                }
+                TerminalEvent::PrivateMode { mode, enabled } => {
+                    if self.mouse_protocol.set_mode(mode, enabled) {
+                        self.last_mouse_cell = None;
+                    }
+                }
+                TerminalEvent::Bell => {
+                    match TerminalSettings::current().bell {
+                        BellStyle::Visual => {
+                            self.bell_flashes = self.bell_flashes.wrapping_add(1);
+                        }
+                        BellStyle::Audible => notifications::beep(),
+                        BellStyle::Off => {}
+                    }
+                    cx.emit(TabViewEvent::Bell);
+                }
+            }
+        }
+        if let Some(exit_code) = finished {
+            self.finish_running_command(exit_code, cx);
+            self.secure_input = None;
+            // Back at the prompt; a program that died with tracking on
+            // should not keep the mouse.
+            self.mouse_protocol = MouseProtocol::default();
+        }
+
+        let normalized = parsed.text.replace("\r\n", "\n").replace('\r', "\n");
         if normalized.is_empty() {
             return;
         }
+        let styled_normalized = parsed.styled.replace("\r\n", "\n").replace('\r', "\n");
+        let styled_lines: Vec<&str> = styled_normalized.split('\n').collect();
 
         let mut lines: Vec<&str> = normalized.split('\n').collect();
         let ends_with_newline = normalized.ends_with('\n');
@@ -6508,6 +11423,7 @@ impl TabView {
         }
 
         let mut batch = Vec::new();
+        let mut styled_batch = Vec::new();
         let mut append_first_to_last = false;
         for (index, line) in lines.iter().enumerate() {
             let append_to_last = index == 0 && self.last_line_incomplete;
@@ -6522,12 +11438,30 @@ impl TabView {
                 append_first_to_last = append_to_last;
             }
             batch.push((*line).to_string());
+            // Only lines that differ from the plain text carry colors.
+            styled_batch.push(
+                styled_lines
+                    .get(index)
+                    .filter(|styled| *styled != line)
+                    .map(|styled| styled.to_string()),
+            );
         }
 
-        let appended_any =
-            self.append_output_batch(&batch, append_first_to_last) && !batch.is_empty();
+        let appended_any = self.append_output_batch(&batch, &styled_batch, append_first_to_last)
+            && !batch.is_empty();
+        if appended_any {
+            self.detect_dev_servers(&batch, cx);
+        }
         self.last_line_incomplete = !ends_with_newline && appended_any;
 
+        // Shells without the integration only show that a command ended by
+        // printing their prompt again.
+        if self.input_visible && !self.shell_reports_status {
+            self.finish_running_command(None, cx);
+        }
+        self.update_secure_input();
+        self.run_next_runbook_fence(cx);
+
         if appended_any {
             self.trim_output_lines();
             self.update_follow_output_from_scroll();
@@ -6626,16 +11560,42 @@ impl TabView {
             && !block.output_lines.is_empty()
         {
             block.output_lines.pop();
+            block.styled_lines.remove(&block.output_lines.len());
             self.total_output_lines = self.total_output_lines.saturating_sub(1);
         }
     }
 
-    fn append_output_batch(&mut self, lines: &[String], append_first_to_last: bool) -> bool {
+    fn append_output_batch(
+        &mut self,
+        lines: &[String],
+        styled: &[Option<String>],
+        append_first_to_last: bool,
+    ) -> bool {
         if lines.is_empty() {
             return false;
         }
 
         let block = self.ensure_output_block();
+        let first_index = if append_first_to_last {
+            block.output_lines.len().saturating_sub(1)
+        } else {
+            block.output_lines.len()
+        };
+        for (offset, (line, styled)) in lines.iter().zip(styled).enumerate() {
+            let index = first_index + offset;
+            let styled = match (offset == 0 && append_first_to_last, styled) {
+                // A continued line keeps its earlier colors, if any.
+                (true, styled) => block
+                    .styled_lines
+                    .remove(&index)
+                    .or_else(|| styled.as_ref().map(|_| block.output_lines[index].clone()))
+                    .map(|earlier| earlier + styled.as_deref().unwrap_or(line)),
+                (false, styled) => styled.clone(),
+            };
+            if let Some(styled) = styled {
+                block.styled_lines.insert(index, styled);
+            }
+        }
         let added_lines = append_output_batch_to_block(block, lines, append_first_to_last);
         self.total_output_lines += added_lines;
         added_lines > 0 || append_first_to_last
@@ -6654,7 +11614,13 @@ impl TabView {
                 continue;
             }
             let remove_count = to_remove.min(self.blocks[0].output_lines.len());
-            self.blocks[0].output_lines.drain(0..remove_count);
+            let block = &mut self.blocks[0];
+            block.output_lines.drain(0..remove_count);
+            block.private_keys.get_mut().drop_front(remove_count);
+            block.styled_lines = std::mem::take(&mut block.styled_lines)
+                .into_iter()
+                .filter_map(|(index, line)| Some((index.checked_sub(remove_count)?, line)))
+                .collect();
             self.total_output_lines -= remove_count;
             to_remove -= remove_count;
             if self.blocks[0].output_lines.is_empty() {
@@ -6678,6 +11644,7 @@ impl TabView {
         &self,
         line: &str,
         has_error: bool,
+        in_private_key: bool,
         block_index: usize,
         line_index: usize,
         cx: &Context<Self>,
@@ -6690,34 +11657,125 @@ impl TabView {
             rgb(0xdddddd)
         };
 
-        let is_selected = self.is_output_line_selected(block_index, line_index);
+        let selection = self.output_line_selection(block_index, line_index);
+        let has_secret = in_private_key || self.secret_scanner().contains_secret(line);
+        let revealed = self
+            .blocks
+            .get(block_index)
+            .is_some_and(|block| block.secrets_revealed);
+        let shown = if in_private_key && !revealed {
+            MASK.into()
+        } else if has_secret && !revealed {
+            self.secret_scanner().mask(line)
+        } else {
+            line.into()
+        };
+        let mut rows = Vec::new();
+        for OutputRow {
+            text,
+            start: row_start,
+            column: row_column,
+        } in output_rows(&shown, 96)
+        {
+            let highlight = selection
+                .as_ref()
+                .and_then(|selection| selection.row_range(&text, row_start, row_column));
+            let content = match highlight {
+                Some(range) => {
+                    let row_chars: Vec<char> = text.chars().collect();
+                    let span = |range: Range<usize>| row_chars[range].iter().collect::<String>();
+                    div()
+                        .flex()
+                        .child(span(0..range.start))
+                        .child(div().bg(rgb(0x1a2f4a)).child(span(range.clone())))
+                        .child(span(range.end..row_chars.len()))
+                }
+                None => div().child(text.clone()),
+            };
+            let down_text = text.clone();
+            rows.push(
+                content
+                    .min_w(px(0.0))
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |view, event: &MouseDownEvent, window, cx| {
+                            let position = view.output_position_at(
+                                &down_text,
+                                row_start,
+                                row_column,
+                                event.position.x,
+                                window,
+                            );
+                            view.on_output_line_mouse_down_at(
+                                block_index,
+                                line_index,
+                                position,
+                                event,
+                                cx,
+                            );
+                        }),
+                    )
+                    .on_mouse_move(
+                        cx.listener(move |view, event: &MouseMoveEvent, window, cx| {
+                            let (_, column) = view.output_position_at(
+                                &text,
+                                row_start,
+                                row_column,
+                                event.position.x,
+                                window,
+                            );
+                            view.on_output_line_mouse_move_at(
+                                block_index,
+                                line_index,
+                                column,
+                                event,
+                                cx,
+                            );
+                        }),
+                    ),
+            );
+        }
         let mut row = div()
             .min_w(px(0.0))
             .text_color(color)
             .cursor(CursorStyle::IBeam)
             .px(px(2.0))
-            .child(
-                div().flex_col().gap(px(0.0)).children(
-                    wrap_terminal_text_lines(line, 96)
-                        .into_iter()
-                        .map(|wrapped| div().min_w(px(0.0)).child(wrapped)),
-                ),
-            )
-            .on_mouse_down(
-                MouseButton::Left,
-                cx.listener(move |view, event: &MouseDownEvent, _window, cx| {
-                    view.on_output_line_mouse_down_at(block_index, line_index, event, cx);
-                }),
-            )
-            .on_mouse_move(
-                cx.listener(move |view, event: &MouseMoveEvent, _window, cx| {
-                    view.on_output_line_mouse_move_at(block_index, line_index, event, cx);
-                }),
-            );
+            .child(div().flex_col().gap(px(0.0)).children(rows));
 
-        if is_selected {
+        if selection == Some(LineSelection::Whole) {
             row = row.bg(rgb(0x1a2f4a)).rounded(px(4.0));
         }
+        if has_secret {
+            row = row.flex().items_start().gap(px(6.0)).child(
+                div()
+                    .flex_none()
+                    .flex()
+                    .items_center()
+                    .gap(px(4.0))
+                    .px(px(5.0))
+                    .rounded(px(4.0))
+                    .bg(rgb(0x2a2214))
+                    .text_size(px(10.0))
+                    .text_color(rgb(0xe0a458))
+                    .cursor(CursorStyle::PointingHand)
+                    .child(lucide_icon(
+                        if revealed { Icon::EyeOff } else { Icon::Eye },
+                        11.0,
+                        0xe0a458,
+                    ))
+                    .child(if revealed { "Hide secret" } else { "Reveal" })
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |view, _event: &MouseDownEvent, _window, cx| {
+                            cx.stop_propagation();
+                            if let Some(block) = view.blocks.get_mut(block_index) {
+                                block.secrets_revealed = !block.secrets_revealed;
+                                cx.notify();
+                            }
+                        }),
+                    ),
+            );
+        }
 
         row
     }
@@ -6895,7 +11953,374 @@ impl TabView {
             })
         });
 
-        card.child(div().flex().gap(px(6.0)).children(buttons))
+        card.child(div().flex().gap(px(6.0)).children(buttons))
+    }
+
+    fn render_fold_chevron(collapsed: bool, index: usize, cx: &Context<Self>) -> Div {
+        div()
+            .flex_none()
+            .cursor(CursorStyle::PointingHand)
+            .child(lucide_icon(
+                if collapsed {
+                    Icon::ChevronRight
+                } else {
+                    Icon::ChevronDown
+                },
+                13.0,
+                0x8a8a8a,
+            ))
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |view, _event: &MouseDownEvent, _window, cx| {
+                    view.toggle_block_collapsed(index, cx);
+                    cx.stop_propagation();
+                }),
+            )
+    }
+
+    /// The command and context of the block being scrolled through, pinned
+    /// to the top of the output. Clicking it scrolls to the block's start.
+    fn render_sticky_block_header(&self, cx: &Context<Self>) -> Option<Div> {
+        let index = self.sticky_block_index()?;
+        let block = &self.blocks[index];
+        let command = if block.secrets_revealed {
+            block.command.clone()
+        } else {
+            self.secret_scanner().mask(&block.command).into_owned()
+        };
+        Some(
+            div()
+                .absolute()
+                .top(px(0.0))
+                .left(px(0.0))
+                .right(px(16.0))
+                .flex()
+                .items_center()
+                .gap(px(8.0))
+                .pl(px(31.0))
+                .pr(px(96.0))
+                .py(px(6.0))
+                .bg(rgb(0x101418))
+                .border_b_1()
+                .border_color(rgb(0x2a2a2a))
+                .font_family("Cascadia Code")
+                .cursor(CursorStyle::PointingHand)
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |view, _event: &MouseDownEvent, _window, cx| {
+                        view.follow_output = false;
+                        view.selected_block = Some(index);
+                        view.scroll_handle.scroll_to_top_of_item(index);
+                        cx.notify();
+                        cx.stop_propagation();
+                    }),
+                )
+                .child(Self::render_fold_chevron(false, index, cx))
+                .child(
+                    div()
+                        .flex()
+                        .flex_col()
+                        .flex_1()
+                        .min_w(px(0.0))
+                        .children(self.block_context_text(block).map(|line| {
+                            div()
+                                .min_w(px(0.0))
+                                .text_size(px(11.0))
+                                .text_color(rgb(0x7a7a7a))
+                                .truncate()
+                                .child(line)
+                        }))
+                        .child(
+                            div()
+                                .min_w(px(0.0))
+                                .text_size(px(13.0))
+                                .text_color(if block.has_error {
+                                    rgb(0xffa3a3)
+                                } else {
+                                    rgb(0xffe29a)
+                                })
+                                .font_weight(FontWeight::BOLD)
+                                .truncate()
+                                .child(command),
+                        ),
+                ),
+        )
+    }
+
+    /// A border over the output that fades out after each visual bell.
+    fn render_bell_flash(&self) -> Option<AnyElement> {
+        if self.bell_flashes == 0 {
+            return None;
+        }
+        Some(
+            div()
+                .absolute()
+                .inset_0()
+                .border_2()
+                .border_color(rgb(0xf2cc60))
+                .bg(rgba(0xf2cc6014))
+                .with_animation(
+                    ("bell_flash", self.bell_flashes),
+                    Animation::new(Duration::from_millis(250)),
+                    |flash, delta| flash.opacity(1.0 - delta),
+                )
+                .into_any_element(),
+        )
+    }
+
+    /// Collapse-all and expand-all buttons over the top right of the output.
+    fn render_fold_controls(&self, cx: &Context<Self>) -> Option<Div> {
+        let command_blocks = self
+            .blocks
+            .iter()
+            .filter(|block| !block.command.is_empty())
+            .count();
+        if command_blocks < 2 {
+            return None;
+        }
+        let any_collapsed = self.blocks.iter().any(|block| block.collapsed);
+        let button = |icon: Icon, tooltip: &'static str| {
+            let mut button = div()
+                .flex()
+                .items_center()
+                .justify_center()
+                .size(px(26.0))
+                .rounded(px(6.0))
+                .bg(rgb(0x1a1a1a))
+                .border_1()
+                .border_color(rgb(0x2a2a2a))
+                .hover(|style| style.bg(rgb(0x242424)).border_color(rgb(0x4a4a4a)))
+                .cursor(CursorStyle::PointingHand)
+                .child(lucide_icon(icon, 13.0, 0xb8b8b8));
+            button.interactivity().tooltip(move |_window, cx| {
+                cx.new(|_| TooltipView {
+                    text: tooltip.to_string(),
+                })
+                .into()
+            });
+            button
+        };
+        Some(
+            div()
+                .absolute()
+                .top(px(6.0))
+                .right(px(28.0))
+                .flex()
+                .gap(px(4.0))
+                .child(
+                    button(Icon::ChevronsDownUp, "Collapse all except last").on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|view, _event: &MouseDownEvent, _window, cx| {
+                            view.collapse_all_blocks_except_last(cx);
+                            cx.stop_propagation();
+                        }),
+                    ),
+                )
+                .children(any_collapsed.then(|| {
+                    button(Icon::ChevronsUpDown, "Expand all").on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|view, _event: &MouseDownEvent, _window, cx| {
+                            view.expand_all_blocks(cx);
+                            cx.stop_propagation();
+                        }),
+                    )
+                })),
+        )
+    }
+
+    fn render_test_button(label: &'static str) -> Div {
+        div()
+            .flex_none()
+            .flex()
+            .items_center()
+            .gap(px(4.0))
+            .px(px(6.0))
+            .py(px(2.0))
+            .rounded(px(5.0))
+            .bg(rgb(0x141414))
+            .border_1()
+            .border_color(rgb(0x2a2a2a))
+            .hover(|this| this.bg(rgb(0x242424)).border_color(rgb(0x4a4a4a)))
+            .cursor(CursorStyle::PointingHand)
+            .text_size(px(11.0))
+            .text_color(rgb(0xb8b8b8))
+            .child(lucide_icon(Icon::RotateCw, 11.0, 0xb8b8b8))
+            .child(label)
+    }
+
+    /// Counts of a test run, with its failures listed below when open.
+    fn render_test_results(&self, block: &Block, index: usize, cx: &Context<Self>) -> Option<Div> {
+        let tests = block.tests.as_ref()?;
+        let report = &tests.report;
+        let can_rerun = self.running_command.is_none() && self.pty.is_some();
+        let count = |icon: Icon, color: u32, count: usize, label: &str| {
+            div()
+                .flex_none()
+                .flex()
+                .items_center()
+                .gap(px(4.0))
+                .text_size(px(11.0))
+                .text_color(rgb(color))
+                .child(lucide_icon(icon, 11.0, color))
+                .child(format!("{count} {label}"))
+        };
+        let summary = div()
+            .flex()
+            .items_center()
+            .gap(px(10.0))
+            .px(px(8.0))
+            .py(px(4.0))
+            .rounded(px(6.0))
+            .bg(rgb(0x101010))
+            .border_1()
+            .border_color(rgb(0x1f1f1f))
+            .children((report.failed > 0).then(|| {
+                div()
+                    .flex_none()
+                    .cursor(CursorStyle::PointingHand)
+                    .child(lucide_icon(
+                        if tests.open {
+                            Icon::ChevronDown
+                        } else {
+                            Icon::ChevronRight
+                        },
+                        12.0,
+                        0x8a8a8a,
+                    ))
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |view, _event: &MouseDownEvent, _window, cx| {
+                            cx.stop_propagation();
+                            view.toggle_test_failures(index, cx);
+                        }),
+                    )
+            }))
+            .child(
+                div()
+                    .flex_none()
+                    .text_size(px(11.0))
+                    .text_color(rgb(0x8a8a8a))
+                    .child(report.runner.label()),
+            )
+            .child(count(Icon::Check, 0x8bd06f, report.passed, "passed"))
+            .child(count(
+                Icon::X,
+                if report.failed > 0 {
+                    0xff7b72
+                } else {
+                    0x6f6f6f
+                },
+                report.failed,
+                "failed",
+            ))
+            .children(
+                (report.ignored > 0)
+                    .then(|| count(Icon::CircleDashed, 0x8a8a8a, report.ignored, "ignored")),
+            )
+            .child(div().flex_1())
+            .children((can_rerun && report.failures().next().is_some()).then(|| {
+                Self::render_test_button("Re-run failed").on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |view, _event: &MouseDownEvent, _window, cx| {
+                        cx.stop_propagation();
+                        view.rerun_tests(index, None, cx);
+                    }),
+                )
+            }));
+
+        let failures = (tests.open && !block.collapsed).then(|| {
+            div().flex_col().gap(px(2.0)).pl(px(8.0)).children(
+                report
+                    .failures()
+                    .map(|test| self.render_test_failure(test, tests, index, can_rerun, cx)),
+            )
+        });
+        Some(
+            div()
+                .flex_col()
+                .gap(px(4.0))
+                .child(summary)
+                .children(failures),
+        )
+    }
+
+    fn render_test_failure(
+        &self,
+        test: &TestCase,
+        tests: &BlockTests,
+        index: usize,
+        can_rerun: bool,
+        cx: &Context<Self>,
+    ) -> Div {
+        let expanded = tests.expanded.contains(&test.name);
+        let toggle_name = test.name.clone();
+        let rerun_name = test.name.clone();
+        let row = div()
+            .flex()
+            .items_center()
+            .gap(px(6.0))
+            .px(px(4.0))
+            .py(px(2.0))
+            .rounded(px(4.0))
+            .hover(|this| this.bg(rgb(0x161616)))
+            .cursor(CursorStyle::PointingHand)
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |view, _event: &MouseDownEvent, _window, cx| {
+                    cx.stop_propagation();
+                    view.toggle_test_output(index, &toggle_name, cx);
+                }),
+            )
+            .child(lucide_icon(
+                if expanded {
+                    Icon::ChevronDown
+                } else {
+                    Icon::ChevronRight
+                },
+                11.0,
+                if test.output.is_empty() {
+                    0x3a3a3a
+                } else {
+                    0x8a8a8a
+                },
+            ))
+            .child(lucide_icon(Icon::X, 11.0, 0xff7b72))
+            .child(
+                div()
+                    .flex_1()
+                    .min_w(px(0.0))
+                    .text_size(px(12.0))
+                    .text_color(rgb(0xffa3a3))
+                    .truncate()
+                    .child(test.name.clone()),
+            )
+            .children(can_rerun.then(|| {
+                Self::render_test_button("Re-run").on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |view, _event: &MouseDownEvent, _window, cx| {
+                        cx.stop_propagation();
+                        view.rerun_tests(index, Some(vec![rerun_name.clone()]), cx);
+                    }),
+                )
+            }));
+        let output = (expanded && !test.output.is_empty()).then(|| {
+            div()
+                .flex_col()
+                .ml(px(22.0))
+                .px(px(8.0))
+                .py(px(4.0))
+                .border_l_1()
+                .border_color(rgb(0x3a1f1f))
+                .text_size(px(OUTPUT_FONT_SIZE))
+                .font_family("Cascadia Code")
+                .text_color(rgb(0xc8c8c8))
+                .children(
+                    self.masked_lines(&test.output)
+                        .into_iter()
+                        .map(|line| div().whitespace_nowrap().child(line)),
+                )
+        });
+        div().flex_col().child(row).children(output)
     }
 
     fn render_block(
@@ -6907,7 +12332,9 @@ impl TabView {
     ) -> Div {
         let has_command = !block.command.is_empty();
         let is_active = index == active_index && has_command;
-        let is_selected_block = self.selected_block == Some(index);
+        let is_selected_block =
+            self.selected_block == Some(index) || self.block_selection.contains(&index);
+        let is_nav_focus = self.block_nav_active && self.selected_block == Some(index);
         let block_bg = if block.has_error {
             rgb(0x2a1515)
         } else if is_selected_block {
@@ -6922,7 +12349,9 @@ impl TabView {
         } else {
             rgb(0x0a0a0a)
         };
-        let accent_color = if is_active {
+        let accent_color = if is_nav_focus {
+            rgb(0x6b9eff)
+        } else if is_active {
             rgb(0x2b7a8f)
         } else {
             rgb(0x0a0a0a)
@@ -6950,7 +12379,11 @@ impl TabView {
                 })
                 .font_weight(FontWeight::BOLD)
                 .truncate()
-                .child(block.command.clone())
+                .child(if block.secrets_revealed {
+                    block.command.clone()
+                } else {
+                    self.secret_scanner().mask(&block.command).into_owned()
+                })
         } else {
             div()
         };
@@ -6974,6 +12407,97 @@ impl TabView {
                 }),
             );
 
+        let watching = self
+            .watch
+            .as_ref()
+            .is_some_and(|watch| watch.block_index == Some(index));
+        let watch_button = has_command.then(|| {
+            div()
+                .flex_none()
+                .px(px(6.0))
+                .py(px(4.0))
+                .rounded(px(5.0))
+                .bg(if watching {
+                    rgb(0x0e2a33)
+                } else {
+                    rgb(0x141414)
+                })
+                .border_1()
+                .border_color(if watching {
+                    rgb(0x2b7a8f)
+                } else {
+                    rgb(0x2a2a2a)
+                })
+                .hover(|this| this.bg(rgb(0x242424)).border_color(rgb(0x4a4a4a)))
+                .cursor(CursorStyle::PointingHand)
+                .child(lucide_icon(
+                    if watching {
+                        Icon::RefreshCwOff
+                    } else {
+                        Icon::RefreshCw
+                    },
+                    12.0,
+                    if watching { 0x7fd4e8 } else { 0xb8b8b8 },
+                ))
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |view, _event: &MouseDownEvent, _window, cx| {
+                        cx.stop_propagation();
+                        view.toggle_watch(index, cx);
+                    }),
+                )
+        });
+        let diff_button = self.previous_run(index).map(|earlier| {
+            div()
+                .flex_none()
+                .px(px(6.0))
+                .py(px(4.0))
+                .rounded(px(5.0))
+                .bg(rgb(0x141414))
+                .border_1()
+                .border_color(rgb(0x2a2a2a))
+                .hover(|this| this.bg(rgb(0x242424)).border_color(rgb(0x4a4a4a)))
+                .cursor(CursorStyle::PointingHand)
+                .child(lucide_icon(Icon::GitCompare, 12.0, 0xb8b8b8))
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |view, _event: &MouseDownEvent, _window, cx| {
+                        cx.stop_propagation();
+                        view.open_block_diff(earlier, index, cx);
+                    }),
+                )
+        });
+
+        let spinner = self.runs_foreground_job(index).then(|| {
+            div()
+                .flex_none()
+                .text_size(px(13.0))
+                .text_color(rgb(0x7fd4e8))
+                .cursor(CursorStyle::PointingHand)
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(|view, _event: &MouseDownEvent, _window, cx| {
+                        cx.stop_propagation();
+                        if view.process_panel.is_none() {
+                            view.toggle_process_panel(cx);
+                        }
+                    }),
+                )
+                .with_animation(
+                    ("block_spinner", index),
+                    Animation::new(Duration::from_millis(800)).repeat(),
+                    |spinner, delta| {
+                        let frame = (delta * SPINNER_FRAMES.len() as f32) as usize;
+                        spinner.child(SPINNER_FRAMES[frame.min(SPINNER_FRAMES.len() - 1)])
+                    },
+                )
+        });
+        let server_chips = self
+            .dev_servers
+            .iter()
+            .filter(|server| server.confirmed && server.block_index == Some(index))
+            .map(|server| self.render_dev_server_chip(server, cx));
+
         let response_line_count = block
             .agent_response_line_count
             .min(block.output_lines.len());
@@ -6982,46 +12506,91 @@ impl TabView {
         } else {
             &block.output_lines[..block.output_lines.len() - response_line_count]
         };
-        let output = if output_lines.is_empty() {
-            div()
-        } else {
-            let (render_start, visible_lines) =
-                renderable_output_window(output_lines, MAX_RENDERED_OUTPUT_LINES_PER_BLOCK);
-            div().flex_col().gap(px(2.0)).text_size(px(12.0)).children(
-                (render_start > 0)
-                    .then(|| {
-                        self.render_output_line(
-                            &format!(
-                                "[... {} earlier lines hidden for performance ...]",
-                                render_start
-                            ),
-                            false,
-                            index,
-                            render_start.saturating_sub(1),
-                            cx,
-                        )
+        let output =
+            if output_lines.is_empty() || block.collapsed {
+                div()
+            } else {
+                let (render_start, visible_lines) =
+                    renderable_output_window(output_lines, MAX_RENDERED_OUTPUT_LINES_PER_BLOCK);
+                let private_key = block.private_key_lines();
+                let text_left = self.output_text_left.clone();
+                let row_height = self.output_row_height.clone();
+                div()
+                    .flex_col()
+                    .gap(px(2.0))
+                    .text_size(px(OUTPUT_FONT_SIZE))
+                    .on_children_prepainted(move |bounds, _window, _cx| {
+                        // Lines have 2px of padding before their text.
+                        if let Some(line) = bounds.first() {
+                            text_left.set(f32::from(line.left()) + 2.0);
+                        }
+                        // Wrapped lines are taller, so the closest pair of
+                        // lines is one row apart, gap included.
+                        if let Some(pitch) = bounds
+                            .windows(2)
+                            .map(|pair| f32::from(pair[1].top() - pair[0].top()))
+                            .filter(|pitch| *pitch > 0.0)
+                            .min_by(f32::total_cmp)
+                        {
+                            row_height.set(pitch);
+                        }
                     })
-                    .into_iter()
-                    .chain(
-                        visible_lines
-                            .iter()
-                            .enumerate()
-                            .map(|(visible_index, line)| {
-                                let line_index = render_start + visible_index;
+                    .children(
+                        (render_start > 0)
+                            .then(|| {
                                 self.render_output_line(
-                                    line,
-                                    block.has_error,
+                                    &format!(
+                                        "[... {} earlier lines hidden for performance ...]",
+                                        render_start
+                                    ),
+                                    false,
+                                    false,
                                     index,
-                                    line_index,
+                                    render_start.saturating_sub(1),
                                     cx,
                                 )
-                            }),
-                    ),
-            )
+                            })
+                            .into_iter()
+                            .chain(visible_lines.iter().enumerate().map(
+                                |(visible_index, line)| {
+                                    let line_index = render_start + visible_index;
+                                    self.render_output_line(
+                                        line,
+                                        block.has_error,
+                                        private_key[line_index],
+                                        index,
+                                        line_index,
+                                        cx,
+                                    )
+                                },
+                            )),
+                    )
+            };
+        let agent_response = if block.collapsed {
+            div()
+        } else {
+            render_agent_response_content(block.agent_response.as_ref(), block.has_error)
         };
-        let agent_response =
-            render_agent_response_content(block.agent_response.as_ref(), block.has_error);
         let permission_prompt = self.render_permission_prompt(index, block, cx);
+        let fold_button = if has_command {
+            Self::render_fold_chevron(block.collapsed, index, cx)
+        } else {
+            div()
+        };
+        let collapsed_summary = if block.collapsed && !block.output_lines.is_empty() {
+            let count = block.output_lines.len();
+            div()
+                .flex_none()
+                .text_size(px(11.0))
+                .text_color(rgb(0x7a7a7a))
+                .child(if count == 1 {
+                    "1 line hidden".to_string()
+                } else {
+                    format!("{count} lines hidden")
+                })
+        } else {
+            div()
+        };
 
         div()
             .flex()
@@ -7051,9 +12620,16 @@ impl TabView {
                             .items_center()
                             .justify_between()
                             .gap(px(8.0))
+                            .child(fold_button)
+                            .children(spinner)
                             .child(div().flex_1().min_w(px(0.0)).child(header))
+                            .child(collapsed_summary)
+                            .children(server_chips)
+                            .children(watch_button)
+                            .children(diff_button)
                             .child(copy_button),
                     )
+                    .children(self.render_test_results(block, index, cx))
                     .child(output)
                     .child(agent_response)
                     .child(permission_prompt),
@@ -7099,6 +12675,7 @@ impl Render for TabView {
             root = root
                 .focusable()
                 .on_key_down(cx.listener(Self::on_key_down))
+                .on_modifiers_changed(cx.listener(Self::on_modifiers_changed))
                 .on_mouse_down(gpui::MouseButton::Left, cx.listener(Self::on_focus_input))
                 .child(self.render_terminal_workspace(window, cx));
         } else if let TabViewMode::Welcome(ref welcome) = self.mode {
@@ -7119,7 +12696,8 @@ impl Render for TabView {
                         .flex_none()
                         .min_w(px(0.0))
                         .overflow_hidden()
-                        .child(self.render_history_menu_container())
+                        .child(self.render_history_menu_container(cx))
+                        .child(self.render_suggestion_menu_container())
                         .child(
                             div()
                                 .min_w(px(0.0))
@@ -7178,145 +12756,154 @@ fn is_error_line(line: &str) -> bool {
         || s.contains("at line:")
 }
 
-fn load_history_from_contents(
-    contents: &str,
-    history: &mut VecDeque<String>,
-    seen: &mut HashSet<String>,
-) {
-    let lines: Vec<&str> = contents.lines().filter(|l| !l.trim().is_empty()).collect();
-    for line in lines.into_iter().rev() {
-        if seen.insert(line.to_string()) {
-            history.push_front(line.to_string());
+/// One line of a history match, with the characters at `positions` (char
+/// indices into the whole command, of which this line starts at `offset`)
+/// highlighted.
+fn render_highlighted_text(line: &str, positions: &[usize], offset: usize) -> Div {
+    let mut runs: Vec<(bool, String)> = Vec::new();
+    for (index, ch) in line.chars().enumerate() {
+        let matched = positions.binary_search(&(offset + index)).is_ok();
+        match runs.last_mut() {
+            Some((run_matched, text)) if *run_matched == matched => text.push(ch),
+            _ => runs.push((matched, ch.to_string())),
         }
     }
+    div()
+        .flex()
+        .min_w(px(0.0))
+        .text_size(px(12.0))
+        .font_family("Cascadia Code")
+        .whitespace_nowrap()
+        .children(runs.into_iter().map(|(matched, text)| {
+            if matched {
+                div()
+                    .text_color(rgb(0x6b9eff))
+                    .font_weight(FontWeight::BOLD)
+                    .child(text)
+            } else {
+                div().text_color(rgb(0xcccccc)).child(text)
+            }
+        }))
 }
 
-fn load_zsh_history_contents(
-    contents: &str,
-    history: &mut VecDeque<String>,
-    seen: &mut HashSet<String>,
-) {
-    for line in contents.lines().rev() {
-        let line = line.trim();
-        if line.is_empty() {
-            continue;
-        }
-        let cmd = if let Some(pos) = line.find(';') {
-            &line[pos + 1..]
-        } else {
-            line
-        };
-        let cmd = cmd.trim();
-        if cmd.is_empty() {
-            continue;
-        }
-        if seen.insert(cmd.to_string()) {
-            history.push_front(cmd.to_string());
-        }
+fn pointer_button(button: MouseButton) -> Option<PointerButton> {
+    match button {
+        MouseButton::Left => Some(PointerButton::Left),
+        MouseButton::Middle => Some(PointerButton::Middle),
+        MouseButton::Right => Some(PointerButton::Right),
+        _ => None,
     }
 }
 
-fn load_fish_history_contents(
-    contents: &str,
-    history: &mut VecDeque<String>,
-    seen: &mut HashSet<String>,
-) {
-    for line in contents.lines().rev() {
-        let line = line.trim();
-        if let Some(cmd) = line.strip_prefix("- cmd:") {
-            let cmd = cmd.trim();
-            if !cmd.is_empty() && seen.insert(cmd.to_string()) {
-                history.push_front(cmd.to_string());
-            }
-        } else if let Some(cmd) = line.strip_prefix("cmd:") {
-            let cmd = cmd.trim();
-            if !cmd.is_empty() && seen.insert(cmd.to_string()) {
-                history.push_front(cmd.to_string());
-            }
-        }
-    }
+/// Index of the wrapped row holding char `at`. A caret at the end of a full
+/// row belongs to the next one, except on the last row.
+fn input_row_at(rows: &[Range<usize>], at: usize) -> usize {
+    rows.iter()
+        .position(|row| at < row.end)
+        .unwrap_or(rows.len().saturating_sub(1))
 }
 
-fn strip_ansi(input: &str) -> String {
-    let mut out = String::with_capacity(input.len());
-    let mut chars = input.chars().peekable();
-    while let Some(ch) = chars.next() {
-        if ch == '\x1b' {
-            if let Some(next) = chars.peek() {
-                match *next {
-                    '[' => {
-                        chars.next();
-                        while let Some(c) = chars.next() {
-                            if ('@'..='~').contains(&c) {
-                                break;
-                            }
-                        }
-                    }
-                    ']' => {
-                        chars.next();
-                        let mut prev = '\0';
-                        while let Some(c) = chars.next() {
-                            if c == '\x07' || (prev == '\x1b' && c == '\\') {
-                                break;
-                            }
-                            prev = c;
-                        }
-                    }
-                    _ => {
-                        continue;
-                    }
-                }
-            }
-        } else {
-            out.push(ch);
-        }
+fn format_memory(bytes: u64) -> String {
+    const MIB: u64 = 1024 * 1024;
+    if bytes >= 1024 * MIB {
+        format!("{:.1} GB", bytes as f64 / (1024 * MIB) as f64)
+    } else {
+        format!("{:.1} MB", bytes as f64 / MIB as f64)
     }
-    out
 }
 
-fn wrap_terminal_line(line: &str, max_chars: usize) -> Vec<String> {
-    if max_chars == 0 || line.is_empty() {
-        return vec![line.to_string()];
+pub(crate) fn format_command_duration(duration: Duration) -> String {
+    let millis = duration.as_millis();
+    if millis < 1000 {
+        return format!("{millis}ms");
     }
-
-    let mut wrapped = Vec::new();
-    let mut chunk = String::new();
-    let mut chunk_len = 0usize;
-
-    for ch in line.chars() {
-        chunk.push(ch);
-        chunk_len += 1;
-
-        if chunk_len >= max_chars {
-            wrapped.push(std::mem::take(&mut chunk));
-            chunk_len = 0;
-        }
+    let secs = duration.as_secs();
+    if secs < 60 {
+        return format!("{:.1}s", duration.as_secs_f32());
     }
-
-    if !chunk.is_empty() {
-        wrapped.push(chunk);
+    if secs < 3600 {
+        return format!("{}m {:02}s", secs / 60, secs % 60);
     }
+    format!("{}h {:02}m", secs / 3600, (secs % 3600) / 60)
+}
 
-    if wrapped.is_empty() {
-        wrapped.push(String::new());
+fn format_relative_time(timestamp: i64, now: i64) -> String {
+    if timestamp <= 0 {
+        return "imported".to_string();
+    }
+    let age = (now - timestamp).max(0);
+    match age {
+        age if age < 60 => "just now".to_string(),
+        age if age < 3600 => format!("{}m ago", age / 60),
+        age if age < 86_400 => format!("{}h ago", age / 3600),
+        age => format!("{}d ago", age / 86_400),
     }
-
-    wrapped
 }
 
-fn wrap_terminal_text_lines(text: &str, max_chars: usize) -> Vec<String> {
-    let normalized = text.replace("\r\n", "\n").replace('\r', "\n");
-    let mut wrapped = Vec::new();
-
-    for line in normalized.split('\n') {
-        wrapped.extend(wrap_terminal_line(line, max_chars));
+fn history_item_description(ranked: &RankedCommand) -> String {
+    let mut parts = Vec::new();
+    if let Some(cwd) = ranked.last_cwd.as_deref() {
+        parts.push(TabView::format_path(Path::new(cwd)));
     }
+    parts.push(format_relative_time(ranked.last_run, now_timestamp()));
+    if ranked.run_count > 1 {
+        parts.push(format!("{}×", ranked.run_count));
+    }
+    if let Some(code) = ranked.last_exit_code.filter(|code| *code != 0) {
+        parts.push(format!("exit {code}"));
+    }
+    parts.join("  ")
+}
+
+/// One screen row of an output line.
+#[derive(Debug, PartialEq, Eq)]
+struct OutputRow {
+    text: String,
+    /// Char offset of the row in the line.
+    start: usize,
+    /// Column the row starts at; it restarts after an embedded newline.
+    column: usize,
+}
 
-    if wrapped.is_empty() {
-        wrapped.push(String::new());
+/// Rows an output line is drawn in: split at embedded `\n`, `\r\n` or `\r`
+/// first, then wrapped at `max_columns`.
+fn output_rows(line: &str, max_columns: usize) -> Vec<OutputRow> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut rows = Vec::new();
+    let mut segment_start = 0;
+    loop {
+        let segment_end = chars[segment_start..]
+            .iter()
+            .position(|ch| matches!(ch, '\n' | '\r'))
+            .map_or(chars.len(), |offset| segment_start + offset);
+        let segment: String = chars[segment_start..segment_end].iter().collect();
+        let mut column = 0;
+        for range in wrap_ranges(&segment, max_columns) {
+            let text: String = chars[segment_start + range.start..segment_start + range.end]
+                .iter()
+                .collect();
+            let width = str_width(&text);
+            rows.push(OutputRow {
+                text,
+                start: segment_start + range.start,
+                column,
+            });
+            column += width;
+        }
+        if segment_end == chars.len() {
+            return rows;
+        }
+        segment_start = segment_end + 1;
+        if chars[segment_end] == '\r' && chars.get(segment_start) == Some(&'\n') {
+            segment_start += 1;
+        }
     }
+}
 
-    wrapped
+/// The selection Ctrl+D looks for more of. An empty selection is no
+/// needle, so the key goes on to the editing mode.
+fn occurrence_needle(selection: Option<(usize, usize)>) -> Option<(usize, usize)> {
+    TextEditState::normalized_selection(selection).filter(|(start, end)| start != end)
 }
 
 fn renderable_output_window(lines: &[String], max_lines: usize) -> (usize, &[String]) {
@@ -7901,25 +13488,31 @@ impl EventEmitter<TabViewEvent> for TabView {}
 mod tests {
     use super::{
         AGENT_CONNECTING_PLACEHOLDER, AGENT_SENDING_PROMPT_PLACEHOLDER, AgentStreamOp, Block,
-        CommitInputTarget, FilePreviewKind, FilePreviewState, HighlightSegment, InitialFocusTarget,
-        InputMode, InputVisualLine, MarkdownBlock, MarkdownInlineSegment, ModelButtonState,
-        PermissionDecision, PermissionRequest, PickerKind, PickerQueryState, PreviewLanguage,
-        PreviewSearchMatchSegment, TabView, append_agent_stream_delta,
-        append_output_batch_to_block, build_agent_picker_state, build_model_picker_state,
-        classify_agent_stream_op, clickable_cursor, compute_row_state, compute_trigger_state,
-        extract_compact_list_items, model_trigger_label, parse_markdown_blocks,
-        parse_markdown_inline, picker_has_search_input, picker_header_is_static,
-        picker_initial_focus_target, picker_typeahead_enabled, renderable_output_window,
-        replace_agent_stream_snapshot, streaming_snapshot_delta, text_input_cursor,
-        update_agent_placeholder_block, wrap_terminal_line, wrap_terminal_text_lines,
+        CommitInputTarget, EditAction, EditBuffer, EditKey, FilePreviewKind, FilePreviewState,
+        HighlightSegment, InitialFocusTarget, InputMode, InputSegment, InputVisualLine,
+        LineSelection, MarkdownBlock, MarkdownInlineSegment, ModalEditor, ModelButtonState,
+        OutputRow, PermissionDecision, PermissionRequest, PickerKind, PickerQueryState,
+        PreviewLanguage, PreviewSearchMatchSegment, PrivateKeyLines, TabView,
+        append_agent_stream_delta, append_output_batch_to_block, build_agent_picker_state,
+        build_model_picker_state, classify_agent_stream_op, clickable_cursor, compute_row_state,
+        compute_trigger_state, extract_compact_list_items, model_trigger_label, occurrence_needle,
+        output_rows, parse_markdown_blocks, parse_markdown_inline, picker_has_search_input,
+        picker_header_is_static, picker_initial_focus_target, picker_typeahead_enabled,
+        renderable_output_window, replace_agent_stream_snapshot, streaming_snapshot_delta,
+        text_input_cursor, update_agent_placeholder_block,
     };
     use crate::acp::manager::AgentSpec;
     use crate::acp::model_discovery::AcpModelOption;
     use crate::acp::resolve::{AgentKey, AgentSourceKind, EffectiveAgentRow};
+    use crate::ui::edit_mode::EditingMode;
+    use crate::ui::text_edit::{CursorRange, MultiEdit, TextEditState};
     use gpui::{CursorStyle, ScrollDelta, point, px};
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
     use std::fs;
     use std::path::{Path, PathBuf};
     use std::sync::Arc;
+    use std::time::Duration;
     use tempfile::tempdir;
 
     fn row(id: &str, name: &str) -> EffectiveAgentRow {
@@ -8016,6 +13609,85 @@ mod tests {
         );
     }
 
+    #[test]
+    fn multi_cursor_edits_apply_at_every_range() {
+        let mut input = "echo foo; cat foo".to_string();
+        let ranges = [
+            CursorRange { anchor: 5, head: 8 },
+            CursorRange::caret(0),
+            CursorRange {
+                anchor: 17,
+                head: 14,
+            },
+        ];
+        let carets = TextEditState::edit_at_ranges(&mut input, &ranges, MultiEdit::Insert("ba"));
+        assert_eq!(input, "baecho ba; cat ba");
+        assert_eq!(carets, vec![9, 2, 17]);
+
+        let carets = TextEditState::edit_at_ranges(
+            &mut input,
+            &[CursorRange::caret(9), CursorRange::caret(17)],
+            MultiEdit::DeleteBackward,
+        );
+        assert_eq!(input, "baecho b; cat b");
+        assert_eq!(carets, vec![8, 15]);
+    }
+
+    #[test]
+    fn multi_cursor_lines_mark_every_caret_and_selection() {
+        let ranges = [CursorRange::caret(2), CursorRange { anchor: 6, head: 8 }];
+        assert_eq!(
+            TabView::multi_cursor_lines("abcdefghij", &ranges, 5, 3, 2),
+            vec![
+                vec![
+                    InputSegment::Text("ab".into()),
+                    InputSegment::Caret,
+                    InputSegment::Text("cde".into()),
+                ],
+                vec![
+                    InputSegment::Text("f".into()),
+                    InputSegment::Selected("gh".into()),
+                    InputSegment::Caret,
+                    InputSegment::Text("ij".into()),
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn block_summary_reads_status_and_size() {
+        let mut block = Block {
+            command: "cargo test".into(),
+            output_lines: vec!["running 3 tests".into(), "test result: FAILED".into()],
+            has_error: true,
+            context: None,
+            agent_placeholder_active: false,
+            pending_permission: None,
+            agent_stream_text: String::new(),
+            agent_stream_line_index: None,
+            agent_response: None,
+            agent_response_line_count: 0,
+            exit_code: Some(101),
+            duration: Some(Duration::from_millis(2300)),
+            secrets_revealed: false,
+            collapsed: true,
+            styled_lines: BTreeMap::new(),
+            tests: None,
+            private_keys: RefCell::default(),
+        };
+
+        assert_eq!(
+            TabView::block_summary(&block, 2, 7, false),
+            "Block 3 of 7: cargo test. Failed with exit code 101 after 2.3s. 2 lines of output. Collapsed."
+        );
+        block.collapsed = false;
+        block.output_lines.clear();
+        assert_eq!(
+            TabView::block_summary(&block, 6, 7, true),
+            "Block 7 of 7: cargo test. Running. No output."
+        );
+    }
+
     #[test]
     fn input_visual_lines_wrap_and_follow_cursor() {
         let input = "abcdefghijklmnopqrstuvwxyz0123456789";
@@ -8064,6 +13736,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn input_visual_lines_wrap_wide_characters_by_column() {
+        // Each CJK character takes two columns, so four fit in eight.
+        let input = "echo 日本語のテキスト";
+
+        assert_eq!(TabView::rendered_input_line_count(input, 8), 3);
+        assert_eq!(
+            TabView::input_visual_lines(input, 8, 8, 3),
+            vec![
+                InputVisualLine {
+                    left: "echo 日".into(),
+                    right: String::new(),
+                    has_cursor: false,
+                },
+                InputVisualLine {
+                    left: "本語".into(),
+                    right: "のテ".into(),
+                    has_cursor: true,
+                },
+                InputVisualLine {
+                    left: "キスト".into(),
+                    right: String::new(),
+                    has_cursor: false,
+                },
+            ]
+        );
+    }
+
     #[test]
     fn preview_search_match_uses_one_based_line_numbers_and_trimmed_query() {
         assert_eq!(
@@ -8517,6 +14217,13 @@ mod tests {
             agent_stream_line_index: Some(1),
             agent_response: None,
             agent_response_line_count: 0,
+            exit_code: None,
+            duration: None,
+            secrets_revealed: false,
+            collapsed: false,
+            styled_lines: BTreeMap::new(),
+            tests: None,
+            private_keys: RefCell::default(),
         };
         let mut total_output_lines = block.output_lines.len();
 
@@ -8554,6 +14261,13 @@ mod tests {
             agent_stream_line_index: Some(1),
             agent_response: None,
             agent_response_line_count: 0,
+            exit_code: None,
+            duration: None,
+            secrets_revealed: false,
+            collapsed: false,
+            styled_lines: BTreeMap::new(),
+            tests: None,
+            private_keys: RefCell::default(),
         };
         let mut total_output_lines = block.output_lines.len();
 
@@ -8593,6 +14307,13 @@ mod tests {
             agent_stream_line_index: Some(1),
             agent_response: None,
             agent_response_line_count: 0,
+            exit_code: None,
+            duration: None,
+            secrets_revealed: false,
+            collapsed: false,
+            styled_lines: BTreeMap::new(),
+            tests: None,
+            private_keys: RefCell::default(),
         };
         let mut total_output_lines = block.output_lines.len();
 
@@ -8667,19 +14388,98 @@ mod tests {
     }
 
     #[test]
-    fn wrap_terminal_line_preserves_multiple_rows() {
+    fn output_rows_preserve_multiple_rows() {
+        let rows = output_rows("abcdefgh", 3);
+        let texts: Vec<&str> = rows.iter().map(|row| row.text.as_str()).collect();
+        assert_eq!(texts, vec!["abc", "def", "gh"]);
+        let starts: Vec<(usize, usize)> = rows.iter().map(|row| (row.start, row.column)).collect();
+        assert_eq!(starts, vec![(0, 0), (3, 3), (6, 6)]);
+    }
+
+    #[test]
+    fn output_rows_split_embedded_newlines_before_wrapping() {
+        let rows = output_rows("abc\ndefghi", 3);
+        let texts: Vec<&str> = rows.iter().map(|row| row.text.as_str()).collect();
+        assert_eq!(texts, vec!["abc", "def", "ghi"]);
         assert_eq!(
-            wrap_terminal_line("abcdefgh", 3),
-            vec!["abc".to_string(), "def".to_string(), "gh".to_string()]
+            rows[1],
+            OutputRow {
+                text: "def".to_string(),
+                start: 4,
+                column: 0,
+            }
         );
+
+        let rows = output_rows("ab\r\ncd\n", 3);
+        let texts: Vec<&str> = rows.iter().map(|row| row.text.as_str()).collect();
+        assert_eq!(texts, vec!["ab", "cd", ""]);
+        assert_eq!(rows[1].start, 4);
     }
 
     #[test]
-    fn wrap_terminal_text_lines_splits_embedded_newlines_before_wrapping() {
-        assert_eq!(
-            wrap_terminal_text_lines("abc\ndefghi", 3),
-            vec!["abc".to_string(), "def".to_string(), "ghi".to_string(),]
+    fn ctrl_d_without_a_selection_deletes_the_char_in_emacs_mode() {
+        assert_eq!(occurrence_needle(None), None);
+        assert_eq!(occurrence_needle(Some((3, 3))), None);
+        assert_eq!(occurrence_needle(Some((5, 2))), Some((2, 5)));
+
+        let mut editor = ModalEditor::new(EditingMode::Emacs);
+        let mut text = "echo hello".to_string();
+        let mut cursor = 5;
+        let action = editor.handle_key(
+            EditKey {
+                key: "d",
+                ctrl: true,
+                ..EditKey::default()
+            },
+            EditBuffer {
+                text: &mut text,
+                cursor: &mut cursor,
+                selection: &mut None,
+                anchor: &mut None,
+            },
         );
+        assert_eq!(action, EditAction::Changed);
+        assert_eq!(text, "echo ello");
+        assert_eq!(cursor, 5);
+    }
+
+    #[test]
+    fn private_key_lines_follow_appended_and_trimmed_output() {
+        let mut keys = PrivateKeyLines::default();
+        let mut lines = vec!["$ cat id_rsa".to_string(), "-----BEGIN RSA".to_string()];
+        keys.update(&lines);
+        assert_eq!(keys.flags, vec![false, false]);
+
+        // The BEGIN line arrives in two pieces.
+        lines[1].push_str(" PRIVATE KEY-----");
+        lines.push("MIIEowIBAAKCAQEA".to_string());
+        keys.update(&lines);
+        assert_eq!(keys.flags, vec![false, true, true]);
+
+        lines.push("-----END RSA PRIVATE KEY-----".to_string());
+        lines.push("done".to_string());
+        keys.update(&lines);
+        assert_eq!(keys.flags, vec![false, true, true, true, false]);
+
+        lines.drain(0..2);
+        keys.drop_front(2);
+        lines.push("MIIEowIBAAKCAQEA".to_string());
+        keys.update(&lines);
+        assert_eq!(keys.flags, vec![true, true, false, false]);
+    }
+
+    #[test]
+    fn partial_selection_highlights_the_chars_of_each_wrapped_row() {
+        // A word from char 2 to 7 over rows of four chars.
+        let word = LineSelection::Chars(2..7);
+        assert_eq!(word.row_range("abcd", 0, 0), Some(2..4));
+        assert_eq!(word.row_range("efgh", 4, 4), Some(0..3));
+        assert_eq!(word.row_range("ijkl", 8, 8), None);
+
+        // Columns 2 to 4 count the wide char as two columns.
+        let columns = LineSelection::Columns(2..4);
+        assert_eq!(columns.row_range("日本語", 0, 0), Some(1..2));
+        assert_eq!(LineSelection::Whole.row_range("abcd", 0, 0), None);
     }
 
     #[test]
@@ -8711,6 +14511,13 @@ mod tests {
             agent_stream_line_index: None,
             agent_response: None,
             agent_response_line_count: 0,
+            exit_code: None,
+            duration: None,
+            secrets_revealed: false,
+            collapsed: false,
+            styled_lines: BTreeMap::new(),
+            tests: None,
+            private_keys: RefCell::default(),
         };
 
         let added = append_output_batch_to_block(
@@ -8811,6 +14618,13 @@ mod tests {
             agent_stream_line_index: None,
             agent_response: None,
             agent_response_line_count: 0,
+            exit_code: None,
+            duration: None,
+            secrets_revealed: false,
+            collapsed: false,
+            styled_lines: BTreeMap::new(),
+            tests: None,
+            private_keys: RefCell::default(),
         };
 
         assert!(update_agent_placeholder_block(
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use git2::Repository;
+
+use crate::ui::file_rules::OrbitshellRules;
+
+/// A scan stops after this many files; changes past them go unseen.
+const MAX_WATCHED_FILES: usize = 20_000;
+
+/// How often files are checked when watching for changes.
+const FILE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Intervals offered for re-running on a timer, in seconds.
+pub const WATCH_INTERVALS: [u64; 4] = [2, 5, 10, 60];
+
+/// Runs of a watched command kept for comparing.
+pub const MAX_WATCH_RUNS: usize = 20;
+
+/// What re-runs a watched command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchTrigger {
+    /// A file under the block's directory changed.
+    FileChanges,
+    /// Every this many seconds.
+    Interval(u64),
+}
+
+impl WatchTrigger {
+    pub fn label(self) -> String {
+        match self {
+            Self::FileChanges => "On file changes".to_string(),
+            Self::Interval(secs) => format!("Every {secs}s"),
+        }
+    }
+
+    /// Time between checks.
+    pub fn tick(self) -> Duration {
+        match self {
+            Self::FileChanges => FILE_POLL_INTERVAL,
+            Self::Interval(secs) => Duration::from_secs(secs),
+        }
+    }
+}
+
+/// Where the output of each new run goes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchOutput {
+    /// Into the watched block, in place of the last run.
+    Replace,
+    /// Into a new block after the others.
+    Append,
+}
+
+/// Modification times and sizes of the files under a directory, leaving out
+/// what the rules skip and what git ignores.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FileSnapshot(HashMap<PathBuf, (SystemTime, u64)>);
+
+impl FileSnapshot {
+    pub fn scan(root: &Path, rules: &OrbitshellRules) -> Self {
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        let repo = Repository::discover(&root).ok();
+        let mut files = HashMap::new();
+        let mut stack = vec![root];
+
+        while let Some(dir) = stack.pop() {
+            let Ok(read) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in read.flatten() {
+                let Ok(file_type) = entry.file_type() else {
+                    continue;
+                };
+                let name = entry.file_name().to_string_lossy().to_string();
+                let is_dir = file_type.is_dir();
+                let skipped = if is_dir {
+                    rules.skips_dir(&name)
+                } else {
+                    rules.skips_file(&name)
+                };
+                let path = entry.path();
+                if skipped
+                    || repo
+                        .as_ref()
+                        .is_some_and(|repo| is_ignored(repo, &path, is_dir))
+                {
+                    continue;
+                }
+                if is_dir {
+                    stack.push(path);
+                    continue;
+                }
+                if let Ok(meta) = entry.metadata() {
+                    let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                    files.insert(path, (modified, meta.len()));
+                }
+                if files.len() >= MAX_WATCHED_FILES {
+                    return Self(files);
+                }
+            }
+        }
+        Self(files)
+    }
+}
+
+fn is_ignored(repo: &Repository, path: &Path, is_dir: bool) -> bool {
+    let Some(relative) = repo
+        .workdir()
+        .and_then(|workdir| path.strip_prefix(workdir).ok())
+    else {
+        return false;
+    };
+    // Directory patterns such as `build/` only match with the slash.
+    let relative = if is_dir {
+        relative.join("")
+    } else {
+        relative.to_path_buf()
+    };
+    repo.is_path_ignored(&relative).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn snapshot_skips_rule_dirs_and_gitignored_files() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let root = dir.path();
+        Repository::init(root).expect("git init");
+        fs::write(root.join(".gitignore"), "*.log\nbuild/\n").unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("build")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(root.join("run.log"), "noise").unwrap();
+        fs::write(root.join("build/out.o"), "obj").unwrap();
+        fs::write(root.join("target/debug"), "bin").unwrap();
+
+        let rules = OrbitshellRules::default();
+        let before = FileSnapshot::scan(root, &rules);
+        let mut names: Vec<String> = before
+            .0
+            .keys()
+            .map(|path| {
+                path.file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default()
+            })
+            .collect();
+        names.sort();
+        assert_eq!(names, vec![".gitignore", "main.rs"]);
+
+        fs::write(root.join("run.log"), "more noise").unwrap();
+        assert_eq!(FileSnapshot::scan(root, &rules), before);
+        fs::write(root.join("src/main.rs"), "fn main() { run() }").unwrap();
+        assert_ne!(FileSnapshot::scan(root, &rules), before);
+    }
+}
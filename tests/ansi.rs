@@ -0,0 +1,47 @@
+use orbitshell::terminal::ansi::{AnsiParser, TerminalEvent, strip_ansi};
+
+#[test]
+fn ansi_parser_reports_exit_status_across_chunks() {
+    let mut parser = AnsiParser::default();
+    let first = parser.feed("done\r\n\x1b]133;D;");
+    assert_eq!(first.text, "done\r\n");
+    assert!(first.events.is_empty());
+
+    let second = parser.feed("1\x07\x1b[1;32m$\x1b[0m ");
+    assert_eq!(second.text, "$ ");
+    assert_eq!(
+        second.events,
+        vec![TerminalEvent::CommandFinished { exit_code: Some(1) }]
+    );
+
+    assert_eq!(strip_ansi("\x1b(Bplain\x1b]0;title\x1b\\"), "plain");
+}
+
+#[test]
+fn ansi_parser_reports_titles_and_bells() {
+    let mut parser = AnsiParser::default();
+    let first = parser.feed("\x1b]2;vim main.rs");
+    assert!(first.events.is_empty());
+    let second = parser.feed("\x07done\x07\x1b]0;user@host: ~\x1b\\");
+    assert_eq!(second.text, "done");
+    assert_eq!(
+        second.events,
+        vec![
+            TerminalEvent::TitleChanged("vim main.rs".to_string()),
+            TerminalEvent::Bell,
+            TerminalEvent::TitleChanged("user@host: ~".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn ansi_parser_keeps_only_sgr_sequences_in_styled_text() {
+    let mut parser = AnsiParser::default();
+    let colored = parser.feed("\x1b[1;31merror\x1b[0m\x1b[2K: bad\x07\x1b]0;title\x07");
+    assert_eq!(colored.text, "error: bad");
+    assert_eq!(colored.styled, "\x1b[1;31merror\x1b[0m: bad");
+
+    // A color split across chunks is kept whole once it completes.
+    assert_eq!(parser.feed("\x1b[3").styled, "");
+    assert_eq!(parser.feed("2mok").styled, "\x1b[32mok");
+}
@@ -0,0 +1,76 @@
+use orbitshell::terminal::asciicast::{Cast, CastEventKind, CastHeader, CastRecorder};
+
+#[test]
+fn recorder_output_parses_back() {
+    let mut header = CastHeader::new(80, 24);
+    header.title = Some("cargo test".to_string());
+    let mut recorder = CastRecorder::new(Vec::new(), &header).expect("header");
+    recorder
+        .record(CastEventKind::Marker, "cargo test")
+        .expect("marker");
+    recorder
+        .record(CastEventKind::Input, "cargo test\r\n")
+        .expect("input");
+    recorder
+        .record(CastEventKind::Output, "\x1b[32mok\x1b[0m \"done\"\r\n")
+        .expect("output");
+    let bytes = recorder.finish().expect("finish");
+
+    let text = String::from_utf8(bytes).expect("utf-8");
+    assert!(
+        text.starts_with("{\"version\":2,\"width\":80,\"height\":24,\"title\":\"cargo test\"}\n")
+    );
+    let cast = Cast::parse(&text).expect("parse");
+    assert_eq!(cast.header, header);
+    let kinds: Vec<CastEventKind> = cast.events.iter().map(|event| event.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            CastEventKind::Marker,
+            CastEventKind::Input,
+            CastEventKind::Output
+        ]
+    );
+    assert_eq!(cast.events[2].data, "\x1b[32mok\x1b[0m \"done\"\r\n");
+    assert!(
+        cast.events
+            .windows(2)
+            .all(|pair| pair[0].time <= pair[1].time)
+    );
+}
+
+#[test]
+fn parse_reads_asciinema_files_and_skips_unknown_events() {
+    let text = concat!(
+        "{\"version\": 2, \"width\": 100, \"height\": 30, \"timestamp\": 1700000000, \"env\": {\"SHELL\": \"/bin/zsh\"}}\n",
+        "[0.248848, \"o\", \"$ \"]\n",
+        "[1.001, \"r\", \"120x40\"]\n",
+        "[2.5, \"o\", \"ls\\r\\n\"]\n",
+    );
+    let cast = Cast::parse(text).expect("parse");
+    assert_eq!(cast.header.width, 100);
+    assert_eq!(cast.header.timestamp, Some(1_700_000_000));
+    assert_eq!(cast.events.len(), 2);
+    assert_eq!(cast.duration(), 2.5);
+
+    assert!(Cast::parse("{\"version\": 1, \"width\": 80, \"height\": 24}").is_err());
+    assert!(Cast::parse("").is_err());
+}
+
+#[test]
+fn record_at_stamps_held_events_with_their_arrival() {
+    let mut recorder = CastRecorder::new(Vec::new(), &CastHeader::new(80, 24)).expect("header");
+    let arrived = std::time::Instant::now();
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    recorder
+        .record_at(CastEventKind::Output, "$ ", arrived)
+        .expect("output");
+    recorder
+        .record(CastEventKind::Input, "ls\r\n")
+        .expect("input");
+
+    let text = String::from_utf8(recorder.finish().expect("finish")).expect("utf-8");
+    let cast = Cast::parse(&text).expect("parse");
+    assert!(cast.events[0].time < 0.02);
+    assert!(cast.events[1].time >= 0.02);
+}
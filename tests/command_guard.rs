@@ -0,0 +1,174 @@
+use orbitshell::terminal::command_guard::{
+    CommandGuard, GuardCache, GuardConfig, GuardContext, GuardRule, PROJECT_GUARD_FILE,
+};
+
+fn rules_hit(guard: &CommandGuard, line: &str, context: &GuardContext) -> Vec<String> {
+    guard
+        .check(line, context)
+        .into_iter()
+        .map(|found| found.rule)
+        .collect()
+}
+
+#[test]
+fn builtin_rules_catch_destructive_commands() {
+    let guard = CommandGuard::builtin();
+    let on_main = GuardContext {
+        git_branch: Some("main".to_string()),
+        kube_context: Some("prod-eu".to_string()),
+    };
+
+    for line in [
+        "rm -rf /",
+        "sudo rm -fr ~/",
+        "cd /tmp && rm --recursive --force $HOME",
+        "git push --force",
+        "git push -f origin feature:main",
+        "psql -c 'DROP DATABASE shop'",
+        "kubectl delete pod web-1",
+        "chmod -R 777 /srv",
+    ] {
+        assert_eq!(rules_hit(&guard, line, &on_main).len(), 1, "{line}");
+    }
+}
+
+#[test]
+fn ordinary_commands_pass() {
+    let guard = CommandGuard::builtin();
+    let context = GuardContext {
+        git_branch: Some("feature/login".to_string()),
+        kube_context: Some("staging".to_string()),
+    };
+
+    for line in [
+        "rm -rf target",
+        "rm ~/notes.txt",
+        "git push --force",
+        "git push origin main",
+        "kubectl delete pod web-1",
+        "kubectl --context dev-cluster delete pod web-1",
+        "chmod 777 script.sh",
+        "echo drop the databases",
+    ] {
+        assert!(rules_hit(&guard, line, &context).is_empty(), "{line}");
+    }
+    assert_eq!(
+        rules_hit(
+            &guard,
+            "kubectl --context prod delete ns web",
+            &GuardContext::default()
+        ),
+        ["kubectl delete in production"]
+    );
+}
+
+#[test]
+fn project_config_only_adds_rules() {
+    let dir = tempfile::tempdir().unwrap();
+    let nested = dir.path().join("crates/app");
+    std::fs::create_dir_all(&nested).unwrap();
+    // Everything here but the terraform rule would weaken the guard.
+    let project = GuardConfig {
+        enabled: false,
+        disabled: vec!["Drop database".to_string()],
+        rules: vec![
+            GuardRule {
+                name: "Terraform destroy".to_string(),
+                command: "terraform".to_string(),
+                subcommand: Some("destroy".to_string()),
+                ..GuardRule::default()
+            },
+            GuardRule {
+                name: "Recursive delete of root or home".to_string(),
+                command: "never-run".to_string(),
+                ..GuardRule::default()
+            },
+        ],
+    };
+    std::fs::write(
+        dir.path().join(PROJECT_GUARD_FILE),
+        serde_json::to_string(&project).unwrap(),
+    )
+    .unwrap();
+
+    let (guard, errors) = CommandGuard::for_project(&GuardConfig::default(), &nested);
+    let context = GuardContext::default();
+    assert_eq!(
+        errors,
+        ["Recursive delete of root or home: a project rule cannot replace an existing rule"]
+    );
+    assert_eq!(
+        rules_hit(&guard, "terraform -chdir=infra destroy", &context),
+        ["Terraform destroy"]
+    );
+    assert_eq!(
+        rules_hit(&guard, "mysql -e 'drop database shop'", &context),
+        ["Drop database"]
+    );
+    assert_eq!(
+        rules_hit(&guard, "rm -rf /", &context),
+        ["Recursive delete of root or home"]
+    );
+
+    // The user's config can still turn it all off.
+    let off = GuardConfig {
+        enabled: false,
+        ..GuardConfig::default()
+    };
+    assert!(CommandGuard::for_project(&off, &nested).0.is_empty());
+}
+
+#[test]
+fn guard_cache_reloads_on_edits_and_settings_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(PROJECT_GUARD_FILE);
+    let rule = |name: &str| format!(r#"{{"rules":[{{"name":"{name}","command":"terraform"}}]}}"#);
+    std::fs::write(&path, rule("First")).unwrap();
+
+    let mut cache = GuardCache::default();
+    let global = GuardConfig::default();
+    let context = GuardContext::default();
+    let hit = |cache: &mut GuardCache, global: &GuardConfig| {
+        rules_hit(cache.guard(global, dir.path()), "terraform apply", &context)
+    };
+    assert_eq!(hit(&mut cache, &global), ["First"]);
+
+    // Rewritten in place with a new timestamp.
+    std::fs::write(&path, rule("Second")).unwrap();
+    let later = std::time::SystemTime::now() + std::time::Duration::from_secs(5);
+    std::fs::File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(later)
+        .unwrap();
+    assert_eq!(hit(&mut cache, &global), ["Second"]);
+
+    let off = GuardConfig {
+        enabled: false,
+        ..GuardConfig::default()
+    };
+    assert!(hit(&mut cache, &off).is_empty());
+}
+
+#[test]
+fn disabled_guard_and_bad_patterns() {
+    let off = GuardConfig {
+        enabled: false,
+        ..GuardConfig::default()
+    };
+    assert!(CommandGuard::new(&[&off]).0.is_empty());
+
+    let broken = GuardConfig {
+        rules: vec![GuardRule {
+            name: "Broken".to_string(),
+            pattern: Some("(".to_string()),
+            ..GuardRule::default()
+        }],
+        ..GuardConfig::default()
+    };
+    let (guard, errors) = CommandGuard::new(&[&broken]);
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].starts_with("Broken:"));
+    assert!(!guard.is_empty());
+}
@@ -0,0 +1,171 @@
+use orbitshell::completion::generator::run_generator;
+use orbitshell::completion::shell::{ShellCommandKind, parse_introspection};
+use orbitshell::completion::spec::{ArgSpec, CommandSpec, GeneratorSpec, OptionSpec};
+use orbitshell::completion::{CompletionEngine, CompletionKind, SpecCompletion, builtin_specs};
+use orbitshell::terminal::command_line::{TokenKind, tokenize};
+use std::path::Path;
+
+fn values(completion: Option<SpecCompletion>) -> Vec<String> {
+    match completion {
+        Some(SpecCompletion::Items { items, .. }) => items.into_iter().map(|i| i.value).collect(),
+        other => panic!("expected items, got {other:?}"),
+    }
+}
+
+#[test]
+fn builtin_specs_cover_common_clis() {
+    let names: Vec<String> = builtin_specs().into_iter().map(|spec| spec.name).collect();
+    for expected in ["git", "cargo", "npm", "pnpm", "docker", "kubectl"] {
+        assert!(
+            names.iter().any(|name| name == expected),
+            "missing {expected}"
+        );
+    }
+}
+
+#[test]
+fn subcommands_and_options_complete_with_descriptions() {
+    let engine = CompletionEngine::builtin();
+    let cwd = Path::new(".");
+
+    let Some(SpecCompletion::Items { token_start, items }) = engine.complete("git che", cwd) else {
+        panic!("expected git subcommands");
+    };
+    assert_eq!(token_start, 4);
+    let checkout = items.iter().find(|i| i.value == "checkout").unwrap();
+    assert_eq!(checkout.kind, CompletionKind::Subcommand);
+    assert!(checkout.description.is_some());
+
+    let flags = values(engine.complete("git commit --am", cwd));
+    assert_eq!(flags, vec!["--amend".to_string()]);
+
+    let formats = values(engine.complete("cargo build --message-format j", cwd));
+    assert!(formats.contains(&"json".to_string()));
+}
+
+#[test]
+fn completion_is_skipped_for_command_names_and_unknown_tools() {
+    let engine = CompletionEngine::builtin();
+    assert_eq!(engine.complete("gi", Path::new(".")), None);
+    assert_eq!(engine.complete("frobnicate --", Path::new(".")), None);
+}
+
+#[test]
+fn path_templates_defer_to_path_completion() {
+    let engine = CompletionEngine::builtin();
+    assert_eq!(
+        engine.complete("cargo new ", Path::new(".")),
+        Some(SpecCompletion::Paths { folders_only: true })
+    );
+}
+
+#[test]
+fn json_generators_read_candidates_relative_to_cwd() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("package.json"),
+        r#"{"scripts": {"dev": "vite", "build": "vite build"}}"#,
+    )
+    .unwrap();
+    let engine = CompletionEngine::builtin();
+
+    let mut scripts = values(engine.complete("npm run ", dir.path()));
+    scripts.sort();
+    assert_eq!(scripts, vec!["build".to_string(), "dev".to_string()]);
+}
+
+#[test]
+fn cached_completion_leaves_generators_to_the_caller() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("package.json"),
+        r#"{"scripts": {"dev": "vite"}}"#,
+    )
+    .unwrap();
+    let engine = CompletionEngine::builtin();
+
+    let (completion, pending) = engine.complete_cached("npm run ", dir.path());
+    assert_eq!(completion, None);
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].json_file.as_deref(), Some("package.json"));
+
+    engine.run_generators(&pending, dir.path());
+    let (completion, pending) = engine.complete_cached("npm run ", dir.path());
+    assert_eq!(values(completion), vec!["dev".to_string()]);
+    assert!(pending.is_empty());
+}
+
+#[test]
+fn option_values_and_command_generators_follow_the_spec() {
+    let spec = CommandSpec {
+        name: "tool".into(),
+        options: vec![OptionSpec {
+            names: vec!["--color".into()],
+            description: None,
+            arg: Some(ArgSpec {
+                generator: Some(GeneratorSpec {
+                    command: vec!["printf".into(), "always\\nnever\\n".into()],
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+        }],
+        ..Default::default()
+    };
+    let engine = CompletionEngine::from_specs(vec![spec]);
+
+    assert_eq!(
+        values(engine.complete("tool --color n", Path::new("."))),
+        vec!["never".to_string()]
+    );
+}
+
+#[test]
+fn hung_command_generators_are_killed() {
+    let spec = GeneratorSpec {
+        command: vec!["sleep".into(), "30".into()],
+        ..Default::default()
+    };
+
+    let started = std::time::Instant::now();
+    assert!(run_generator(&spec, Path::new(".")).is_empty());
+    assert!(started.elapsed() < std::time::Duration::from_secs(10));
+}
+
+#[test]
+fn tokenizer_tracks_quotes_operators_and_offsets() {
+    let tokens = tokenize("echo \"a b\" | grep 'x");
+    assert_eq!(tokens[1].value, "a b");
+    assert_eq!((tokens[1].start, tokens[1].end), (5, 10));
+    assert_eq!(tokens[2].kind, TokenKind::Operator);
+    assert!(tokens[4].unterminated_quote);
+}
+
+#[test]
+fn shell_introspection_reads_aliases_functions_and_builtins() {
+    let output = "rc noise\n::aliases\nalias gst='git status'\nll='ls -l'\nalias gp 'git push'\n\
+                  ::functions\nmkcd\n_git_helper\n::builtins\ncd\nmkcd\n";
+    let commands = parse_introspection(output);
+
+    let gst = commands.iter().find(|c| c.name == "gst").unwrap();
+    assert_eq!(gst.kind, ShellCommandKind::Alias);
+    assert_eq!(gst.description(), "alias: git status");
+    let expansions: Vec<_> = commands
+        .iter()
+        .filter_map(|c| c.expansion.as_deref())
+        .collect();
+    assert_eq!(expansions, vec!["git status", "ls -l", "git push"]);
+
+    let names: Vec<_> = commands
+        .iter()
+        .filter(|c| c.kind != ShellCommandKind::Alias)
+        .map(|c| (c.name.as_str(), c.kind))
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            ("mkcd", ShellCommandKind::Function),
+            ("cd", ShellCommandKind::Builtin),
+        ]
+    );
+}
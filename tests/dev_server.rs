@@ -0,0 +1,57 @@
+use orbitshell::terminal::dev_server::{ServerUrl, find_server_urls, parse_listening_sockets};
+
+fn urls(line: &str) -> Vec<String> {
+    find_server_urls(line)
+        .into_iter()
+        .map(|found| found.url)
+        .collect()
+}
+
+#[test]
+fn server_urls_are_found_in_common_banners() {
+    assert_eq!(
+        urls("  ➜  Local:   http://127.0.0.1:5173/"),
+        vec!["http://127.0.0.1:5173/"]
+    );
+    assert_eq!(
+        find_server_urls("Listening on http://0.0.0.0:3000."),
+        vec![ServerUrl {
+            url: "http://localhost:3000".to_string(),
+            port: 3000,
+        }]
+    );
+    assert_eq!(
+        urls("Serving HTTP on :: port 8000 (http://[::]:8000/) ..."),
+        vec!["http://localhost:8000/"]
+    );
+    assert_eq!(
+        urls("Server running on port 8080"),
+        vec!["http://localhost:8080"]
+    );
+    assert!(urls("see https://example.com:8443/docs").is_empty());
+    assert!(urls("exported 3000 rows").is_empty());
+}
+
+#[test]
+fn proc_net_tcp_keeps_listening_sockets() {
+    let table = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:0BB8 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 41234 1 0000000000000000 100 0 0 10 0
+   1: 0100007F:A1B2 0100007F:0BB8 01 00000000:00000000 00:00000000 00000000  1000        0 41299 1 0000000000000000 20 4 30 10 -1
+";
+    assert_eq!(parse_listening_sockets(table), vec![(3000, 41234)]);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn listening_ports_sees_sockets_of_the_process() {
+    use orbitshell::terminal::dev_server::listening_ports;
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+    let port = listener.local_addr().expect("addr").port();
+    let ports = listening_ports(std::process::id()).expect("proc is readable");
+    assert!(
+        ports
+            .iter()
+            .any(|found| found.port == port && found.pid == std::process::id())
+    );
+}
@@ -0,0 +1,205 @@
+use orbitshell::history::fuzzy::fuzzy_match;
+use orbitshell::history::import::{
+    ImportedCommand, parse_fish_history, parse_plain_history, parse_zsh_history,
+};
+use orbitshell::history::{HistoryFilter, HistoryQuery, HistoryStore, flush_writes};
+use std::time::Duration;
+
+const NOW: i64 = 1_700_000_000;
+const DAY: i64 = 24 * 60 * 60;
+
+fn ranked_commands(store: &HistoryStore, query: HistoryQuery<'_>) -> Vec<String> {
+    store
+        .ranked(&query)
+        .into_iter()
+        .map(|ranked| ranked.command)
+        .collect()
+}
+
+#[test]
+fn finished_commands_survive_reopening() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("history.jsonl");
+
+    let mut store = HistoryStore::open(&path).unwrap();
+    let id = store.start("cargo test", Some("/work".into()), Some("main".into()), NOW);
+    store.finish(id, Some(101), Duration::from_millis(1500));
+    store.start("ls", None, None, NOW);
+
+    let reopened = HistoryStore::open(&path).unwrap();
+    assert_eq!(reopened.entries().len(), 2);
+    let entry = &reopened.entries()[0];
+    assert_eq!(entry.command, "cargo test");
+    assert_eq!(entry.cwd.as_deref(), Some("/work"));
+    assert_eq!(entry.git_branch.as_deref(), Some("main"));
+    assert_eq!(entry.exit_code, Some(101));
+    assert_eq!(entry.duration_ms, Some(1500));
+
+    let mut reopened = reopened;
+    let next = reopened.start("pwd", None, None, NOW);
+    assert!(next > id);
+}
+
+#[test]
+fn writes_reach_the_file_once_flushed() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("history.jsonl");
+
+    let mut store = HistoryStore::open(&path).unwrap();
+    let id = store.start("make", None, None, NOW);
+    store.finish(id, Some(0), Duration::from_millis(20));
+    flush_writes();
+
+    let raw = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(raw.lines().count(), 2);
+    assert!(raw.lines().all(|line| line.contains("\"make\"")));
+}
+
+#[test]
+fn ranking_prefers_frequent_recent_and_local_commands() {
+    let mut store = HistoryStore::in_memory();
+    for _ in 0..3 {
+        store.start("git status", Some("/a".into()), None, NOW - 60 * DAY);
+    }
+    store.start("git stash", Some("/b".into()), None, NOW - 60);
+    store.start("git log", Some("/a".into()), None, NOW - 2 * 60 * 60);
+
+    let global = ranked_commands(
+        &store,
+        HistoryQuery {
+            prefix: "git s",
+            now: NOW,
+            ..Default::default()
+        },
+    );
+    assert_eq!(global, vec!["git stash", "git status"]);
+
+    let in_a = ranked_commands(
+        &store,
+        HistoryQuery {
+            prefix: "git",
+            cwd: Some("/a"),
+            now: NOW,
+            limit: 2,
+            ..Default::default()
+        },
+    );
+    assert_eq!(in_a, vec!["git log", "git stash"]);
+}
+
+#[test]
+fn filters_select_failed_or_succeeded_runs() {
+    let mut store = HistoryStore::in_memory();
+    let ok = store.start("make", None, None, NOW);
+    store.finish(ok, Some(0), Duration::ZERO);
+    let failed = store.start("make test", None, None, NOW);
+    store.finish(failed, Some(2), Duration::ZERO);
+    store.start("make run", None, None, NOW);
+
+    let query = |filter| HistoryQuery {
+        prefix: "make",
+        filter,
+        now: NOW,
+        ..Default::default()
+    };
+    assert_eq!(
+        ranked_commands(&store, query(HistoryFilter::Failed)),
+        vec!["make test"]
+    );
+    assert_eq!(
+        ranked_commands(&store, query(HistoryFilter::Succeeded)),
+        Vec::<String>::new()
+    );
+    assert_eq!(ranked_commands(&store, query(HistoryFilter::All)).len(), 2);
+}
+
+#[test]
+fn shell_histories_are_parsed_with_timestamps() {
+    let bash = parse_plain_history("#1690000000\nls -la\nls -la\necho hi\n");
+    assert_eq!(
+        bash,
+        vec![
+            ImportedCommand {
+                command: "ls -la".into(),
+                timestamp: Some(1690000000),
+            },
+            ImportedCommand {
+                command: "echo hi".into(),
+                timestamp: None,
+            },
+        ]
+    );
+
+    let zsh = parse_zsh_history(
+        ": 1690000001:0;git status\n: 1690000002:3;for f in *; do\\\necho $f; done\n",
+    );
+    assert_eq!(zsh[0].command, "git status");
+    assert_eq!(zsh[1].command, "for f in *; do\necho $f; done");
+    assert_eq!(zsh[1].timestamp, Some(1690000002));
+
+    let fish = parse_fish_history("- cmd: cargo build\n  when: 1690000003\n- cmd: echo a\\nb\n");
+    assert_eq!(fish[0].timestamp, Some(1690000003));
+    assert_eq!(fish[1].command, "echo a\nb");
+}
+
+#[test]
+fn import_runs_only_once_per_store() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("history.jsonl");
+    let source = || {
+        vec![ImportedCommand {
+            command: "htop".into(),
+            timestamp: Some(NOW),
+        }]
+    };
+
+    let mut store = HistoryStore::open(&path).unwrap();
+    store.import_once(source);
+    let mut store = HistoryStore::open(&path).unwrap();
+    store.import_once(source);
+    assert_eq!(store.entries().len(), 1);
+    assert_eq!(store.entries()[0].command, "htop");
+}
+
+#[test]
+fn fuzzy_match_prefers_word_starts_and_runs() {
+    let found = fuzzy_match("gco", "git checkout main").unwrap();
+    assert_eq!(found.positions, vec![0, 4, 9]);
+    assert!(fuzzy_match("xyz", "git status").is_none());
+
+    // 'İ' lowercases to "i\u{307}" on both sides and maps back to one char.
+    let dotted = fuzzy_match("İs", "cd İstanbul").unwrap();
+    assert_eq!(dotted.positions, vec![3, 4]);
+    assert!(fuzzy_match("i\u{307}", "İ").is_some());
+
+    let tight = fuzzy_match("stat", "git status").unwrap();
+    let loose = fuzzy_match("stat", "set target").unwrap();
+    assert!(tight.score > loose.score);
+}
+
+#[test]
+fn search_ranks_fuzzy_matches_and_delete_forgets_a_command() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("history.jsonl");
+    let mut store = HistoryStore::open(&path).unwrap();
+    store.start("docker compose up", None, None, NOW);
+    store.start("cargo clippy", None, None, NOW);
+    store.start("cargo clippy", None, None, NOW);
+    store.start("cd ..", None, None, NOW);
+
+    let query = HistoryQuery {
+        now: NOW,
+        ..Default::default()
+    };
+    let found: Vec<String> = store
+        .search("ccl", &query)
+        .into_iter()
+        .map(|found| found.ranked.command)
+        .collect();
+    assert_eq!(found, vec!["cargo clippy"]);
+
+    assert_eq!(store.delete_command("cargo clippy"), 2);
+    let reopened = HistoryStore::open(&path).unwrap();
+    assert!(reopened.search("ccl", &query).is_empty());
+    assert_eq!(reopened.entries().len(), 2);
+}
@@ -0,0 +1,168 @@
+use orbitshell::terminal::problems::{Problem, Severity, parse_problems};
+
+fn lines(text: &str) -> Vec<String> {
+    text.lines().map(str::to_string).collect()
+}
+
+fn problem(
+    severity: Severity,
+    message: &str,
+    file: &str,
+    line: Option<usize>,
+    column: Option<usize>,
+) -> Problem {
+    Problem {
+        severity,
+        message: message.to_string(),
+        file: file.to_string(),
+        line,
+        column,
+    }
+}
+
+#[test]
+fn rustc_human_and_json_output_are_parsed() {
+    let output = lines(concat!(
+        "   Compiling demo v0.1.0 (/work/demo)\n",
+        "error[E0425]: cannot find value `x` in this scope\n",
+        " --> src/main.rs:3:13\n",
+        "  |\n",
+        "3 |     println!(\"{}\", x);\n",
+        "  |                    ^ not found in this scope\n",
+        "warning: unused variable: `y`\n",
+        "  --> src/lib.rs:10:9\n",
+        "{\"reason\":\"compiler-artifact\",\"package_id\":\"demo\"}\n",
+        "{\"reason\":\"compiler-message\",\"message\":{\"level\":\"warning\",\"message\":\"unused import: `std::fs`\",\"spans\":[{\"file_name\":\"src/io.rs\",\"line_start\":1,\"column_start\":5,\"is_primary\":true}]}}\n",
+        "{\"$message_type\":\"diagnostic\",\"level\":\"error\",\"message\":\"aborting due to 1 previous error\",\"spans\":[]}\n",
+        "error: could not compile `demo` (bin \"demo\") due to 1 previous error\n",
+    ));
+    assert_eq!(
+        parse_problems(&output),
+        vec![
+            problem(
+                Severity::Error,
+                "cannot find value `x` in this scope",
+                "src/main.rs",
+                Some(3),
+                Some(13)
+            ),
+            problem(
+                Severity::Warning,
+                "unused variable: `y`",
+                "src/lib.rs",
+                Some(10),
+                Some(9)
+            ),
+            problem(
+                Severity::Warning,
+                "unused import: `std::fs`",
+                "src/io.rs",
+                Some(1),
+                Some(5)
+            ),
+        ]
+    );
+}
+
+#[test]
+fn compiler_tsc_eslint_and_go_lines_are_parsed() {
+    let output = lines(concat!(
+        "main.c:12:5: error: use of undeclared identifier 'count'\n",
+        "main.c:12:5: error: use of undeclared identifier 'count'\n",
+        "util.h:4:1: warning: declaration does not declare anything [-Wmissing-declarations]\n",
+        "src/app.ts(7,3): error TS2322: Type 'string' is not assignable to type 'number'.\n",
+        "src/cli.ts:2:10 - error TS2305: Module './x' has no exported member 'y'.\n",
+        "\n",
+        "/work/web/src/index.js\n",
+        "   3:7   error    'unused' is assigned a value but never used  no-unused-vars\n",
+        "  12:1   warning  Unexpected console statement                 no-console\n",
+        "\n",
+        "./cmd/main.go:8:2: undefined: handler\n",
+        "    server_test.go:41: expected 200, got 500\n",
+    ));
+    let parsed = parse_problems(&output);
+    assert_eq!(parsed.len(), 8);
+    assert_eq!(
+        parsed[0],
+        problem(
+            Severity::Error,
+            "use of undeclared identifier 'count'",
+            "main.c",
+            Some(12),
+            Some(5)
+        )
+    );
+    assert_eq!(parsed[1].severity, Severity::Warning);
+    assert_eq!(
+        (parsed[2].file.as_str(), parsed[2].line, parsed[2].column),
+        ("src/app.ts", Some(7), Some(3))
+    );
+    assert_eq!(
+        (parsed[3].file.as_str(), parsed[3].line, parsed[3].column),
+        ("src/cli.ts", Some(2), Some(10))
+    );
+    assert_eq!(
+        parsed[4],
+        problem(
+            Severity::Error,
+            "'unused' is assigned a value but never used (no-unused-vars)",
+            "/work/web/src/index.js",
+            Some(3),
+            Some(7)
+        )
+    );
+    assert_eq!(parsed[5].severity, Severity::Warning);
+    assert_eq!(
+        parsed[6],
+        problem(
+            Severity::Error,
+            "undefined: handler",
+            "./cmd/main.go",
+            Some(8),
+            Some(2)
+        )
+    );
+    assert_eq!(
+        parsed[7],
+        problem(
+            Severity::Error,
+            "expected 200, got 500",
+            "server_test.go",
+            Some(41),
+            None
+        )
+    );
+}
+
+#[test]
+fn pytest_failures_use_the_traceback_location() {
+    let output = lines(concat!(
+        "    def test_total():\n",
+        ">       assert total([1, 2]) == 4\n",
+        "E       assert 3 == 4\n",
+        "E        +  where 3 = total([1, 2])\n",
+        "\n",
+        "tests/test_cart.py:9: AssertionError\n",
+        "FAILED tests/test_cart.py::test_total - assert 3 == 4\n",
+        "ERROR tests/test_db.py - ModuleNotFoundError: No module named 'psycopg'\n",
+    ));
+    assert_eq!(
+        parse_problems(&output),
+        vec![
+            problem(
+                Severity::Error,
+                "AssertionError: assert 3 == 4",
+                "tests/test_cart.py",
+                Some(9),
+                None
+            ),
+            problem(
+                Severity::Error,
+                "ModuleNotFoundError: No module named 'psycopg'",
+                "tests/test_db.py",
+                None,
+                None
+            ),
+        ]
+    );
+}
@@ -0,0 +1,67 @@
+use orbitshell::terminal::processes::{parse_stat, tree_order};
+
+#[test]
+fn stat_names_may_hold_spaces_and_parentheses() {
+    let stat = "4242 (tmux: server (1)) S 1 4242 4242 0 -1 4194560 1085 0 0 0 37 12 0 0 20 0 1 0 987654 9043968 1130 18446744073709551615 1 1 0 0 0 0 0 3674116 1266777851 0 0 0 17 3 0 0 0 0 0";
+    let parsed = parse_stat(stat).expect("parses");
+    assert_eq!(parsed.name, "tmux: server (1)");
+    assert_eq!(parsed.ppid, 1);
+    assert_eq!(parsed.cpu_ticks, 49);
+    assert_eq!(parsed.start_ticks, 987654);
+    assert_eq!(parsed.rss_pages, 1130);
+    assert_eq!(parse_stat("4242 (truncated"), None);
+}
+
+#[test]
+fn tree_order_walks_descendants_depth_first() {
+    let parents = [
+        (1, 0),
+        (100, 1),
+        (205, 100),
+        (201, 100),
+        (300, 201),
+        (400, 1),
+    ];
+    assert_eq!(
+        tree_order(&parents, 100),
+        vec![(100, 0), (201, 1), (300, 2), (205, 1)]
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn sampler_reads_the_process_tree() {
+    use orbitshell::terminal::processes::ProcessSampler;
+
+    let mut child = std::process::Command::new("sleep")
+        .arg("5")
+        .spawn()
+        .expect("spawn sleep");
+    let mut sampler = ProcessSampler::default();
+    // Under load the child can still show the parent's command line for a
+    // moment after the spawn returns.
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(2);
+    let processes = loop {
+        let processes = sampler
+            .sample(std::process::id())
+            .expect("proc is readable");
+        let started = processes
+            .iter()
+            .any(|process| process.pid == child.id() && process.command == "sleep 5");
+        if started || std::time::Instant::now() > deadline {
+            break processes;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    };
+    child.kill().expect("kill sleep");
+    let _ = child.wait();
+
+    assert_eq!(processes[0].pid, std::process::id());
+    assert!(processes[0].memory_bytes > 0);
+    let sleep = processes
+        .iter()
+        .find(|process| process.pid == child.id())
+        .expect("child is listed");
+    assert_eq!(sleep.depth, 1);
+    assert_eq!(sleep.command, "sleep 5");
+}
@@ -0,0 +1,27 @@
+use orbitshell::terminal::secure_input::is_password_prompt;
+
+#[test]
+fn password_prompts_are_recognised() {
+    for prompt in [
+        "[sudo] password for carlos: ",
+        "Password:",
+        "Enter passphrase for key '/home/me/.ssh/id_ed25519': ",
+        "Enter PIN:",
+        "git@github.com's Password: ",
+    ] {
+        assert!(is_password_prompt(prompt), "{prompt}");
+    }
+}
+
+#[test]
+fn ordinary_output_is_not_a_prompt() {
+    for line in [
+        "Changing password for carlos.",
+        "spinning:",
+        "password123",
+        "Username: ",
+        "$ ",
+    ] {
+        assert!(!is_password_prompt(line), "{line}");
+    }
+}
@@ -0,0 +1,92 @@
+use orbitshell::terminal::ansi::{AnsiParser, TerminalEvent};
+use orbitshell::terminal::mouse::{
+    MouseInput, MouseProtocol, MouseReport, MouseTracking, PointerButton,
+};
+
+fn report(input: MouseInput, column: u16, row: u16) -> MouseReport {
+    MouseReport {
+        input,
+        column,
+        row,
+        shift: false,
+        alt: false,
+        control: false,
+    }
+}
+
+fn protocol_from(output: &str) -> MouseProtocol {
+    let mut protocol = MouseProtocol::default();
+    for event in AnsiParser::default().feed(output).events {
+        if let TerminalEvent::PrivateMode { mode, enabled } = event {
+            protocol.set_mode(mode, enabled);
+        }
+    }
+    protocol
+}
+
+#[test]
+fn private_modes_turn_tracking_on_and_off() {
+    let protocol = protocol_from("\x1b[?1049h\x1b[?1002;1006hhtop");
+    assert_eq!(protocol.tracking, MouseTracking::Drags);
+    assert!(protocol.sgr);
+
+    let protocol = protocol_from("\x1b[?1003h\x1b[?1006h\x1b[?1003l\x1b[?1006l");
+    assert!(!protocol.is_active());
+    assert!(!protocol.sgr);
+}
+
+#[test]
+fn sgr_reports_presses_releases_drags_and_wheel() {
+    let protocol = protocol_from("\x1b[?1002h\x1b[?1006h");
+    let encode = |report| String::from_utf8(protocol.encode(report).unwrap()).unwrap();
+
+    assert_eq!(
+        encode(report(MouseInput::Press(PointerButton::Left), 12, 3)),
+        "\x1b[<0;12;3M"
+    );
+    assert_eq!(
+        encode(report(MouseInput::Release(PointerButton::Left), 12, 3)),
+        "\x1b[<0;12;3m"
+    );
+    assert_eq!(
+        encode(report(
+            MouseInput::Move(Some(PointerButton::Right)),
+            300,
+            40
+        )),
+        "\x1b[<34;300;40M"
+    );
+    assert_eq!(
+        encode(MouseReport {
+            control: true,
+            ..report(MouseInput::WheelDown, 1, 1)
+        }),
+        "\x1b[<81;1;1M"
+    );
+    assert_eq!(protocol.encode(report(MouseInput::Move(None), 5, 5)), None);
+}
+
+#[test]
+fn legacy_reports_use_offset_bytes_and_stop_at_223() {
+    let protocol = protocol_from("\x1b[?1000h");
+    assert_eq!(
+        protocol.encode(report(MouseInput::Press(PointerButton::Middle), 1, 2)),
+        Some(vec![0x1b, b'[', b'M', 33, 33, 34])
+    );
+    assert_eq!(
+        protocol.encode(report(MouseInput::Release(PointerButton::Middle), 1, 2)),
+        Some(vec![0x1b, b'[', b'M', 35, 33, 34])
+    );
+    assert_eq!(
+        protocol.encode(report(MouseInput::Press(PointerButton::Left), 224, 1)),
+        None
+    );
+    assert_eq!(
+        protocol.encode(report(MouseInput::Move(Some(PointerButton::Left)), 1, 1)),
+        None
+    );
+    assert_eq!(
+        MouseProtocol::default().encode(report(MouseInput::WheelUp, 1, 1)),
+        None
+    );
+}
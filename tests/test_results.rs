@@ -0,0 +1,153 @@
+use orbitshell::terminal::test_results::{
+    TestOutcome, TestRunner, parse_test_report, rerun_command,
+};
+
+fn lines(text: &str) -> Vec<String> {
+    text.lines().map(str::to_string).collect()
+}
+
+#[test]
+fn runners_are_detected_through_wrappers() {
+    assert_eq!(
+        TestRunner::detect("cargo test -p core"),
+        Some(TestRunner::Cargo)
+    );
+    assert_eq!(
+        TestRunner::detect("RUST_LOG=debug cargo +nightly t"),
+        Some(TestRunner::Cargo)
+    );
+    assert_eq!(
+        TestRunner::detect("python3 -m pytest -x tests"),
+        Some(TestRunner::Pytest)
+    );
+    assert_eq!(TestRunner::detect("npx jest src"), Some(TestRunner::Jest));
+    assert_eq!(TestRunner::detect("npm run test"), Some(TestRunner::Jest));
+    assert_eq!(TestRunner::detect("cargo build"), None);
+    assert_eq!(TestRunner::detect("ls tests"), None);
+}
+
+#[test]
+fn cargo_results_keep_failure_output() {
+    let output = lines(concat!(
+        "running 3 tests\n",
+        "test cart::adds_items ... ok\n",
+        "test cart::totals ... FAILED\n",
+        "test cart::slow ... ignored, needs network\n",
+        "\n",
+        "failures:\n",
+        "\n",
+        "---- cart::totals stdout ----\n",
+        "thread 'cart::totals' panicked at src/cart.rs:40:9:\n",
+        "assertion `left == right` failed\n",
+        "\n",
+        "\n",
+        "failures:\n",
+        "    cart::totals\n",
+        "\n",
+        "test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out\n",
+        "{\"type\":\"test\",\"event\":\"failed\",\"name\":\"api::login\",\"stdout\":\"expected 200\\n\"}\n",
+        "{\"type\":\"suite\",\"event\":\"failed\",\"passed\":4,\"failed\":1,\"ignored\":0}\n",
+    ));
+    let report = parse_test_report(TestRunner::Cargo, &output).expect("report");
+    assert_eq!((report.passed, report.failed, report.ignored), (5, 2, 1));
+    let failures: Vec<_> = report.failures().collect();
+    assert_eq!(failures.len(), 2);
+    assert_eq!(failures[0].name, "cart::totals");
+    assert_eq!(
+        failures[0].output,
+        vec![
+            "thread 'cart::totals' panicked at src/cart.rs:40:9:",
+            "assertion `left == right` failed",
+        ]
+    );
+    assert_eq!(failures[1].output, vec!["expected 200"]);
+    assert_eq!(
+        parse_test_report(TestRunner::Cargo, &lines("Compiling")),
+        None
+    );
+}
+
+#[test]
+fn pytest_results_match_sections_to_node_ids() {
+    let output = lines(concat!(
+        "tests/test_cart.py::test_add PASSED                                [ 33%]\n",
+        "tests/test_cart.py::TestTotals::test_sum FAILED                    [ 66%]\n",
+        "tests/test_cart.py::test_slow SKIPPED (network)                    [100%]\n",
+        "=================================== FAILURES ===================================\n",
+        "____________________________ TestTotals.test_sum _____________________________\n",
+        "\n",
+        ">       assert total([1, 2]) == 4\n",
+        "E       assert 3 == 4\n",
+        "\n",
+        "tests/test_cart.py:9: AssertionError\n",
+        "=========================== short test summary info ============================\n",
+        "FAILED tests/test_cart.py::TestTotals::test_sum - assert 3 == 4\n",
+        "==================== 1 failed, 1 passed, 1 skipped in 0.12s ====================\n",
+    ));
+    let report = parse_test_report(TestRunner::Pytest, &output).expect("report");
+    assert_eq!((report.passed, report.failed, report.ignored), (1, 1, 1));
+    let failed = report.failures().next().expect("failure");
+    assert_eq!(failed.name, "tests/test_cart.py::TestTotals::test_sum");
+    assert_eq!(
+        failed.output.first().map(String::as_str),
+        Some(">       assert total([1, 2]) == 4")
+    );
+    assert_eq!(
+        failed.output.last().map(String::as_str),
+        Some("tests/test_cart.py:9: AssertionError")
+    );
+}
+
+#[test]
+fn jest_failures_take_their_full_name() {
+    let output = lines(concat!(
+        "FAIL src/math.test.js\n",
+        "  math\n",
+        "    ✓ adds (2 ms)\n",
+        "    ✕ subtracts (4 ms)\n",
+        "    ○ skipped divides\n",
+        "\n",
+        "  ● math › subtracts\n",
+        "\n",
+        "    expect(received).toBe(expected)\n",
+        "\n",
+        "Tests:       1 failed, 1 skipped, 1 passed, 3 total\n",
+    ));
+    let report = parse_test_report(TestRunner::Jest, &output).expect("report");
+    assert_eq!((report.passed, report.failed, report.ignored), (1, 1, 1));
+    let failed = report.failures().next().expect("failure");
+    assert_eq!(failed.name, "math subtracts");
+    assert_eq!(failed.output, vec!["    expect(received).toBe(expected)"]);
+    assert_eq!(report.tests[0].outcome, TestOutcome::Passed);
+}
+
+#[test]
+fn rerun_commands_select_only_the_given_tests() {
+    let tests = |names: &[&str]| {
+        names
+            .iter()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        rerun_command(
+            "cargo test -p core cart -- --nocapture",
+            &tests(&["cart::totals", "api::login"])
+        )
+        .as_deref(),
+        Some("cargo test -p core -- --nocapture --exact cart::totals api::login")
+    );
+    assert_eq!(
+        rerun_command(
+            "pytest -x -k cart tests/",
+            &tests(&["tests/test_cart.py::test_sum[a b]"])
+        )
+        .as_deref(),
+        Some("pytest -x 'tests/test_cart.py::test_sum[a b]'")
+    );
+    assert_eq!(
+        rerun_command("npm test -t old", &tests(&["math subtracts"])).as_deref(),
+        Some("npm test -- -t '^(?:math subtracts)$'")
+    );
+    assert_eq!(rerun_command("make test", &tests(&["a"])), None);
+}